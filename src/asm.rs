@@ -0,0 +1,1033 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+/* ==================================================
+ * ENSAMBLADOR Z80
+ * ==================================================
+ * Lo justo para los programas de tests/z80 (sintaxis de sjasmplus):
+ *
+ *   - etiquetas ("bucle:", o en la columna 0 sin ':'), locales ".x"
+ *   - ORG, DISP / ENT, EQU / '=', DB / DW / DS (DEFB, DEFW, DEFS, DEFM), END
+ *   - DEVICE (se ignora), OUTPUT "f" y SAVEBIN "f", inicio, longitud
+ *   - expresiones: + - * / % & | ^ ~ << >> y paréntesis, '$' es la
+ *     dirección actual; números $1F, #1F, 0x1F, 1Fh, %0101, 0101b, 'c'
+ *   - todo el juego de instrucciones documentado (y IXH/IXL/IYH/IYL)
+ *
+ * Dos pasadas: la primera calcula las direcciones de las etiquetas
+ * (las referencias hacia delante valen 0), la segunda genera el código.
+ * El resultado es una imagen de 64 KB como la del DEVICE de sjasmplus.
+ */
+
+/// Fichero que pide guardar el fuente (SAVEBIN u OUTPUT)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SaveBin {
+    pub name: String,
+    pub start: u16,
+    pub len: usize,
+}
+
+pub struct Assembly {
+    memory: Vec<u8>,
+    /// Dirección más baja y más alta con código
+    low: Option<u16>,
+    high: u16,
+    pub symbols: HashMap<String, u16>,
+    pub saves: Vec<SaveBin>,
+    /// Dirección de inicio de "END <dir>"
+    pub entry: Option<u16>,
+}
+
+impl Assembly {
+    /// Bytes de la imagen de 64 KB
+    pub fn bytes(&self, start: u16, len: usize) -> &[u8] {
+        let start = start as usize;
+        &self.memory[start..(start + len).min(0x10000)]
+    }
+
+    /// Lo que se guardaría: el primer SAVEBIN/OUTPUT o, sin ninguno,
+    /// desde la primera hasta la última dirección con código
+    pub fn binary(&self) -> (u16, Vec<u8>) {
+        match (self.saves.first(), self.low) {
+            (Some(s), _) => (s.start, self.bytes(s.start, s.len).to_vec()),
+            (None, Some(low)) => (low, self.memory[low as usize..=self.high as usize].to_vec()),
+            (None, None) => (0, Vec::new()),
+        }
+    }
+
+    /// Escribe los ficheros de SAVEBIN/OUTPUT en `dir`
+    pub fn write_files(&self, dir: &Path) -> Result<(), String> {
+        for s in &self.saves {
+            let path = dir.join(&s.name);
+            std::fs::write(&path, self.bytes(s.start, s.len))
+                .map_err(|e| format!("{}: {}", path.display(), e))?;
+        }
+        Ok(())
+    }
+}
+
+pub fn assemble_file(path: &Path) -> Result<Assembly, String> {
+    let source = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    assemble(&source).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+pub fn assemble(source: &str) -> Result<Assembly, String> {
+    let mut asm = Assembler::new();
+    asm.pass(source)?;
+
+    let symbols = std::mem::take(&mut asm.symbols);
+    let mut asm = Assembler::new();
+    asm.known = symbols;
+    asm.last_pass = true;
+    asm.pass(source)?;
+
+    Ok(Assembly {
+        memory: asm.memory,
+        low: asm.low,
+        high: asm.high,
+        symbols: asm.symbols,
+        saves: asm.saves,
+        entry: asm.entry,
+    })
+}
+
+/* ==================================================
+ * PASADAS
+ * ================================================== */
+
+struct Assembler {
+    memory: Vec<u8>,
+    /// Dirección donde se escribe
+    pc: u16,
+    /// Con DISP, lo que se suma a `pc` para la dirección de ejecución
+    disp: u16,
+    low: Option<u16>,
+    high: u16,
+    symbols: HashMap<String, u16>,
+    /// Símbolos de la primera pasada
+    known: HashMap<String, u16>,
+    last_global: String,
+    last_pass: bool,
+    saves: Vec<SaveBin>,
+    /// OUTPUT abierto: nombre y dirección de inicio
+    output: Option<(String, u16)>,
+    entry: Option<u16>,
+}
+
+impl Assembler {
+    fn new() -> Self {
+        Self {
+            memory: vec![0; 0x10000],
+            pc: 0,
+            disp: 0,
+            low: None,
+            high: 0,
+            symbols: HashMap::new(),
+            known: HashMap::new(),
+            last_global: String::new(),
+            last_pass: false,
+            saves: Vec::new(),
+            output: None,
+            entry: None,
+        }
+    }
+
+    fn pass(&mut self, source: &str) -> Result<(), String> {
+        for (n, line) in source.lines().enumerate() {
+            match self.line(line) {
+                Ok(true) => {}
+                Ok(false) => break, // END
+                Err(e) => return Err(format!("Línea {}: {}", n + 1, e)),
+            }
+        }
+        self.close_output();
+        Ok(())
+    }
+
+    /// Devuelve false con END
+    fn line(&mut self, line: &str) -> Result<bool, String> {
+        let line = strip_comment(line);
+        if line.trim().is_empty() {
+            return Ok(true);
+        }
+
+        // Etiqueta: con ':' o en la columna 0
+        let mut rest = line.trim_start();
+        let mut label = None;
+        let first = rest.split(|c: char| c.is_whitespace()).next().unwrap_or("");
+        if let Some(name) = first.strip_suffix(':') {
+            label = Some(name.to_string());
+            rest = rest[first.len()..].trim_start();
+        } else if !line.starts_with(char::is_whitespace) && !is_keyword(first) {
+            label = Some(first.to_string());
+            rest = rest[first.len()..].trim_start();
+        }
+
+        let (mnemonic, args) = match rest.split_once(|c: char| c.is_whitespace()) {
+            Some((m, a)) => (m.to_ascii_uppercase(), a.trim()),
+            None => (rest.to_ascii_uppercase(), ""),
+        };
+
+        // "x EQU 5" / "x = 5"
+        if mnemonic == "EQU" || mnemonic == "=" {
+            let name = label.ok_or("EQU sin etiqueta")?;
+            let value = self.eval(args)?;
+            self.define(&name, value as u16)?;
+            return Ok(true);
+        }
+        if let Some(name) = label {
+            self.define(&name, self.here())?;
+        }
+        if mnemonic.is_empty() {
+            return Ok(true);
+        }
+
+        let ops = split_operands(args);
+        match mnemonic.as_str() {
+            "ORG" => self.pc = self.eval(one(&ops)?)? as u16,
+            "DISP" => self.disp = (self.eval(one(&ops)?)? as u16).wrapping_sub(self.pc),
+            "ENT" => self.disp = 0,
+            "DEVICE" => {}
+            "END" => {
+                if let Some(e) = ops.first() {
+                    self.entry = Some(self.eval(e)? as u16);
+                }
+                return Ok(false);
+            }
+            // '$' es el inicio de la línea en todos los valores
+            "DB" | "DEFB" | "DEFM" | "BYTE" => {
+                let mut data = Vec::new();
+                for op in &ops {
+                    match string_literal(op) {
+                        Some(s) => data.extend(s.bytes()),
+                        None => data.push(self.byte(op)?),
+                    }
+                }
+                self.emit(&data);
+            }
+            "DW" | "DEFW" | "WORD" => {
+                let mut data = Vec::new();
+                for op in &ops {
+                    data.extend(self.word(op)?.to_le_bytes());
+                }
+                self.emit(&data);
+            }
+            "DS" | "DEFS" | "BLOCK" => {
+                let count = self.eval(ops.first().ok_or("Falta el tamaño")?)?;
+                let fill = match ops.get(1) {
+                    Some(f) => self.byte(f)?,
+                    None => 0,
+                };
+                if !(0..=0x10000).contains(&count) {
+                    return Err(format!("Tamaño no válido: {}", count));
+                }
+                for _ in 0..count {
+                    self.emit(&[fill]);
+                }
+            }
+            "OUTPUT" => {
+                self.close_output();
+                let name = string_literal(one(&ops)?).ok_or("OUTPUT necesita un nombre entre comillas")?;
+                self.output = Some((name, self.pc));
+            }
+            "SAVEBIN" => {
+                let name = string_literal(ops.first().ok_or("Falta el nombre")?)
+                    .ok_or("SAVEBIN necesita un nombre entre comillas")?;
+                let start = self.eval(ops.get(1).ok_or("Falta la dirección")?)? as u16;
+                let len = match ops.get(2) {
+                    Some(l) => self.eval(l)?.max(0) as usize,
+                    None => 0x10000,
+                };
+                let len = len.min(0x10000 - start as usize);
+                if self.last_pass {
+                    self.saves.push(SaveBin { name, start, len });
+                }
+            }
+            _ => {
+                let code = self.instruction(&mnemonic, &ops)?;
+                self.emit(&code);
+            }
+        }
+
+        Ok(true)
+    }
+
+    fn close_output(&mut self) {
+        if let Some((name, start)) = self.output.take()
+            && self.last_pass
+        {
+            let len = self.pc.wrapping_sub(start) as usize;
+            self.saves.push(SaveBin { name, start, len });
+        }
+    }
+
+    fn define(&mut self, name: &str, value: u16) -> Result<(), String> {
+        let name = if name.starts_with('.') {
+            format!("{}{}", self.last_global, name)
+        } else {
+            self.last_global = name.to_string();
+            name.to_string()
+        };
+        if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || "_.?".contains(c)) {
+            return Err(format!("Etiqueta no válida: {}", name));
+        }
+        if self.symbols.insert(name.clone(), value).is_some() {
+            return Err(format!("Etiqueta repetida: {}", name));
+        }
+        Ok(())
+    }
+
+    /// Dirección de ejecución de lo que se ensambla ('$')
+    fn here(&self) -> u16 {
+        self.pc.wrapping_add(self.disp)
+    }
+
+    fn emit(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.memory[self.pc as usize] = b;
+            self.low = Some(self.low.map_or(self.pc, |l| l.min(self.pc)));
+            self.high = self.high.max(self.pc);
+            self.pc = self.pc.wrapping_add(1);
+        }
+    }
+
+    /* ==================================================
+     * EXPRESIONES
+     * ================================================== */
+
+    fn eval(&self, src: &str) -> Result<i64, String> {
+        let tokens = tokenize(src)?;
+        let mut p = ExprParser { tokens: &tokens, pos: 0, asm: self };
+        let v = p.or()?;
+        if p.pos != tokens.len() {
+            return Err(format!("Expresión no válida: {}", src));
+        }
+        Ok(v)
+    }
+
+    fn symbol(&self, name: &str) -> Result<i64, String> {
+        let full = if name.starts_with('.') {
+            format!("{}{}", self.last_global, name)
+        } else {
+            name.to_string()
+        };
+        match self.symbols.get(&full).or_else(|| self.known.get(&full)) {
+            Some(v) => Ok(*v as i64),
+            // "FFh": hexadecimal sin el 0 delante
+            None if let Some(n) = parse_number(name) => Ok(n),
+            None if !self.last_pass => Ok(0),
+            None => Err(format!("Símbolo no definido: {}", name)),
+        }
+    }
+
+    fn byte(&self, src: &str) -> Result<u8, String> {
+        let v = self.eval(src)?;
+        if self.last_pass && !(-128..=255).contains(&v) {
+            return Err(format!("No cabe en un byte: {}", src));
+        }
+        Ok(v as u8)
+    }
+
+    fn word(&self, src: &str) -> Result<u16, String> {
+        let v = self.eval(src)?;
+        if self.last_pass && !(-32768..=65535).contains(&v) {
+            return Err(format!("No cabe en 16 bits: {}", src));
+        }
+        Ok(v as u16)
+    }
+
+    fn displacement(&self, src: &str) -> Result<u8, String> {
+        if src.is_empty() {
+            return Ok(0);
+        }
+        let v = self.eval(src)?;
+        if self.last_pass && !(-128..=127).contains(&v) {
+            return Err(format!("Desplazamiento fuera de rango: {}", src));
+        }
+        Ok(v as u8)
+    }
+
+    /// Salto relativo desde la instrucción de 2 bytes en `pc`
+    fn relative(&self, src: &str) -> Result<u8, String> {
+        let target = self.eval(src)?;
+        let offset = target - (self.here() as i64 + 2);
+        if self.last_pass && !(-128..=127).contains(&offset) {
+            return Err(format!("Salto relativo fuera de rango: {}", src));
+        }
+        Ok(offset as u8)
+    }
+
+    /* ==================================================
+     * INSTRUCCIONES
+     * ================================================== */
+
+    fn instruction(&self, mnemonic: &str, args: &[String]) -> Result<Vec<u8>, String> {
+        if !is_keyword(mnemonic) {
+            return Err(format!("Instrucción desconocida: {}", mnemonic));
+        }
+        let ops: Vec<Operand> = args.iter().map(|a| Operand::parse(a)).collect();
+        self.encode(mnemonic, &ops).map_err(|e| {
+            if e == BAD_OPERANDS {
+                format!("{}: {} {}", e, mnemonic, args.join(","))
+            } else {
+                e
+            }
+        })
+    }
+
+    fn encode(&self, mnemonic: &str, ops: &[Operand]) -> Result<Vec<u8>, String> {
+        use Operand::*;
+
+        if ops.is_empty()
+            && let Some(code) = implied(mnemonic)
+        {
+            return Ok(code);
+        }
+
+        match (mnemonic, ops) {
+            ("LD", [dst, src]) => self.ld(dst, src),
+
+            ("PUSH" | "POP", [Reg(r)]) => {
+                let base = if mnemonic == "PUSH" { 0xC5 } else { 0xC1 };
+                match r.as_str() {
+                    "AF" => Ok(vec![base + 0x30]),
+                    _ => {
+                        let (prefix, rr) = pair(r).filter(|(_, rr)| *rr != 3).ok_or_else(bad)?;
+                        Ok([prefix.as_slice(), &[base + rr * 0x10]].concat())
+                    }
+                }
+            }
+
+            ("EX", [a, b]) => match (a, b) {
+                (Reg(a), Reg(b)) if a == "AF" && b == "AF'" => Ok(vec![0x08]),
+                (Reg(a), Reg(b)) if a == "DE" && b == "HL" => Ok(vec![0xEB]),
+                (Ind(sp), Reg(r)) if sp == "SP" => {
+                    let prefix = index_prefix(r).ok_or_else(bad)?;
+                    Ok([prefix.as_slice(), &[0xE3]].concat())
+                }
+                _ => Err(bad()),
+            },
+
+            // Aritmética de 16 bits
+            ("ADD" | "ADC" | "SBC", [Reg(dst), Reg(src)]) if index_prefix(dst).is_some() => {
+                let prefix = index_prefix(dst).unwrap_or_default();
+                let (src_prefix, rr) = pair(src).ok_or_else(bad)?;
+                if rr == 2 && src_prefix != prefix {
+                    return Err(bad());
+                }
+                match (mnemonic, prefix.len()) {
+                    ("ADD", _) => Ok([prefix.as_slice(), &[0x09 + rr * 0x10]].concat()),
+                    ("ADC", 0) => Ok(vec![0xED, 0x4A + rr * 0x10]),
+                    ("SBC", 0) => Ok(vec![0xED, 0x42 + rr * 0x10]),
+                    _ => Err(bad()),
+                }
+            }
+
+            // Aritmética y lógica de 8 bits ("SUB B" o "SUB A,B")
+            ("ADD" | "ADC" | "SUB" | "SBC" | "AND" | "XOR" | "OR" | "CP", [Reg(a), src]) if a == "A" => {
+                self.alu(mnemonic, src)
+            }
+            ("ADD" | "ADC" | "SUB" | "SBC" | "AND" | "XOR" | "OR" | "CP", [src]) => self.alu(mnemonic, src),
+
+            ("INC" | "DEC", [op]) => {
+                let dec = mnemonic == "DEC";
+                if let Reg(r) = op
+                    && let Some((prefix, rr)) = pair(r)
+                {
+                    return Ok([prefix.as_slice(), &[if dec { 0x0B } else { 0x03 } + rr * 0x10]].concat());
+                }
+                let (mut v, r, disp) = self.r8(op)?;
+                v.push(if dec { 0x05 } else { 0x04 } + r * 8);
+                v.extend(disp);
+                Ok(v)
+            }
+
+            // Rotaciones y desplazamientos (CB)
+            ("RLC" | "RRC" | "RL" | "RR" | "SLA" | "SRA" | "SLL" | "SLI" | "SRL", [op]) => {
+                let n = ["RLC", "RRC", "RL", "RR", "SLA", "SRA", "SLL", "SRL"]
+                    .iter()
+                    .position(|m| *m == mnemonic)
+                    .unwrap_or(6) as u8;
+                self.cb(n * 8, op)
+            }
+            ("BIT" | "RES" | "SET", [Imm(bit), op]) => {
+                let b = self.eval(bit)?;
+                if !(0..=7).contains(&b) {
+                    return Err(format!("Bit no válido: {}", b));
+                }
+                let base = match mnemonic {
+                    "BIT" => 0x40,
+                    "RES" => 0x80,
+                    _ => 0xC0,
+                };
+                self.cb(base + b as u8 * 8, op)
+            }
+
+            // Saltos
+            ("JP", [Ind(r)]) if r == "HL" => Ok(vec![0xE9]),
+            ("JP", [Idx(r, d)]) if d.is_empty() => Ok([index_prefix(r).ok_or_else(bad)?.as_slice(), &[0xE9]].concat()),
+            ("JP", [Imm(nn)]) => self.with_word(&[0xC3], nn),
+            ("JP", [Reg(cc), Imm(nn)]) => self.with_word(&[0xC2 + cond(cc, 8)? * 8], nn),
+            ("CALL", [Imm(nn)]) => self.with_word(&[0xCD], nn),
+            ("CALL", [Reg(cc), Imm(nn)]) => self.with_word(&[0xC4 + cond(cc, 8)? * 8], nn),
+            ("JR", [Imm(t)]) => Ok(vec![0x18, self.relative(t)?]),
+            ("JR", [Reg(cc), Imm(t)]) => Ok(vec![0x20 + cond(cc, 4)? * 8, self.relative(t)?]),
+            ("DJNZ", [Imm(t)]) => Ok(vec![0x10, self.relative(t)?]),
+            ("RET", [Reg(cc)]) => Ok(vec![0xC0 + cond(cc, 8)? * 8]),
+            ("RST", [Imm(p)]) => {
+                let p = self.eval(p)?;
+                if p & !0x38 != 0 {
+                    return Err(format!("RST no válido: {:X}", p));
+                }
+                Ok(vec![0xC7 + p as u8])
+            }
+
+            ("IM", [Imm(m)]) => match self.eval(m)? {
+                0 => Ok(vec![0xED, 0x46]),
+                1 => Ok(vec![0xED, 0x56]),
+                2 => Ok(vec![0xED, 0x5E]),
+                m => Err(format!("Modo de interrupción no válido: {}", m)),
+            },
+
+            // Entrada / salida
+            ("IN", [Reg(a), Mem(n)]) if a == "A" => Ok(vec![0xDB, self.byte(n)?]),
+            ("IN", [Reg(f), Ind(c)]) if c == "C" && f == "F" => Ok(vec![0xED, 0x70]),
+            ("IN", [Ind(c)]) if c == "C" => Ok(vec![0xED, 0x70]),
+            ("IN", [Reg(r), Ind(c)]) if c == "C" => Ok(vec![0xED, 0x40 + r8_code(r).ok_or_else(bad)? * 8]),
+            ("OUT", [Mem(n), Reg(a)]) if a == "A" => Ok(vec![0xD3, self.byte(n)?]),
+            ("OUT", [Ind(c), Reg(r)]) if c == "C" => Ok(vec![0xED, 0x41 + r8_code(r).ok_or_else(bad)? * 8]),
+            ("OUT", [Ind(c), Imm(z)]) if c == "C" && self.eval(z)? == 0 => Ok(vec![0xED, 0x71]),
+
+            _ => Err(bad()),
+        }
+    }
+
+    /// Código seguido de una palabra
+    fn with_word(&self, code: &[u8], nn: &str) -> Result<Vec<u8>, String> {
+        let w = self.word(nn)?;
+        Ok([code, &w.to_le_bytes()].concat())
+    }
+
+    /// Registro de 8 bits, (HL) o (IX+d): prefijo, código y desplazamiento
+    fn r8(&self, op: &Operand) -> Result<(Vec<u8>, u8, Vec<u8>), String> {
+        match op {
+            Operand::Reg(r) => match r.as_str() {
+                "IXH" => Ok((vec![0xDD], 4, vec![])),
+                "IXL" => Ok((vec![0xDD], 5, vec![])),
+                "IYH" => Ok((vec![0xFD], 4, vec![])),
+                "IYL" => Ok((vec![0xFD], 5, vec![])),
+                _ => Ok((vec![], r8_code(r).ok_or_else(bad)?, vec![])),
+            },
+            Operand::Ind(r) if r == "HL" => Ok((vec![], 6, vec![])),
+            Operand::Idx(r, d) => Ok((index_prefix(r).ok_or_else(bad)?, 6, vec![self.displacement(d)?])),
+            _ => Err(bad()),
+        }
+    }
+
+    fn alu(&self, mnemonic: &str, src: &Operand) -> Result<Vec<u8>, String> {
+        let op = ["ADD", "ADC", "SUB", "SBC", "AND", "XOR", "OR", "CP"]
+            .iter()
+            .position(|m| *m == mnemonic)
+            .unwrap_or(0) as u8;
+
+        if let Operand::Imm(n) = src {
+            return Ok(vec![0xC6 + op * 8, self.byte(n)?]);
+        }
+        let (mut v, r, disp) = self.r8(src)?;
+        v.push(0x80 + op * 8 + r);
+        v.extend(disp);
+        Ok(v)
+    }
+
+    /// Instrucciones CB; con índice el desplazamiento va antes del código
+    fn cb(&self, base: u8, op: &Operand) -> Result<Vec<u8>, String> {
+        match self.r8(op)? {
+            (p, r, _) if p.is_empty() => Ok(vec![0xCB, base + r]),
+            (p, 6, d) => Ok(vec![p[0], 0xCB, d[0], base + 6]),
+            _ => Err(bad()),
+        }
+    }
+
+    fn ld(&self, dst: &Operand, src: &Operand) -> Result<Vec<u8>, String> {
+        use Operand::*;
+
+        match (dst, src) {
+            // Registros especiales
+            (Reg(a), Reg(i)) if a == "A" && i == "I" => return Ok(vec![0xED, 0x57]),
+            (Reg(a), Reg(r)) if a == "A" && r == "R" => return Ok(vec![0xED, 0x5F]),
+            (Reg(i), Reg(a)) if i == "I" && a == "A" => return Ok(vec![0xED, 0x47]),
+            (Reg(r), Reg(a)) if r == "R" && a == "A" => return Ok(vec![0xED, 0x4F]),
+
+            // A con (BC) / (DE) / (nn)
+            (Reg(a), Ind(rr)) if a == "A" && rr == "BC" => return Ok(vec![0x0A]),
+            (Reg(a), Ind(rr)) if a == "A" && rr == "DE" => return Ok(vec![0x1A]),
+            (Ind(rr), Reg(a)) if a == "A" && rr == "BC" => return Ok(vec![0x02]),
+            (Ind(rr), Reg(a)) if a == "A" && rr == "DE" => return Ok(vec![0x12]),
+            (Reg(a), Mem(nn)) if a == "A" => return self.with_word(&[0x3A], nn),
+            (Mem(nn), Reg(a)) if a == "A" => return self.with_word(&[0x32], nn),
+
+            // 16 bits
+            (Reg(sp), Reg(r)) if sp == "SP" && index_prefix(r).is_some() => {
+                return Ok([index_prefix(r).unwrap_or_default().as_slice(), &[0xF9]].concat());
+            }
+            (Reg(r), Imm(nn)) if pair(r).is_some() => {
+                let (prefix, rr) = pair(r).unwrap_or_default();
+                return self.with_word(&[prefix.as_slice(), &[0x01 + rr * 0x10]].concat(), nn);
+            }
+            (Reg(r), Mem(nn)) | (Mem(nn), Reg(r)) if pair(r).is_some() => {
+                let load = matches!(dst, Reg(_));
+                let (prefix, rr) = pair(r).unwrap_or_default();
+                let code = match (rr, load) {
+                    (2, true) => [prefix.as_slice(), &[0x2A]].concat(),
+                    (2, false) => [prefix.as_slice(), &[0x22]].concat(),
+                    (_, true) => vec![0xED, 0x4B + rr * 0x10],
+                    (_, false) => vec![0xED, 0x43 + rr * 0x10],
+                };
+                return self.with_word(&code, nn);
+            }
+            _ => {}
+        }
+
+        // 8 bits: r,n / r,r' / r,(HL) / (IX+d),n ...
+        let (pd, d, dd) = self.r8(dst)?;
+        if let Imm(n) = src {
+            let mut v = pd;
+            v.push(0x06 + d * 8);
+            v.extend(dd);
+            v.push(self.byte(n)?);
+            return Ok(v);
+        }
+
+        let (ps, s, ds) = self.r8(src)?;
+        // (HL),(HL) sería HALT y con prefijo H/L pasan a ser IXH/IXL:
+        // no se pueden mezclar H/L con IX ni IX con IY
+        let plain_hl = |op: &Operand| matches!(op, Reg(r) if r == "H" || r == "L");
+        let prefix = match (pd.as_slice(), ps.as_slice()) {
+            _ if d == 6 && s == 6 => return Err(bad()),
+            ([], []) => vec![],
+            ([p], []) if !plain_hl(src) || !dd.is_empty() => vec![*p],
+            ([], [p]) if !plain_hl(dst) || !ds.is_empty() => vec![*p],
+            ([a], [b]) if a == b && dd.is_empty() && ds.is_empty() => vec![*a],
+            _ => return Err(bad()),
+        };
+
+        let mut v = prefix;
+        v.push(0x40 + d * 8 + s);
+        v.extend(dd);
+        v.extend(ds);
+        Ok(v)
+    }
+}
+
+const BAD_OPERANDS: &str = "Operandos no válidos";
+
+fn bad() -> String {
+    BAD_OPERANDS.to_string()
+}
+
+/* ==================================================
+ * OPERANDOS
+ * ================================================== */
+
+#[derive(Clone, Debug)]
+enum Operand {
+    /// Registro o condición (en mayúsculas)
+    Reg(String),
+    /// (HL), (BC), (DE), (SP), (C)
+    Ind(String),
+    /// (IX+d) / (IY+d): registro y expresión del desplazamiento
+    Idx(String, String),
+    /// (nn)
+    Mem(String),
+    Imm(String),
+}
+
+const REGISTERS: [&str; 22] = [
+    "A", "B", "C", "D", "E", "H", "L", "I", "R", "F",
+    "AF", "AF'", "BC", "DE", "HL", "SP", "IX", "IY", "IXH", "IXL", "IYH", "IYL",
+];
+const CONDITIONS: [&str; 8] = ["NZ", "Z", "NC", "C", "PO", "PE", "P", "M"];
+
+impl Operand {
+    fn parse(src: &str) -> Self {
+        let s = src.trim();
+        let upper = s.to_ascii_uppercase();
+
+        if REGISTERS.contains(&upper.as_str()) || CONDITIONS.contains(&upper.as_str()) {
+            return Operand::Reg(upper);
+        }
+
+        if let Some(inner) = full_parens(s) {
+            let inner = inner.trim();
+            let up = inner.to_ascii_uppercase();
+            if ["HL", "BC", "DE", "SP", "C"].contains(&up.as_str()) {
+                return Operand::Ind(up);
+            }
+            if up.starts_with("IX") || up.starts_with("IY") {
+                let rest = inner[2..].trim();
+                if rest.is_empty() || rest.starts_with(['+', '-']) {
+                    let disp = rest.strip_prefix('+').map_or(rest, str::trim);
+                    return Operand::Idx(up[..2].to_string(), disp.to_string());
+                }
+            }
+            return Operand::Mem(inner.to_string());
+        }
+
+        Operand::Imm(s.to_string())
+    }
+}
+
+/// "(...)" que envuelve todo el operando
+fn full_parens(s: &str) -> Option<&str> {
+    let inner = s.strip_prefix('(')?.strip_suffix(')')?;
+    let mut depth = 0;
+    for c in inner.chars() {
+        match c {
+            '(' => depth += 1,
+            ')' if depth == 0 => return None,
+            ')' => depth -= 1,
+            _ => {}
+        }
+    }
+    Some(inner)
+}
+
+/// B C D E H L - A (el 6 es (HL))
+fn r8_code(r: &str) -> Option<u8> {
+    ["B", "C", "D", "E", "H", "L", "", "A"]
+        .iter()
+        .position(|x| *x == r && !r.is_empty())
+        .map(|p| p as u8)
+}
+
+/// Prefijo de HL / IX / IY
+fn index_prefix(r: &str) -> Option<Vec<u8>> {
+    match r {
+        "HL" => Some(vec![]),
+        "IX" => Some(vec![0xDD]),
+        "IY" => Some(vec![0xFD]),
+        _ => None,
+    }
+}
+
+/// Par de registros BC DE HL SP (IX/IY en lugar de HL con su prefijo)
+fn pair(r: &str) -> Option<(Vec<u8>, u8)> {
+    match r {
+        "BC" => Some((vec![], 0)),
+        "DE" => Some((vec![], 1)),
+        "SP" => Some((vec![], 3)),
+        _ => index_prefix(r).map(|p| (p, 2)),
+    }
+}
+
+/// Código de condición; JR solo admite las 4 primeras
+fn cond(c: &str, max: usize) -> Result<u8, String> {
+    CONDITIONS[..max]
+        .iter()
+        .position(|x| *x == c)
+        .map(|p| p as u8)
+        .ok_or_else(|| format!("Condición no válida: {}", c))
+}
+
+fn implied(m: &str) -> Option<Vec<u8>> {
+    Some(match m {
+        "NOP" => vec![0x00],
+        "HALT" => vec![0x76],
+        "DI" => vec![0xF3],
+        "EI" => vec![0xFB],
+        "EXX" => vec![0xD9],
+        "DAA" => vec![0x27],
+        "CPL" => vec![0x2F],
+        "SCF" => vec![0x37],
+        "CCF" => vec![0x3F],
+        "RLCA" => vec![0x07],
+        "RRCA" => vec![0x0F],
+        "RLA" => vec![0x17],
+        "RRA" => vec![0x1F],
+        "RET" => vec![0xC9],
+        "NEG" => vec![0xED, 0x44],
+        "RETN" => vec![0xED, 0x45],
+        "RETI" => vec![0xED, 0x4D],
+        "RRD" => vec![0xED, 0x67],
+        "RLD" => vec![0xED, 0x6F],
+        "LDI" => vec![0xED, 0xA0],
+        "CPI" => vec![0xED, 0xA1],
+        "INI" => vec![0xED, 0xA2],
+        "OUTI" => vec![0xED, 0xA3],
+        "LDD" => vec![0xED, 0xA8],
+        "CPD" => vec![0xED, 0xA9],
+        "IND" => vec![0xED, 0xAA],
+        "OUTD" => vec![0xED, 0xAB],
+        "LDIR" => vec![0xED, 0xB0],
+        "CPIR" => vec![0xED, 0xB1],
+        "INIR" => vec![0xED, 0xB2],
+        "OTIR" => vec![0xED, 0xB3],
+        "LDDR" => vec![0xED, 0xB8],
+        "CPDR" => vec![0xED, 0xB9],
+        "INDR" => vec![0xED, 0xBA],
+        "OTDR" => vec![0xED, 0xBB],
+        _ => return None,
+    })
+}
+
+/// Instrucción o directiva (para distinguir etiquetas sin ':')
+fn is_keyword(word: &str) -> bool {
+    const KEYWORDS: [&str; 49] = [
+        "LD", "PUSH", "POP", "EX", "ADD", "ADC", "SUB", "SBC", "AND", "XOR", "OR", "CP",
+        "INC", "DEC", "RLC", "RRC", "RL", "RR", "SLA", "SRA", "SLL", "SLI", "SRL", "BIT",
+        "RES", "SET", "JP", "JR", "DJNZ", "CALL", "RST", "IM", "IN", "OUT",
+        "ORG", "EQU", "DB", "DEFB", "DEFM", "DW", "DEFW", "DS", "DEFS", "DEVICE", "OUTPUT",
+        "SAVEBIN", "END", "DISP", "ENT",
+    ];
+    let w = word.to_ascii_uppercase();
+    KEYWORDS.contains(&w.as_str()) || implied(&w).is_some() || ["BYTE", "WORD", "BLOCK"].contains(&w.as_str())
+}
+
+/* ==================================================
+ * LÉXICO
+ * ================================================== */
+
+/// Quita el comentario (';' fuera de comillas)
+fn strip_comment(line: &str) -> &str {
+    let mut quote = None;
+    for (i, c) in line.char_indices() {
+        match (quote, c) {
+            (None, ';') => return &line[..i],
+            (None, '"') => quote = Some('"'),
+            (None, '\'') if is_char_literal(&line[i..]) => quote = Some('\''),
+            (Some(q), c) if c == q => quote = None,
+            _ => {}
+        }
+    }
+    line
+}
+
+/// 'x' (y no el apóstrofo de AF')
+fn is_char_literal(s: &str) -> bool {
+    let mut chars = s.chars();
+    chars.next() == Some('\'') && chars.next().is_some() && chars.next() == Some('\'')
+}
+
+/// Separa por comas fuera de comillas y paréntesis
+fn split_operands(args: &str) -> Vec<String> {
+    let mut ops = Vec::new();
+    let mut current = String::new();
+    let mut depth = 0;
+    let mut quote = None;
+
+    for (i, c) in args.char_indices() {
+        match (quote, c) {
+            (None, '"') => quote = Some('"'),
+            (None, '\'') if is_char_literal(&args[i..]) => quote = Some('\''),
+            (Some(q), c) if c == q => quote = None,
+            (None, '(') => depth += 1,
+            (None, ')') => depth -= 1,
+            (None, ',') if depth == 0 => {
+                ops.push(current.trim().to_string());
+                current.clear();
+                continue;
+            }
+            _ => {}
+        }
+        current.push(c);
+    }
+    if !current.trim().is_empty() {
+        ops.push(current.trim().to_string());
+    }
+    ops
+}
+
+fn one(ops: &[String]) -> Result<&str, String> {
+    match ops {
+        [op] => Ok(op),
+        _ => Err("Se esperaba un operando".into()),
+    }
+}
+
+/// "texto" (sin las comillas). Un 'x' solo es un número.
+fn string_literal(s: &str) -> Option<String> {
+    let inner = s.strip_prefix('"')?.strip_suffix('"')?;
+    Some(inner.replace("\\\"", "\"").replace("\\\\", "\\"))
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Num(i64),
+    Ident(String),
+    Dollar,
+    Op(&'static str),
+}
+
+fn tokenize(src: &str) -> Result<Vec<Token>, String> {
+    const OPS: [&str; 13] = ["<<", ">>", "+", "-", "*", "/", "%", "&", "|", "^", "~", "(", ")"];
+
+    let chars: Vec<char> = src.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+
+        // 'x'
+        if c == '\'' {
+            if i + 2 < chars.len() && chars[i + 2] == '\'' {
+                tokens.push(Token::Num(chars[i + 1] as i64));
+                i += 3;
+                continue;
+            }
+            return Err(format!("Carácter no válido en: {}", src));
+        }
+
+        // $ / $1F / #1F / %0101
+        if (c == '$' || c == '#' || c == '%') && i + 1 < chars.len() {
+            let radix = if c == '%' { 2 } else { 16 };
+            let digits: String = chars[i + 1..].iter().take_while(|d| d.is_digit(radix)).collect();
+            if !digits.is_empty() {
+                let v = i64::from_str_radix(&digits, radix).map_err(|_| format!("Número no válido: {}", src))?;
+                tokens.push(Token::Num(v));
+                i += 1 + digits.len();
+                continue;
+            }
+        }
+        if c == '$' {
+            tokens.push(Token::Dollar);
+            i += 1;
+            continue;
+        }
+
+        if c.is_ascii_digit() {
+            let word: String = chars[i..].iter().take_while(|d| d.is_ascii_alphanumeric()).collect();
+            i += word.len();
+            tokens.push(Token::Num(parse_number(&word).ok_or(format!("Número no válido: {}", word))?));
+            continue;
+        }
+
+        if c.is_ascii_alphabetic() || c == '_' || c == '.' {
+            let word: String = chars[i..]
+                .iter()
+                .take_while(|d| d.is_ascii_alphanumeric() || **d == '_' || **d == '.' || **d == '?')
+                .collect();
+            i += word.len();
+            tokens.push(Token::Ident(word));
+            continue;
+        }
+
+        let rest: String = chars[i..].iter().take(2).collect();
+        match OPS.iter().find(|op| rest.starts_with(*op)) {
+            Some(op) => {
+                tokens.push(Token::Op(op));
+                i += op.len();
+            }
+            None => return Err(format!("Carácter no válido '{}' en: {}", c, src)),
+        }
+    }
+
+    Ok(tokens)
+}
+
+/// 123, 0x1F, 1Fh, 0101b
+fn parse_number(word: &str) -> Option<i64> {
+    let lower = word.to_ascii_lowercase();
+    if let Some(hex) = lower.strip_prefix("0x") {
+        return i64::from_str_radix(hex, 16).ok();
+    }
+    if let Some(hex) = lower.strip_suffix('h') {
+        return i64::from_str_radix(hex, 16).ok();
+    }
+    if let Some(bin) = lower.strip_suffix('b')
+        && bin.chars().all(|c| c == '0' || c == '1')
+    {
+        return i64::from_str_radix(bin, 2).ok();
+    }
+    lower.parse().ok()
+}
+
+/// Precedencia: | ^ & << >> + - * / % y unarios
+struct ExprParser<'a> {
+    tokens: &'a [Token],
+    pos: usize,
+    asm: &'a Assembler,
+}
+
+impl ExprParser<'_> {
+    fn peek_op(&self, ops: &[&str]) -> Option<&'static str> {
+        match self.tokens.get(self.pos) {
+            Some(Token::Op(op)) if ops.contains(op) => Some(op),
+            _ => None,
+        }
+    }
+
+    fn binary(&mut self, ops: &[&str], next: fn(&mut Self) -> Result<i64, String>) -> Result<i64, String> {
+        let mut v = next(self)?;
+        while let Some(op) = self.peek_op(ops) {
+            self.pos += 1;
+            let r = next(self)?;
+            v = match op {
+                "|" => v | r,
+                "^" => v ^ r,
+                "&" => v & r,
+                "<<" => v << (r & 63),
+                ">>" => v >> (r & 63),
+                "+" => v + r,
+                "-" => v - r,
+                "*" => v * r,
+                "/" | "%" if r == 0 => return Err("División por cero".into()),
+                "/" => v / r,
+                _ => v % r,
+            };
+        }
+        Ok(v)
+    }
+
+    fn or(&mut self) -> Result<i64, String> {
+        self.binary(&["|"], Self::xor)
+    }
+
+    fn xor(&mut self) -> Result<i64, String> {
+        self.binary(&["^"], Self::and)
+    }
+
+    fn and(&mut self) -> Result<i64, String> {
+        self.binary(&["&"], Self::shift)
+    }
+
+    fn shift(&mut self) -> Result<i64, String> {
+        self.binary(&["<<", ">>"], Self::sum)
+    }
+
+    fn sum(&mut self) -> Result<i64, String> {
+        self.binary(&["+", "-"], Self::product)
+    }
+
+    fn product(&mut self) -> Result<i64, String> {
+        self.binary(&["*", "/", "%"], Self::unary)
+    }
+
+    fn unary(&mut self) -> Result<i64, String> {
+        let token = self.tokens.get(self.pos).cloned().ok_or("Expresión incompleta")?;
+        self.pos += 1;
+
+        match token {
+            Token::Num(n) => Ok(n),
+            Token::Dollar => Ok(self.asm.here() as i64),
+            Token::Ident(name) => self.asm.symbol(&name),
+            Token::Op("-") => Ok(-self.unary()?),
+            Token::Op("+") => self.unary(),
+            Token::Op("~") => Ok(!self.unary()?),
+            Token::Op("(") => {
+                let v = self.or()?;
+                match self.tokens.get(self.pos) {
+                    Some(Token::Op(")")) => {
+                        self.pos += 1;
+                        Ok(v)
+                    }
+                    _ => Err("Falta ')'".into()),
+                }
+            }
+            Token::Op(op) => Err(format!("Operador inesperado: {}", op)),
+        }
+    }
+}
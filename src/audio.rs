@@ -0,0 +1,55 @@
+use sdl2::audio::{AudioQueue, AudioSpecDesired};
+use sdl2::Sdl;
+
+use crate::constantes::AUDIO_SAMPLE_RATE;
+
+/* ==================================================
+ * SALIDA DE AUDIO (SDL2 AudioQueue)
+ * ==================================================
+ * Recibe las muestras del beeper al final de cada frame. El tamaño
+ * de la cola sirve además como reloj de sincronización: si hay
+ * demasiado audio pendiente, la emulación va adelantada y se espera.
+ */
+
+pub struct AudioOut {
+    queue: AudioQueue<i16>,
+    freq: u32,
+}
+
+impl AudioOut {
+    /// Abre la salida mono de 16 bits (44,1 kHz por defecto)
+    pub fn new(sdl: &Sdl) -> Result<Self, String> {
+        let audio = sdl.audio()?;
+
+        let desired = AudioSpecDesired {
+            freq: Some(AUDIO_SAMPLE_RATE as i32),
+            channels: Some(1),
+            samples: Some(1024),
+        };
+
+        let queue: AudioQueue<i16> = audio.open_queue(None, &desired)?;
+        let freq = queue.spec().freq as u32;
+        queue.resume();
+
+        Ok(Self { queue, freq })
+    }
+
+    /// Frecuencia real concedida por SDL (44100 o 48000 normalmente)
+    pub fn freq(&self) -> u32 {
+        self.freq
+    }
+
+    pub fn push(&self, samples: &[i16]) -> Result<(), String> {
+        self.queue.queue_audio(samples)
+    }
+
+    /// Milisegundos de audio pendientes de reproducir
+    pub fn queued_ms(&self) -> u32 {
+        let samples = self.queue.size() / std::mem::size_of::<i16>() as u32;
+        samples * 1000 / self.freq
+    }
+
+    pub fn clear(&self) {
+        self.queue.clear();
+    }
+}
@@ -0,0 +1,356 @@
+use crate::constantes::{AUDIO_SAMPLE_RATE, CPU_HZ};
+
+/* ==================================================
+ * AY-3-8912 (128K e interfaces AY del 48K)
+ * ==================================================
+ * Puertos (decodificación parcial, A1 = 0):
+ *   0xFFFD  escritura: selecciona registro / lectura: valor
+ *   0xBFFD  escritura: valor del registro seleccionado
+ *
+ * Registros:
+ *   0-5    periodo de tono A, B, C (12 bits)
+ *   6      periodo de ruido (5 bits)
+ *   7      mezclador: bits 0-2 tono off, bits 3-5 ruido off
+ *   8-10   volumen A, B, C (bit 4 = usar envolvente)
+ *   11-12  periodo de la envolvente (16 bits)
+ *   13     forma de la envolvente (escribirlo la reinicia)
+ *   14-15  puertos de E/S
+ *
+ * El chip va a la mitad del reloj de la CPU. Los tonos cambian de
+ * nivel cada `periodo` ticks de 8 ciclos suyos (un tick = 16
+ * T-states); ruido y envolvente van a la mitad. Cada
+ * escritura hace avanzar el chip hasta su T-state, así que los
+ * cambios caen en su sitio; la salida se promedia por muestra.
+ */
+
+/// T-states por tick de los generadores de tono (reloj AY / 8)
+const TICK_T: u64 = 16;
+
+// Amplitud de un canal a volumen máximo (tres canales + beeper caben en i16)
+const CHANNEL_VOLUME: f32 = 6000.0;
+
+// Filtro paso alto para quitar la continua (la salida del AY es unipolar)
+const DC_BLOCK: f32 = 0.995;
+
+/// DAC logarítmico del AY (16 niveles, normalizado)
+const DAC: [f32; 16] = [
+    0.0, 0.0106, 0.0150, 0.0222, 0.0320, 0.0466, 0.0665, 0.1039,
+    0.1237, 0.1986, 0.2803, 0.3548, 0.4702, 0.5916, 0.7730, 1.0,
+];
+
+/// Bits válidos de cada registro (el resto se lee a 0)
+const REG_MASK: [u8; 16] = [
+    0xFF, 0x0F, 0xFF, 0x0F, 0xFF, 0x0F, 0x1F, 0xFF,
+    0x1F, 0x1F, 0x1F, 0xFF, 0xFF, 0x0F, 0xFF, 0xFF,
+];
+
+pub struct Ay {
+    regs: [u8; 16],
+    selected: u8,
+
+    // Generadores de tono
+    tone_count: [u16; 3],
+    tone_out: [bool; 3],
+
+    // Ruido y envolvente avanzan cada dos ticks
+    prescaler: bool,
+
+    // Ruido: LFSR de 17 bits
+    noise_count: u8,
+    noise_rng: u32,
+
+    // Envolvente
+    env_count: u16,
+    env_step: u8,
+    env_attack: bool,
+    env_holding: bool,
+
+    /// Canales silenciados (depuración de drivers de música)
+    pub muted: [bool; 3],
+
+    // Reloj: T-state absoluto del próximo tick
+    t: u64,
+    sample_rate: u32,
+    cpu_hz: u64,
+
+    // Promedio de la muestra en curso
+    acc: f64,
+    acc_t: f64,
+
+    hp_prev_in: f32,
+    hp_prev_out: f32,
+
+    /// Muestras generadas pendientes de mezclar con el beeper
+    pub samples: Vec<i16>,
+}
+
+impl Ay {
+    pub fn new(sample_rate: u32) -> Self {
+        Self {
+            regs: [0; 16],
+            selected: 0,
+            tone_count: [0; 3],
+            tone_out: [false; 3],
+            prescaler: false,
+            noise_count: 0,
+            noise_rng: 1,
+            env_count: 0,
+            env_step: 0,
+            env_attack: false,
+            env_holding: true,
+            muted: [false; 3],
+            t: 0,
+            sample_rate,
+            cpu_hz: CPU_HZ,
+            acc: 0.0,
+            acc_t: 0.0,
+            hp_prev_in: 0.0,
+            hp_prev_out: 0.0,
+            samples: Vec::new(),
+        }
+    }
+
+    pub fn set_sample_rate(&mut self, sample_rate: u32) {
+        self.sample_rate = sample_rate;
+    }
+
+    /// Reloj de la CPU (el AY va siempre a la mitad)
+    pub fn set_cpu_hz(&mut self, cpu_hz: u64) {
+        self.cpu_hz = cpu_hz;
+    }
+
+    /// Reset del chip: registros a cero y generadores parados
+    pub fn reset(&mut self) {
+        self.regs = [0; 16];
+        self.selected = 0;
+        self.tone_count = [0; 3];
+        self.tone_out = [false; 3];
+        self.noise_count = 0;
+        self.noise_rng = 1;
+        self.env_count = 0;
+        self.env_step = 0;
+        self.env_attack = false;
+        self.env_holding = true;
+    }
+
+    /// Reinicia el reloj del chip en el T-state absoluto `t`
+    pub fn reset_clock(&mut self, t: u64) {
+        self.t = t;
+        self.acc = 0.0;
+        self.acc_t = 0.0;
+    }
+
+    pub fn toggle_mute(&mut self, channel: usize) {
+        self.muted[channel] = !self.muted[channel];
+    }
+
+    pub fn selected_register(&self) -> u8 {
+        self.selected
+    }
+
+    pub fn register(&self, reg: u8) -> u8 {
+        self.regs[(reg & 0x0F) as usize]
+    }
+
+    /* ==================================================
+     * PUERTOS
+     * ================================================== */
+
+    /// OUT a 0xFFFD
+    pub fn select(&mut self, reg: u8) {
+        self.selected = reg;
+    }
+
+    /// IN de 0xFFFD (registros inexistentes, 16-255, leen 0xFF)
+    pub fn read(&self) -> u8 {
+        if self.selected < 16 {
+            self.regs[self.selected as usize]
+        } else {
+            0xFF
+        }
+    }
+
+    /// OUT a 0xBFFD en el T-state absoluto `t`
+    pub fn write(&mut self, t: u64, value: u8) {
+        if self.selected >= 16 {
+            return;
+        }
+
+        // El chip suena con los valores viejos hasta este instante
+        self.run_until(t);
+
+        let reg = self.selected as usize;
+        self.regs[reg] = value & REG_MASK[reg];
+
+        if reg == 13 {
+            // Nueva forma: la envolvente empieza de cero
+            self.env_count = 0;
+            self.env_step = 0;
+            self.env_attack = value & 0x04 != 0;
+            self.env_holding = false;
+        }
+    }
+
+    /// Cierra el frame en el T-state absoluto `t` (deja las muestras en `samples`)
+    pub fn end_frame(&mut self, t: u64) {
+        self.run_until(t);
+    }
+
+    /// Devuelve y vacía hasta `n` muestras generadas
+    pub fn take_samples(&mut self, n: usize) -> Vec<i16> {
+        let n = n.min(self.samples.len());
+        self.samples.drain(..n).collect()
+    }
+
+    /* ==================================================
+     * GENERADORES
+     * ================================================== */
+
+    fn run_until(&mut self, t: u64) {
+        while self.t + TICK_T <= t {
+            let level = self.output();
+            self.accumulate(level, TICK_T as f64);
+            self.tick();
+            self.t += TICK_T;
+        }
+    }
+
+    /// Un tick de los contadores de tono, ruido y envolvente
+    fn tick(&mut self) {
+        for ch in 0..3 {
+            let period = self.tone_period(ch);
+            self.tone_count[ch] += 1;
+            if self.tone_count[ch] >= period {
+                self.tone_count[ch] = 0;
+                self.tone_out[ch] = !self.tone_out[ch];
+            }
+        }
+
+        self.prescaler = !self.prescaler;
+        if !self.prescaler {
+            return;
+        }
+
+        let period = (self.regs[6] & 0x1F).max(1);
+        self.noise_count += 1;
+        if self.noise_count >= period {
+            self.noise_count = 0;
+            let bit = (self.noise_rng ^ (self.noise_rng >> 3)) & 1;
+            self.noise_rng = (self.noise_rng >> 1) | (bit << 16);
+        }
+
+        let period = u16::from_le_bytes([self.regs[11], self.regs[12]]).max(1);
+        self.env_count += 1;
+        if self.env_count >= period {
+            self.env_count = 0;
+            self.step_envelope();
+        }
+    }
+
+    /// Avanza un paso la envolvente (16 pasos por ciclo).
+    /// Forma (registro 13): bit 3 continuar, 2 ataque, 1 alternar, 0 mantener.
+    fn step_envelope(&mut self) {
+        if self.env_holding {
+            return;
+        }
+
+        self.env_step += 1;
+        if self.env_step < 16 {
+            return;
+        }
+
+        let shape = self.regs[13];
+        let cont = shape & 0x08 != 0;
+        let alt = shape & 0x02 != 0;
+        let hold = shape & 0x01 != 0;
+
+        if !cont {
+            // Formas 0-7: un ciclo y silencio
+            self.env_attack = false;
+            self.env_holding = true;
+            self.env_step = 15;
+        } else {
+            if alt {
+                self.env_attack = !self.env_attack;
+            }
+            if hold {
+                self.env_holding = true;
+                self.env_step = 15;
+            } else {
+                self.env_step = 0;
+            }
+        }
+    }
+
+    fn envelope_level(&self) -> u8 {
+        if self.env_attack {
+            self.env_step
+        } else {
+            15 - self.env_step
+        }
+    }
+
+    fn tone_period(&self, ch: usize) -> u16 {
+        let fine = self.regs[ch * 2] as u16;
+        let coarse = (self.regs[ch * 2 + 1] & 0x0F) as u16;
+        ((coarse << 8) | fine).max(1)
+    }
+
+    /// Mezcla de los tres canales (sin filtrar)
+    fn output(&self) -> f32 {
+        let mixer = self.regs[7];
+        let noise = self.noise_rng & 1 != 0;
+
+        (0..3)
+            .filter(|&ch| !self.muted[ch])
+            .map(|ch| {
+                let tone_off = mixer & (1 << ch) != 0;
+                let noise_off = mixer & (8 << ch) != 0;
+                if !((self.tone_out[ch] || tone_off) && (noise || noise_off)) {
+                    return 0.0;
+                }
+
+                let vol = self.regs[8 + ch];
+                let level = if vol & 0x10 != 0 {
+                    self.envelope_level()
+                } else {
+                    vol & 0x0F
+                };
+                DAC[level as usize] * CHANNEL_VOLUME
+            })
+            .sum()
+    }
+
+    /// Suma `level` durante `dur` T-states a la muestra en curso
+    fn accumulate(&mut self, level: f32, mut dur: f64) {
+        let t_per_sample = self.cpu_hz as f64 / self.sample_rate as f64;
+
+        loop {
+            let room = t_per_sample - self.acc_t;
+            if dur < room {
+                self.acc += level as f64 * dur;
+                self.acc_t += dur;
+                return;
+            }
+
+            // Muestra completa
+            self.acc += level as f64 * room;
+            dur -= room;
+
+            let x = (self.acc / t_per_sample) as f32;
+            let out = x - self.hp_prev_in + DC_BLOCK * self.hp_prev_out;
+            self.hp_prev_in = x;
+            self.hp_prev_out = out;
+
+            self.samples.push(out.clamp(i16::MIN as f32, i16::MAX as f32) as i16);
+            self.acc = 0.0;
+            self.acc_t = 0.0;
+        }
+    }
+}
+
+impl Default for Ay {
+    fn default() -> Self {
+        Self::new(AUDIO_SAMPLE_RATE)
+    }
+}
@@ -0,0 +1,210 @@
+use crate::teclado::{char_chords, matrix_key, MatrixKey, CAPS_SHIFT, SYMBOL_SHIFT};
+
+/* ==================================================
+ * TECLEO DE LISTADOS EN EL BASIC DEL 48K
+ * ==================================================
+ * En el editor del 48K las palabras clave no se escriben letra a letra:
+ * salen de una tecla según el modo del cursor.
+ *
+ *   K: al principio de la línea (tras el número), tras ':' y tras THEN.
+ *      Cada letra es una orden (P = PRINT, G = GO TO...)
+ *   L: el resto. Las letras son letras; SYMBOL + tecla da los operadores
+ *      y palabras en rojo (THEN, TO, STEP, AND, <>...)
+ *   E: CAPS + SYMBOL, una sola tecla: funciones (SIN, CHR$...) y, con
+ *      SYMBOL, las de debajo de las teclas (BEEP, INK, DEF FN...)
+ *
+ * Un listado en texto se convierte en las teclas que lo escriben
+ * siguiendo el modo: las palabras clave se buscan (la más larga) donde
+ * empieza una palabra, fuera de las cadenas y de los REM, y los espacios
+ * de alrededor se quitan porque la ROM pone los suyos.
+ */
+
+/// Cómo se teclea una palabra clave
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Entry {
+    /// Letra en modo K
+    K(u8),
+    /// SYMBOL + tecla
+    Sym(u8),
+    /// Modo E y tecla
+    Ext(u8),
+    /// Modo E y SYMBOL + tecla
+    ExtSym(u8),
+}
+
+use Entry::{Ext, ExtSym, Sym, K};
+
+/// Las 91 palabras clave del 48K. Un espacio en el nombre vale por
+/// cualquier número de espacios ("GO TO", "GOTO")
+const KEYWORDS: [(&str, Entry); 91] = [
+    ("NEW", K(b'A')), ("BORDER", K(b'B')), ("CONTINUE", K(b'C')), ("DIM", K(b'D')),
+    ("REM", K(b'E')), ("FOR", K(b'F')), ("GO TO", K(b'G')), ("GO SUB", K(b'H')),
+    ("INPUT", K(b'I')), ("LOAD", K(b'J')), ("LIST", K(b'K')), ("LET", K(b'L')),
+    ("PAUSE", K(b'M')), ("NEXT", K(b'N')), ("POKE", K(b'O')), ("PRINT", K(b'P')),
+    ("PLOT", K(b'Q')), ("RUN", K(b'R')), ("SAVE", K(b'S')), ("RANDOMIZE", K(b'T')),
+    ("IF", K(b'U')), ("CLS", K(b'V')), ("DRAW", K(b'W')), ("CLEAR", K(b'X')),
+    ("RETURN", K(b'Y')), ("COPY", K(b'Z')),
+
+    ("STOP", Sym(b'A')), ("STEP", Sym(b'D')), (">=", Sym(b'E')), ("TO", Sym(b'F')),
+    ("THEN", Sym(b'G')), ("AT", Sym(b'I')), ("<=", Sym(b'Q')), ("NOT", Sym(b'S')),
+    ("OR", Sym(b'U')), ("<>", Sym(b'W')), ("AND", Sym(b'Y')),
+
+    ("READ", Ext(b'A')), ("BIN", Ext(b'B')), ("LPRINT", Ext(b'C')), ("DATA", Ext(b'D')),
+    ("TAN", Ext(b'E')), ("SGN", Ext(b'F')), ("ABS", Ext(b'G')), ("SQR", Ext(b'H')),
+    ("CODE", Ext(b'I')), ("VAL", Ext(b'J')), ("LEN", Ext(b'K')), ("USR", Ext(b'L')),
+    ("PI", Ext(b'M')), ("INKEY$", Ext(b'N')), ("PEEK", Ext(b'O')), ("TAB", Ext(b'P')),
+    ("SIN", Ext(b'Q')), ("INT", Ext(b'R')), ("RESTORE", Ext(b'S')), ("RND", Ext(b'T')),
+    ("CHR$", Ext(b'U')), ("LLIST", Ext(b'V')), ("COS", Ext(b'W')), ("EXP", Ext(b'X')),
+    ("STR$", Ext(b'Y')), ("LN", Ext(b'Z')),
+
+    ("BRIGHT", ExtSym(b'B')), ("PAPER", ExtSym(b'C')), ("ATN", ExtSym(b'E')),
+    ("CIRCLE", ExtSym(b'H')), ("IN", ExtSym(b'I')), ("VAL$", ExtSym(b'J')),
+    ("SCREEN$", ExtSym(b'K')), ("ATTR", ExtSym(b'L')), ("INVERSE", ExtSym(b'M')),
+    ("OVER", ExtSym(b'N')), ("OUT", ExtSym(b'O')), ("ASN", ExtSym(b'Q')),
+    ("VERIFY", ExtSym(b'R')), ("MERGE", ExtSym(b'T')), ("FLASH", ExtSym(b'V')),
+    ("ACS", ExtSym(b'W')), ("INK", ExtSym(b'X')), ("BEEP", ExtSym(b'Z')),
+    ("DEF FN", ExtSym(b'1')), ("FN", ExtSym(b'2')), ("LINE", ExtSym(b'3')),
+    ("OPEN #", ExtSym(b'4')), ("CLOSE #", ExtSym(b'5')), ("MOVE", ExtSym(b'6')),
+    ("ERASE", ExtSym(b'7')), ("POINT", ExtSym(b'8')), ("CAT", ExtSym(b'9')),
+    ("FORMAT", ExtSym(b'0')),
+];
+
+/// Lo que se teclea: un carácter o una palabra clave
+#[derive(Copy, Clone)]
+enum Item {
+    Char(char),
+    Keyword(Entry),
+}
+
+/// Longitud de `name` al principio de `text` (sin distinguir mayúsculas;
+/// un espacio del nombre admite cero o más espacios)
+fn match_len(text: &[char], name: &str) -> Option<usize> {
+    let mut i = 0;
+    for n in name.chars() {
+        if n == ' ' {
+            while text.get(i) == Some(&' ') {
+                i += 1;
+            }
+        } else if text.get(i).is_some_and(|c| c.eq_ignore_ascii_case(&n)) {
+            i += 1;
+        } else {
+            return None;
+        }
+    }
+    Some(i)
+}
+
+/// Palabra clave más larga que empieza en `pos`; las órdenes de modo K
+/// solo si `k_mode`
+fn keyword_at(line: &[char], pos: usize, k_mode: bool) -> Option<(usize, &'static str, Entry)> {
+    let word = |c: &char| c.is_ascii_alphanumeric() || *c == '$';
+    let starts_word = pos == 0 || !word(&line[pos - 1]);
+
+    KEYWORDS
+        .iter()
+        .filter(|(_, e)| k_mode || !matches!(e, K(_)))
+        .filter_map(|&(name, entry)| {
+            let len = match_len(&line[pos..], name)?;
+            let alpha = name.starts_with(|c: char| c.is_ascii_alphabetic());
+            let ends_alpha = name.ends_with(|c: char| c.is_ascii_alphabetic());
+            // Las de letras no pueden estar dentro de otra palabra (en modo K
+            // pueden ir pegadas al número de línea)
+            if alpha && !starts_word && !k_mode || ends_alpha && line.get(pos + len).is_some_and(word) {
+                return None;
+            }
+            Some((len, name, entry))
+        })
+        .max_by_key(|(len, _, _)| *len)
+}
+
+/// Una línea del listado, siguiendo el modo del cursor
+fn line_items(line: &[char]) -> Vec<Item> {
+    let mut items = Vec::new();
+    let mut k_mode = true;
+    let mut line_number = true;
+    let mut in_string = false;
+    let mut rem = false;
+    // Espacios pendientes: se quitan si van junto a una palabra clave
+    let mut spaces = 0;
+    let mut after_keyword = false;
+
+    let mut pos = 0;
+    while pos < line.len() {
+        let c = line[pos];
+
+        if in_string || rem {
+            items.push(Item::Char(c));
+            in_string &= c != '"';
+            pos += 1;
+            continue;
+        }
+
+        if c == ' ' {
+            if !k_mode && !after_keyword {
+                spaces += 1;
+            }
+            pos += 1;
+            continue;
+        }
+
+        if let Some((len, name, entry)) = keyword_at(line, pos, k_mode) {
+            items.push(Item::Keyword(entry));
+            spaces = 0;
+            after_keyword = true;
+            line_number = false;
+            rem = name == "REM";
+            k_mode = name == "THEN";
+            pos += len;
+            // Tras REM el resto es texto (sin los espacios del principio)
+            while rem && line.get(pos) == Some(&' ') {
+                pos += 1;
+            }
+            continue;
+        }
+
+        items.extend(std::iter::repeat_n(Item::Char(' '), spaces));
+        spaces = 0;
+        after_keyword = false;
+        items.push(Item::Char(c));
+        in_string = c == '"';
+
+        // El número de línea no cambia el modo K
+        line_number &= c.is_ascii_digit();
+        k_mode = c == ':' || k_mode && line_number;
+        pos += 1;
+    }
+
+    items
+}
+
+/// Combinaciones que teclean `text` en el editor del 48K; cada '\n' es un
+/// ENTER y los caracteres sin tecla se ignoran
+pub fn keyword_chords(text: &str) -> Vec<Vec<MatrixKey>> {
+    let key = |k: u8| matrix_key(k).unwrap();
+    let mut chords = Vec::new();
+
+    let lines: Vec<&str> = text.split('\n').collect();
+    for (i, line) in lines.iter().enumerate() {
+        let line: Vec<char> = line.trim_end_matches('\r').chars().collect();
+        for item in line_items(&line) {
+            match item {
+                Item::Char(c) => chords.extend(char_chords(c).unwrap_or_default()),
+                Item::Keyword(K(k)) => chords.push(vec![key(k)]),
+                Item::Keyword(Sym(k)) => chords.push(vec![SYMBOL_SHIFT, key(k)]),
+                Item::Keyword(Ext(k)) => {
+                    chords.push(vec![CAPS_SHIFT, SYMBOL_SHIFT]);
+                    chords.push(vec![key(k)]);
+                }
+                Item::Keyword(ExtSym(k)) => {
+                    chords.push(vec![CAPS_SHIFT, SYMBOL_SHIFT]);
+                    chords.push(vec![SYMBOL_SHIFT, key(k)]);
+                }
+            }
+        }
+        if i + 1 < lines.len() {
+            chords.push(vec![key(b'\n')]);
+        }
+    }
+
+    chords
+}
@@ -0,0 +1,301 @@
+use std::path::{Path, PathBuf};
+use crate::breakpoints::{parse_address, Condition};
+use crate::machine::model::MachineType;
+use crate::machine::zx_machine::ZxMachine;
+use crate::video::ScreenMemory;
+
+/* ==================================================
+ * EJECUCIÓN SIN VENTANA (PRUEBAS AUTOMÁTICAS)
+ * ==================================================
+ * Carga un programa, lo corre hasta un PC, un HALT o un número
+ * de frames y comprueba:
+ *   - condiciones como las de los breakpoints ("A==0x12 && (HL)==3")
+ *   - bloques de memoria ("4000=01,02,FF")
+ *   - el hash de la pantalla (6912 bytes de píxeles y atributos)
+ *
+ * Lo usan los tests de cargo y el binario zx-batch.
+ */
+
+pub const BATCH_USAGE: &str = "\
+Uso: zx-batch [opciones] <fichero>
+
+  --rom <fichero>          ROM (por defecto ninguna: memoria a cero)
+  --machine <48k|128k>     modelo emulado (48k por defecto)
+  --org <dir>              cargar el fichero sin cabecera en esa dirección
+  --frames <n>             frames como máximo (50 por defecto)
+  --until-pc <dir>         parar al llegar a esa dirección
+  --until-halt             parar en un HALT
+  --expect <cond>          condición como las de los breakpoints (se puede repetir)
+  --expect-mem <dir=b,..>  bytes en memoria (se puede repetir)
+  --expect-screen <hash>   hash de la pantalla
+  --print-screen-hash      mostrar el hash de la pantalla al terminar
+
+Sale con 0 si todo se cumple, 1 si algo falla y 2 si hay un error.";
+
+pub const BATCH_FRAMES_DEFAULT: u64 = 50;
+
+/// Dónde parar antes de agotar los frames
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Until {
+    Pc(u16),
+    Halt,
+}
+
+/// Por qué terminó la ejecución
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Stop {
+    Pc(u16),
+    Halt,
+    Frames(u64),
+    /// El PC ha llegado a 0xFFFF (el core no da la vuelta)
+    PcOverflow,
+}
+
+/// Corre la máquina instrucción a instrucción hasta `until` o
+/// hasta completar `max_frames` frames
+pub fn run(m: &mut ZxMachine, max_frames: u64, until: Option<Until>) -> Stop {
+    let last_frame = m.frames + max_frames;
+
+    loop {
+        match until {
+            Some(Until::Pc(pc)) if m.cpu.reg.pc == pc => return Stop::Pc(pc),
+            Some(Until::Halt) if m.run_state.halted => return Stop::Halt,
+            _ => {}
+        }
+        if m.frames >= last_frame {
+            return Stop::Frames(max_frames);
+        }
+        if m.cpu.reg.pc == 0xFFFF {
+            return Stop::PcOverflow;
+        }
+        m.step_once();
+    }
+}
+
+/// Comprobación sobre el estado final
+pub enum Expect {
+    Condition(Condition),
+    Memory(u16, Vec<u8>),
+    ScreenHash(u64),
+}
+
+impl Expect {
+    /// Condición de breakpoint
+    pub fn condition(src: &str) -> Result<Self, String> {
+        Ok(Expect::Condition(Condition::parse(src)?))
+    }
+
+    /// "4000=01,02,FF" (bytes en hexadecimal)
+    pub fn memory(src: &str) -> Result<Self, String> {
+        let bad = || format!("Memoria no válida: {} (dir=byte,byte...)", src);
+        let (addr, bytes) = src.split_once('=').ok_or_else(bad)?;
+        let addr = parse_address(addr.trim()).ok_or_else(bad)?;
+        let bytes = bytes
+            .split(',')
+            .map(|b| parse_address(b.trim()).filter(|v| *v <= 0xFF).map(|v| v as u8))
+            .collect::<Option<Vec<u8>>>()
+            .ok_or_else(bad)?;
+
+        Ok(Expect::Memory(addr, bytes))
+    }
+
+    /// Hash en hexadecimal (el que imprime `screen_hash`)
+    pub fn screen(src: &str) -> Result<Self, String> {
+        let hex = src.trim().trim_start_matches("0x");
+        u64::from_str_radix(hex, 16)
+            .map(Expect::ScreenHash)
+            .map_err(|_| format!("Hash no válido: {}", src))
+    }
+
+    pub fn check(&self, m: &ZxMachine) -> Result<(), String> {
+        match self {
+            Expect::Condition(c) if c.eval(&m.cpu) => Ok(()),
+            Expect::Condition(c) => Err(format!("No se cumple: {}", c.source)),
+
+            Expect::Memory(addr, bytes) => {
+                let found: Vec<u8> = (0..bytes.len())
+                    .map(|i| m.cpu.bus.read_byte(addr.wrapping_add(i as u16)))
+                    .collect();
+                if found == *bytes {
+                    Ok(())
+                } else {
+                    Err(format!("Memoria en {:04X}: {} (se esperaba {})", addr, hex_bytes(&found), hex_bytes(bytes)))
+                }
+            }
+
+            Expect::ScreenHash(hash) => {
+                let found = screen_hash(m);
+                if found == *hash {
+                    Ok(())
+                } else {
+                    Err(format!("Pantalla: hash {:016X} (se esperaba {:016X})", found, hash))
+                }
+            }
+        }
+    }
+}
+
+/// Resultado de una ejecución con sus comprobaciones
+pub struct BatchResult {
+    pub stop: Stop,
+    pub failures: Vec<String>,
+}
+
+impl BatchResult {
+    pub fn passed(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+/// Corre y comprueba todo. Un `until` que no se alcanza es un fallo.
+pub fn run_and_check(m: &mut ZxMachine, max_frames: u64, until: Option<Until>, expects: &[Expect]) -> BatchResult {
+    let stop = run(m, max_frames, until);
+
+    let mut failures = Vec::new();
+    match (until, stop) {
+        (Some(u), Stop::Frames(n)) => {
+            failures.push(format!("No se llegó a {} en {} frames", describe_until(u), n));
+        }
+        (_, Stop::PcOverflow) => failures.push("PC en FFFF: ejecución detenida".to_string()),
+        _ => {}
+    }
+    failures.extend(expects.iter().filter_map(|e| e.check(m).err()));
+
+    BatchResult { stop, failures }
+}
+
+fn describe_until(u: Until) -> String {
+    match u {
+        Until::Pc(pc) => format!("PC={:04X}", pc),
+        Until::Halt => "HALT".to_string(),
+    }
+}
+
+/// Registros en una línea (para los informes)
+pub fn registers(m: &ZxMachine) -> String {
+    let r = &m.cpu.reg;
+    format!(
+        "PC={:04X} SP={:04X} AF={:04X} BC={:04X} DE={:04X} HL={:04X} IX={:04X} IY={:04X} T={}",
+        r.pc, r.sp, r.get_af(), r.get_bc(), r.get_de(), r.get_hl(), r.get_ix(), r.get_iy(),
+        m.run_state.t_states
+    )
+}
+
+/// FNV-1a de los 6912 bytes de la pantalla visible (en el 128K, la
+/// pantalla que esté mostrando la ULA)
+pub fn screen_hash(m: &ZxMachine) -> u64 {
+    match &m.bus.mem128 {
+        Some(mem) => fnv1a(&mem.screen(&m.cpu.bus)),
+        None => fnv1a(&m.cpu.bus),
+    }
+}
+
+fn fnv1a<M: ScreenMemory + ?Sized>(mem: &M) -> u64 {
+    let mut h = 0xCBF2_9CE4_8422_2325u64;
+    for offset in 0..6912 {
+        h ^= mem.screen_byte(offset) as u64;
+        h = h.wrapping_mul(0x0000_0100_0000_01B3);
+    }
+    h
+}
+
+fn hex_bytes(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02X}", b)).collect::<Vec<_>>().join(",")
+}
+
+/* ==================================================
+ * OPCIONES DEL BINARIO zx-batch
+ * ================================================== */
+
+pub struct BatchOptions {
+    pub file: PathBuf,
+    pub rom: Option<PathBuf>,
+    pub machine: MachineType,
+    pub org: Option<u16>,
+    pub frames: u64,
+    pub until: Option<Until>,
+    pub expects: Vec<Expect>,
+    pub print_screen_hash: bool,
+}
+
+impl BatchOptions {
+    /// Argumentos sin el nombre del programa
+    pub fn parse<I, S>(args: I) -> Result<Self, String>
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let mut file = None;
+        let mut o = BatchOptions {
+            file: PathBuf::new(),
+            rom: None,
+            machine: MachineType::Spectrum48K,
+            org: None,
+            frames: BATCH_FRAMES_DEFAULT,
+            until: None,
+            expects: Vec::new(),
+            print_screen_hash: false,
+        };
+        let mut args = args.into_iter().map(Into::into);
+
+        while let Some(arg) = args.next() {
+            if !arg.starts_with("--") {
+                if file.is_some() {
+                    return Err(format!("Sobra el argumento: {}", arg));
+                }
+                file = Some(PathBuf::from(arg));
+                continue;
+            }
+
+            let mut value = || args.next().ok_or(format!("Falta el valor de {}", arg));
+            let address = |v: String| parse_address(&v).ok_or(format!("Dirección no válida: {}", v));
+
+            match arg.as_str() {
+                "--rom" => o.rom = Some(PathBuf::from(value()?)),
+                "--machine" => {
+                    let v = value()?;
+                    o.machine = MachineType::parse(&v).ok_or(format!("Modelo no válido: {} (48k o 128k)", v))?;
+                }
+                "--org" => o.org = Some(address(value()?)?),
+                "--frames" => {
+                    let v = value()?;
+                    o.frames = v.parse().map_err(|_| format!("Número de frames no válido: {}", v))?;
+                }
+                "--until-pc" => o.until = Some(Until::Pc(address(value()?)?)),
+                "--until-halt" => o.until = Some(Until::Halt),
+                "--expect" => o.expects.push(Expect::condition(&value()?)?),
+                "--expect-mem" => o.expects.push(Expect::memory(&value()?)?),
+                "--expect-screen" => o.expects.push(Expect::screen(&value()?)?),
+                "--print-screen-hash" => o.print_screen_hash = true,
+                _ => return Err(format!("Opción desconocida: {}", arg)),
+            }
+        }
+
+        o.file = file.ok_or("Falta el fichero")?;
+        Ok(o)
+    }
+
+    /// Máquina con el programa cargado
+    pub fn build_machine(&self) -> Result<ZxMachine, String> {
+        let mut builder = ZxMachine::builder().machine(self.machine);
+        if let Some(rom) = &self.rom {
+            builder = builder.rom(std::fs::read(rom).map_err(|e| format!("{}: {}", rom.display(), e))?);
+        }
+        let mut m = builder.build()?;
+        load(&mut m, &self.file, self.org)?;
+        Ok(m)
+    }
+}
+
+/// Carga `path` según su extensión; un .bin con `org` va sin cabecera
+/// a esa dirección
+pub fn load(m: &mut ZxMachine, path: &Path, org: Option<u16>) -> Result<(), String> {
+    match org {
+        Some(org) => {
+            let data = std::fs::read(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+            m.load_bytes(&data, org);
+            Ok(())
+        }
+        None => m.load_file(path),
+    }
+}
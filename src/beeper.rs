@@ -0,0 +1,179 @@
+use crate::constantes::{AUDIO_SAMPLE_RATE, CPU_HZ};
+
+/* ==================================================
+ * BEEPER (BIT 4 DEL PUERTO 0xFE)
+ * ==================================================
+ * Se registran los cambios de nivel del altavoz con su T-state y al
+ * final de cada frame se convierten a muestras con síntesis limitada
+ * en banda: cada cambio suma un impulso sinc (con ventana) en un
+ * buffer de deltas que luego se integra. Así una onda cuadrada no
+ * genera aliasing aunque sus flancos caigan entre dos muestras.
+ */
+
+// Fases del kernel (resolución sub-muestra) y ancho en muestras
+const PHASES: usize = 32;
+const WIDTH: usize = 16;
+
+// Amplitud de salida de un flanco completo
+const VOLUME: f32 = 8000.0;
+
+// Filtro paso alto para quitar la continua (altavoz en reposo = silencio)
+const DC_BLOCK: f32 = 0.995;
+
+pub struct Beeper {
+    sample_rate: u32,
+    cpu_hz: u64,
+    level: bool,
+
+    // kernel[fase][i]: impulso limitado en banda, suma 1
+    kernel: Vec<[f32; WIDTH]>,
+    // Deltas pendientes; índice 0 = primera muestra aún no emitida
+    deltas: Vec<f32>,
+
+    // T-state absoluto del inicio del frame y su posición en muestras
+    frame_start: u64,
+    sample_offset: f64,
+
+    integrator: f32,
+    hp_prev_in: f32,
+    hp_prev_out: f32,
+
+    /// Muestras generadas pendientes de enviar a la salida de audio
+    pub samples: Vec<i16>,
+}
+
+impl Beeper {
+    pub fn new(sample_rate: u32) -> Self {
+        Self {
+            sample_rate,
+            cpu_hz: CPU_HZ,
+            level: false,
+            kernel: build_kernel(),
+            deltas: vec![0.0; WIDTH],
+            frame_start: 0,
+            sample_offset: 0.0,
+            integrator: 0.0,
+            hp_prev_in: 0.0,
+            hp_prev_out: 0.0,
+            samples: Vec::new(),
+        }
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// Cambia la frecuencia de salida (p. ej. la que conceda SDL)
+    pub fn set_sample_rate(&mut self, sample_rate: u32) {
+        self.sample_rate = sample_rate;
+    }
+
+    /// Reloj de la CPU (el del 128K es algo más rápido que el del 48K)
+    pub fn set_cpu_hz(&mut self, cpu_hz: u64) {
+        self.cpu_hz = cpu_hz;
+    }
+
+    /// Reinicia el reloj del beeper en el T-state absoluto `t`
+    pub fn reset(&mut self, t: u64) {
+        self.frame_start = t;
+        self.sample_offset = 0.0;
+        self.deltas.iter_mut().for_each(|d| *d = 0.0);
+    }
+
+    /// Nivel del altavoz en el T-state absoluto `t`
+    pub fn set_level(&mut self, t: u64, level: bool) {
+        if level == self.level {
+            return;
+        }
+        self.level = level;
+
+        let delta = if level { VOLUME } else { -VOLUME };
+        let pos = self.sample_pos(t);
+        let index = pos.floor() as usize;
+        let phase = ((pos - pos.floor()) * PHASES as f64) as usize;
+
+        if self.deltas.len() < index + WIDTH {
+            self.deltas.resize(index + WIDTH, 0.0);
+        }
+        for (i, k) in self.kernel[phase.min(PHASES - 1)].iter().enumerate() {
+            self.deltas[index + i] += delta * k;
+        }
+    }
+
+    /// Cierra el frame en el T-state absoluto `t`: genera las muestras
+    /// completas hasta ese instante y las deja en `samples`.
+    pub fn end_frame(&mut self, t: u64) {
+        let end = self.sample_pos(t);
+        let count = end.floor() as usize;
+
+        if self.deltas.len() < count + WIDTH {
+            self.deltas.resize(count + WIDTH, 0.0);
+        }
+
+        for i in 0..count {
+            self.integrator += self.deltas[i];
+
+            let out = self.integrator - self.hp_prev_in + DC_BLOCK * self.hp_prev_out;
+            self.hp_prev_in = self.integrator;
+            self.hp_prev_out = out;
+
+            self.samples.push(out.clamp(i16::MIN as f32, i16::MAX as f32) as i16);
+        }
+
+        self.deltas.drain(..count);
+        self.sample_offset = end - count as f64;
+        self.frame_start = t;
+    }
+
+    /// Devuelve y vacía las muestras generadas
+    pub fn take_samples(&mut self) -> Vec<i16> {
+        std::mem::take(&mut self.samples)
+    }
+
+    /// Posición (en muestras, desde la primera pendiente) del T-state `t`
+    fn sample_pos(&self, t: u64) -> f64 {
+        let dt = t.saturating_sub(self.frame_start) as f64;
+        self.sample_offset + dt * self.sample_rate as f64 / self.cpu_hz as f64
+    }
+}
+
+impl Default for Beeper {
+    fn default() -> Self {
+        Self::new(AUDIO_SAMPLE_RATE)
+    }
+}
+
+/// Sinc con ventana Blackman, corte al 90% de Nyquist, una fila por fase
+fn build_kernel() -> Vec<[f32; WIDTH]> {
+    let cutoff = 0.9;
+    let half = WIDTH as f64 / 2.0;
+
+    (0..PHASES)
+        .map(|p| {
+            let frac = p as f64 / PHASES as f64;
+            let mut row = [0f32; WIDTH];
+            let mut sum = 0.0;
+
+            for (i, v) in row.iter_mut().enumerate() {
+                let x = i as f64 - (half - 1.0) - frac;
+                let sinc = if x == 0.0 {
+                    1.0
+                } else {
+                    let a = std::f64::consts::PI * cutoff * x;
+                    a.sin() / a
+                };
+                let w = if x.abs() >= half {
+                    0.0
+                } else {
+                    let n = std::f64::consts::PI * x / half;
+                    0.42 + 0.5 * n.cos() + 0.08 * (2.0 * n).cos()
+                };
+                *v = (sinc * w) as f32;
+                sum += sinc * w;
+            }
+
+            row.iter_mut().for_each(|v| *v /= sum as f32);
+            row
+        })
+        .collect()
+}
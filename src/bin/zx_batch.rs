@@ -0,0 +1,38 @@
+use std::process::ExitCode;
+
+use zx::batch::{registers, run_and_check, screen_hash, BatchOptions, BATCH_USAGE};
+
+fn main() -> ExitCode {
+    let opts = match BatchOptions::parse(std::env::args().skip(1)) {
+        Ok(o) => o,
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, BATCH_USAGE);
+            return ExitCode::from(2);
+        }
+    };
+
+    let mut machine = match opts.build_machine() {
+        Ok(m) => m,
+        Err(e) => {
+            eprintln!("{}", e);
+            return ExitCode::from(2);
+        }
+    };
+
+    let result = run_and_check(&mut machine, opts.frames, opts.until, &opts.expects);
+
+    println!("{:?}: {}", result.stop, registers(&machine));
+    if opts.print_screen_hash {
+        println!("Pantalla: {:016X}", screen_hash(&machine));
+    }
+
+    if result.passed() {
+        println!("OK {}", opts.file.display());
+        ExitCode::SUCCESS
+    } else {
+        for f in &result.failures {
+            println!("FALLO: {}", f);
+        }
+        ExitCode::FAILURE
+    }
+}
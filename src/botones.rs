@@ -1,13 +1,32 @@
 #[derive(Copy, Clone, PartialEq, Eq)]
 pub enum ButtonAction {
     Step,
+    StepOver,
+    StepOut,
+    RunToCursor,
+    StepBack,
+    RunBack,
     Run,
     RunFast,
     Pause,
     Reset,
     HwReset,
     Load,
+    Save,
     DebugToggle,
+    TapePlay,
+    TapeStop,
+    TapeRewind,
+    MachineToggle,
+    MuteA,
+    MuteB,
+    MuteC,
+    ContentionToggle,
+    Console,
+    Nmi,
+    JoystickToggle,
+    KeyMappingToggle,
+    Paste,
 }
 
 pub struct Button {
@@ -33,11 +52,50 @@ pub fn default_buttons() -> Vec<Button> {
         Button { x: 400, y: 10, w: 80, h: 30, action: ButtonAction::Reset },
         Button { x: 490, y: 10, w: 80, h: 30, action: ButtonAction::HwReset, },
 
+        // Cinta
+        Button { x: 600, y: 10, w: 80, h: 30, action: ButtonAction::TapePlay },
+        Button { x: 690, y: 10, w: 80, h: 30, action: ButtonAction::TapeStop },
+        Button { x: 780, y: 10, w: 80, h: 30, action: ButtonAction::TapeRewind },
+
+        // Modelo (48K <-> 128K)
+        Button { x: 890, y: 10, w: 100, h: 30, action: ButtonAction::MachineToggle },
+
+        // AY: silenciar canales
+        Button { x: 1000, y: 10, w: 70, h: 30, action: ButtonAction::MuteA },
+        Button { x: 1080, y: 10, w: 70, h: 30, action: ButtonAction::MuteB },
+        Button { x: 1160, y: 10, w: 70, h: 30, action: ButtonAction::MuteC },
+
+        // Contención ON/OFF
+        Button { x: 1240, y: 10, w: 70, h: 30, action: ButtonAction::ContentionToggle },
+
         // Fila inferior (carga)
         //Button { x: 10, y: 50, w: 120, h: 30, action: ButtonAction::LoadRom },
         //Button { x: 140, y: 50, w: 120, h: 30, action: ButtonAction::LoadSna },
 
         Button { x: 10, y: 50, w: 120, h: 30, action: ButtonAction::Load },
         Button { x: 140, y: 50, w: 80, h: 30, action: ButtonAction::DebugToggle },
+        Button { x: 230, y: 50, w: 80, h: 30, action: ButtonAction::Save },
+        Button { x: 1240, y: 50, w: 70, h: 30, action: ButtonAction::Console },
+
+        // Step over / step out / run to cursor
+        Button { x: 1330, y: 10, w: 70, h: 30, action: ButtonAction::StepOver },
+        Button { x: 1410, y: 10, w: 70, h: 30, action: ButtonAction::StepOut },
+        Button { x: 1490, y: 10, w: 70, h: 30, action: ButtonAction::RunToCursor },
+
+        // Hacia atrás: step back / run back
+        Button { x: 1570, y: 10, w: 70, h: 30, action: ButtonAction::StepBack },
+        Button { x: 1650, y: 10, w: 70, h: 30, action: ButtonAction::RunBack },
+
+        // Interrupción no enmascarable
+        Button { x: 1730, y: 10, w: 70, h: 30, action: ButtonAction::Nmi },
+
+        // Interfaz de joystick (ninguno → Kempston → Sinclair 1 → ...)
+        Button { x: 1810, y: 10, w: 70, h: 30, action: ButtonAction::JoystickToggle },
+
+        // Teclado posicional / simbólico
+        Button { x: 1890, y: 10, w: 70, h: 30, action: ButtonAction::KeyMappingToggle },
+
+        // Teclear el texto del portapapeles
+        Button { x: 1970, y: 10, w: 70, h: 30, action: ButtonAction::Paste },
     ]
 }
\ No newline at end of file
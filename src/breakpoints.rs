@@ -0,0 +1,568 @@
+use zilog_z80::cpu::CPU;
+
+/* ==================================================
+ * BREAKPOINTS
+ * ==================================================
+ * Lista de breakpoints de PC con:
+ *   - activar / desactivar sin borrarlos
+ *   - contador de veces que se han alcanzado
+ *   - temporales (se borran al saltar la primera vez)
+ *   - condición opcional sobre registros y memoria:
+ *       A==0x20 && (HL)!=0
+ *       BC>=$4000 || (IX+5)==20h
+ *
+ * Se manejan con comandos de texto (consola del debugger) y se
+ * guardan junto al programa cargado en un fichero .bp (con los
+ * watchpoints).
+ */
+
+pub struct Breakpoint {
+    pub addr: u16,
+    pub enabled: bool,
+    pub hits: u32,
+    /// Se borra al saltar
+    pub temporary: bool,
+    pub condition: Option<Condition>,
+}
+
+impl Breakpoint {
+    /// Línea de la lista: "1 * 8000 (3) T if A==0x20"
+    pub fn describe(&self, n: usize) -> String {
+        let mut s = format!(
+            "{} {} {:04X} ({})",
+            n,
+            if self.enabled { '*' } else { ' ' },
+            self.addr,
+            self.hits
+        );
+        if self.temporary {
+            s.push_str(" T");
+        }
+        if let Some(c) = &self.condition {
+            s.push_str(" if ");
+            s.push_str(&c.source);
+        }
+        s
+    }
+}
+
+#[derive(Default)]
+pub struct Breakpoints {
+    list: Vec<Breakpoint>,
+}
+
+impl Breakpoints {
+    pub fn new() -> Self {
+        Self { list: Vec::new() }
+    }
+
+    pub fn list(&self) -> &[Breakpoint] {
+        &self.list
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.list.is_empty()
+    }
+
+    /// ¿Hay algún breakpoint (activo o no) en `addr`?
+    pub fn has(&self, addr: u16) -> bool {
+        self.list.iter().any(|b| b.addr == addr)
+    }
+
+    /// Añade un breakpoint; devuelve su número (desde 1)
+    pub fn add(&mut self, addr: u16, condition: Option<&str>, temporary: bool) -> Result<usize, String> {
+        let condition = condition.map(Condition::parse).transpose()?;
+
+        self.list.push(Breakpoint {
+            addr,
+            enabled: true,
+            hits: 0,
+            temporary,
+            condition,
+        });
+        Ok(self.list.len())
+    }
+
+    /// Borra el breakpoint `n` (desde 1)
+    pub fn remove(&mut self, n: usize) -> Result<(), String> {
+        self.index(n)?;
+        self.list.remove(n - 1);
+        Ok(())
+    }
+
+    pub fn clear(&mut self) {
+        self.list.clear();
+    }
+
+    pub fn set_enabled(&mut self, n: usize, enabled: bool) -> Result<(), String> {
+        let i = self.index(n)?;
+        self.list[i].enabled = enabled;
+        Ok(())
+    }
+
+    /// Pone o quita un breakpoint simple en `addr` (tecla rápida)
+    pub fn toggle_at(&mut self, addr: u16) {
+        if self.has(addr) {
+            self.list.retain(|b| b.addr != addr);
+        } else {
+            self.list.push(Breakpoint {
+                addr,
+                enabled: true,
+                hits: 0,
+                temporary: false,
+                condition: None,
+            });
+        }
+    }
+
+    fn index(&self, n: usize) -> Result<usize, String> {
+        if n == 0 || n > self.list.len() {
+            Err(format!("No existe el breakpoint {}", n))
+        } else {
+            Ok(n - 1)
+        }
+    }
+
+    /// ¿Hay que parar antes de ejecutar la instrucción en PC?
+    /// Cuenta los aciertos y borra los temporales que saltan.
+    pub fn check(&mut self, cpu: &CPU) -> bool {
+        let pc = cpu.reg.pc;
+        let mut stop = false;
+
+        for b in self.list.iter_mut().filter(|b| b.enabled && b.addr == pc) {
+            let hit = b.condition.as_ref().is_none_or(|c| c.eval(cpu));
+            if hit {
+                b.hits += 1;
+                stop = true;
+            }
+        }
+
+        if stop {
+            self.list.retain(|b| !(b.temporary && b.addr == pc && b.hits > 0));
+        }
+        stop
+    }
+
+    /* ==================================================
+     * COMANDOS
+     * ==================================================
+     *   bp <dir> [if <cond>]   nuevo breakpoint (dirección en hex)
+     *   tbp <dir> [if <cond>]  temporal
+     *   bd <n> / be <n>        desactivar / activar
+     *   bc <n> | bc *          borrar uno / todos
+     *   bl                     listar
+     */
+
+    /// Ejecuta un comando; devuelve el texto a mostrar
+    pub fn command(&mut self, line: &str) -> Result<String, String> {
+        let line = line.trim();
+        let (cmd, rest) = line.split_once(' ').unwrap_or((line, ""));
+        let rest = rest.trim();
+
+        match cmd.to_ascii_lowercase().as_str() {
+            "bp" | "tbp" => {
+                let (addr, cond) = match rest.split_once(" if ") {
+                    Some((a, c)) => (a.trim(), Some(c.trim())),
+                    None => (rest, None),
+                };
+                let addr = parse_address(addr).ok_or(format!("Dirección no válida: {}", addr))?;
+                let n = self.add(addr, cond, cmd.eq_ignore_ascii_case("tbp"))?;
+                Ok(self.list[n - 1].describe(n))
+            }
+            "bd" | "be" => {
+                let n = parse_index(rest)?;
+                self.set_enabled(n, cmd.eq_ignore_ascii_case("be"))?;
+                Ok(self.list[n - 1].describe(n))
+            }
+            "bc" if rest == "*" => {
+                self.clear();
+                Ok("Breakpoints borrados".into())
+            }
+            "bc" => {
+                self.remove(parse_index(rest)?)?;
+                Ok("Breakpoint borrado".into())
+            }
+            "bl" if self.list.is_empty() => Ok("Sin breakpoints".into()),
+            "bl" => Ok(self
+                .list
+                .iter()
+                .enumerate()
+                .map(|(i, b)| b.describe(i + 1))
+                .collect::<Vec<_>>()
+                .join("\n")),
+            _ => Err(format!("Comando desconocido: {}", cmd)),
+        }
+    }
+
+    /* ==================================================
+     * PERSISTENCIA (.bp)
+     * ==================================================
+     * Una línea por breakpoint con el comando que lo crea;
+     * los desactivados llevan "#" delante.
+     */
+
+    pub fn to_text(&self) -> String {
+        self.list
+            .iter()
+            .map(|b| {
+                let mut s = format!(
+                    "{}{} {:04X}",
+                    if b.enabled { "" } else { "#" },
+                    if b.temporary { "tbp" } else { "bp" },
+                    b.addr
+                );
+                if let Some(c) = &b.condition {
+                    s.push_str(" if ");
+                    s.push_str(&c.source);
+                }
+                s.push('\n');
+                s
+            })
+            .collect()
+    }
+
+    /// Carga una línea de `to_text`
+    pub fn load_line(&mut self, line: &str) -> Result<(), String> {
+        let (enabled, cmd) = match line.strip_prefix('#') {
+            Some(cmd) => (false, cmd),
+            None => (true, line),
+        };
+        self.command(cmd)?;
+        if let Some(b) = self.list.last_mut() {
+            b.enabled = enabled;
+        }
+        Ok(())
+    }
+}
+
+fn parse_index(s: &str) -> Result<usize, String> {
+    s.parse().map_err(|_| format!("Número de breakpoint no válido: {}", s))
+}
+
+/// Dirección de un comando: en hexadecimal aunque no lleve prefijo
+pub fn parse_address(s: &str) -> Option<u16> {
+    let s = s.trim();
+    let hex = s
+        .strip_prefix("0x")
+        .or_else(|| s.strip_prefix("0X"))
+        .or_else(|| s.strip_prefix('$'))
+        .or_else(|| s.strip_suffix(['h', 'H']))
+        .unwrap_or(s);
+    u16::from_str_radix(hex, 16).ok()
+}
+
+/// 0x20, $20, 20h o decimal
+pub fn parse_number(s: &str) -> Option<u32> {
+    let s = s.trim();
+    let lower = s.to_ascii_lowercase();
+
+    if let Some(hex) = lower.strip_prefix("0x").or_else(|| lower.strip_prefix('$')) {
+        u32::from_str_radix(hex, 16).ok()
+    } else if let Some(hex) = lower.strip_suffix('h') {
+        u32::from_str_radix(hex, 16).ok()
+    } else {
+        lower.parse().ok()
+    }
+}
+
+/* ==================================================
+ * CONDICIONES
+ * ==================================================
+ * Precedencia (de menor a mayor):
+ *   ||   &&   == != < > <= >=   |   &   + -   !
+ * "(x)" es una lectura de memoria si x es un número, un registro
+ * de 16 bits o una suma/resta de ellos: (HL), (IX+5), (0x5C3A).
+ * Con cualquier otra cosa dentro son paréntesis normales.
+ */
+
+pub struct Condition {
+    /// Texto original (para listarla y guardarla)
+    pub source: String,
+    expr: Expr,
+}
+
+impl Condition {
+    pub fn parse(source: &str) -> Result<Self, String> {
+        let tokens = tokenize(source)?;
+        let mut p = Parser { tokens, pos: 0 };
+        let expr = p.or()?;
+
+        if p.pos != p.tokens.len() {
+            return Err(format!("Sobra texto en la condición: {}", source));
+        }
+
+        Ok(Self {
+            source: source.trim().to_string(),
+            expr,
+        })
+    }
+
+    pub fn eval(&self, cpu: &CPU) -> bool {
+        self.expr.eval(cpu) != 0
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Reg {
+    A, F, B, C, D, E, H, L, I, R,
+    AF, BC, DE, HL, IX, IY, SP, PC,
+}
+
+impl Reg {
+    fn from_name(name: &str) -> Option<Self> {
+        use Reg::*;
+        Some(match name.to_ascii_uppercase().as_str() {
+            "A" => A, "F" => F, "B" => B, "C" => C, "D" => D,
+            "E" => E, "H" => H, "L" => L, "I" => I, "R" => R,
+            "AF" => AF, "BC" => BC, "DE" => DE, "HL" => HL,
+            "IX" => IX, "IY" => IY, "SP" => SP, "PC" => PC,
+            _ => return None,
+        })
+    }
+
+    fn is_16bit(self) -> bool {
+        use Reg::*;
+        matches!(self, AF | BC | DE | HL | IX | IY | SP | PC)
+    }
+
+    fn read(self, cpu: &CPU) -> u32 {
+        use Reg::*;
+        let r = &cpu.reg;
+        (match self {
+            A => r.a as u16,
+            F => r.get_af() & 0xFF,
+            B => r.b as u16,
+            C => r.c as u16,
+            D => r.d as u16,
+            E => r.e as u16,
+            H => r.h as u16,
+            L => r.l as u16,
+            I => r.i as u16,
+            R => r.r as u16,
+            AF => r.get_af(),
+            BC => r.get_bc(),
+            DE => r.get_de(),
+            HL => r.get_hl(),
+            IX => r.get_ix(),
+            IY => r.get_iy(),
+            SP => r.sp,
+            PC => r.pc,
+        }) as u32
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Op {
+    Or, And, Eq, Ne, Lt, Gt, Le, Ge, BitOr, BitAnd, Add, Sub,
+}
+
+#[derive(Debug)]
+enum Expr {
+    Num(u32),
+    Reg(Reg),
+    /// Byte de memoria en la dirección
+    Mem(Box<Expr>),
+    Not(Box<Expr>),
+    Bin(Op, Box<Expr>, Box<Expr>),
+}
+
+impl Expr {
+    /// ¿Vale como dirección de memoria dentro de "( )"?
+    fn is_address(&self) -> bool {
+        match self {
+            Expr::Num(_) => true,
+            Expr::Reg(r) => r.is_16bit(),
+            Expr::Bin(Op::Add | Op::Sub, a, b) => a.is_address() && b.is_address(),
+            _ => false,
+        }
+    }
+
+    fn eval(&self, cpu: &CPU) -> u32 {
+        match self {
+            Expr::Num(n) => *n,
+            Expr::Reg(r) => r.read(cpu),
+            Expr::Mem(addr) => cpu.bus.read_byte(addr.eval(cpu) as u16) as u32,
+            Expr::Not(e) => (e.eval(cpu) == 0) as u32,
+            Expr::Bin(op, a, b) => {
+                let (a, b) = (a.eval(cpu), b.eval(cpu));
+                match op {
+                    Op::Or => (a != 0 || b != 0) as u32,
+                    Op::And => (a != 0 && b != 0) as u32,
+                    Op::Eq => (a == b) as u32,
+                    Op::Ne => (a != b) as u32,
+                    Op::Lt => (a < b) as u32,
+                    Op::Gt => (a > b) as u32,
+                    Op::Le => (a <= b) as u32,
+                    Op::Ge => (a >= b) as u32,
+                    Op::BitOr => a | b,
+                    Op::BitAnd => a & b,
+                    Op::Add => a.wrapping_add(b) & 0xFFFF,
+                    Op::Sub => a.wrapping_sub(b) & 0xFFFF,
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Token {
+    Num(u32),
+    Reg(Reg),
+    Op(Op),
+    Not,
+    LParen,
+    RParen,
+}
+
+fn tokenize(src: &str) -> Result<Vec<Token>, String> {
+    let chars: Vec<char> = src.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+
+        // Operadores de dos caracteres
+        let two = match (c, next) {
+            ('|', Some('|')) => Some(Op::Or),
+            ('&', Some('&')) => Some(Op::And),
+            ('=', Some('=')) => Some(Op::Eq),
+            ('!', Some('=')) => Some(Op::Ne),
+            ('<', Some('=')) => Some(Op::Le),
+            ('>', Some('=')) => Some(Op::Ge),
+            _ => None,
+        };
+        if let Some(op) = two {
+            tokens.push(Token::Op(op));
+            i += 2;
+            continue;
+        }
+
+        let single = match c {
+            '<' => Some(Token::Op(Op::Lt)),
+            '>' => Some(Token::Op(Op::Gt)),
+            '|' => Some(Token::Op(Op::BitOr)),
+            '&' => Some(Token::Op(Op::BitAnd)),
+            '+' => Some(Token::Op(Op::Add)),
+            '-' => Some(Token::Op(Op::Sub)),
+            '!' => Some(Token::Not),
+            '(' => Some(Token::LParen),
+            ')' => Some(Token::RParen),
+            _ => None,
+        };
+        if let Some(t) = single {
+            tokens.push(t);
+            i += 1;
+            continue;
+        }
+
+        // Palabra: número o registro
+        if c.is_ascii_alphanumeric() || c == '$' {
+            let start = i;
+            i += 1;
+            while i < chars.len() && chars[i].is_ascii_alphanumeric() {
+                i += 1;
+            }
+            let word: String = chars[start..i].iter().collect();
+
+            // Los registros alternativos (AF', ...) no se pueden consultar
+            let token = if c.is_ascii_digit() || c == '$' {
+                parse_number(&word).map(Token::Num)
+            } else {
+                Reg::from_name(&word).map(Token::Reg).or_else(|| {
+                    // Hexadecimal con sufijo que empieza por letra (FFh)
+                    word.to_ascii_lowercase()
+                        .strip_suffix('h')
+                        .and_then(|h| u32::from_str_radix(h, 16).ok())
+                        .map(Token::Num)
+                })
+            };
+            tokens.push(token.ok_or(format!("No entiendo '{}'", word))?);
+            continue;
+        }
+
+        return Err(format!("Carácter inesperado '{}'", c));
+    }
+
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek_op(&self, ops: &[Op]) -> Option<Op> {
+        match self.tokens.get(self.pos) {
+            Some(Token::Op(op)) if ops.contains(op) => Some(*op),
+            _ => None,
+        }
+    }
+
+    /// Operadores binarios de un nivel, asociativos por la izquierda
+    fn binary(&mut self, ops: &[Op], next: fn(&mut Self) -> Result<Expr, String>) -> Result<Expr, String> {
+        let mut left = next(self)?;
+        while let Some(op) = self.peek_op(ops) {
+            self.pos += 1;
+            let right = next(self)?;
+            left = Expr::Bin(op, Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn or(&mut self) -> Result<Expr, String> {
+        self.binary(&[Op::Or], Self::and)
+    }
+
+    fn and(&mut self) -> Result<Expr, String> {
+        self.binary(&[Op::And], Self::cmp)
+    }
+
+    fn cmp(&mut self) -> Result<Expr, String> {
+        self.binary(&[Op::Eq, Op::Ne, Op::Lt, Op::Gt, Op::Le, Op::Ge], Self::bit_or)
+    }
+
+    fn bit_or(&mut self) -> Result<Expr, String> {
+        self.binary(&[Op::BitOr], Self::bit_and)
+    }
+
+    fn bit_and(&mut self) -> Result<Expr, String> {
+        self.binary(&[Op::BitAnd], Self::sum)
+    }
+
+    fn sum(&mut self) -> Result<Expr, String> {
+        self.binary(&[Op::Add, Op::Sub], Self::unary)
+    }
+
+    fn unary(&mut self) -> Result<Expr, String> {
+        let token = self.tokens.get(self.pos).ok_or("Condición incompleta")?;
+        self.pos += 1;
+
+        match token {
+            Token::Num(n) => Ok(Expr::Num(*n)),
+            Token::Reg(r) => Ok(Expr::Reg(*r)),
+            Token::Not => Ok(Expr::Not(Box::new(self.unary()?))),
+            Token::LParen => {
+                let inner = self.or()?;
+                if self.tokens.get(self.pos) != Some(&Token::RParen) {
+                    return Err("Falta ')'".into());
+                }
+                self.pos += 1;
+
+                if inner.is_address() {
+                    Ok(Expr::Mem(Box::new(inner)))
+                } else {
+                    Ok(inner)
+                }
+            }
+            t => Err(format!("No se esperaba {:?}", t)),
+        }
+    }
+}
@@ -1,11 +1,46 @@
+#[cfg(feature = "sdl-frontend")]
+use sdl2::keyboard::Keycode;
+use zilog_z80::bus::Bus;
+use crate::ay::Ay;
+use crate::beeper::Beeper;
+use crate::cinta::TapeDeck;
+use crate::contencion::Contention;
+use crate::joystick::Joystick;
+use crate::machine::model::MachineType;
+use crate::memoria::Memory128;
 use crate::teclado::Keyboard;
+use crate::traza::Trace;
+use crate::video::ScreenMemory;
+use crate::watchpoints::Watchpoints;
 
 pub struct ZxBus {
     //pub rom: Vec<u8>,      // 16 KB
     //pub ram: Vec<u8>,      // 48 KB
     //pub rom_enabled: bool,
     pub keyboard: Keyboard,
+    /// Joystick (Kempston o sobre las teclas)
+    pub joystick: Joystick,
     pub border: u8,
+    /// Cambios de borde del frame en curso: (T-state absoluto, color)
+    pub border_writes: Vec<(u64, u8)>,
+    pub mic: bool,
+    pub speaker: bool,
+    pub beeper: Beeper,
+    /// Reproductor de cinta (señal EAR y carga rápida)
+    pub tape: TapeDeck,
+    /// Memoria paginada (solo en el 128K)
+    pub mem128: Option<Memory128>,
+    /// Chip de sonido AY (128K o interfaz AY en el 48K)
+    pub ay: Option<Ay>,
+    /// Contención de memoria y puertos de la ULA
+    pub contention: Contention,
+    /// Watchpoints de memoria y puertos
+    pub watch: Watchpoints,
+    /// Bytes sobrescritos por la instrucción en curso (dirección, valor
+    /// anterior); solo con el historial activo
+    pub journal: Option<Vec<(u16, u8)>>,
+    /// Traza de ejecución a fichero
+    pub trace: Trace,
 }
 
 impl ZxBus {
@@ -14,8 +49,20 @@ impl ZxBus {
             //rom: vec![0; 16 * 1024],
             //ram: vec![0; 48 * 1024],
             keyboard: Keyboard::new(),
+            joystick: Joystick::new(),
             //rom_enabled: true,
             border: 0,
+            border_writes: Vec::new(),
+            mic: false,
+            speaker: false,
+            beeper: Beeper::default(),
+            tape: TapeDeck::new(),
+            mem128: None,
+            ay: None,
+            contention: Contention::new(MachineType::Spectrum48K),
+            watch: Watchpoints::new(),
+            journal: None,
+            trace: Trace::new(),
         }
     }
 
@@ -50,38 +97,144 @@ impl ZxBus {
         self.ram[(addr as usize) & 0xFFFF] = value;
     }*/
 
-    pub fn in_port(&mut self, port: u16) -> u8 {
+    /// Historial: guarda lo que hay en `addr` (y en su otra ventana, si
+    /// el banco está mapeado dos veces) antes de escribir ahí
+    pub fn journal_write(&mut self, mem: &Bus, addr: u16) {
+        let alias = self.mem128.as_ref().and_then(|m| m.alias(addr));
+        if let Some(journal) = self.journal.as_mut() {
+            journal.push((addr, mem.read_byte(addr)));
+            if let Some(alias) = alias {
+                journal.push((alias, mem.read_byte(alias)));
+            }
+        }
+    }
+
+    /// 128K: copia lo escrito en `addr` a la otra ventana del mismo banco
+    pub fn mirror_write(&self, mem: &mut Bus, addr: u16) {
+        if let Some(alias) = self.mem128.as_ref().and_then(|m| m.alias(addr)) {
+            mem.write_byte(alias, mem.read_byte(addr));
+        }
+    }
+
+    /// Tecla del PC: primero las del joystick, el resto al teclado
+    #[cfg(feature = "sdl-frontend")]
+    pub fn key_event(&mut self, key: Keycode, pressed: bool) {
+        if self.joystick.key(key, pressed) {
+            return;
+        }
+        if pressed {
+            self.keyboard.key_down(key);
+        } else {
+            self.keyboard.key_up(key);
+        }
+    }
+
+    /// `t` es el T-state absoluto en el que se lee el puerto; `mem` es la
+    /// memoria de la CPU (para el bus flotante)
+    pub fn in_port(&mut self, port: u16, t: u64, mem: &Bus) -> u8 {
+        let value = self.read_port(port, t, mem);
+        self.watch.on_io(port, value, false);
+        value
+    }
+
+    fn read_port(&mut self, port: u16, t: u64, mem: &Bus) -> u8 {
         // En el Spectrum, el teclado se lee cuando el bit 0 del puerto es 0 (puerto 0xFE).
         if (port & 0x0001) == 0 {
             let high = (port >> 8) as u8;
-            let keys = self.keyboard.read_port_fe(high);
+            let keys = self.keyboard.read_port_fe(high) & self.joystick.read_port_fe(high);
+
+            // Bits 5 y 7 siempre a 1. Bit 6: EAR (señal de la cinta;
+            // a 1 con la cinta parada para evitar ruido de carga)
+            let ear = if self.tape.ear(t) { 0x40 } else { 0x00 };
+            return (keys & 0x1F) | 0xA0 | ear;
+        }
 
-            // Obligatorio para que la ROM no se confunda:
-            // Bits 5 y 7 siempre a 1. Bit 6 (EAR) a 1.
-            // data |= 0xE0;
-            // return data;
+        // Kempston: 0x1F
+        if let Some(value) = self.joystick.read_kempston(port) {
+            return value;
+        }
 
-            // Bits 5 y 7 a 1, Bit 6 (EAR) a 1 para evitar ruido de carga
-            return (keys & 0x1F) | 0xE0;
+        // AY: lectura del registro seleccionado en 0xFFFD
+        if let Some(ay) = &self.ay
+            && port & 0xC002 == 0xC000
+        {
+            return ay.read();
         }
 
-        // Bus flotante: por defecto devolvemos 0xFF (no hay nada conectado en otros puertos)
-        0xFF
+        // Bus flotante: nadie responde y se lee lo que la ULA esté sacando
+        // de la pantalla en ese momento (0xFF en el borde)
+        self.floating_bus(t, mem)
+    }
+
+    /// Byte que hay en el bus de datos cuando ningún periférico responde
+    pub fn floating_bus(&self, t: u64, mem: &Bus) -> u8 {
+        match (self.contention.ula_fetch(t), &self.mem128) {
+            (None, _) => 0xFF,
+            (Some(offset), Some(mem128)) => mem128.screen(mem).screen_byte(offset),
+            (Some(offset), None) => mem.screen_byte(offset),
+        }
     }
 
     // -------------------------
     // SALIDA DE PUERTOS (OUT)
     // -------------------------
-    pub fn out_port(&mut self, port: u16, value: u8) {
+    /// `t` es el T-state absoluto en el que la escritura llega al puerto
+    pub fn out_port(&mut self, port: u16, value: u8, t: u64) {
+        self.watch.on_io(port, value, true);
+
         // Si el bit 0 del puerto es 0, es una escritura a la ULA (Borde, Mic, Beeper)
         if (port & 0x0001) == 0 {
             // Los bits 0, 1 y 2 definen el color del borde (0-7)
-            self.border = value & 0x07;
+            let border = value & 0x07;
+            if border != self.border {
+                self.border_writes.push((t, border));
+            }
+            self.border = border;
+
+            // Bit 3: MIC (salida a cinta). Bit 4: EAR (altavoz)
+            self.mic = value & 0x08 != 0;
+            self.speaker = value & 0x10 != 0;
+            self.beeper.set_level(t, self.speaker);
+        }
+
+        // 128K: paginación en 0x7FFD (decodifica A15 = 0 y A1 = 0)
+        if let Some(mem) = self.mem128.as_mut()
+            && port & 0x8002 == 0
+        {
+            mem.write_port(value);
+        }
 
-            // Debug para confirmar que funciona
-            println!("Cambio de borde a color: {}", self.border);
+        // AY: 0xFFFD selecciona registro, 0xBFFD escribe (A15 = 1, A1 = 0)
+        if let Some(ay) = self.ay.as_mut() {
+            match port & 0xC002 {
+                0xC000 => ay.select(value),
+                0x8000 => ay.write(t, value),
+                _ => {}
+            }
         }
     }
+
+    /// Audio del frame: beeper y AY mezclados
+    pub fn take_audio(&mut self) -> Vec<i16> {
+        let mut samples = self.beeper.take_samples();
+
+        if let Some(ay) = self.ay.as_mut() {
+            // Mismo reloj y frecuencia: como mucho difieren en una muestra,
+            // que se queda para el frame siguiente
+            let n = samples.len();
+            for (s, a) in samples.iter_mut().zip(ay.take_samples(n)) {
+                *s = s.saturating_add(a);
+            }
+        }
+
+        samples
+    }
+}
+
+impl Default for ZxBus {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 
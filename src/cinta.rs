@@ -0,0 +1,359 @@
+use crate::constantes::CPU_HZ;
+use crate::formatos::tzx::TzxBlock;
+
+/* ==================================================
+ * REPRODUCTOR DE CINTA
+ * ==================================================
+ * Genera la señal EAR a partir de los bloques de la cinta,
+ * siguiendo el reloj de T-states de la CPU.
+ *
+ * Cada bloque se expande al entrar en él a una lista de
+ * segmentos (nivel, duración): un pulso TZX es un cambio de
+ * nivel seguido de `duración` T-states estable.
+ *
+ * La señal avanza de forma perezosa: cada lectura del puerto
+ * (`ear(t)`) consume los segmentos que ya han pasado.
+ */
+
+// Tiempos de la ROM (T-states)
+const PILOT: u16 = 2168;
+const SYNC1: u16 = 667;
+const SYNC2: u16 = 735;
+const ZERO: u16 = 855;
+const ONE: u16 = 1710;
+const PILOT_HEADER: u16 = 8063;
+const PILOT_DATA: u16 = 3223;
+
+/// T-states por milisegundo
+const T_PER_MS: u32 = (CPU_HZ / 1000) as u32;
+
+pub struct TapeDeck {
+    blocks: Vec<TzxBlock>,
+    /// Bloque en curso (el siguiente a expandir)
+    block: usize,
+    /// Segmentos del bloque en curso: (nivel, duración)
+    segments: Vec<(bool, u32)>,
+    seg_idx: usize,
+    /// T-state en el que termina el segmento actual
+    next_edge: u64,
+    level: bool,
+    playing: bool,
+    /// Bucles abiertos: (primer bloque del bucle, repeticiones pendientes)
+    loops: Vec<(usize, u16)>,
+}
+
+impl TapeDeck {
+    pub fn new() -> Self {
+        Self {
+            blocks: Vec::new(),
+            block: 0,
+            segments: Vec::new(),
+            seg_idx: 0,
+            next_edge: 0,
+            level: false,
+            playing: false,
+            loops: Vec::new(),
+        }
+    }
+
+    /// Inserta una cinta (parada y rebobinada)
+    pub fn insert(&mut self, blocks: Vec<TzxBlock>) {
+        self.blocks = blocks;
+        self.rewind();
+    }
+
+    pub fn eject(&mut self) {
+        self.insert(Vec::new());
+    }
+
+    pub fn has_tape(&self) -> bool {
+        !self.blocks.is_empty()
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.playing
+    }
+
+    /// Bloque en curso y total de bloques (para la UI)
+    pub fn position(&self) -> (usize, usize) {
+        (self.block, self.blocks.len())
+    }
+
+    pub fn at_end(&self) -> bool {
+        self.block >= self.blocks.len() && self.seg_idx >= self.segments.len()
+    }
+
+    /// PLAY en el T-state `t`. El segmento en curso empieza de nuevo.
+    pub fn play(&mut self, t: u64) {
+        if !self.has_tape() || self.at_end() {
+            return;
+        }
+
+        self.playing = true;
+        if self.seg_idx >= self.segments.len() {
+            self.next_edge = t;
+            self.advance(t);
+        } else {
+            self.next_edge = t + self.segments[self.seg_idx].1 as u64;
+        }
+    }
+
+    pub fn stop(&mut self) {
+        self.playing = false;
+    }
+
+    pub fn rewind(&mut self) {
+        self.playing = false;
+        self.block = 0;
+        self.segments.clear();
+        self.seg_idx = 0;
+        self.level = false;
+        self.loops.clear();
+    }
+
+    /// Nivel de la señal EAR en el T-state `t`.
+    /// Con la cinta parada devuelve 1 (sin ruido de carga).
+    pub fn ear(&mut self, t: u64) -> bool {
+        if !self.playing {
+            return true;
+        }
+
+        self.advance(t);
+        self.level
+    }
+
+    /// Consume los segmentos que terminan antes de `t`
+    fn advance(&mut self, t: u64) {
+        while self.playing && self.next_edge <= t {
+            // Siguiente segmento del bloque en curso
+            if self.seg_idx + 1 < self.segments.len() {
+                self.seg_idx += 1;
+            } else if !self.next_block() {
+                self.playing = false;
+                return;
+            }
+
+            let (level, dur) = self.segments[self.seg_idx];
+            self.level = level;
+            self.next_edge += dur as u64;
+        }
+    }
+
+    /// Entra en el siguiente bloque con señal. Devuelve false si la
+    /// cinta se ha acabado o hay que pararla.
+    fn next_block(&mut self) -> bool {
+        self.segments.clear();
+        self.seg_idx = 0;
+
+        while self.segments.is_empty() {
+            let Some(block) = self.blocks.get(self.block) else {
+                return false;
+            };
+            self.block += 1;
+
+            match block {
+                TzxBlock::Pause(0) | TzxBlock::StopIf48K => return false,
+
+                // Un salto de 0 no es válido (bucle infinito): se ignora
+                TzxBlock::JumpTo(rel) if *rel != 0 => {
+                    self.block = (self.block as isize - 1 + *rel as isize).max(0) as usize;
+                }
+
+                TzxBlock::LoopStart(n) => self.loops.push((self.block, *n)),
+
+                TzxBlock::LoopEnd => {
+                    if let Some((start, n)) = self.loops.pop()
+                        && n > 1
+                    {
+                        self.loops.push((start, n - 1));
+                        self.block = start;
+                    }
+                }
+
+                _ => self.segments = expand(block, self.level),
+            }
+        }
+
+        true
+    }
+
+    /// Carga rápida: si el siguiente bloque con señal es un bloque
+    /// estándar (el formato de la ROM), lo consume y devuelve sus datos.
+    /// Con la cinta en marcha no se interviene.
+    pub fn take_rom_block(&mut self) -> Option<Vec<u8>> {
+        if self.playing {
+            return None;
+        }
+
+        let mut i = self.block;
+        while let Some(block) = self.blocks.get(i) {
+            match block {
+                TzxBlock::Standard { data, .. } => {
+                    self.block = i + 1;
+                    self.segments.clear();
+                    self.seg_idx = 0;
+                    return Some(data.clone());
+                }
+
+                TzxBlock::GroupStart(_)
+                | TzxBlock::GroupEnd
+                | TzxBlock::Text(_)
+                | TzxBlock::ArchiveInfo(_)
+                | TzxBlock::Other(_) => i += 1,
+
+                _ => return None,
+            }
+        }
+
+        None
+    }
+}
+
+impl Default for TapeDeck {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/* ==================================================
+ * EXPANSIÓN DE BLOQUES A SEGMENTOS
+ * ================================================== */
+
+/// Segmentos de un bloque, empezando con la señal a `level`
+fn expand(block: &TzxBlock, level: bool) -> Vec<(bool, u32)> {
+    let mut s = Segments { out: Vec::new(), level };
+
+    match block {
+        TzxBlock::Standard { pause_ms, data } => {
+            let pilot_len = match data.first() {
+                Some(&flag) if flag < 0x80 => PILOT_HEADER,
+                _ => PILOT_DATA,
+            };
+            s.tone(PILOT, pilot_len);
+            s.pulse(SYNC1 as u32);
+            s.pulse(SYNC2 as u32);
+            s.data(ZERO, ONE, 8, data);
+            s.pause(*pause_ms);
+        }
+
+        TzxBlock::Turbo {
+            pilot, sync1, sync2, zero, one, pilot_len, used_bits, pause_ms, data,
+        } => {
+            s.tone(*pilot, *pilot_len);
+            s.pulse(*sync1 as u32);
+            s.pulse(*sync2 as u32);
+            s.data(*zero, *one, *used_bits, data);
+            s.pause(*pause_ms);
+        }
+
+        TzxBlock::PureTone { pulse, count } => s.tone(*pulse, *count),
+
+        TzxBlock::PulseSeq(pulses) => {
+            for &p in pulses {
+                s.pulse(p as u32);
+            }
+        }
+
+        TzxBlock::PureData { zero, one, used_bits, pause_ms, data } => {
+            s.data(*zero, *one, *used_bits, data);
+            s.pause(*pause_ms);
+        }
+
+        TzxBlock::DirectRecording { tstates_per_sample, pause_ms, used_bits, data } => {
+            for (i, &byte) in data.iter().enumerate() {
+                let bits = if i + 1 == data.len() { *used_bits } else { 8 };
+                for b in 0..bits.min(8) {
+                    s.level = byte & (0x80 >> b) != 0;
+                    s.out.push((s.level, *tstates_per_sample as u32));
+                }
+            }
+            s.pause(*pause_ms);
+        }
+
+        TzxBlock::Csw { pause_ms, sample_rate, pulses } => {
+            for &p in pulses {
+                s.pulse((p as u64 * CPU_HZ / *sample_rate as u64) as u32);
+            }
+            s.pause(*pause_ms);
+        }
+
+        TzxBlock::Generalized { pause_ms, defs, symbols } => {
+            for &sym in symbols {
+                let (flags, pulses) = &defs[sym as usize];
+                for (i, &p) in pulses.iter().enumerate() {
+                    if i == 0 {
+                        // Polaridad del primer pulso del símbolo
+                        s.level = match flags {
+                            0 => !s.level,
+                            1 => s.level,
+                            2 => false,
+                            _ => true,
+                        };
+                        s.out.push((s.level, p as u32));
+                    } else {
+                        s.pulse(p as u32);
+                    }
+                }
+            }
+            s.pause(*pause_ms);
+        }
+
+        TzxBlock::Pause(ms) => s.pause(*ms),
+
+        TzxBlock::SetLevel(level) => {
+            s.level = *level;
+            s.out.push((s.level, 0));
+        }
+
+        // Bloques informativos: sin señal
+        _ => {}
+    }
+
+    s.out
+}
+
+/// Constructor de segmentos llevando el nivel actual
+struct Segments {
+    out: Vec<(bool, u32)>,
+    level: bool,
+}
+
+impl Segments {
+    /// Pulso: cambio de nivel y `dur` T-states estable
+    fn pulse(&mut self, dur: u32) {
+        self.level = !self.level;
+        self.out.push((self.level, dur));
+    }
+
+    fn tone(&mut self, pulse: u16, count: u16) {
+        for _ in 0..count {
+            self.pulse(pulse as u32);
+        }
+    }
+
+    /// Dos pulsos por bit, MSB primero. Del último byte solo se
+    /// usan `used_bits` bits.
+    fn data(&mut self, zero: u16, one: u16, used_bits: u8, data: &[u8]) {
+        for (i, &byte) in data.iter().enumerate() {
+            let bits = if i + 1 == data.len() { used_bits } else { 8 };
+            for b in 0..bits.min(8) {
+                let dur = if byte & (0x80 >> b) != 0 { one } else { zero };
+                self.pulse(dur as u32);
+                self.pulse(dur as u32);
+            }
+        }
+    }
+
+    /// Pausa: un último flanco de 1 ms y el resto a nivel bajo
+    fn pause(&mut self, ms: u16) {
+        if ms == 0 {
+            return;
+        }
+
+        self.pulse(T_PER_MS);
+        if ms > 1 {
+            self.level = false;
+            self.out.push((false, (ms as u32 - 1) * T_PER_MS));
+        }
+    }
+}
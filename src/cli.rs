@@ -0,0 +1,195 @@
+use std::path::PathBuf;
+use crate::joystick::JoystickType;
+use crate::teclado::{unescape_text, KeyMapping};
+use crate::constantes::{AUTOTYPE_BOOT_FRAMES, ESCALA_VENTANA_ZX, HISTORY_SECONDS};
+use crate::machine::model::MachineType;
+use crate::machine::zx_machine::{ZxMachine, ROM128_PATH_DEFAULT, ROM_PATH_DEFAULT};
+
+/* ==================================================
+ * LÍNEA DE COMANDOS
+ * ==================================================
+ * zx [opciones] [fichero]
+ *
+ * El fichero puede ser cualquier formato que entienda load_file
+ * (rom, sna, z80, bin, tap, tzx). Las opciones aceptan tanto
+ * "--scale 3" como "--scale=3".
+ */
+
+pub const USAGE: &str = "\
+Uso: zx [opciones] [fichero]
+
+  fichero                 programa a cargar (rom, sna, z80, bin, tap, tzx)
+  --rom <fichero>         ROM a usar (por defecto la del modelo en ROMS/)
+  --machine <48k|128k>    modelo emulado (48k por defecto)
+  --scale <n>             tamaño de cada píxel del Spectrum en la ventana
+  --no-debug-window       solo la ventana del Spectrum
+  --breakpoint <bp>       breakpoint como en la consola: \"8000\" o \"8000 if A==0\"
+                          (se puede repetir)
+  --run                   empezar corriendo (por defecto en pausa)
+  --frames <n>            correr n frames y salir (implica --run)
+  --screenshot <png>      guardar la pantalla en PNG al salir
+  --joystick <tipo>       none, kempston, sinclair1, sinclair2 o cursor
+  --joystick-keys <t>     teclas del joystick con los nombres de SDL:
+                          \"Up,Down,Left,Right,Right Ctrl\" (arriba, abajo,
+                          izquierda, derecha, fuego)
+  --keys <modo>           teclado positional (teclas en su sitio, por defecto)
+                          o symbolic (se escribe el carácter del PC)
+  --type <texto>          teclear el texto al arrancar (\\n = ENTER), p.ej.
+                          'LOAD \"\"\\n' o '10 PRINT \"HOLA\"\\nRUN\\n'
+  -h, --help              esta ayuda";
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CliOptions {
+    pub file: Option<PathBuf>,
+    pub rom: Option<PathBuf>,
+    pub machine: MachineType,
+    pub scale: Option<u32>,
+    pub debug_window: bool,
+    pub breakpoints: Vec<String>,
+    pub run: bool,
+    pub frames: Option<u64>,
+    pub screenshot: Option<PathBuf>,
+    pub joystick: JoystickType,
+    /// Se comprueban al abrir la ventana (los nombres son de SDL)
+    pub joystick_keys: Option<String>,
+    pub keys: KeyMapping,
+    /// Ya con los "\n" convertidos
+    pub type_text: Option<String>,
+    pub help: bool,
+}
+
+impl Default for CliOptions {
+    fn default() -> Self {
+        Self {
+            file: None,
+            rom: None,
+            machine: MachineType::Spectrum48K,
+            scale: None,
+            debug_window: true,
+            breakpoints: Vec::new(),
+            run: false,
+            frames: None,
+            screenshot: None,
+            joystick: JoystickType::None,
+            joystick_keys: None,
+            keys: KeyMapping::Positional,
+            type_text: None,
+            help: false,
+        }
+    }
+}
+
+impl CliOptions {
+    /// Argumentos sin el nombre del programa
+    pub fn parse<I, S>(args: I) -> Result<Self, String>
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let mut o = Self::default();
+        let mut args = args.into_iter().map(Into::into);
+
+        while let Some(arg) = args.next() {
+            if !arg.starts_with('-') || arg == "-" {
+                if o.file.is_some() {
+                    return Err(format!("Sobra el argumento: {}", arg));
+                }
+                o.file = Some(PathBuf::from(arg));
+                continue;
+            }
+
+            // "--opcion=valor" o "--opcion valor"
+            let (name, inline) = match arg.split_once('=') {
+                Some((n, v)) => (n.to_string(), Some(v.to_string())),
+                None => (arg.clone(), None),
+            };
+            let mut value = || {
+                inline
+                    .clone()
+                    .or_else(|| args.next())
+                    .ok_or(format!("Falta el valor de {}", name))
+            };
+
+            match name.as_str() {
+                "--rom" => o.rom = Some(PathBuf::from(value()?)),
+                "--machine" => {
+                    let v = value()?;
+                    o.machine = MachineType::parse(&v).ok_or(format!("Modelo no válido: {} (48k o 128k)", v))?;
+                }
+                "--scale" => {
+                    let v = value()?;
+                    o.scale = Some(
+                        v.parse()
+                            .ok()
+                            .filter(|s| (1..=16).contains(s))
+                            .ok_or(format!("Escala no válida: {} (1-16)", v))?,
+                    );
+                }
+                "--breakpoint" => o.breakpoints.push(value()?),
+                "--frames" => {
+                    let v = value()?;
+                    o.frames = Some(v.parse().map_err(|_| format!("Número de frames no válido: {}", v))?);
+                }
+                "--screenshot" => o.screenshot = Some(PathBuf::from(value()?)),
+                "--joystick" => {
+                    let v = value()?;
+                    o.joystick = JoystickType::parse(&v)
+                        .ok_or(format!("Joystick no válido: {} (none, kempston, sinclair1, sinclair2, cursor)", v))?;
+                }
+                "--joystick-keys" => o.joystick_keys = Some(value()?),
+                "--type" => o.type_text = Some(unescape_text(&value()?)),
+                "--keys" => {
+                    let v = value()?;
+                    o.keys = KeyMapping::parse(&v).ok_or(format!("Modo de teclado no válido: {} (positional, symbolic)", v))?;
+                }
+                "--no-debug-window" | "--run" | "-h" | "--help" if inline.is_some() => {
+                    return Err(format!("{} no lleva valor", name));
+                }
+                "--no-debug-window" => o.debug_window = false,
+                "--run" => o.run = true,
+                "-h" | "--help" => o.help = true,
+                _ => return Err(format!("Opción desconocida: {}", name)),
+            }
+        }
+
+        Ok(o)
+    }
+
+    /// Máquina lista para arrancar: ROM, programa, breakpoints y modo
+    pub fn build_machine(&self) -> Result<ZxMachine, String> {
+        let rom_path = match &self.rom {
+            Some(p) => p.clone(),
+            None => PathBuf::from(match self.machine {
+                MachineType::Spectrum48K => ROM_PATH_DEFAULT,
+                MachineType::Spectrum128K => ROM128_PATH_DEFAULT,
+            }),
+        };
+        let rom = std::fs::read(&rom_path).map_err(|e| format!("{}: {}", rom_path.display(), e))?;
+
+        let mut m = ZxMachine::builder()
+            .machine(self.machine)
+            .rom(rom)
+            .video_scale(self.scale.unwrap_or(ESCALA_VENTANA_ZX))
+            .history(HISTORY_SECONDS)
+            .build()?;
+
+        if let Some(file) = &self.file {
+            m.load_file(file)?;
+        }
+        // No se guardan en el .bp del programa: son solo de esta sesión
+        for bp in &self.breakpoints {
+            m.debugger.breakpoints.command(&format!("bp {}", bp))?;
+        }
+        m.bus.joystick.set_kind(self.joystick);
+        m.bus.keyboard.mode = self.keys;
+        if let Some(text) = &self.type_text {
+            m.bus.keyboard.pause_typing(AUTOTYPE_BOOT_FRAMES);
+            m.autotype(text);
+        }
+        if self.run || self.frames.is_some() {
+            m.debugger.run();
+        }
+
+        Ok(m)
+    }
+}
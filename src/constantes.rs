@@ -1,4 +1,28 @@
 pub const TSTATES_PER_FRAME: u64 = 69888;
+// Reloj de la CPU del 48K (Hz)
+pub const CPU_HZ: u64 = 3_500_000;
+// Temporización de la ULA 48K: 224 T-states por línea, 312 líneas por frame
+pub const TSTATES_PER_LINE: u64 = 224;
+pub const LINES_PER_FRAME: u64 = 312;
+// Línea (desde el inicio del frame / INT) del primer píxel de pantalla
+pub const FIRST_SCREEN_LINE: u64 = 64;
+// Temporización del 128K: 228 T-states por línea, 311 líneas, reloj más rápido
+pub const TSTATES_PER_FRAME_128: u64 = 70908;
+pub const TSTATES_PER_LINE_128: u64 = 228;
+pub const FIRST_SCREEN_LINE_128: u64 = 63;
+pub const CPU_HZ_128: u64 = 3_546_900;
+// Interrupciones: la ULA mantiene INT activa 32 T-states al principio del
+// frame; en IM 2 el byte del vector es el del bus flotante (0xFF).
+// Aceptarla cuesta 13 T-states en IM 0/1 y 19 en IM 2
+pub const INT_PULSE_TSTATES: u64 = 32;
+pub const INT_VECTOR_BYTE: u8 = 0xFF;
+pub const INT_ACK_TSTATES: u32 = 13;
+pub const INT_ACK_TSTATES_IM2: u32 = 19;
+// NMI: salta a 0x0066 en 11 T-states
+pub const NMI_ADDR: u16 = 0x0066;
+pub const NMI_ACK_TSTATES: u32 = 11;
+// Tamaño de un banco de memoria / ROM
+pub const BANK_SIZE: usize = 16 * 1024;
 pub const ANCHO_VENTANA: u32 = 3800;
 pub const ALTO_VENTANA: u32 = 2800;
 pub const ESCALA_VENTANA_ZX: u32 = 4;
@@ -11,6 +35,41 @@ pub const SIZE_SNA: usize = 49179;
 pub const ZX_W: i32 = 256;
 pub const ZX_H: i32 = 192;
 pub const MARGEN_NEGRO: i32 = 20;
-pub const ZX_BORDER: i32 = 16;
+// Borde visible del 48K: 48 px a izquierda, derecha y arriba, 56 abajo
+pub const ZX_BORDER: i32 = 48;
+pub const ZX_BORDER_INF: i32 = 56;
+// Imagen completa (pantalla + borde): 352x296
+pub const ZX_FRAME_W: i32 = ZX_W + 2 * ZX_BORDER;
+pub const ZX_FRAME_H: i32 = ZX_H + ZX_BORDER + ZX_BORDER_INF;
 
 pub const DIR_BIN_DEFAULT: u16 = 0x8000;
+
+// Historial (ejecución hacia atrás): segundos de keyframes en el frontend,
+// instrucciones que se pueden deshacer y frames entre keyframes
+pub const HISTORY_SECONDS: u32 = 10;
+pub const HISTORY_STEPS: usize = 300_000;
+pub const KEYFRAME_FRAMES: u64 = 5;
+
+// Audio
+pub const AUDIO_SAMPLE_RATE: u32 = 44100;
+// Audio en cola por encima del cual se espera (sincronización por audio)
+pub const AUDIO_LATENCY_MS: u32 = 60;
+
+// Joystick: valor de la palanca analógica (de 32767) a partir del cual
+// cuenta como dirección pulsada
+pub const JOY_AXIS_DEADZONE: i16 = 8000;
+
+// Teclado: frames que se mantiene pulsada cada tecla escrita como texto y
+// frames que tiene que estar suelta una tecla para que la ROM la olvide
+// (el contador de KSTATE empieza en 5) y acepte otra pulsación
+pub const KEY_HOLD_FRAMES: u32 = 2;
+pub const KEY_FORGET_FRAMES: u64 = 6;
+
+// Autotecleo: frames que se espera a que la ROM recoja una tecla (FLAGS,
+// bit 5) antes de pulsar la siguiente de todos modos, y frames que se
+// deja arrancar a la ROM antes de escribir lo de --type
+pub const AUTOTYPE_WAIT_FRAMES: u64 = 100;
+pub const AUTOTYPE_BOOT_FRAMES: u64 = 150;
+// Variable del sistema FLAGS; su bit 5 indica tecla nueva sin recoger
+pub const SYSVAR_FLAGS: u16 = 0x5C3B;
+pub const FLAGS_NEW_KEY: u8 = 0x20;
@@ -0,0 +1,498 @@
+use zilog_z80::cpu::CPU;
+use crate::machine::model::MachineType;
+use crate::video::{zx_attr_addr, zx_screen_addr};
+
+/* ==================================================
+ * CONTENCIÓN DE MEMORIA Y PUERTOS (ULA)
+ * ==================================================
+ * Mientras la ULA lee la pantalla, los accesos de la CPU a
+ * 0x4000-0x7FFF (y en el 128K a los bancos impares en 0xC000)
+ * se retrasan según el patrón 6,5,4,3,2,1,0,0 durante los 128
+ * T-states de papel de cada una de las 192 líneas.
+ *
+ * El core zilog_z80 ejecuta cada instrucción de golpe, así que
+ * la secuencia de accesos (dirección y duración de cada ciclo,
+ * notación de Fuse: "pc:4, hl:3, ir:1 x2...") se reconstruye
+ * con los registros de ANTES de ejecutarla y los ciclos que ha
+ * devuelto el core (saltos tomados, bloques que se repiten).
+ * El retraso total se suma a los T-states de la instrucción.
+ *
+ * Si la secuencia no cuadra con los ciclos del core (prefijos
+ * raros), solo se contiende la lectura del opcode.
+ */
+
+/// Retraso según la posición dentro del grupo de 8 T-states
+const DELAYS: [u32; 8] = [6, 5, 4, 3, 2, 1, 0, 0];
+
+// T-state (desde la INT) del primer ciclo contendido
+const FIRST_CONTENDED_48K: u64 = 14335;
+const FIRST_CONTENDED_128K: u64 = 14361;
+
+/// Un ciclo de la CPU visto desde el bus
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Access {
+    /// Acceso a memoria (o ciclo interno con la dirección en el bus)
+    Mem(u16, u32),
+    /// Ciclo de E/S (4 T-states, contención según el puerto)
+    Io(u16),
+    /// T-states sin contención posible
+    Free(u32),
+}
+
+impl Access {
+    fn len(&self) -> u32 {
+        match *self {
+            Access::Mem(_, t) => t,
+            Access::Io(_) => 4,
+            Access::Free(t) => t,
+        }
+    }
+}
+
+/// Registros de antes de ejecutar la instrucción
+#[derive(Copy, Clone, Debug)]
+pub struct PreRegs {
+    pub pc: u16,
+    pub a: u8,
+    pub bc: u16,
+    pub de: u16,
+    pub hl: u16,
+    pub sp: u16,
+    pub ix: u16,
+    pub iy: u16,
+    /// I*256 + R (dirección en el bus durante el refresco)
+    pub ir: u16,
+}
+
+impl PreRegs {
+    pub fn capture(cpu: &CPU) -> Self {
+        Self {
+            pc: cpu.reg.pc,
+            a: cpu.reg.a,
+            bc: cpu.reg.get_bc(),
+            de: cpu.reg.get_de(),
+            hl: cpu.reg.get_hl(),
+            sp: cpu.reg.sp,
+            ix: cpu.reg.get_ix(),
+            iy: cpu.reg.get_iy(),
+            ir: ((cpu.reg.i as u16) << 8) | cpu.reg.r as u16,
+        }
+    }
+}
+
+pub struct Contention {
+    /// Interruptor: sin contención todo va a la velocidad del core
+    pub enabled: bool,
+    /// T-state absoluto de la última INT (inicio del frame)
+    pub frame_start: u64,
+    first_contended: u64,
+    line_t: u64,
+}
+
+impl Contention {
+    pub fn new(machine_type: MachineType) -> Self {
+        let first_contended = match machine_type {
+            MachineType::Spectrum48K => FIRST_CONTENDED_48K,
+            MachineType::Spectrum128K => FIRST_CONTENDED_128K,
+        };
+
+        Self {
+            enabled: true,
+            frame_start: 0,
+            first_contended,
+            line_t: machine_type.tstates_per_line(),
+        }
+    }
+
+    /// Retraso de un acceso contendido que empieza en el T-state absoluto `t`
+    pub fn delay(&self, t: u64) -> u32 {
+        let ft = t.wrapping_sub(self.frame_start);
+        if ft < self.first_contended || ft >= self.first_contended + 192 * self.line_t {
+            return 0;
+        }
+
+        let offset = (ft - self.first_contended) % self.line_t;
+        if offset >= 128 {
+            0
+        } else {
+            DELAYS[(offset % 8) as usize]
+        }
+    }
+
+    /// Byte de pantalla (offset 0..6912) que lee la ULA en el T-state
+    /// absoluto `t`, o `None` si el bus está libre (borde o huecos).
+    ///
+    /// En cada grupo de 8 T-states del papel la ULA lee píxeles y
+    /// atributo de dos columnas a partir del 3.º T-state (14338 en
+    /// el 48K) y deja el bus libre los otros 4.
+    pub fn ula_fetch(&self, t: u64) -> Option<u16> {
+        let ft = t.wrapping_sub(self.frame_start).checked_sub(self.first_contended)?;
+        let line = (ft / self.line_t) as usize;
+        let offset = ft % self.line_t;
+        if line >= 192 || offset >= 128 {
+            return None;
+        }
+
+        let column = (offset / 8 * 2) as usize;
+        match offset % 8 {
+            3 => Some(zx_screen_addr(column, line)),
+            4 => Some(zx_attr_addr(column, line)),
+            5 => Some(zx_screen_addr(column + 1, line)),
+            6 => Some(zx_attr_addr(column + 1, line)),
+            _ => None,
+        }
+    }
+
+    /// T-states extra de una instrucción que empieza en `t`.
+    /// `c000_contended`: banco impar paginado en 0xC000 (128K).
+    pub fn instr_delay(&self, accesses: &[Access], t: u64, c000_contended: bool) -> u32 {
+        if !self.enabled {
+            return 0;
+        }
+
+        let contended = |addr: u16| {
+            (0x4000..0x8000).contains(&addr) || (c000_contended && addr >= 0xC000)
+        };
+
+        let mut now = t;
+        let mut base = 0u64;
+        for a in accesses {
+            match *a {
+                Access::Mem(addr, len) => {
+                    if contended(addr) {
+                        now += self.delay(now) as u64;
+                    }
+                    now += len as u64;
+                }
+
+                // Puertos: byte alto en memoria contendida y/o bit 0 a 0 (ULA)
+                Access::Io(port) => {
+                    match (contended(port), port & 0x0001 == 0) {
+                        // N:1, C:3
+                        (false, true) => {
+                            now += 1;
+                            now += self.delay(now) as u64 + 3;
+                        }
+                        // N:4
+                        (false, false) => now += 4,
+                        // C:1, C:3
+                        (true, true) => {
+                            now += self.delay(now) as u64 + 1;
+                            now += self.delay(now) as u64 + 3;
+                        }
+                        // C:1 x4
+                        (true, false) => {
+                            for _ in 0..4 {
+                                now += self.delay(now) as u64 + 1;
+                            }
+                        }
+                    }
+                }
+
+                Access::Free(len) => now += len as u64,
+            }
+            base += a.len() as u64;
+        }
+
+        (now - t - base) as u32
+    }
+}
+
+/* ==================================================
+ * SECUENCIAS DE ACCESO POR INSTRUCCIÓN
+ * ================================================== */
+
+/// Ciclos de la instrucción en `bytes`, ejecutada con los registros `r`,
+/// que ha costado `cycles` T-states en el core
+pub fn instr_accesses(r: &PreRegs, bytes: &[u8; 4], cycles: u32) -> Vec<Access> {
+    let mut v = Vec::with_capacity(12);
+
+    match bytes[0] {
+        0xCB => cb_pattern(r.pc, r.hl, bytes[1], &mut v),
+        0xED => ed_pattern(r, bytes, cycles, &mut v),
+        0xDD => indexed_pattern(r, r.ix, bytes, cycles, &mut v),
+        0xFD => indexed_pattern(r, r.iy, bytes, cycles, &mut v),
+        op => main_pattern(r, r.pc, r.hl, op, [bytes[1], bytes[2]], cycles, &mut v),
+    }
+
+    if v.iter().map(Access::len).sum::<u32>() == cycles {
+        v
+    } else if cycles >= 4 {
+        vec![Access::Mem(r.pc, 4), Access::Free(cycles - 4)]
+    } else {
+        vec![Access::Free(cycles)]
+    }
+}
+
+fn rep(v: &mut Vec<Access>, addr: u16, n: usize) {
+    v.extend(std::iter::repeat_n(Access::Mem(addr, 1), n));
+}
+
+/// Instrucciones sin prefijo. `pc` apunta al opcode, `hl` es HL (o IX/IY
+/// con prefijo) y `n` son los dos bytes que siguen al opcode.
+fn main_pattern(r: &PreRegs, pc: u16, hl: u16, op: u8, n: [u8; 2], cycles: u32, v: &mut Vec<Access>) {
+    use Access::Mem;
+
+    let n1 = pc.wrapping_add(1);
+    let n2 = pc.wrapping_add(2);
+    let nn = u16::from_le_bytes(n);
+    let sp = r.sp;
+    let (x, y, z) = (op >> 6, (op >> 3) & 7, op & 7);
+
+    v.push(Mem(pc, 4));
+
+    match (x, z) {
+        // LD r,(HL) / LD (HL),r (LD r,r' y HALT no acceden)
+        (1, _) if op != 0x76 && (y == 6 || z == 6) => v.push(Mem(hl, 3)),
+        (1, _) => {}
+        // ALU A,r / ALU A,(HL)
+        (2, 6) => v.push(Mem(hl, 3)),
+        (2, _) => {}
+
+        (0, 0) => match y {
+            // DJNZ
+            2 => {
+                rep(v, r.ir, 1);
+                v.push(Mem(n1, 3));
+                if cycles == 13 {
+                    rep(v, n1, 5);
+                }
+            }
+            // JR e / JR cc,e
+            3..=7 => {
+                v.push(Mem(n1, 3));
+                if y == 3 || cycles == 12 {
+                    rep(v, n1, 5);
+                }
+            }
+            // NOP, EX AF,AF'
+            _ => {}
+        },
+        // LD rr,nn / ADD HL,rr
+        (0, 1) => {
+            if y & 1 == 0 {
+                v.extend([Mem(n1, 3), Mem(n2, 3)]);
+            } else {
+                rep(v, r.ir, 7);
+            }
+        }
+        (0, 2) => match y {
+            0 | 1 => v.push(Mem(r.bc, 3)),
+            2 | 3 => v.push(Mem(r.de, 3)),
+            // LD (nn),HL / LD HL,(nn)
+            4 | 5 => v.extend([Mem(n1, 3), Mem(n2, 3), Mem(nn, 3), Mem(nn.wrapping_add(1), 3)]),
+            // LD (nn),A / LD A,(nn)
+            _ => v.extend([Mem(n1, 3), Mem(n2, 3), Mem(nn, 3)]),
+        },
+        // INC rr / DEC rr
+        (0, 3) => rep(v, r.ir, 2),
+        // INC (HL) / DEC (HL) (con registro no acceden)
+        (0, 4) | (0, 5) if y == 6 => v.extend([Mem(hl, 3), Mem(hl, 1), Mem(hl, 3)]),
+        // LD r,n / LD (HL),n
+        (0, 6) => {
+            v.push(Mem(n1, 3));
+            if y == 6 {
+                v.push(Mem(hl, 3));
+            }
+        }
+        (0, _) => {}
+
+        // RET cc
+        (3, 0) => {
+            rep(v, r.ir, 1);
+            if cycles == 11 {
+                v.extend([Mem(sp, 3), Mem(sp.wrapping_add(1), 3)]);
+            }
+        }
+        (3, 1) => match y {
+            // POP rr / RET
+            0 | 1 | 2 | 4 | 6 => v.extend([Mem(sp, 3), Mem(sp.wrapping_add(1), 3)]),
+            // LD SP,HL
+            7 => rep(v, r.ir, 2),
+            // EXX, JP (HL)
+            _ => {}
+        },
+        // JP cc,nn
+        (3, 2) => v.extend([Mem(n1, 3), Mem(n2, 3)]),
+        (3, 3) => match y {
+            // JP nn
+            0 => v.extend([Mem(n1, 3), Mem(n2, 3)]),
+            // OUT (n),A / IN A,(n)
+            2 | 3 => {
+                v.push(Mem(n1, 3));
+                v.push(Access::Io(((r.a as u16) << 8) | n[0] as u16));
+            }
+            // EX (SP),HL
+            4 => {
+                let sp1 = sp.wrapping_add(1);
+                v.extend([Mem(sp, 3), Mem(sp1, 3), Mem(sp1, 1), Mem(sp1, 3), Mem(sp, 3)]);
+                rep(v, sp, 2);
+            }
+            // EX DE,HL, DI, EI
+            _ => {}
+        },
+        // CALL cc,nn
+        (3, 4) => {
+            v.extend([Mem(n1, 3), Mem(n2, 3)]);
+            if cycles == 17 {
+                push_call(v, n2, sp);
+            }
+        }
+        (3, 5) => {
+            if y & 1 == 0 {
+                // PUSH rr
+                rep(v, r.ir, 1);
+                v.extend([Mem(sp.wrapping_sub(1), 3), Mem(sp.wrapping_sub(2), 3)]);
+            } else if y == 1 {
+                // CALL nn
+                v.extend([Mem(n1, 3), Mem(n2, 3)]);
+                push_call(v, n2, sp);
+            }
+        }
+        // ALU A,n
+        (3, 6) => v.push(Mem(n1, 3)),
+        // RST p
+        (3, 7) => {
+            rep(v, r.ir, 1);
+            v.extend([Mem(sp.wrapping_sub(1), 3), Mem(sp.wrapping_sub(2), 3)]);
+        }
+
+        _ => {}
+    }
+}
+
+/// Final de CALL: ciclo interno y dirección de vuelta a la pila
+fn push_call(v: &mut Vec<Access>, n2: u16, sp: u16) {
+    v.extend([
+        Access::Mem(n2, 1),
+        Access::Mem(sp.wrapping_sub(1), 3),
+        Access::Mem(sp.wrapping_sub(2), 3),
+    ]);
+}
+
+/// CB xx: rotaciones, BIT, SET, RES
+fn cb_pattern(pc: u16, hl: u16, op: u8, v: &mut Vec<Access>) {
+    v.extend([Access::Mem(pc, 4), Access::Mem(pc.wrapping_add(1), 4)]);
+
+    if op & 0x07 == 6 {
+        v.extend([Access::Mem(hl, 3), Access::Mem(hl, 1)]);
+        // BIT solo lee
+        if op >> 6 != 1 {
+            v.push(Access::Mem(hl, 3));
+        }
+    }
+}
+
+/// ED xx
+fn ed_pattern(r: &PreRegs, b: &[u8; 4], cycles: u32, v: &mut Vec<Access>) {
+    use Access::Mem;
+
+    let pc = r.pc;
+    let op = b[1];
+    let hl = r.hl;
+    // Las de bloque van de una pasada en una (puertos::exec_io): 21 si
+    // repite, 16 si es la última
+    let repeats = cycles == 21;
+
+    v.extend([Mem(pc, 4), Mem(pc.wrapping_add(1), 4)]);
+
+    match op {
+        // IN r,(C) / OUT (C),r
+        _ if op & 0xC6 == 0x40 => v.push(Access::Io(r.bc)),
+        // SBC HL,rr / ADC HL,rr
+        _ if op & 0xC7 == 0x42 => rep(v, r.ir, 7),
+        // LD (nn),rr / LD rr,(nn)
+        _ if op & 0xC7 == 0x43 => {
+            let nn = u16::from_le_bytes([b[2], b[3]]);
+            v.extend([
+                Mem(pc.wrapping_add(2), 3),
+                Mem(pc.wrapping_add(3), 3),
+                Mem(nn, 3),
+                Mem(nn.wrapping_add(1), 3),
+            ]);
+        }
+        // RETN / RETI
+        _ if op & 0xC7 == 0x45 => v.extend([Mem(r.sp, 3), Mem(r.sp.wrapping_add(1), 3)]),
+        // LD I,A / LD R,A / LD A,I / LD A,R
+        0x47 | 0x4F | 0x57 | 0x5F => rep(v, r.ir, 1),
+        // RRD / RLD
+        0x67 | 0x6F => {
+            v.push(Mem(hl, 3));
+            rep(v, hl, 4);
+            v.push(Mem(hl, 3));
+        }
+        // LDI / LDD / LDIR / LDDR
+        0xA0 | 0xA8 | 0xB0 | 0xB8 => {
+            v.extend([Mem(hl, 3), Mem(r.de, 3)]);
+            rep(v, r.de, if repeats { 7 } else { 2 });
+        }
+        // CPI / CPD / CPIR / CPDR
+        0xA1 | 0xA9 | 0xB1 | 0xB9 => {
+            v.push(Mem(hl, 3));
+            rep(v, hl, if repeats { 10 } else { 5 });
+        }
+        // INI / IND / INIR / INDR (puerto con B antes de decrementar)
+        0xA2 | 0xAA | 0xB2 | 0xBA => {
+            rep(v, r.ir, 1);
+            v.push(Access::Io(r.bc));
+            v.push(Mem(hl, 3));
+            if repeats {
+                rep(v, hl, 5);
+            }
+        }
+        // OUTI / OUTD / OTIR / OTDR (puerto con B ya decrementado)
+        0xA3 | 0xAB | 0xB3 | 0xBB => {
+            let bc = r.bc.wrapping_sub(0x0100);
+            rep(v, r.ir, 1);
+            v.push(Mem(hl, 3));
+            v.push(Access::Io(bc));
+            if repeats {
+                rep(v, bc, 5);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// DD/FD xx: `ii` es IX o IY
+fn indexed_pattern(r: &PreRegs, ii: u16, b: &[u8; 4], cycles: u32, v: &mut Vec<Access>) {
+    use Access::Mem;
+
+    let pc = r.pc;
+    let op = b[1];
+    let n2 = pc.wrapping_add(2);
+    let n3 = pc.wrapping_add(3);
+    let addr = ii.wrapping_add(b[2] as i8 as u16);
+    let (x, y, z) = (op >> 6, (op >> 3) & 7, op & 7);
+
+    let uses_index = (x == 1 && op != 0x76 && (y == 6 || z == 6))
+        || (x == 2 && z == 6)
+        || matches!(op, 0x34..=0x36);
+
+    if op == 0xCB {
+        // DD CB d op
+        v.extend([Mem(pc, 4), Mem(pc.wrapping_add(1), 4), Mem(n2, 3), Mem(n3, 3)]);
+        rep(v, n3, 2);
+        v.extend([Mem(addr, 3), Mem(addr, 1)]);
+        if b[3] >> 6 != 1 {
+            v.push(Mem(addr, 3));
+        }
+    } else if op == 0x36 {
+        // LD (IX+d),n
+        v.extend([Mem(pc, 4), Mem(pc.wrapping_add(1), 4), Mem(n2, 3), Mem(n3, 3)]);
+        rep(v, n3, 2);
+        v.push(Mem(addr, 3));
+    } else if uses_index {
+        v.extend([Mem(pc, 4), Mem(pc.wrapping_add(1), 4), Mem(n2, 3)]);
+        rep(v, n2, 5);
+        v.push(Mem(addr, 3));
+        // INC (IX+d) / DEC (IX+d)
+        if op == 0x34 || op == 0x35 {
+            v.extend([Mem(addr, 1), Mem(addr, 3)]);
+        }
+    } else {
+        // Resto: prefijo + instrucción normal con IX/IY en lugar de HL
+        v.push(Mem(pc, 4));
+        main_pattern(r, pc.wrapping_add(1), ii, op, [b[2], b[3]], cycles.saturating_sub(4), v);
+    }
+}
@@ -1,7 +1,10 @@
 use zilog_z80::cpu::CPU;
 use std::collections::{HashMap, HashSet};
 use crate::bus::ZxBus;
+use crate::constantes::{INT_ACK_TSTATES, INT_ACK_TSTATES_IM2, INT_VECTOR_BYTE, NMI_ACK_TSTATES, NMI_ADDR};
+use crate::contencion::{instr_accesses, PreRegs};
 use crate::stack_tracker::{StackTracker, StackWriteKind};
+use crate::watchpoints::{data_accesses, MemAccess};
 
 /* ==================================================
  * SNAPSHOT DE CPU
@@ -149,14 +152,18 @@ pub struct CpuSnapshot {
 /* ==================================================
  * ESTADO DE EJECUCIÓN
  * ================================================== */
+#[derive(Copy, Clone, Debug)]
 pub struct CpuRunState {
     pub halted: bool,
     pub iff1: bool,
+    pub iff2: bool,
     pub iff1_pending: bool,
     pub iff1_delay: u8,
     pub im: u8,
     pub t_states: u64,
     pub allow_interrupts: bool,
+    /// NMI pedida: se acepta antes de la siguiente instrucción
+    pub nmi_pending: bool,
 }
 
 impl CpuRunState {
@@ -164,15 +171,23 @@ impl CpuRunState {
         Self {
             halted: false,
             iff1: true, // ⬅️ ANTES estaba en false (esto mataba el cursor)
+            iff2: true,
             iff1_pending: false,
             iff1_delay: 0,
             im: 1,
             t_states: 0,
             allow_interrupts: true,
+            nmi_pending: false,
         }
     }
 }
 
+impl Default for CpuRunState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 /* ==================================================
  * TRACKER DE INSTRUCCIONES (UNIMPL)
  * ================================================== */
@@ -198,6 +213,12 @@ impl UnimplTracker {
     }
 }
 
+impl Default for UnimplTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 // Carga una ROM en la memoria de la CPU
 pub fn load_rom(cpu: &mut CPU, path: &str) {
     // Limpiar memoria
@@ -254,6 +275,7 @@ pub fn init_cpu_with_test(rom_path: &str, test_path: &str) -> CPU {
  * STEP (EJECUCIÓN)
  * ================================================== */
 
+#[allow(clippy::too_many_arguments)]
 pub fn step(
     cpu: &mut CPU,
     zx_bus: &mut ZxBus,
@@ -269,19 +291,40 @@ pub fn step(
     // averiguamos antes de ejecutar el valor de F para poner colores
     let f_before = (cpu.reg.get_af() & 0x00FF) as u8;
 
+    // NMI: no depende de IFF1 y también saca del HALT
+    if run_state.nmi_pending {
+        let cycles = accept_nmi(cpu, zx_bus, run_state, stack_tracker);
+        return snapshot(cpu, pc_before, false, f_before, 0, cycles);
+    }
+
+    // En HALT la CPU ejecuta NOPs (sin avanzar el PC) hasta la INT
     if run_state.halted {
-        if interrupt_pending && run_state.iff1 {
-            run_state.halted = false;
+        let cycles = if interrupt_pending && run_state.iff1 && run_state.allow_interrupts {
+            accept_interrupt(cpu, zx_bus, run_state, stack_tracker)
         } else {
             run_state.t_states += 4;
-            return snapshot(cpu, pc_before, false, f_before, 0, 4);
-        }
+            4
+        };
+        return snapshot(cpu, pc_before, false, f_before, 0, cycles);
+    }
+
+    // Carga rápida: la rutina LD-BYTES de la ROM se sustituye por
+    // la copia directa del siguiente bloque de la cinta. En el 128K solo
+    // con la ROM 1 (BASIC 48): en la 0x0556 de la ROM 0 hay otro código
+    if pc_before == crate::formatos::tap::LD_BYTES
+        && zx_bus.mem128.as_ref().is_none_or(|m| m.rom_selected() == 1)
+        && let Some(cycles) = crate::formatos::tap::ld_bytes_trap(cpu, zx_bus, run_state)
+    {
+        zx_bus.trace.record(cpu, run_state.t_states, pc_before, &[], "LD-BYTES (TRAP)");
+        run_state.t_states += cycles as u64;
+        executed.insert(pc_before, (1, "LD-BYTES (TRAP)".to_string()));
+        return snapshot(cpu, pc_before, from_step, f_before, 0, cycles);
     }
 
     // Leemos bytes para el desensamblador
     let mut instr_bytes = [0u8; 4];
-    for i in 0..4 {
-        instr_bytes[i] = cpu.bus.read_byte(pc_before.wrapping_add(i as u16));
+    for (i, b) in instr_bytes.iter_mut().enumerate() {
+        *b = cpu.bus.read_byte(pc_before.wrapping_add(i as u16));
     }
 
     let (mnemonic, instr_len) = crate::disasm::disassemble(&instr_bytes, pc_before, pc_before);
@@ -290,76 +333,88 @@ pub fn step(
         unimpl.report(pc_before, &instr_bytes[..instr_len as usize], &mnemonic);
     }
 
-    // 1. Intercepción universal de entrada de puertos ANTES de ejecutar
-    // El opcode 0xDB es "IN A, (n)"
-    if instr_bytes[0] == 0xDB {
-        let n = instr_bytes[1];
-        let port = ((cpu.reg.a as u16) << 8) | (n as u16);
-
-        // Si es el puerto de teclado (bit 0 = 0)
-        if (port & 0x01) == 0 {
-            let val = zx_bus.in_port(port);
-
-            cpu.reg.a = val;
-            // Nota: IN A, (n) NO afecta a los flags en un Z80 real.
-            cpu.reg.pc = pc_before.wrapping_add(2);
-            run_state.t_states += 11;
-
-            executed.insert(pc_before, (2, mnemonic));
-            return snapshot(cpu, pc_before, false, f_before, 2, 11);
-        }
+    // Traza: registros de antes de ejecutar
+    zx_bus.trace.record(cpu, run_state.t_states, pc_before, &instr_bytes[..instr_len as usize], &mnemonic);
+
+    // Registros y paginación de antes de ejecutar (para la contención)
+    let pre = PreRegs::capture(cpu);
+    let c000_contended = zx_bus.mem128.as_ref().is_some_and(|m| m.paged_bank() & 1 == 1);
+    // Watchpoints: valores de memoria que la instrucción puede sobrescribir
+    let watch_old = zx_bus.watch.before(cpu, &pre, &instr_bytes);
+    // Bytes que la instrucción puede sobrescribir: para el historial y
+    // para copiarlos a la otra ventana del banco 5 o 2 en el 128K
+    let writes: Vec<u16> = if zx_bus.journal.is_some() || zx_bus.mem128.as_ref().is_some_and(|m| m.aliased()) {
+        data_accesses(&pre, &instr_bytes, None)
+            .into_iter()
+            .filter_map(|a| match a {
+                MemAccess::Write(addr) => Some(addr),
+                _ => None,
+            })
+            .collect()
+    } else {
+        Vec::new()
+    };
+    for &addr in &writes {
+        zx_bus.journal_write(&cpu.bus, addr);
     }
 
-    /*// Intercepción para IN A, (C) -> Opcode ED 78
-    if instr_bytes[0] == 0xED && instr_bytes[1] == 0x78 {
-        let port = cpu.reg.get_bc(); // Usa el registro BC completo como puerto
-        if (port & 0x01) == 0 {
-            cpu.reg.a = zx_bus.in_port(port);
-            cpu.reg.pc = pc_before.wrapping_add(2);
-            run_state.t_states += 12;
-            return snapshot(cpu, pc_before, false, f_before, 2, 12);
-        }
-    }*/
-
-    // 2. Intercepción universal para IN r, (C) -> Opcodes ED 40 a ED 78
-    if instr_bytes[0] == 0xED && (instr_bytes[1] & 0xC7 == 0x40) {
-        let port = cpu.reg.get_bc();
-        if (port & 0x01) == 0 {
-            let val = zx_bus.in_port(port);
-
-            // 1. Guardar el valor en el registro correspondiente
-            match (instr_bytes[1] >> 3) & 0x07 {
-                0 => cpu.reg.b = val,
-                1 => cpu.reg.c = val,
-                2 => cpu.reg.d = val,
-                3 => cpu.reg.e = val,
-                4 => cpu.reg.h = val,
-                5 => cpu.reg.l = val,
-                7 => cpu.reg.a = val,
-                _ => {} // Caso 6 es IN (C) que solo afecta a flags
+    // Las instrucciones de E/S (IN/OUT y sus variantes de bloque) se
+    // ejecutan fuera del core y se despachan al ZxBus; LDIR/CPIR y
+    // familia también, una pasada por step
+    let instr_cycles = match crate::puertos::exec_io(cpu, zx_bus, &instr_bytes, run_state.t_states) {
+        Some(cycles) => {
+            // 128K: un OUT a 0x7FFD cambia los bancos del bus
+            if let Some(mem) = zx_bus.mem128.as_mut() {
+                mem.apply_pending(&mut cpu.bus);
             }
-
-            // 2. Actualizar FLAGS
-            // En zilog_z80, el registro F es el byte bajo de AF.
-            let mut f = (cpu.reg.get_af() & 0xFF00); // Limpiamos los flags viejos
-            let mut new_f: u8 = 0;
-
-            if val & 0x80 != 0 { new_f |= 0x80; } // Sign
-            if val == 0 { new_f |= 0x40; }        // Zero
-            if val.count_ones() % 2 == 0 { new_f |= 0x04; } // Parity
-
-            // El bit 1 (N) se pone a 0 en instrucciones IN
-            // El bit 4 (H) se pone a 0 en instrucciones IN
-
-            cpu.reg.set_af(f | (new_f as u16));
-
-            cpu.reg.pc = pc_before.wrapping_add(2);
-            run_state.t_states += 12;
-            return snapshot(cpu, pc_before, false, f_before, 2, 12);
+            cycles
+        }
+        // HALT no llega al core: se queda parado para siempre. El PC
+        // apunta a la instrucción siguiente, que es la que apila la INT.
+        None if instr_bytes[0] == 0x76 => {
+            cpu.reg.pc = pc_before.wrapping_add(1);
+            4
+        }
+        // La tabla de ciclos del core da 0 T al NOP
+        None if instr_bytes[0] == 0x00 => {
+            cpu.execute();
+            4
         }
+        // Y 0 T a RETN/RETI (ED 45/4D): todos sus códigos cuestan 14 T
+        None if instr_bytes[0] == 0xED && instr_bytes[1] & 0xC7 == 0x45 => {
+            cpu.execute();
+            14
+        }
+        None => cpu.execute(),
+    };
+
+    // IM, RETN/RETI y LD A,I / LD A,R usan los IFF y el modo de
+    // CpuRunState, no los internos del core
+    if instr_bytes[0] == 0xED {
+        match instr_bytes[1] {
+            0x46 | 0x4E | 0x66 | 0x6E => run_state.im = 0,
+            0x56 | 0x76 => run_state.im = 1,
+            0x5E | 0x7E => run_state.im = 2,
+            0x45 | 0x4D | 0x55 | 0x5D | 0x65 | 0x6D | 0x75 | 0x7D => run_state.iff1 = run_state.iff2,
+            0x57 | 0x5F => cpu.reg.flags.p = run_state.iff2,
+            _ => {}
+        }
+    }
+
+    for &addr in &writes {
+        zx_bus.mirror_write(&mut cpu.bus, addr);
     }
 
-    let instr_cycles = cpu.execute();
+    zx_bus.watch.after(cpu, &pre, &instr_bytes, instr_cycles, &watch_old);
+
+    // Contención de la ULA: los accesos a memoria contendida se retrasan
+    let instr_cycles = if zx_bus.contention.enabled {
+        let accesses = instr_accesses(&pre, &instr_bytes, instr_cycles);
+        instr_cycles + zx_bus.contention.instr_delay(&accesses, run_state.t_states, c000_contended)
+    } else {
+        instr_cycles
+    };
+    run_state.t_states += instr_cycles as u64;
 
     // -------------- BLOQUE DE DEBUG PRINTLNS ------------------
     // let iy_full = ((cpu.reg.iyh as u16) << 8) | (cpu.reg.iyl as u16);
@@ -401,6 +456,7 @@ pub fn step(
         run_state.iff1_delay -= 1;
         if run_state.iff1_delay == 0 {
             run_state.iff1 = true;
+            run_state.iff2 = true;
             run_state.iff1_pending = false;
         }
     }
@@ -412,30 +468,14 @@ pub fn step(
         }
         0xF3 => {
             run_state.iff1 = false;
+            run_state.iff2 = false;
             run_state.iff1_pending = false;
         }
         0x76 => { run_state.halted = true; }
         _ => {}
     }
 
-    if interrupt_pending && run_state.iff1 && run_state.allow_interrupts {
-        run_state.halted = false;
-        run_state.iff1 = false;
-
-        let pc_at_int = cpu.reg.pc;
-
-        let sp = cpu.reg.sp.wrapping_sub(2);
-        cpu.reg.sp = sp;
-        cpu.bus.write_byte(sp, (pc_at_int & 0x00FF) as u8);
-        cpu.bus.write_byte(sp.wrapping_add(1), (pc_at_int >> 8) as u8);
-
-        cpu.reg.pc = 0x0038;
-        run_state.t_states += 13;
-
-        return snapshot(cpu, pc_at_int, false, f_before, 0, 13);
-    }
-
-    // Tracking stack
+    // Tracking stack (antes de la INT, que también apila)
     let sp_after = cpu.reg.sp;
     if sp_after < sp_before {
         let kind = if mnemonic.starts_with("CALL") || mnemonic.starts_with("RST") {
@@ -450,12 +490,102 @@ pub fn step(
         }
     }
 
-    run_state.t_states += instr_cycles as u64;
+    // La INT se mira al final de cada instrucción (no justo después de EI,
+    // que deja iff1 pendiente una instrucción más)
+    if interrupt_pending && run_state.iff1 && run_state.allow_interrupts {
+        let pc_at_int = cpu.reg.pc;
+        let ack_cycles = accept_interrupt(cpu, zx_bus, run_state, stack_tracker);
+
+        // Los ciclos de la instrucción más los de aceptar la INT
+        return snapshot(cpu, pc_at_int, false, f_before, 0, instr_cycles + ack_cycles);
+    }
+
     executed.insert(pc_before, (instr_len, mnemonic));
 
     snapshot(cpu, pc_before, from_step, f_before, instr_len, instr_cycles)
 }
 
+/* ==================================================
+ * INTERRUPCIONES
+ * ================================================== */
+
+/// Acepta la INT enmascarable: apila el PC y salta según el modo.
+///   - IM 0: ejecuta el byte del bus de datos; el bus flotante da 0xFF
+///     (RST 38), así que acaba igual que IM 1. 13 T-states.
+///   - IM 1: RST 38. 13 T-states.
+///   - IM 2: salta a la dirección guardada en I*256 + byte del bus.
+///     19 T-states.
+///
+/// Devuelve los T-states de la aceptación (ya sumados a `run_state`)
+pub fn accept_interrupt(
+    cpu: &mut CPU,
+    zx_bus: &mut ZxBus,
+    run_state: &mut CpuRunState,
+    stack_tracker: &mut StackTracker,
+) -> u32 {
+    run_state.halted = false;
+    run_state.iff1 = false;
+    run_state.iff2 = false;
+    run_state.iff1_pending = false;
+
+    push_pc(cpu, zx_bus, stack_tracker);
+
+    let cycles = match run_state.im {
+        2 => {
+            let vector = ((cpu.reg.i as u16) << 8) | INT_VECTOR_BYTE as u16;
+            let lo = cpu.bus.read_byte(vector) as u16;
+            let hi = cpu.bus.read_byte(vector.wrapping_add(1)) as u16;
+            cpu.reg.pc = (hi << 8) | lo;
+            INT_ACK_TSTATES_IM2
+        }
+        _ => {
+            cpu.reg.pc = 0x0038;
+            INT_ACK_TSTATES
+        }
+    };
+
+    run_state.t_states += cycles as u64;
+    cycles
+}
+
+/// Acepta la NMI: guarda IFF1 en IFF2 (RETN lo recupera), apila el PC
+/// y salta a 0x0066. 11 T-states.
+pub fn accept_nmi(
+    cpu: &mut CPU,
+    zx_bus: &mut ZxBus,
+    run_state: &mut CpuRunState,
+    stack_tracker: &mut StackTracker,
+) -> u32 {
+    run_state.nmi_pending = false;
+    run_state.halted = false;
+    // Un EI recién ejecutado ya ha puesto los IFF (solo retrasa la INT)
+    run_state.iff2 = run_state.iff1 || run_state.iff1_pending;
+    run_state.iff1 = false;
+    run_state.iff1_pending = false;
+
+    push_pc(cpu, zx_bus, stack_tracker);
+    cpu.reg.pc = NMI_ADDR;
+
+    run_state.t_states += NMI_ACK_TSTATES as u64;
+    NMI_ACK_TSTATES
+}
+
+/// Apila el PC como lo hace una interrupción (con historial y tracker)
+fn push_pc(cpu: &mut CPU, zx_bus: &mut ZxBus, stack_tracker: &mut StackTracker) {
+    let pc = cpu.reg.pc;
+    let sp = cpu.reg.sp.wrapping_sub(2);
+    cpu.reg.sp = sp;
+
+    zx_bus.journal_write(&cpu.bus, sp);
+    zx_bus.journal_write(&cpu.bus, sp.wrapping_add(1));
+    cpu.bus.write_byte(sp, (pc & 0x00FF) as u8);
+    cpu.bus.write_byte(sp.wrapping_add(1), (pc >> 8) as u8);
+    zx_bus.mirror_write(&mut cpu.bus, sp);
+    zx_bus.mirror_write(&mut cpu.bus, sp.wrapping_add(1));
+    stack_tracker.record(sp, StackWriteKind::Interrupt, pc);
+    stack_tracker.record(sp.wrapping_add(1), StackWriteKind::Interrupt, pc);
+}
+
 /* ==================================================
  * SNAPSHOT (BUFFER AMPLIADO PARA GUI)
  * ================================================== */
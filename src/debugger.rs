@@ -1,3 +1,6 @@
+use crate::breakpoints::Breakpoints;
+use zilog_z80::cpu::CPU;
+
 #[derive(Copy, Clone, PartialEq, Eq, Debug)]
 pub enum RunMode {
     Step,
@@ -7,12 +10,43 @@ pub enum RunMode {
 }
 
 /* ==================================================
- * BREAKPOINT (POR AHORA FIJO)
+ * OBJETIVOS DE EJECUCIÓN (step over / step out / run to)
+ * ================================================== */
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum RunTarget {
+    /// Parar al llegar a `pc`; con `min_sp`, solo con la pila a esa
+    /// altura o por encima (no dentro de una llamada recursiva)
+    Pc { pc: u16, min_sp: Option<u16> },
+    /// Parar cuando se retira de la pila la dirección de vuelta
+    /// guardada en `slot` (RET, RETI o un POP que la descarta)
+    Return { slot: u16 },
+}
+
+impl RunTarget {
+    pub fn reached(&self, cpu: &CPU) -> bool {
+        let sp = cpu.reg.sp;
+        match *self {
+            RunTarget::Pc { pc, min_sp } => {
+                cpu.reg.pc == pc && min_sp.is_none_or(|min| sp.wrapping_sub(min) as i16 >= 0)
+            }
+            RunTarget::Return { slot } => sp.wrapping_sub(slot) as i16 > 0,
+        }
+    }
+}
+
+/* ==================================================
+ * CONSOLA DE COMANDOS (ventana de debug)
  * ================================================== */
 
-//pub const BREAKPOINT_ADDR: u16 = 0x0038;
-pub const BREAKPOINT_ADDR: u16 = 0xFFFF; // sin Breakpoint
-//pub const BREAKPOINT_ADDR: u16 = 0x8007;
+#[derive(Default)]
+pub struct DebugConsole {
+    /// Recibiendo texto (el teclado no va al Spectrum)
+    pub active: bool,
+    pub input: String,
+    /// Resultado del último comando
+    pub output: String,
+}
 
 /* ==================================================
  * DEBUGGER
@@ -20,31 +54,64 @@ pub const BREAKPOINT_ADDR: u16 = 0xFFFF; // sin Breakpoint
 
 pub struct Debugger {
     pub mode: RunMode,
+    pub breakpoints: Breakpoints,
+    pub console: DebugConsole,
+    /// Hasta dónde ejecutar (step over / step out / run to)
+    pub target: Option<RunTarget>,
+    /// Dirección marcada en la ventana de instrucciones (run to cursor)
+    pub cursor: Option<u16>,
+    /// Al reanudar no se para en el breakpoint en el que estamos
+    resuming: bool,
 }
 
 impl Debugger {
     pub fn new() -> Self {
         Self {
             mode: RunMode::Paused,
+            breakpoints: Breakpoints::new(),
+            console: DebugConsole::default(),
+            target: None,
+            cursor: None,
+            resuming: false,
         }
     }
 
     /// Devuelve true si hay que parar antes de ejecutar la instrucción
-    pub fn check_breakpoint(&mut self, pc: u16) -> bool {
+    pub fn check_breakpoint(&mut self, cpu: &CPU) -> bool {
+        if std::mem::take(&mut self.resuming) {
+            return false;
+        }
+
         match self.mode {
-            RunMode::Run | RunMode::RunFast => {
-                if pc == BREAKPOINT_ADDR {
-                    self.mode = RunMode::Paused;
-                    return true;
-                }
+            RunMode::Run | RunMode::RunFast if self.breakpoints.check(cpu) => {
+                self.pause();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Tras ejecutar una instrucción: true si se ha llegado al objetivo
+    pub fn check_target(&mut self, cpu: &CPU) -> bool {
+        match self.target {
+            Some(target) if target.reached(cpu) => {
+                self.pause();
+                true
             }
-            _ => {}
+            _ => false,
         }
-        false
+    }
+
+    /// Corre (con render) hasta alcanzar `target`
+    pub fn run_until(&mut self, target: RunTarget) {
+        self.run();
+        self.target = Some(target);
     }
 
     pub fn run(&mut self) {
+        self.resuming = self.mode != RunMode::Run;
         self.mode = RunMode::Run;
+        self.target = None;
     }
 
     pub fn step(&mut self) {
@@ -53,9 +120,18 @@ impl Debugger {
 
     pub fn pause(&mut self) {
         self.mode = RunMode::Paused;
+        self.target = None;
     }
 
     pub fn run_fast(&mut self) {
+        self.resuming = self.mode != RunMode::RunFast;
         self.mode = RunMode::RunFast;
+        self.target = None;
+    }
+}
+
+impl Default for Debugger {
+    fn default() -> Self {
+        Self::new()
     }
 }
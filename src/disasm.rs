@@ -109,7 +109,7 @@ pub fn disassemble(mem: &[u8], pc: u16, base: u16) -> (String, u8) {
         0x06 | 0x0E | 0x16 | 0x1E |
         0x26 | 0x2E | 0x36 | 0x3E => decode_ld_r_n(mem, index, b0),
 
-        0x40..=0x7F => decode_ld_r_r__halt(b0),
+        0x40..=0x7F => decode_ld_r_r_halt(b0),
         0x22 | 0x2A => decode_ld_nn_hl(mem, index, b0),
         0x32 | 0x3A => decode_ld_nn_a(mem, index, b0),
 
@@ -248,7 +248,7 @@ fn decode_ld_r_n(mem: &[u8], index: usize, b0: u8) -> (String, u8) {
     (format!("LD {},0x{:02X}", regs()[r as usize], n), 2)
 }
 
-fn decode_ld_r_r__halt(b0: u8) -> (String, u8) {
+fn decode_ld_r_r_halt(b0: u8) -> (String, u8) {
     if b0 == 0x76 {
         ("HALT".to_string(), 1)
     } else {
@@ -1,5 +1,3 @@
-use std::fs::File;
-use std::io::Read;
 use std::path::Path;
 use crate::cpu_exec::CpuRunState;
 use zilog_z80::cpu::CPU;
@@ -74,6 +72,26 @@ pub fn load_bin(
         cpu.reg.pc = org;
     }
 
+    clean_state(run_state);
+    Ok(())
+}
+
+/// Código sin cabecera en `org` (el PC empieza ahí)
+pub fn load_bin_at(
+    cpu: &mut CPU,
+    run_state: &mut CpuRunState,
+    data: &[u8],
+    org: u16,
+) {
+    for (i, b) in data.iter().enumerate() {
+        cpu.bus.write_byte(org.wrapping_add(i as u16), *b);
+    }
+    cpu.reg.pc = org;
+
+    clean_state(run_state);
+}
+
+fn clean_state(run_state: &mut CpuRunState) {
     // Estado limpio
     run_state.halted = false;
     run_state.allow_interrupts = false;
@@ -84,8 +102,6 @@ pub fn load_bin(
     run_state.iff1_pending = false;
 
     run_state.im = 1;
-
-    Ok(())
 }
 
 
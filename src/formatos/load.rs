@@ -1,8 +1,13 @@
 use std::path::Path;
+#[cfg(feature = "sdl-frontend")]
+use std::path::PathBuf;
 use zilog_z80::cpu::CPU;
+use crate::bus::ZxBus;
+#[cfg(feature = "sdl-frontend")]
 use rfd::FileDialog;
 use crate::cpu_exec::CpuRunState;
-use crate::formatos::{bin, sna, z80};
+use crate::formatos::{bin, sna, tap, tzx, z80};
+use crate::memoria::Memory128;
 
 /// Resultado de la carga (para la UI)
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -11,25 +16,24 @@ pub enum LoadResult {
     Sna,
     Z80,
     Bin,
+    Tap,
+    Tzx,
 }
 
-/// Ventana de selección y carga automática
-pub fn load_file_dialog(
-    cpu: &mut CPU,
-    run_state: &mut CpuRunState,
-) -> Result<LoadResult, String> {
-    let file = FileDialog::new()
-        .set_title("Cargar ROM / SNA / Z80")
-        .add_filter("ZX Spectrum", &["rom", "sna", "z80", "bin"])
+/// Ventana de selección del fichero a cargar
+#[cfg(feature = "sdl-frontend")]
+pub fn pick_load_file() -> Result<PathBuf, String> {
+    FileDialog::new()
+        .set_title("Cargar ROM / SNA / Z80 / TAP / TZX")
+        .add_filter("ZX Spectrum", &["rom", "sna", "z80", "bin", "tap", "tzx"])
         .pick_file()
-        .ok_or("Carga cancelada")?;
-
-    load_file(cpu, run_state, &file)
+        .ok_or("Carga cancelada".into())
 }
 
 /// Carga según extensión
 pub fn load_file(
     cpu: &mut CPU,
+    zx_bus: &mut ZxBus,
     run_state: &mut CpuRunState,
     path: &Path,
 ) -> Result<LoadResult, String> {
@@ -41,10 +45,10 @@ pub fn load_file(
 
     match ext.as_str() {
         // -----------------------------
-        // ROM 16K (0x0000)
+        // ROM 16K (0x0000); 32K en el 128K
         // -----------------------------
         "rom" => {
-            load_rom(cpu, path)?;
+            load_rom(cpu, zx_bus, path)?;
             Ok(LoadResult::Rom)
         }
         // -----------------------------
@@ -54,7 +58,9 @@ pub fn load_file(
             let snap = sna::SnaSnapshot::load(path)
                 .map_err(|e| e.to_string())?;
 
+            page_48k(cpu, zx_bus);
             sna::apply_sna(cpu, run_state, &snap);
+            zx_bus.border = snap.border;
             Ok(LoadResult::Sna)
         }
         // -----------------------------
@@ -64,7 +70,22 @@ pub fn load_file(
             let snap = z80::Z80Snapshot::load(path)
                 .map_err(|e| e.to_string())?;
 
+            match (&snap.banks_128, zx_bus.mem128.as_mut()) {
+                (Some(banks), Some(mem)) => {
+                    // Primero la paginación, luego los 8 bancos
+                    mem.page(&mut cpu.bus, snap.port_7ffd);
+                    for (n, bank) in banks.iter().enumerate() {
+                        mem.set_bank(&mut cpu.bus, n, bank);
+                    }
+                }
+                (Some(_), None) => {
+                    return Err("Snapshot de 128K: cambia la máquina a 128K".into());
+                }
+                (None, _) => page_48k(cpu, zx_bus),
+            }
+
             z80::apply_z80(cpu, run_state, &snap);
+            zx_bus.border = snap.border;
             Ok(LoadResult::Z80)
         }
         // -----------------------------
@@ -75,16 +96,48 @@ pub fn load_file(
             bin::load_bin(cpu, run_state, path)?;
             Ok(LoadResult::Bin)
         }
+        // -----------------------------
+        // Cinta TAP (se inserta; LOAD "" la lee)
+        // -----------------------------
+        "tap" => {
+            zx_bus.tape.insert(tap::TapFile::load(path)?.into_blocks());
+            Ok(LoadResult::Tap)
+        }
+        // -----------------------------
+        // Cinta TZX (los bloques estándar van por el trap;
+        // el resto se reproduce por la señal EAR)
+        // -----------------------------
+        "tzx" => {
+            zx_bus.tape.insert(tzx::TzxFile::load(path)?.blocks);
+            Ok(LoadResult::Tzx)
+        }
 
         _ => Err("Formato no soportado".into()),
     }
 }
 
-/// Carga de ROM pura (16K en 0x0000)
-fn load_rom(cpu: &mut CPU, path: &Path) -> Result<(), String> {
+/// Un snapshot de 48K en el 128K: ROM 1 (BASIC 48) y paginación bloqueada
+fn page_48k(cpu: &mut CPU, zx_bus: &mut ZxBus) {
+    if let Some(mem) = zx_bus.mem128.as_mut() {
+        mem.page(&mut cpu.bus, 0x30);
+    }
+}
+
+/// Carga de ROM pura (16K en 0x0000; en el 128K las dos ROMs, 32K)
+fn load_rom(cpu: &mut CPU, zx_bus: &mut ZxBus, path: &Path) -> Result<(), String> {
     let data = std::fs::read(path)
         .map_err(|e| format!("ROM: {}", e))?;
 
+    if let Some(mem) = zx_bus.mem128.as_mut() {
+        if data.len() != 32 * 1024 {
+            return Err("La ROM del 128K debe ser de 32 KB".into());
+        }
+        *mem = Memory128::new(&data);
+        mem.reset(&mut cpu.bus, false);
+        cpu.reg.pc = 0x0000;
+        return Ok(());
+    }
+
     if data.len() != 16 * 1024 {
         return Err("La ROM debe ser de 16 KB".into());
     }
@@ -1,4 +1,8 @@
 pub mod sna;
 pub mod z80;
 pub mod load;
+pub mod save;
 pub mod bin;
+pub mod tap;
+pub mod tzx;
+pub mod png;
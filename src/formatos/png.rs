@@ -0,0 +1,99 @@
+use std::path::Path;
+use crate::video::{rgb, FB_H, FB_W};
+
+/* ==================================================
+ * CAPTURA DE PANTALLA EN PNG
+ * ==================================================
+ * PNG RGB de 8 bits sin comprimir: el zlib lleva bloques
+ * "stored" de deflate, así no hace falta ninguna dependencia.
+ * Una captura del framebuffer (352x296) ocupa unos 300 KB.
+ */
+
+const PNG_SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A];
+// Máximo de un bloque "stored" de deflate
+const STORED_MAX: usize = 0xFFFF;
+
+/// Guarda el framebuffer (índices de color 0-15) como PNG
+pub fn save_screenshot(framebuffer: &[u8], path: &Path) -> Result<(), String> {
+    let png = encode(framebuffer, FB_W, FB_H)?;
+    std::fs::write(path, png).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+/// PNG de una imagen de `w` x `h` índices de color del Spectrum
+pub fn encode(pixels: &[u8], w: usize, h: usize) -> Result<Vec<u8>, String> {
+    if pixels.len() != w * h {
+        return Err(format!("Imagen de {} bytes, se esperaban {}x{}", pixels.len(), w, h));
+    }
+
+    // Filas RGB precedidas del filtro 0 (ninguno)
+    let mut raw = Vec::with_capacity(h * (1 + w * 3));
+    for row in pixels.chunks(w) {
+        raw.push(0);
+        for &index in row {
+            let (r, g, b) = rgb(index);
+            raw.extend_from_slice(&[r, g, b]);
+        }
+    }
+
+    let mut ihdr = Vec::with_capacity(13);
+    ihdr.extend_from_slice(&(w as u32).to_be_bytes());
+    ihdr.extend_from_slice(&(h as u32).to_be_bytes());
+    ihdr.extend_from_slice(&[8, 2, 0, 0, 0]); // 8 bits, RGB, deflate, filtro, sin entrelazado
+
+    let mut png = PNG_SIGNATURE.to_vec();
+    chunk(&mut png, b"IHDR", &ihdr);
+    chunk(&mut png, b"IDAT", &zlib_stored(&raw));
+    chunk(&mut png, b"IEND", &[]);
+    Ok(png)
+}
+
+fn chunk(out: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = out.len();
+    out.extend_from_slice(kind);
+    out.extend_from_slice(data);
+    let crc = crc32(&out[start..]);
+    out.extend_from_slice(&crc.to_be_bytes());
+}
+
+/// Flujo zlib con los datos sin comprimir
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len() + data.len() / STORED_MAX * 5 + 16);
+    out.extend_from_slice(&[0x78, 0x01]);
+
+    let mut blocks = data.chunks(STORED_MAX).peekable();
+    if blocks.peek().is_none() {
+        out.extend_from_slice(&[1, 0, 0, 0xFF, 0xFF]);
+    }
+    while let Some(block) = blocks.next() {
+        let last = blocks.peek().is_none();
+        let len = block.len() as u16;
+        out.push(last as u8);
+        out.extend_from_slice(&len.to_le_bytes());
+        out.extend_from_slice(&(!len).to_le_bytes());
+        out.extend_from_slice(block);
+    }
+
+    out.extend_from_slice(&adler32(data).to_be_bytes());
+    out
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+    for &b in data {
+        crc ^= b as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for &x in data {
+        a = (a + x as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
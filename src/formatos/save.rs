@@ -0,0 +1,60 @@
+use std::path::Path;
+use zilog_z80::cpu::CPU;
+use crate::bus::ZxBus;
+#[cfg(feature = "sdl-frontend")]
+use rfd::FileDialog;
+use crate::cpu_exec::CpuRunState;
+use crate::formatos::{sna, z80};
+
+/// Ventana de guardado de snapshot
+#[cfg(feature = "sdl-frontend")]
+pub fn save_file_dialog(
+    cpu: &CPU,
+    zx_bus: &ZxBus,
+    run_state: &CpuRunState,
+) -> Result<(), String> {
+    let file = FileDialog::new()
+        .set_title("Guardar snapshot SNA / Z80")
+        .add_filter("Snapshot Z80", &["z80"])
+        .add_filter("Snapshot SNA", &["sna"])
+        .set_file_name("snapshot.z80")
+        .save_file()
+        .ok_or("Guardado cancelado")?;
+
+    save_file(cpu, zx_bus, run_state, &file)
+}
+
+/// Guarda según extensión
+pub fn save_file(
+    cpu: &CPU,
+    zx_bus: &ZxBus,
+    run_state: &CpuRunState,
+    path: &Path,
+) -> Result<(), String> {
+    let ext = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|s| s.to_ascii_lowercase())
+        .ok_or("Fichero sin extensión")?;
+
+    match ext.as_str() {
+        "sna" if zx_bus.mem128.is_some() => {
+            Err("El .sna de 48K no guarda los bancos del 128K: usa .z80".into())
+        }
+
+        "sna" => sna::SnaSnapshot::capture(cpu, run_state, zx_bus.border)
+            .save(path)
+            .map_err(|e| e.to_string()),
+
+        "z80" => {
+            let mut snap = z80::Z80Snapshot::capture(cpu, run_state, zx_bus.border);
+            if let Some(mem) = &zx_bus.mem128 {
+                snap.port_7ffd = mem.port_7ffd;
+                snap.banks_128 = Some((0..8).map(|n| mem.bank(&cpu.bus, n)).collect());
+            }
+            snap.save(path)
+        }
+
+        _ => Err("Formato no soportado (sna / z80)".into()),
+    }
+}
@@ -1,5 +1,5 @@
 use std::fs::File;
-use std::io::{Read, Result};
+use std::io::{Read, Result, Write};
 use std::path::Path;
 use zilog_z80::cpu::CPU;
 use crate::constantes::SIZE_SNA;
@@ -27,6 +27,7 @@ pub struct SnaSnapshot {
     pub af: u16,
     pub sp: u16,
     pub im: u8,
+    pub border: u8,
 
     // Memoria RAM completa (48 KB)
     pub ram: Vec<u8>,
@@ -34,7 +35,6 @@ pub struct SnaSnapshot {
 
 impl SnaSnapshot {
     /// Carga un fichero .sna (48K)
-
     pub fn load(path: &Path) -> Result<Self> {
         let mut file = File::open(path)?;
         let mut data = Vec::new();
@@ -66,8 +66,7 @@ impl SnaSnapshot {
         let sp = u16::from_le_bytes([data[23], data[24]]);
 
         let im = data[25] & 0x03;
-
-        // data[26] = border color (opcional, no crítico ahora)
+        let border = data[26] & 0x07;
 
         // --- RAM ---
         let ram = data[27..].to_vec();
@@ -88,9 +87,74 @@ impl SnaSnapshot {
             af,
             sp,
             im,
+            border,
             ram,
         })
     }
+
+    /// Captura el estado de la máquina.
+    ///
+    /// El formato no guarda el PC: se mete en la pila (en la copia de
+    /// la RAM, la memoria real no se toca) como haría una interrupción.
+    /// En HALT se guarda el PC del HALT, como en el .z80.
+    pub fn capture(cpu: &CPU, run_state: &CpuRunState, border: u8) -> Self {
+        let mut ram: Vec<u8> = (0x4000..=0xFFFFu16).map(|a| cpu.bus.read_byte(a)).collect();
+
+        let sp = cpu.reg.sp.wrapping_sub(2);
+        let pc = crate::formatos::z80::snapshot_pc(cpu, run_state);
+        for (i, b) in pc.to_le_bytes().into_iter().enumerate() {
+            let addr = sp.wrapping_add(i as u16);
+            if addr >= 0x4000 {
+                ram[(addr - 0x4000) as usize] = b;
+            }
+        }
+
+        Self {
+            i: cpu.reg.i,
+            hl_: cpu.alt.get_hl(),
+            de_: cpu.alt.get_de(),
+            bc_: cpu.alt.get_bc(),
+            af_: cpu.alt.get_af(),
+            hl: cpu.reg.get_hl(),
+            de: cpu.reg.get_de(),
+            bc: cpu.reg.get_bc(),
+            iy: cpu.reg.get_iy(),
+            ix: cpu.reg.get_ix(),
+            iff2: run_state.iff2,
+            r: cpu.reg.r,
+            af: cpu.reg.get_af(),
+            sp,
+            im: run_state.im,
+            border: border & 0x07,
+            ram,
+        }
+    }
+
+    /// Imagen .sna (cabecera + RAM)
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut data = Vec::with_capacity(SIZE_SNA);
+
+        data.push(self.i);
+        for rr in [self.hl_, self.de_, self.bc_, self.af_, self.hl, self.de, self.bc, self.iy, self.ix] {
+            data.extend_from_slice(&rr.to_le_bytes());
+        }
+        // Byte 19: bit 2 = IFF2
+        data.push(if self.iff2 { 0x04 } else { 0x00 });
+        data.push(self.r);
+        data.extend_from_slice(&self.af.to_le_bytes());
+        data.extend_from_slice(&self.sp.to_le_bytes());
+        data.push(self.im);
+        data.push(self.border);
+
+        data.extend_from_slice(&self.ram);
+        data
+    }
+
+    /// Guarda un fichero .sna (48K)
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let mut file = File::create(path)?;
+        file.write_all(&self.to_bytes())
+    }
 }
 
 /* pub fn load(path: &str) -> Result<Self> {
@@ -197,6 +261,7 @@ pub fn apply_sna(cpu: &mut CPU, run_state: &mut CpuRunState, sna: &SnaSnapshot)
     // Interrupciones
     // -------------------------
     run_state.iff1 = sna.iff2;
+    run_state.iff2 = sna.iff2;
     run_state.iff1_pending = false;
     run_state.im = sna.im;
     run_state.halted = false;
@@ -0,0 +1,195 @@
+use std::path::Path;
+use zilog_z80::cpu::CPU;
+use crate::bus::ZxBus;
+use crate::cpu_exec::CpuRunState;
+use crate::formatos::tzx::TzxBlock;
+
+/// Dirección de la rutina LD-BYTES de la ROM 48K
+pub const LD_BYTES: u16 = 0x0556;
+
+/// Variable del sistema BORDCR (color del borde en bits 3-5)
+const BORDCR: u16 = 0x5C48;
+
+/// Cinta .tap
+///
+/// Formato: secuencia de bloques, cada uno con
+/// - 2 bytes de longitud (little endian)
+/// - flag (0x00 cabecera, 0xFF datos) + datos + checksum (XOR de todo)
+pub struct TapFile {
+    pub blocks: Vec<Vec<u8>>,
+}
+
+impl TapFile {
+    /// Carga un fichero .tap
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let data = std::fs::read(path)
+            .map_err(|e| format!("TAP: {}", e))?;
+
+        Self::from_bytes(&data)
+    }
+
+    /// Separa los bloques de una imagen .tap en memoria
+    pub fn from_bytes(data: &[u8]) -> Result<Self, String> {
+        let mut blocks = Vec::new();
+        let mut pos = 0;
+
+        while pos < data.len() {
+            if pos + 2 > data.len() {
+                return Err("TAP: longitud de bloque incompleta".into());
+            }
+
+            let len = u16::from_le_bytes([data[pos], data[pos + 1]]) as usize;
+            pos += 2;
+
+            if pos + len > data.len() {
+                return Err(format!("TAP: bloque {} truncado", blocks.len()));
+            }
+
+            blocks.push(data[pos..pos + len].to_vec());
+            pos += len;
+        }
+
+        Ok(Self { blocks })
+    }
+
+    /// Bloques para el reproductor: cada bloque del .tap equivale a
+    /// un bloque estándar del .tzx con 1 s de pausa
+    pub fn into_blocks(self) -> Vec<TzxBlock> {
+        self.blocks
+            .into_iter()
+            .map(|data| TzxBlock::Standard { pause_ms: 1000, data })
+            .collect()
+    }
+}
+
+/* ==================================================
+ * TRAP DE CARGA RÁPIDA (LD-BYTES)
+ * ==================================================
+ * Entrada de LD-BYTES (0x0556):
+ *   A  = flag esperado
+ *   F  = carry a 1 para LOAD, a 0 para VERIFY
+ *   IX = dirección destino
+ *   DE = número de bytes
+ * Salida (vía SA/LD-RET):
+ *   carry a 1 si todo fue bien, a 0 si hubo error
+ *   IX incrementado y DE decrementado por los bytes leídos
+ *   borde restaurado desde BORDCR e interrupciones habilitadas
+ */
+
+/// Ejecuta LD-BYTES copiando el siguiente bloque de la cinta.
+///
+/// Devuelve los T-states a contabilizar, o `None` si no hay un bloque
+/// estándar a continuación (la ROM sigue su curso normal). En ese caso,
+/// si quedan bloques (turbo, etc.) se pone la cinta en marcha para que
+/// la ROM o el cargador los lea de la señal EAR.
+pub fn ld_bytes_trap(cpu: &mut CPU, zx_bus: &mut ZxBus, run_state: &mut CpuRunState) -> Option<u32> {
+    let Some(block) = zx_bus.tape.take_rom_block() else {
+        if !zx_bus.tape.is_playing() {
+            zx_bus.tape.play(run_state.t_states);
+        }
+        return None;
+    };
+
+    let expected_flag = cpu.reg.a;
+    let load = cpu.reg.flags.c;
+    let mut ix = cpu.reg.get_ix();
+    let mut de = cpu.reg.get_de();
+
+    let mut ok = false;
+    let mut last = 0u8;
+    let mut parity = 0u8;
+
+    // Flag distinto: la ROM sale por LD-FLAG (XOR C / RET NZ)
+    if let Some(&flag) = block.first() && flag != expected_flag {
+        let a = expected_flag ^ flag;
+        cpu.reg.a = a;
+        let f = &mut cpu.reg.flags;
+        f.s = a & 0x80 != 0;
+        f.z = false;
+        f.h = false;
+        f.p = a.count_ones().is_multiple_of(2);
+        f.n = false;
+        f.c = false;
+
+        sa_ld_ret(cpu, zx_bus, run_state);
+        return Some(10);
+    }
+
+    if let Some(&flag) = block.first() {
+        parity = flag;
+        let data = &block[1..];
+        let mut i = 0;
+        ok = true;
+
+        // Bytes de datos
+        while de > 0 {
+            let Some(&b) = data.get(i) else {
+                ok = false; // cinta más corta de lo pedido
+                break;
+            };
+
+            if load {
+                zx_bus.journal_write(&cpu.bus, ix);
+                cpu.bus.write_byte(ix, b);
+                zx_bus.mirror_write(&mut cpu.bus, ix);
+            } else if cpu.bus.read_byte(ix) != b {
+                ok = false; // VERIFY falló
+                break;
+            }
+
+            parity ^= b;
+            last = b;
+            ix = ix.wrapping_add(1);
+            de = de.wrapping_sub(1);
+            i += 1;
+        }
+
+        // Byte de paridad (checksum)
+        if ok {
+            match data.get(i) {
+                Some(&b) => {
+                    parity ^= b;
+                    ok = parity == 0;
+                }
+                None => ok = false,
+            }
+        }
+    }
+
+    cpu.reg.set_ix(ix);
+    cpu.reg.set_de(de);
+    cpu.reg.h = parity;
+    cpu.reg.l = last;
+
+    // Igual que el final de la ROM: LD A,H / CP 1 -> carry si paridad 0
+    cpu.reg.a = parity;
+    let f = &mut cpu.reg.flags;
+    f.c = ok;
+    f.z = false;
+    f.n = true;
+    if ok {
+        f.s = true;
+        f.h = true;
+    }
+    cpu.reg.b = if ok { 0xB0 } else { 0x00 };
+
+    sa_ld_ret(cpu, zx_bus, run_state);
+    Some(10)
+}
+
+/// SA/LD-RET: restaurar borde, habilitar interrupciones y volver al llamador
+fn sa_ld_ret(cpu: &mut CPU, zx_bus: &mut ZxBus, run_state: &mut CpuRunState) {
+    let border = (cpu.bus.read_byte(BORDCR) >> 3) & 0x07;
+    zx_bus.out_port(0x00FE, border, run_state.t_states);
+    run_state.iff1 = true;
+    run_state.iff2 = true;
+    run_state.iff1_pending = false;
+
+    // RET al llamador de LD-BYTES
+    // (byte a byte: con SP=0xFFFF el alto está en 0x0000)
+    let sp = cpu.reg.sp;
+    let lo = cpu.bus.read_byte(sp) as u16;
+    let hi = cpu.bus.read_byte(sp.wrapping_add(1)) as u16;
+    cpu.reg.pc = hi << 8 | lo;
+    cpu.reg.sp = sp.wrapping_add(2);
+}
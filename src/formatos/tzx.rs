@@ -0,0 +1,448 @@
+use std::path::Path;
+
+/// Firma de cabecera de un .tzx ("ZXTape!" + 0x1A)
+const TZX_SIGNATURE: &[u8; 8] = b"ZXTape!\x1A";
+
+/// Bloque de un fichero .tzx
+///
+/// Los tiempos (pulsos) están en T-states del 48K (3.5 MHz).
+#[derive(Debug, Clone, PartialEq)]
+pub enum TzxBlock {
+    /// 0x10: bloque a velocidad normal de la ROM
+    Standard { pause_ms: u16, data: Vec<u8> },
+
+    /// 0x11: bloque turbo (tiempos configurables)
+    Turbo {
+        pilot: u16,
+        sync1: u16,
+        sync2: u16,
+        zero: u16,
+        one: u16,
+        pilot_len: u16,
+        used_bits: u8,
+        pause_ms: u16,
+        data: Vec<u8>,
+    },
+
+    /// 0x12: tono puro (`count` pulsos de `pulse` T-states)
+    PureTone { pulse: u16, count: u16 },
+
+    /// 0x13: secuencia de pulsos de distinta longitud
+    PulseSeq(Vec<u16>),
+
+    /// 0x14: solo datos (sin tono guía ni sincronismo)
+    PureData {
+        zero: u16,
+        one: u16,
+        used_bits: u8,
+        pause_ms: u16,
+        data: Vec<u8>,
+    },
+
+    /// 0x15: grabación directa (1 bit = 1 muestra del nivel)
+    DirectRecording {
+        tstates_per_sample: u16,
+        pause_ms: u16,
+        used_bits: u8,
+        data: Vec<u8>,
+    },
+
+    /// 0x18: CSW. Cada pulso es un número de muestras a `sample_rate`
+    Csw { pause_ms: u16, sample_rate: u32, pulses: Vec<u32> },
+
+    /// 0x19: datos generalizados.
+    /// `defs`: tabla de símbolos (polaridad del primer pulso, pulsos);
+    /// `symbols`: secuencia de índices en `defs` ya expandida.
+    Generalized { pause_ms: u16, defs: Vec<(u8, Vec<u16>)>, symbols: Vec<u16> },
+
+    /// 0x20: pausa (0 = parar la cinta)
+    Pause(u16),
+
+    /// 0x21 / 0x22: grupo de bloques
+    GroupStart(String),
+    GroupEnd,
+
+    /// 0x23: salto relativo a otro bloque
+    JumpTo(i16),
+
+    /// 0x24 / 0x25: bucle
+    LoopStart(u16),
+    LoopEnd,
+
+    /// 0x2A: parar la cinta si es un 48K
+    StopIf48K,
+
+    /// 0x2B: fijar el nivel de la señal
+    SetLevel(bool),
+
+    /// 0x30 / 0x31: texto descriptivo o mensaje
+    Text(String),
+
+    /// 0x32: información del archivo (id, texto)
+    ArchiveInfo(Vec<(u8, String)>),
+
+    /// Resto de bloques informativos o no soportados (id)
+    Other(u8),
+}
+
+/// Cinta .tzx
+pub struct TzxFile {
+    pub version: (u8, u8),
+    pub blocks: Vec<TzxBlock>,
+}
+
+impl TzxFile {
+    /// Carga un fichero .tzx
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let data = std::fs::read(path)
+            .map_err(|e| format!("TZX: {}", e))?;
+
+        Self::from_bytes(&data)
+    }
+
+    /// Interpreta una imagen .tzx en memoria
+    pub fn from_bytes(data: &[u8]) -> Result<Self, String> {
+        if data.len() < 10 || &data[0..8] != TZX_SIGNATURE {
+            return Err("TZX: cabecera no válida".into());
+        }
+
+        let version = (data[8], data[9]);
+        let mut r = Reader { data, pos: 10 };
+        let mut blocks = Vec::new();
+
+        while !r.at_end() {
+            let id = r.u8()?;
+            blocks.push(parse_block(&mut r, id)?);
+        }
+
+        Ok(Self { version, blocks })
+    }
+}
+
+/* ==================================================
+ * LECTURA DE BLOQUES
+ * ================================================== */
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl Reader<'_> {
+    fn at_end(&self) -> bool {
+        self.pos >= self.data.len()
+    }
+
+    fn bytes(&mut self, n: usize) -> Result<&[u8], String> {
+        if self.pos + n > self.data.len() {
+            return Err(format!("TZX: bloque truncado en offset {}", self.pos));
+        }
+
+        let s = &self.data[self.pos..self.pos + n];
+        self.pos += n;
+        Ok(s)
+    }
+
+    fn u8(&mut self) -> Result<u8, String> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, String> {
+        let b = self.bytes(2)?;
+        Ok(u16::from_le_bytes([b[0], b[1]]))
+    }
+
+    fn u24(&mut self) -> Result<u32, String> {
+        let b = self.bytes(3)?;
+        Ok(u32::from_le_bytes([b[0], b[1], b[2], 0]))
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        let b = self.bytes(4)?;
+        Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    fn text(&mut self, n: usize) -> Result<String, String> {
+        Ok(self.bytes(n)?.iter().map(|&c| c as char).collect())
+    }
+}
+
+fn parse_block(r: &mut Reader, id: u8) -> Result<TzxBlock, String> {
+    let block = match id {
+        0x10 => {
+            let pause_ms = r.u16()?;
+            let len = r.u16()? as usize;
+            TzxBlock::Standard { pause_ms, data: r.bytes(len)?.to_vec() }
+        }
+
+        0x11 => {
+            let pilot = r.u16()?;
+            let sync1 = r.u16()?;
+            let sync2 = r.u16()?;
+            let zero = r.u16()?;
+            let one = r.u16()?;
+            let pilot_len = r.u16()?;
+            let used_bits = r.u8()?;
+            let pause_ms = r.u16()?;
+            let len = r.u24()? as usize;
+            TzxBlock::Turbo {
+                pilot, sync1, sync2, zero, one, pilot_len, used_bits, pause_ms,
+                data: r.bytes(len)?.to_vec(),
+            }
+        }
+
+        0x12 => {
+            let pulse = r.u16()?;
+            let count = r.u16()?;
+            TzxBlock::PureTone { pulse, count }
+        }
+
+        0x13 => {
+            let n = r.u8()? as usize;
+            let mut pulses = Vec::with_capacity(n);
+            for _ in 0..n {
+                pulses.push(r.u16()?);
+            }
+            TzxBlock::PulseSeq(pulses)
+        }
+
+        0x14 => {
+            let zero = r.u16()?;
+            let one = r.u16()?;
+            let used_bits = r.u8()?;
+            let pause_ms = r.u16()?;
+            let len = r.u24()? as usize;
+            TzxBlock::PureData { zero, one, used_bits, pause_ms, data: r.bytes(len)?.to_vec() }
+        }
+
+        0x15 => {
+            let tstates_per_sample = r.u16()?;
+            let pause_ms = r.u16()?;
+            let used_bits = r.u8()?;
+            let len = r.u24()? as usize;
+            TzxBlock::DirectRecording {
+                tstates_per_sample, pause_ms, used_bits,
+                data: r.bytes(len)?.to_vec(),
+            }
+        }
+
+        0x18 => parse_csw(r)?,
+        0x19 => parse_generalized(r)?,
+
+        0x20 => TzxBlock::Pause(r.u16()?),
+
+        0x21 => {
+            let len = r.u8()? as usize;
+            TzxBlock::GroupStart(r.text(len)?)
+        }
+        0x22 => TzxBlock::GroupEnd,
+
+        0x23 => TzxBlock::JumpTo(r.u16()? as i16),
+
+        0x24 => TzxBlock::LoopStart(r.u16()?),
+        0x25 => TzxBlock::LoopEnd,
+
+        // Call sequence / return: no se siguen
+        0x26 => {
+            let n = r.u16()? as usize;
+            r.bytes(n * 2)?;
+            TzxBlock::Other(id)
+        }
+        0x27 => TzxBlock::Other(id),
+
+        // Select block
+        0x28 => {
+            let len = r.u16()? as usize;
+            r.bytes(len)?;
+            TzxBlock::Other(id)
+        }
+
+        0x2A => {
+            r.u32()?;
+            TzxBlock::StopIf48K
+        }
+
+        0x2B => {
+            r.u32()?;
+            TzxBlock::SetLevel(r.u8()? != 0)
+        }
+
+        0x30 => {
+            let len = r.u8()? as usize;
+            TzxBlock::Text(r.text(len)?)
+        }
+
+        0x31 => {
+            let _secs = r.u8()?;
+            let len = r.u8()? as usize;
+            TzxBlock::Text(r.text(len)?)
+        }
+
+        0x32 => {
+            let _len = r.u16()?;
+            let n = r.u8()? as usize;
+            let mut info = Vec::with_capacity(n);
+            for _ in 0..n {
+                let kind = r.u8()?;
+                let len = r.u8()? as usize;
+                info.push((kind, r.text(len)?));
+            }
+            TzxBlock::ArchiveInfo(info)
+        }
+
+        // Hardware type
+        0x33 => {
+            let n = r.u8()? as usize;
+            r.bytes(n * 3)?;
+            TzxBlock::Other(id)
+        }
+
+        // Emulation info (obsoleto): tamaño fijo
+        0x34 => {
+            r.bytes(8)?;
+            TzxBlock::Other(id)
+        }
+
+        // Custom info
+        0x35 => {
+            r.bytes(16)?;
+            let len = r.u32()? as usize;
+            r.bytes(len)?;
+            TzxBlock::Other(id)
+        }
+
+        // Snapshot (obsoleto): tipo y longitud de 3 bytes
+        0x40 => {
+            r.u8()?;
+            let len = r.u24()? as usize;
+            r.bytes(len)?;
+            TzxBlock::Other(id)
+        }
+
+        // Glue (varios .tzx concatenados)
+        0x5A => {
+            r.bytes(9)?;
+            TzxBlock::Other(id)
+        }
+
+        // Desconocido: desde la v1.10 todos empiezan por su longitud
+        _ => {
+            let len = r.u32()? as usize;
+            r.bytes(len)?;
+            TzxBlock::Other(id)
+        }
+    };
+
+    Ok(block)
+}
+
+/// 0x18: CSW v2 dentro del .tzx. Solo se admite compresión RLE;
+/// Z-RLE (zlib) se salta y queda como bloque no soportado.
+fn parse_csw(r: &mut Reader) -> Result<TzxBlock, String> {
+    let len = r.u32()? as usize;
+    let body = r.bytes(len)?;
+
+    if len < 10 {
+        return Err("TZX: bloque CSW demasiado corto".into());
+    }
+
+    let pause_ms = u16::from_le_bytes([body[0], body[1]]);
+    let sample_rate = u32::from_le_bytes([body[2], body[3], body[4], 0]);
+    let compression = body[5];
+
+    if compression != 1 || sample_rate == 0 {
+        return Ok(TzxBlock::Other(0x18));
+    }
+
+    // RLE: cada byte es un pulso; 0 indica que sigue un u32
+    let rle = &body[10..];
+    let mut pulses = Vec::new();
+    let mut i = 0;
+    while i < rle.len() {
+        if rle[i] == 0 && i + 4 < rle.len() {
+            pulses.push(u32::from_le_bytes([rle[i + 1], rle[i + 2], rle[i + 3], rle[i + 4]]));
+            i += 5;
+        } else {
+            pulses.push(rle[i] as u32);
+            i += 1;
+        }
+    }
+
+    Ok(TzxBlock::Csw { pause_ms, sample_rate, pulses })
+}
+
+/// 0x19: datos generalizados. Se expanden aquí los flujos de
+/// tono guía (PRLE) y datos a una lista de símbolos.
+fn parse_generalized(r: &mut Reader) -> Result<TzxBlock, String> {
+    let len = r.u32()? as usize;
+    let body = r.bytes(len)?;
+    let mut b = Reader { data: body, pos: 0 };
+
+    let pause_ms = b.u16()?;
+    let totp = b.u32()?;
+    let npp = b.u8()? as usize;
+    let asp = match b.u8()? { 0 => 256, n => n as usize };
+    let totd = b.u32()?;
+    let npd = b.u8()? as usize;
+    let asd = match b.u8()? { 0 => 256, n => n as usize };
+
+    let mut defs = Vec::new();
+    let mut symbols = Vec::new();
+
+    // Tono guía y sincronismo
+    if totp > 0 {
+        defs = read_symdefs(&mut b, asp, npp)?;
+        for _ in 0..totp {
+            let sym = b.u8()? as usize;
+            let rep = b.u16()?;
+            if sym >= asp {
+                return Err("TZX: símbolo de tono guía no definido".into());
+            }
+            symbols.extend(std::iter::repeat_n(sym as u16, rep as usize));
+        }
+    }
+
+    // Datos: NB bits por símbolo, MSB primero.
+    // Sus definiciones van detrás de las del tono guía
+    if totd > 0 {
+        let base = defs.len();
+        defs.extend(read_symdefs(&mut b, asd, npd)?);
+        let nb = usize::BITS - (asd - 1).leading_zeros();
+        let nb = nb.max(1) as usize;
+        let stream = b.bytes((totd as usize * nb).div_ceil(8))?;
+
+        for n in 0..totd as usize {
+            let mut sym = 0usize;
+            for k in 0..nb {
+                let bit = n * nb + k;
+                let v = (stream[bit / 8] >> (7 - bit % 8)) & 1;
+                sym = (sym << 1) | v as usize;
+            }
+            // Con ASD que no es potencia de 2 caben índices sin definir
+            if sym >= asd {
+                return Err("TZX: símbolo de datos no definido".into());
+            }
+            symbols.push((base + sym) as u16);
+        }
+    }
+
+    Ok(TzxBlock::Generalized { pause_ms, defs, symbols })
+}
+
+/// Tabla de símbolos: flags (polaridad) + `np` pulsos
+fn read_symdefs(r: &mut Reader, count: usize, np: usize) -> Result<Vec<(u8, Vec<u16>)>, String> {
+    let mut defs = Vec::with_capacity(count);
+    for _ in 0..count {
+        let flags = r.u8()? & 0x03;
+        let mut pulses = Vec::with_capacity(np);
+        for _ in 0..np {
+            pulses.push(r.u16()?);
+        }
+        // Un pulso 0 termina el símbolo
+        if let Some(end) = pulses.iter().position(|&p| p == 0) {
+            pulses.truncate(end);
+        }
+        defs.push((flags, pulses));
+    }
+    Ok(defs)
+}
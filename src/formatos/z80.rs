@@ -37,6 +37,11 @@ pub struct Z80Snapshot {
 
     /// RAM 48K (0x4000–0xFFFF)
     pub ram: Vec<u8>,
+
+    /// Último valor escrito en 0x7FFD (solo 128K)
+    pub port_7ffd: u8,
+    /// Bancos 0..7 del 128K (páginas 3..10 del fichero); None en 48K
+    pub banks_128: Option<Vec<Vec<u8>>>,
 }
 
 impl Z80Snapshot {
@@ -62,10 +67,12 @@ impl Z80Snapshot {
         let pc = u16::from_le_bytes([data[6], data[7]]);
         let sp = u16::from_le_bytes([data[8], data[9]]);
         let i = data[10];
-        let r = data[11];
 
-        let flags = data[12];
-        let border = flags & 0x07;
+        // Byte 12: bit 0 = bit 7 de R, bits 1-3 = borde, bit 5 = comprimido
+        // (0xFF se trata como 1 por compatibilidad)
+        let flags = if data[12] == 0xFF { 0x01 } else { data[12] };
+        let r = (data[11] & 0x7F) | ((flags & 0x01) << 7);
+        let border = (flags >> 1) & 0x07;
         let compressed = (flags & 0x20) != 0;
 
         let de = u16::from_le_bytes([data[13], data[14]]);
@@ -84,9 +91,9 @@ impl Z80Snapshot {
         let im = data[29] & 0x03;
 
         if pc == 0 {  // Es version 2 o 3
-            let (pos, real_pc) = read_extended_header(&data)?;
+            let ext = read_extended_header(&data)?;
 
-            let pages = read_ram_blocks(&data, pos)?;
+            let pages = read_ram_blocks(&data, ext.ram_start)?;
 
             return Ok(Z80SnapshotV23 {
                 af,
@@ -100,7 +107,7 @@ impl Z80Snapshot {
                 ix,
                 iy,
                 sp,
-                pc: real_pc,
+                pc: ext.pc,
                 i,
                 r,
                 iff1,
@@ -108,6 +115,8 @@ impl Z80Snapshot {
                 im,
                 border,
                 //compressed: true,
+                is_128k: ext.is_128k(),
+                port_7ffd: ext.port_7ffd,
                 ram_pages: pages,
             }.into());
         }
@@ -170,10 +179,187 @@ impl Z80Snapshot {
 
             border,
             ram,
+
+            port_7ffd: 0,
+            banks_128: None,
         })
     }
 }
 
+/* ==================================================
+ * GUARDADO (.z80 versión 3)
+ * ==================================================
+ * Cabecera de 30 bytes con PC = 0, cabecera extendida de
+ * 54 bytes y las páginas de 16K comprimidas con RLE:
+ *   48K:  página 8 -> 0x4000, 4 -> 0x8000, 5 -> 0xC000
+ *   128K: páginas 3..10 -> bancos 0..7 (modo hardware 4)
+ */
+
+/// PC que se guarda en un snapshot: en HALT, el del propio HALT
+pub fn snapshot_pc(cpu: &CPU, run_state: &CpuRunState) -> u16 {
+    if run_state.halted {
+        cpu.reg.pc.wrapping_sub(1)
+    } else {
+        cpu.reg.pc
+    }
+}
+
+/// Longitud de la cabecera extendida v3
+const Z80_V3_EXT_LEN: u16 = 54;
+/// Modo hardware del 128K en la cabecera v3
+const Z80_HW_128K: u8 = 4;
+
+impl Z80Snapshot {
+    /// Captura el estado de la máquina. En HALT el PC ya apunta a la
+    /// instrucción siguiente: se guarda el del HALT para que, al cargar,
+    /// la CPU siga parada (como Fuse)
+    pub fn capture(cpu: &CPU, run_state: &CpuRunState, border: u8) -> Self {
+        Self {
+            af: cpu.reg.get_af(),
+            bc: cpu.reg.get_bc(),
+            de: cpu.reg.get_de(),
+            hl: cpu.reg.get_hl(),
+
+            af_: cpu.alt.get_af(),
+            bc_: cpu.alt.get_bc(),
+            de_: cpu.alt.get_de(),
+            hl_: cpu.alt.get_hl(),
+
+            ix: cpu.reg.get_ix(),
+            iy: cpu.reg.get_iy(),
+
+            sp: cpu.reg.sp,
+            pc: snapshot_pc(cpu, run_state),
+
+            i: cpu.reg.i,
+            r: cpu.reg.r,
+
+            iff1: run_state.iff1,
+            iff2: run_state.iff2,
+            im: run_state.im,
+
+            border: border & 0x07,
+            ram: (0x4000..=0xFFFFu16).map(|a| cpu.bus.read_byte(a)).collect(),
+
+            port_7ffd: 0,
+            banks_128: None,
+        }
+    }
+
+    /// Imagen .z80 versión 3
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut data = Vec::with_capacity(32 + Z80_V3_EXT_LEN as usize + RAM_LEN_MAX);
+
+        // -------------------------
+        // Cabecera (30 bytes)
+        // -------------------------
+        data.extend_from_slice(&self.af.to_be_bytes()); // A, F
+        data.extend_from_slice(&self.bc.to_le_bytes());
+        data.extend_from_slice(&self.hl.to_le_bytes());
+        data.extend_from_slice(&[0, 0]); // PC = 0: versión 2 o 3
+        data.extend_from_slice(&self.sp.to_le_bytes());
+        data.push(self.i);
+        data.push(self.r & 0x7F);
+        data.push((self.r >> 7) | (self.border << 1));
+        data.extend_from_slice(&self.de.to_le_bytes());
+        data.extend_from_slice(&self.bc_.to_le_bytes());
+        data.extend_from_slice(&self.de_.to_le_bytes());
+        data.extend_from_slice(&self.hl_.to_le_bytes());
+        data.extend_from_slice(&self.af_.to_be_bytes()); // A', F'
+        data.extend_from_slice(&self.iy.to_le_bytes());
+        data.extend_from_slice(&self.ix.to_le_bytes());
+        data.push(self.iff1 as u8);
+        data.push(self.iff2 as u8);
+        data.push(self.im & 0x03);
+
+        // -------------------------
+        // Cabecera extendida: PC, máquina y 0x7FFD; el resto
+        // (AY, periféricos) a 0
+        // -------------------------
+        data.extend_from_slice(&Z80_V3_EXT_LEN.to_le_bytes());
+        let mut ext = [0u8; Z80_V3_EXT_LEN as usize];
+        ext[0..2].copy_from_slice(&self.pc.to_le_bytes());
+        if self.banks_128.is_some() {
+            ext[2] = Z80_HW_128K;
+            ext[3] = self.port_7ffd;
+        }
+        data.extend_from_slice(&ext);
+
+        // -------------------------
+        // Páginas de RAM
+        // -------------------------
+        match &self.banks_128 {
+            Some(banks) => {
+                for (n, bank) in banks.iter().enumerate() {
+                    push_page(&mut data, n as u8 + 3, bank);
+                }
+            }
+            None => {
+                for (page, offset) in [(8u8, 0x0000), (4, 0x4000), (5, 0x8000)] {
+                    push_page(&mut data, page, &self.ram[offset..offset + 0x4000]);
+                }
+            }
+        }
+
+        data
+    }
+
+    /// Guarda un fichero .z80 (versión 3)
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        std::fs::write(path, self.to_bytes()).map_err(|e| e.to_string())
+    }
+}
+
+/// Añade una página (longitud, número, datos).
+/// Si la compresión no gana, la página va tal cual (0xFFFF).
+fn push_page(data: &mut Vec<u8>, page: u8, raw: &[u8]) {
+    let packed = compress_rle(raw);
+
+    if packed.len() >= 0x4000 {
+        data.extend_from_slice(&0xFFFFu16.to_le_bytes());
+        data.push(page);
+        data.extend_from_slice(raw);
+    } else {
+        data.extend_from_slice(&(packed.len() as u16).to_le_bytes());
+        data.push(page);
+        data.extend_from_slice(&packed);
+    }
+}
+
+/// RLE de los .z80: 5 o más bytes iguales -> ED ED n b.
+/// Dos ED seguidos siempre se codifican (ED ED 02 ED) y el byte
+/// que sigue a un ED suelto nunca empieza una secuencia.
+fn compress_rle(src: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(src.len());
+    let mut i = 0;
+
+    while i < src.len() {
+        let b = src[i];
+        let mut run = 1;
+        while i + run < src.len() && src[i + run] == b && run < 255 {
+            run += 1;
+        }
+
+        if run >= 5 || (b == 0xED && run >= 2) {
+            out.extend_from_slice(&[0xED, 0xED, run as u8, b]);
+            i += run;
+        } else if b == 0xED {
+            // ED suelto: el siguiente byte va literal
+            out.push(0xED);
+            i += 1;
+            if i < src.len() {
+                out.push(src[i]);
+                i += 1;
+            }
+        } else {
+            out.push(b);
+            i += 1;
+        }
+    }
+
+    out
+}
+
 /// Aplica un snapshot .z80 v1 a la CPU
 pub fn apply_z80(cpu: &mut CPU, run_state: &mut CpuRunState, snap: &Z80Snapshot) {
     // -------------------------
@@ -211,19 +397,17 @@ pub fn apply_z80(cpu: &mut CPU, run_state: &mut CpuRunState, snap: &Z80Snapshot)
     // Interrupciones
     // -------------------------
     run_state.iff1 = snap.iff1;
+    run_state.iff2 = snap.iff2;
     run_state.iff1_pending = false;
     run_state.im = snap.im;
     run_state.halted = false;
     run_state.allow_interrupts = true;
     run_state.t_states = 0;
 
-    // -------------------------
-    // Border (opcional)
-    // -------------------------
-    //cpu.bus.border = snap.border;
+    // El borde lo restaura quien tiene el ZxBus (load_file)
 }
 
-/// Snapshot .z80 versión 2 o 3 (48K por ahora)
+/// Snapshot .z80 versión 2 o 3 (48K o 128K)
 pub struct Z80SnapshotV23 {
     // ======================
     // Registros CPU (igual que v1)
@@ -258,6 +442,9 @@ pub struct Z80SnapshotV23 {
     // // ======================
     // pub machine_type: u8,   // 48K / 128K / etc
     //pub compressed: bool,
+    /// Modo hardware 128K (páginas 3..10)
+    pub is_128k: bool,
+    pub port_7ffd: u8,
     //
     // // ======================
     // // RAM por páginas
@@ -272,21 +459,38 @@ impl From<Z80SnapshotV23> for Z80Snapshot {
     fn from(v: Z80SnapshotV23) -> Self {
         let mut ram = vec![0u8; 48 * 1024];
 
-        // Page 4 → 0x8000
-        if let Some(p) = v.ram_pages.get(&4) {
+        // `ram` empieza en 0x4000
+
+        // Page 8 → 0x4000
+        if let Some(p) = v.ram_pages.get(&8) {
             ram[0x0000..0x4000].copy_from_slice(p);
         }
 
-        // Page 5 → 0xC000
-        if let Some(p) = v.ram_pages.get(&5) {
+        // Page 4 → 0x8000
+        if let Some(p) = v.ram_pages.get(&4) {
             ram[0x4000..0x8000].copy_from_slice(p);
         }
 
-        // Page 8 → 0x4000
-        if let Some(p) = v.ram_pages.get(&8) {
+        // Page 5 → 0xC000
+        if let Some(p) = v.ram_pages.get(&5) {
             ram[0x8000..0xC000].copy_from_slice(p);
         }
 
+        // 128K: página n+3 → banco n; `ram` queda como la ve la CPU
+        // (bancos 5, 2 y el paginado en 0xC000)
+        let banks_128 = if v.is_128k {
+            let banks: Vec<Vec<u8>> = (0..8u8)
+                .map(|n| v.ram_pages.get(&(n + 3)).cloned().unwrap_or_else(|| vec![0; 0x4000]))
+                .collect();
+
+            ram[0x0000..0x4000].copy_from_slice(&banks[5]);
+            ram[0x4000..0x8000].copy_from_slice(&banks[2]);
+            ram[0x8000..0xC000].copy_from_slice(&banks[(v.port_7ffd & 0x07) as usize]);
+            Some(banks)
+        } else {
+            None
+        };
+
         Z80Snapshot {
             af: v.af,
             bc: v.bc,
@@ -311,6 +515,9 @@ impl From<Z80SnapshotV23> for Z80Snapshot {
 
             border: v.border,
             ram,
+
+            port_7ffd: v.port_7ffd,
+            banks_128,
         }
     }
 }
@@ -320,7 +527,30 @@ impl From<Z80SnapshotV23> for Z80Snapshot {
     V23(Z80SnapshotV23),
 }*/
 
-fn read_extended_header(data: &[u8]) -> Result<(usize, u16), String> {
+/// Lo que interesa de la cabecera extendida (v2/v3)
+struct ExtHeader {
+    /// Offset del primer bloque de memoria
+    ram_start: usize,
+    /// Longitud de la cabecera extendida (23 = v2, 54/55 = v3)
+    len: usize,
+    pc: u16,
+    hw_mode: u8,
+    port_7ffd: u8,
+}
+
+impl ExtHeader {
+    /// El modo hardware depende de la versión: en v2 el 128K es 3/4,
+    /// en v3 es 4/5/6 (128K, +3) o 12 (+2)
+    fn is_128k(&self) -> bool {
+        if self.len == 23 {
+            matches!(self.hw_mode, 3 | 4)
+        } else {
+            matches!(self.hw_mode, 4..=6 | 12)
+        }
+    }
+}
+
+fn read_extended_header(data: &[u8]) -> Result<ExtHeader, String> {
     if data.len() < 32 {
         return Err("Fichero .z80 demasiado pequeño".into());
     }
@@ -335,10 +565,19 @@ fn read_extended_header(data: &[u8]) -> Result<(usize, u16), String> {
         return Err("Cabecera extendida incompleta".into());
     }
 
-    // PC REAL está en los primeros 2 bytes de la cabecera extendida
-    let pc = u16::from_le_bytes([data[32], data[33]]);
+    if header_len < 4 {
+        return Err("Cabecera extendida incompleta".into());
+    }
 
-    Ok((32 + header_len, pc))
+    // PC REAL está en los primeros 2 bytes de la cabecera extendida,
+    // seguido del modo hardware y el último OUT a 0x7FFD
+    Ok(ExtHeader {
+        ram_start: 32 + header_len,
+        len: header_len,
+        pc: u16::from_le_bytes([data[32], data[33]]),
+        hw_mode: data[34],
+        port_7ffd: data[35],
+    })
 }
 
 /*fn read_memory_blocks(
@@ -495,7 +734,7 @@ fn read_ram_blocks(
                     let value = data[pos];
                     pos += 1;
 
-                    buf.extend(std::iter::repeat(value).take(count));
+                    buf.extend(std::iter::repeat_n(value, count));
                 } else {
                     buf.push(b);
                 }
@@ -552,6 +791,7 @@ pub fn apply_z80_v23(
     cpu.reg.r = snap.r;
 
     run_state.iff1 = snap.iff1;
+    run_state.iff2 = snap.iff2;
     run_state.iff1_pending = false;
     run_state.im = snap.im;
     run_state.halted = false;
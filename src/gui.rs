@@ -9,10 +9,15 @@ use sdl2::{
 use crate::{botones, LoadState};
 use crate::disasm::disassemble;
 use crate::cpu_exec::CpuSnapshot;
+use crate::cinta::TapeDeck;
+use crate::breakpoints::Breakpoints;
+use crate::watchpoints::Watchpoints;
+use crate::historial::History;
+use crate::debugger::{DebugConsole, Debugger};
 use crate::botones::{Button, ButtonAction};
-use crate::constantes::{MARGEN_NEGRO, ZX_BORDER, ZX_H, ZX_W};
+use crate::constantes::{MARGEN_NEGRO, ZX_FRAME_H, ZX_FRAME_W};
 use crate::stack_tracker::{StackTracker, StackWriteKind};
-use crate::video::Video;
+use crate::video::{rgb, Video};
 
 // const ZX_W: i32 = 256;
 // const ZX_H: i32 = 192;
@@ -42,101 +47,83 @@ pub fn draw_screen(
     y0: i32,
 ) -> Result<(), String> {
     let scale = video.scale as i32;
-    let screen_w = ZX_W * scale;
-    let screen_h = ZX_H * scale;
-    let border = ZX_BORDER * scale;
-
-    // 1) MARCO NEGRO (BEZEL)
-    let frame = Rect::new(
-        x0 - border,
-        y0 - border,
-        (screen_w + 2 * border) as u32,
-        (screen_h + 2 * border) as u32,
-    );
-    canvas.set_draw_color(Color::BLACK);
-    canvas.fill_rect(frame)?;
 
-    // 2) CLIP SOLO ÁREA ZX
-    let clip = Rect::new(x0, y0, screen_w as u32, screen_h as u32);
-    canvas.set_clip_rect(clip);
+    // DIBUJAR IMAGEN COMPLETA (BORDE + PANTALLA) DESDE EL FRAMEBUFFER
+    // Agrupamos píxeles consecutivos del mismo color en una sola fila
+    // de rectángulo: el borde es casi siempre un único color.
+    for y in 0..ZX_FRAME_H {
+        let row = &video.framebuffer[(y * ZX_FRAME_W) as usize..((y + 1) * ZX_FRAME_W) as usize];
 
-    // 3) DIBUJAR PANTALLA (USANDO EL FRAMEBUFFER PROCESADO)
-    for y in 0..ZX_H {
-        for x in 0..ZX_W {
+        let mut x = 0;
+        while x < ZX_FRAME_W {
             // Obtenemos el índice de color (0-15) del framebuffer consolidado
-            let color_idx = video.framebuffer[(y * 256 + x) as usize];
+            let color_idx = row[x as usize];
+            let mut run = 1;
+            while x + run < ZX_FRAME_W && row[(x + run) as usize] == color_idx {
+                run += 1;
+            }
 
             // Convertimos el índice a Color RGB usando nuestra paleta
-            let color = zx_color_from_index(color_idx);
-
-            canvas.set_draw_color(color);
+            canvas.set_draw_color(zx_color_from_index(color_idx));
 
             let r = Rect::new(
                 x0 + x * scale,
                 y0 + y * scale,
-                scale as u32,
+                (run * scale) as u32,
                 scale as u32,
             );
             canvas.fill_rect(r)?;
+
+            x += run;
         }
     }
 
-    canvas.set_clip_rect(None);
     Ok(())
 }
 
 /// Convierte el índice 0-15 del framebuffer al color RGB real del Spectrum
 fn zx_color_from_index(index: u8) -> Color {
-    let bright = index >= 8;
-    let code = index & 0x07;
-
-    match (code, bright) {
-        (0, _) => Color::RGB(0, 0, 0),             // Negro
-        (1, false) => Color::RGB(0, 0, 192),       // Azul
-        (1, true) => Color::RGB(0, 0, 255),        // Azul Brillante
-        (2, false) => Color::RGB(192, 0, 0),       // Rojo
-        (2, true) => Color::RGB(255, 0, 0),        // Rojo Brillante
-        (3, false) => Color::RGB(192, 0, 192),     // Magenta
-        (3, true) => Color::RGB(255, 0, 255),      // Magenta Brillante
-        (4, false) => Color::RGB(0, 192, 0),       // Verde
-        (4, true) => Color::RGB(0, 255, 0),        // Verde Brillante
-        (5, false) => Color::RGB(0, 192, 192),     // Cian
-        (5, true) => Color::RGB(0, 255, 255),      // Cian Brillante
-        (6, false) => Color::RGB(192, 192, 0),     // Amarillo
-        (6, true) => Color::RGB(255, 255, 0),      // Amarillo Brillante
-        (7, false) => Color::RGB(192, 192, 192),   // Blanco (Gris)
-        (7, true) => Color::RGB(255, 255, 255),    // Blanco puro
-        _ => Color::BLACK,
-    }
+    let (r, g, b) = rgb(index);
+    Color::RGB(r, g, b)
 }
 
 /* ================================================== */
 /* DEBUGGER Y OTROS (MANTENIDO IGUAL)                 */
 /* ================================================== */
 
+#[allow(clippy::too_many_arguments)]
 pub fn draw_debug(
     canvas: &mut Canvas<Window>,
     font: &Font,
     snapshot: Option<&CpuSnapshot>,
     stack_tracker: &StackTracker,
     load_state: LoadState,
+    tape: &TapeDeck,
+    debugger: &Debugger,
+    watchpoints: &Watchpoints,
+    history: &History,
+    history_span: f32,
     debug_enabled: bool,
 ) -> Result<(), String> {
     canvas.set_draw_color(Color::BLACK);
     canvas.clear();
 
-    if debug_enabled {
-        if let Some(s) = snapshot {
-            draw_registers(canvas, font, s)?;
-            draw_flags(canvas, font, s)?;
-            draw_memory_dump(canvas, font, s)?;
-            draw_instruction_window(canvas, font, s)?;
-            draw_stack(canvas, font, s, stack_tracker, 600, 360)?;
-        }
+    if debug_enabled && let Some(s) = snapshot {
+        draw_registers(canvas, font, s)?;
+        draw_flags(canvas, font, s)?;
+        draw_memory_dump(canvas, font, s)?;
+        draw_instruction_window(canvas, font, s, &debugger.breakpoints, debugger.cursor)?;
+        draw_stack(canvas, font, s, stack_tracker, 600, 360)?;
     }
 
+    draw_breakpoints(canvas, font, &debugger.breakpoints, 1250, 100)?;
+    draw_watchpoints(canvas, font, watchpoints, 1650, 100)?;
+    draw_console(canvas, font, &debugger.console, 20, 820)?;
+    draw_rewind_bar(canvas, font, history, history_span)?;
+
     draw_buttons(canvas, font, &botones::default_buttons(), debug_enabled)?;
     draw_load_state(canvas, font, load_state)?;
+    draw_tape_state(canvas, font, tape)?;
 
     Ok(())
 }
@@ -155,9 +142,174 @@ fn draw_load_state(
         LoadState::Sna => ("SNA CARGADO", Color::RGB(255, 255, 0)), // Amarillo
         LoadState::Z80 => ("Z80 CARGADO", Color::RGB(255, 255, 0)), // Amarillo
         LoadState::Bin => ("BIN CARGADO", Color::RGB(255, 0, 255)), // Violeta
+        LoadState::Tap => ("TAP INSERTADA", Color::RGB(0, 255, 255)), // Cian
+        LoadState::Tzx => ("TZX INSERTADA", Color::RGB(0, 255, 255)), // Cian
     };
 
-    draw_text_color(canvas, font, &format!("ESTADO: {}", text), 280, 56, color)
+    draw_text_color(canvas, font, &format!("ESTADO: {}", text), 330, 56, color)
+}
+
+/* ================================================== */
+/* DIBUJA EL ESTADO DE LA CINTA                       */
+/* ================================================== */
+fn draw_tape_state(
+    canvas: &mut Canvas<Window>,
+    font: &Font,
+    tape: &TapeDeck,
+) -> Result<(), String> {
+    if !tape.has_tape() {
+        return draw_text_color(canvas, font, "CINTA: VACIA", 600, 56, Color::RGB(128, 128, 128));
+    }
+
+    let (block, total) = tape.position();
+    let (state, color) = if tape.is_playing() {
+        ("PLAY", Color::RGB(0, 255, 0))
+    } else {
+        ("STOP", Color::RGB(255, 255, 0))
+    };
+
+    draw_text_color(
+        canvas,
+        font,
+        &format!("CINTA: {} {}/{}", state, block.min(total), total),
+        600,
+        56,
+        color,
+    )
+}
+
+/* ================================================== */
+/* LISTA DE BREAKPOINTS                               */
+/* ================================================== */
+fn draw_breakpoints(
+    canvas: &mut Canvas<Window>,
+    font: &Font,
+    breakpoints: &Breakpoints,
+    x: i32,
+    y: i32,
+) -> Result<(), String> {
+    let line_h = 20;
+    draw_text(canvas, font, "BREAKPOINTS", x, y)?;
+
+    for (i, b) in breakpoints.list().iter().enumerate().take(30) {
+        let color = if b.enabled {
+            Color::RGB(255, 80, 80)
+        } else {
+            Color::RGB(128, 128, 128) // Desactivado
+        };
+        draw_text_color(canvas, font, &b.describe(i + 1), x, y + (i as i32 + 1) * line_h, color)?;
+    }
+
+    Ok(())
+}
+
+/* ================================================== */
+/* LISTA DE WATCHPOINTS                               */
+/* ================================================== */
+fn draw_watchpoints(
+    canvas: &mut Canvas<Window>,
+    font: &Font,
+    watchpoints: &Watchpoints,
+    x: i32,
+    y: i32,
+) -> Result<(), String> {
+    let line_h = 20;
+    draw_text(canvas, font, "WATCHPOINTS", x, y)?;
+
+    for (i, w) in watchpoints.list().iter().enumerate().take(30) {
+        let color = if w.enabled {
+            Color::RGB(255, 160, 0)
+        } else {
+            Color::RGB(128, 128, 128) // Desactivado
+        };
+        draw_text_color(canvas, font, &w.describe(i + 1), x, y + (i as i32 + 1) * line_h, color)?;
+    }
+
+    Ok(())
+}
+
+/* ================================================== */
+/* CONSOLA DE COMANDOS                                */
+/* ================================================== */
+fn draw_console(
+    canvas: &mut Canvas<Window>,
+    font: &Font,
+    console: &DebugConsole,
+    x: i32,
+    y: i32,
+) -> Result<(), String> {
+    let line_h = 20;
+
+    if console.active {
+        draw_text_color(canvas, font, &format!("> {}_", console.input), x, y, Color::RGB(0, 255, 0))?;
+    } else {
+        draw_text_color(canvas, font, "CMD: bp tbp bd be bc bl / wp wio wd we wc wl / go / trace / nmi / joy / keys / type  (F9: breakpoint en PC, F10: pegar)", x, y, Color::RGB(128, 128, 128))?;
+    }
+
+    for (i, line) in console.output.lines().take(10).enumerate() {
+        draw_text(canvas, font, line, x, y + (i as i32 + 1) * line_h)?;
+    }
+
+    Ok(())
+}
+
+/* ================================================== */
+/* BARRA DE REBOBINADO                                */
+/* ================================================== */
+pub const REWIND_X: i32 = 20;
+pub const REWIND_Y: i32 = 1080;
+pub const REWIND_W: i32 = 1000;
+pub const REWIND_H: i32 = 20;
+
+/// Segundos hacia atrás según el clic en la barra (a la derecha, "ahora")
+pub fn rewind_at(span: f32, x: i32, y: i32) -> Option<f32> {
+    if span <= 0.0
+        || !(REWIND_X..REWIND_X + REWIND_W).contains(&x)
+        || !(REWIND_Y..REWIND_Y + REWIND_H).contains(&y)
+    {
+        return None;
+    }
+    let f = (x - REWIND_X) as f32 / REWIND_W as f32;
+    Some(span * (1.0 - f))
+}
+
+fn draw_rewind_bar(
+    canvas: &mut Canvas<Window>,
+    font: &Font,
+    history: &History,
+    span: f32,
+) -> Result<(), String> {
+    let rect = Rect::new(REWIND_X, REWIND_Y, REWIND_W as u32, REWIND_H as u32);
+
+    canvas.set_draw_color(Color::RGB(40, 40, 40));
+    canvas.fill_rect(rect)?;
+
+    // Marcas de los keyframes
+    if span > 0.0 {
+        let now = history.keyframes().back().map_or(0, |k| k.t_states());
+        let first = history.keyframes().front().map_or(0, |k| k.t_states());
+        let total = (now - first).max(1) as f32;
+
+        canvas.set_draw_color(Color::RGB(0, 160, 255));
+        for k in history.keyframes() {
+            let x = REWIND_X + ((k.t_states() - first) as f32 / total * (REWIND_W - 1) as f32) as i32;
+            canvas.draw_line((x, REWIND_Y + 4), (x, REWIND_Y + REWIND_H - 5))?;
+        }
+    }
+
+    canvas.set_draw_color(Color::RGB(200, 200, 200));
+    canvas.draw_rect(rect)?;
+
+    let text = if history.enabled() {
+        format!(
+            "REBOBINAR: {:.1} s / {} instr.  (F6: step back, Shift+F6: run back)",
+            span,
+            history.len()
+        )
+    } else {
+        "REBOBINAR: historial desactivado".to_string()
+    };
+    draw_text_color(canvas, font, &text, REWIND_X, REWIND_Y + REWIND_H + 4, Color::RGB(128, 128, 128))
 }
 
 /* ================================================== */
@@ -344,7 +496,7 @@ fn draw_memory_dump(
                 canvas,
                 font,
                 &format!("{:02X}", byte),
-                start_x + 65 + (col as i32 * 35),
+                start_x + 65 + (col * 35),
                 y,
                 color,
             )?;
@@ -355,15 +507,14 @@ fn draw_memory_dump(
 /* ================================================== */
 /* VENTANA DE INSTRUCCIONES (DESENSAMBLADO, 21 LÍNEAS, CENTRADA EN PC) */
 /* ================================================== */
-fn draw_instruction_window(
-    canvas: &mut Canvas<Window>,
-    font: &Font,
-    s: &CpuSnapshot,
-) -> Result<(), String> {
-    let start_x = 20;
-    let start_y = 360;
-    let line_h = 22;
-
+// Posición de la ventana de instrucciones
+const INSTR_X: i32 = 20;
+const INSTR_Y: i32 = 360;
+const INSTR_LINE_H: i32 = 22;
+const INSTR_LINES: usize = 20;
+
+/// Las líneas visibles: (dirección, mnemónico, longitud)
+fn instruction_lines(s: &CpuSnapshot) -> Vec<(u16, String, u8)> {
     // 1) Empezamos un poco antes del PC actual.
     // Usamos el PC del snapshot como referencia absoluta.
     let mut current_pc = s.pc.saturating_sub(15);
@@ -392,7 +543,7 @@ fn draw_instruction_window(
         // y también como base del buffer para que el offset interno sea 0.
         let (mnemonic, len) = disassemble(bytes_restantes, current_pc, current_pc);
 
-        let safe_len = if len == 0 { 1 } else { len as u8 };
+        let safe_len = if len == 0 { 1 } else { len };
 
         instrs.push((current_pc, mnemonic, safe_len));
 
@@ -403,17 +554,36 @@ fn draw_instruction_window(
     // 3) Buscamos dónde quedó el PC real en nuestra lista generada para centrar la vista
     let pc_pos = instrs.iter().position(|(addr, _, _)| *addr == s.pc).unwrap_or(0);
 
-    // Dibujamos 20 líneas a partir de un poco antes del PC encontrado
+    // Mostramos 20 líneas a partir de un poco antes del PC encontrado
     let start_idx = pc_pos.saturating_sub(5);
-    let mut y = start_y;
+    instrs.into_iter().skip(start_idx).take(INSTR_LINES).collect()
+}
 
-    for i in start_idx..(start_idx + 20) {
-        if i >= instrs.len() { break; }
+/// Dirección de la instrucción bajo el ratón (para run to cursor)
+pub fn instruction_at(s: &CpuSnapshot, x: i32, y: i32) -> Option<u16> {
+    if !(INSTR_X..INSTR_X + 500).contains(&x) || y < INSTR_Y {
+        return None;
+    }
+    let line = ((y - INSTR_Y) / INSTR_LINE_H) as usize;
+    instruction_lines(s).get(line).map(|(addr, _, _)| *addr)
+}
 
-        let (pc, mnemonic, len) = &instrs[i];
+fn draw_instruction_window(
+    canvas: &mut Canvas<Window>,
+    font: &Font,
+    s: &CpuSnapshot,
+    breakpoints: &Breakpoints,
+    cursor: Option<u16>,
+) -> Result<(), String> {
+    let mut y = INSTR_Y;
 
-        let color = if *pc == s.pc {
+    for (pc, mnemonic, len) in instruction_lines(s) {
+        let color = if pc == s.pc {
             Color::RGB(255, 255, 0) // Amarillo para el PC actual
+        } else if breakpoints.has(pc) {
+            Color::RGB(255, 80, 80) // Rojo para los breakpoints
+        } else if cursor == Some(pc) {
+            Color::RGB(0, 200, 255) // Azul para el cursor (run to)
         } else {
             Color::WHITE
         };
@@ -421,15 +591,16 @@ fn draw_instruction_window(
         // Re-extraemos los bytes para el texto HEX
         let mut hex_str = String::new();
         let off = pc.wrapping_sub(s.mem_base) as usize;
-        for j in 0..(*len as usize) {
+        for j in 0..(len as usize) {
             if off + j < s.mem_dump.len() {
                 hex_str.push_str(&format!("{:02X} ", s.mem_dump[off + j]));
             }
         }
 
-        let text = format!("{:04X}: {:<12}  {}", pc, hex_str, mnemonic);
-        draw_text_color(canvas, font, &text, start_x, y, color)?;
-        y += line_h;
+        let marker = if cursor == Some(pc) { '>' } else { ' ' };
+        let text = format!("{}{:04X}: {:<12}  {}", marker, pc, hex_str, mnemonic);
+        draw_text_color(canvas, font, &text, INSTR_X, y, color)?;
+        y += INSTR_LINE_H;
     }
 
     Ok(())
@@ -529,7 +700,26 @@ pub fn draw_buttons(
             //ButtonAction::LoadRom => "LROM",
             //ButtonAction::LoadSna => "LSNA",
             ButtonAction::Load => "LOAD",
+            ButtonAction::Save => "SAVE",
             ButtonAction::DebugToggle => "DBG",
+            ButtonAction::TapePlay => "PLAY",
+            ButtonAction::TapeStop => "STOP",
+            ButtonAction::TapeRewind => "REW",
+            ButtonAction::MachineToggle => "48/128",
+            ButtonAction::MuteA => "AY A",
+            ButtonAction::MuteB => "AY B",
+            ButtonAction::MuteC => "AY C",
+            ButtonAction::ContentionToggle => "CONT",
+            ButtonAction::Console => "CMD",
+            ButtonAction::StepOver => "OVER",
+            ButtonAction::StepOut => "OUT",
+            ButtonAction::RunToCursor => "TO",
+            ButtonAction::StepBack => "BACK",
+            ButtonAction::RunBack => "RBACK",
+            ButtonAction::Nmi => "NMI",
+            ButtonAction::JoystickToggle => "JOY",
+            ButtonAction::KeyMappingToggle => "KEYS",
+            ButtonAction::Paste => "PASTE",
         };
 
         let surface = font
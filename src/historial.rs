@@ -0,0 +1,225 @@
+use std::collections::VecDeque;
+use zilog_z80::cpu::CPU;
+use crate::cpu_exec::CpuRunState;
+
+/* ==================================================
+ * HISTORIAL (EJECUCIÓN HACIA ATRÁS)
+ * ==================================================
+ * Dos niveles:
+ *   - Deltas por instrucción: el estado de la CPU de ANTES de
+ *     ejecutarla y los bytes que ha sobrescrito (los anota el
+ *     ZxBus::journal durante cpu_exec::step). Deshacer una
+ *     instrucción es aplicar su delta: STEP BACK y RUN BACK.
+ *   - Keyframes: estado completo (RAM y bancos) cada pocos frames,
+ *     para saltar segundos hacia atrás (barra de rebobinado).
+ *
+ * Ambos son anillos acotados: lo más viejo se va descartando.
+ * No se rebobinan el sonido (AY / beeper) ni la cinta.
+ */
+
+/// Registros completos del Z80 (el core no implementa Clone)
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct CpuRegs {
+    pub af: u16,
+    pub bc: u16,
+    pub de: u16,
+    pub hl: u16,
+    pub af_: u16,
+    pub bc_: u16,
+    pub de_: u16,
+    pub hl_: u16,
+    pub ix: u16,
+    pub iy: u16,
+    pub sp: u16,
+    pub pc: u16,
+    pub i: u8,
+    pub r: u8,
+}
+
+impl CpuRegs {
+    pub fn capture(cpu: &CPU) -> Self {
+        Self {
+            af: cpu.reg.get_af(),
+            bc: cpu.reg.get_bc(),
+            de: cpu.reg.get_de(),
+            hl: cpu.reg.get_hl(),
+            af_: cpu.alt.get_af(),
+            bc_: cpu.alt.get_bc(),
+            de_: cpu.alt.get_de(),
+            hl_: cpu.alt.get_hl(),
+            ix: cpu.reg.get_ix(),
+            iy: cpu.reg.get_iy(),
+            sp: cpu.reg.sp,
+            pc: cpu.reg.pc,
+            i: cpu.reg.i,
+            r: cpu.reg.r,
+        }
+    }
+
+    pub fn restore(&self, cpu: &mut CPU) {
+        cpu.reg.set_af(self.af);
+        cpu.reg.set_bc(self.bc);
+        cpu.reg.set_de(self.de);
+        cpu.reg.set_hl(self.hl);
+        cpu.alt.set_af(self.af_);
+        cpu.alt.set_bc(self.bc_);
+        cpu.alt.set_de(self.de_);
+        cpu.alt.set_hl(self.hl_);
+        cpu.reg.set_ix(self.ix);
+        cpu.reg.set_iy(self.iy);
+        cpu.reg.sp = self.sp;
+        cpu.reg.pc = self.pc;
+        cpu.reg.i = self.i;
+        cpu.reg.r = self.r;
+    }
+}
+
+/// Estado de la máquina que no está en la memoria
+#[derive(Copy, Clone, Debug)]
+pub struct MachineRegs {
+    pub cpu: CpuRegs,
+    pub run_state: CpuRunState,
+    pub interrupt_pending: bool,
+    /// T-states transcurridos del frame (InterruptController)
+    pub frame_t: u64,
+    pub border: u8,
+    /// Paginación del 128K
+    pub port_7ffd: Option<u8>,
+}
+
+/// Deshacer una instrucción
+pub struct Delta {
+    pub before: MachineRegs,
+    /// Bytes sobrescritos (dirección, valor anterior), en orden
+    pub writes: Vec<(u16, u8)>,
+}
+
+/// Estado completo
+pub struct Keyframe {
+    pub regs: MachineRegs,
+    /// RAM 0x4000-0xFFFF tal como la ve la CPU
+    pub ram: Vec<u8>,
+    /// Bancos 0..7 del 128K
+    pub banks: Option<Vec<Vec<u8>>>,
+    /// Número de instrucciones grabadas al tomarlo
+    step: u64,
+}
+
+impl Keyframe {
+    pub fn new(regs: MachineRegs, ram: Vec<u8>, banks: Option<Vec<Vec<u8>>>) -> Self {
+        Self { regs, ram, banks, step: 0 }
+    }
+
+    pub fn t_states(&self) -> u64 {
+        self.regs.run_state.t_states
+    }
+}
+
+pub struct History {
+    deltas: VecDeque<Delta>,
+    keyframes: VecDeque<Keyframe>,
+    max_deltas: usize,
+    max_keyframes: usize,
+    /// T-states entre keyframes
+    keyframe_every: u64,
+    /// Instrucciones grabadas (índice de la siguiente)
+    steps: u64,
+}
+
+impl History {
+    /// `max_deltas` instrucciones y keyframes cada `keyframe_every`
+    /// T-states durante `span` T-states. Con 0 no se graba nada.
+    pub fn new(max_deltas: usize, keyframe_every: u64, span: u64) -> Self {
+        Self {
+            deltas: VecDeque::new(),
+            keyframes: VecDeque::new(),
+            max_deltas,
+            max_keyframes: span.checked_div(keyframe_every).unwrap_or(0) as usize,
+            keyframe_every,
+            steps: 0,
+        }
+    }
+
+    /// Sin historial
+    pub fn disabled() -> Self {
+        Self::new(0, 0, 0)
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.max_deltas > 0
+    }
+
+    pub fn clear(&mut self) {
+        self.deltas.clear();
+        self.keyframes.clear();
+    }
+
+    /// Instrucciones que se pueden deshacer
+    pub fn len(&self) -> usize {
+        self.deltas.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.deltas.is_empty()
+    }
+
+    pub fn keyframes(&self) -> &VecDeque<Keyframe> {
+        &self.keyframes
+    }
+
+    pub fn push(&mut self, delta: Delta) {
+        if self.deltas.len() >= self.max_deltas {
+            self.deltas.pop_front();
+        }
+        self.deltas.push_back(delta);
+        self.steps += 1;
+    }
+
+    /// Saca la última instrucción (para deshacerla)
+    pub fn pop(&mut self) -> Option<Delta> {
+        let d = self.deltas.pop_back()?;
+        self.steps -= 1;
+        // Los keyframes posteriores ya no son el pasado
+        while self.keyframes.back().is_some_and(|k| k.step > self.steps) {
+            self.keyframes.pop_back();
+        }
+        Some(d)
+    }
+
+    /// ¿Toca keyframe en el T-state `t`?
+    pub fn keyframe_due(&self, t: u64) -> bool {
+        self.max_keyframes > 0
+            && self
+                .keyframes
+                .back()
+                .is_none_or(|k| t >= k.t_states() + self.keyframe_every)
+    }
+
+    pub fn push_keyframe(&mut self, mut k: Keyframe) {
+        k.step = self.steps;
+        if self.keyframes.len() >= self.max_keyframes {
+            self.keyframes.pop_front();
+        }
+        self.keyframes.push_back(k);
+    }
+
+    /// Vuelve al keyframe `i`: se descarta todo lo posterior.
+    /// Las instrucciones de antes del keyframe se pueden seguir deshaciendo.
+    pub fn rewind_to(&mut self, i: usize) -> Option<&Keyframe> {
+        let step = self.keyframes.get(i)?.step;
+
+        self.keyframes.truncate(i + 1);
+        let newer = (self.steps - step) as usize;
+        let keep = self.deltas.len().saturating_sub(newer);
+        self.deltas.truncate(keep);
+        self.steps = step;
+
+        self.keyframes.back()
+    }
+}
+
+impl Default for History {
+    fn default() -> Self {
+        Self::disabled()
+    }
+}
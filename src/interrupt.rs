@@ -1,3 +1,7 @@
+use crate::constantes::INT_PULSE_TSTATES;
+
+/// Reloj del frame: la ULA genera la INT al principio de cada frame y
+/// la mantiene activa INT_PULSE_TSTATES T-states
 pub struct InterruptController {
     pub tstates_accum: u64,
     pub next_int: u64,
@@ -11,6 +15,14 @@ impl InterruptController {
         }
     }
 
+    /// Controlador con otra duración de frame (p.ej. 70908 en el 128K)
+    pub fn with_frame(tstates_per_frame: u64) -> Self {
+        Self {
+            tstates_accum: 0,
+            next_int: tstates_per_frame,
+        }
+    }
+
     /// Devuelve true cuando hay que generar una INT
     pub fn add_cycles(&mut self, cycles: u32) -> bool {
         self.tstates_accum += cycles as u64;
@@ -22,4 +34,15 @@ impl InterruptController {
             false
         }
     }
+
+    /// Línea INT activa: dentro del pulso del principio del frame
+    pub fn int_active(&self) -> bool {
+        self.tstates_accum < INT_PULSE_TSTATES
+    }
+}
+
+impl Default for InterruptController {
+    fn default() -> Self {
+        Self::new()
+    }
 }
@@ -0,0 +1,206 @@
+#[cfg(feature = "sdl-frontend")]
+use sdl2::controller::{Axis, Button};
+#[cfg(feature = "sdl-frontend")]
+use sdl2::keyboard::Keycode;
+
+#[cfg(feature = "sdl-frontend")]
+use crate::constantes::JOY_AXIS_DEADZONE;
+
+/* ==================================================
+ * JOYSTICK
+ * ==================================================
+ * Kempston: puerto 0x1F (decodifica A5 = A6 = A7 = 0), bits a 1 =
+ * pulsado: 0 derecha, 1 izquierda, 2 abajo, 3 arriba, 4 fuego.
+ *
+ * Sinclair 1/2 (Interface 2) y Cursor (Protek/AGF) no tienen puerto:
+ * pulsan teclas de las filas 1-5 y 0-6 del teclado.
+ *
+ *   Sinclair 1:  izq 6, der 7, abajo 8, arriba 9, fuego 0
+ *   Sinclair 2:  izq 1, der 2, abajo 3, arriba 4, fuego 5
+ *   Cursor:      izq 5, abajo 6, arriba 7, der 8, fuego 0
+ */
+
+/// Interfaz de joystick conectada
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum JoystickType {
+    None,
+    Kempston,
+    Sinclair1,
+    Sinclair2,
+    Cursor,
+}
+
+impl JoystickType {
+    /// "none", "kempston", "sinclair1", "sinclair2", "cursor" (o "protek")
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "none" | "no" => Some(JoystickType::None),
+            "kempston" => Some(JoystickType::Kempston),
+            "sinclair1" | "sinclair" => Some(JoystickType::Sinclair1),
+            "sinclair2" => Some(JoystickType::Sinclair2),
+            "cursor" | "protek" => Some(JoystickType::Cursor),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            JoystickType::None => "ninguno",
+            JoystickType::Kempston => "Kempston",
+            JoystickType::Sinclair1 => "Sinclair 1",
+            JoystickType::Sinclair2 => "Sinclair 2",
+            JoystickType::Cursor => "Cursor",
+        }
+    }
+
+    /// Siguiente interfaz (botón JOY del debugger)
+    pub fn next(self) -> Self {
+        match self {
+            JoystickType::None => JoystickType::Kempston,
+            JoystickType::Kempston => JoystickType::Sinclair1,
+            JoystickType::Sinclair1 => JoystickType::Sinclair2,
+            JoystickType::Sinclair2 => JoystickType::Cursor,
+            JoystickType::Cursor => JoystickType::None,
+        }
+    }
+
+    /// Teclas (fila, bit) de derecha, izquierda, abajo, arriba y fuego
+    fn keys(self) -> Option<[(usize, u8); 5]> {
+        match self {
+            JoystickType::Sinclair1 => Some([(4, 3), (4, 4), (4, 2), (4, 1), (4, 0)]),
+            JoystickType::Sinclair2 => Some([(3, 1), (3, 0), (3, 2), (3, 3), (3, 4)]),
+            JoystickType::Cursor => Some([(4, 2), (3, 4), (4, 4), (4, 3), (4, 0)]),
+            JoystickType::None | JoystickType::Kempston => None,
+        }
+    }
+}
+
+/// Dirección o botón del joystick (el valor es su bit en el Kempston)
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum JoyInput {
+    Right = 0,
+    Left = 1,
+    Down = 2,
+    Up = 3,
+    Fire = 4,
+}
+
+pub struct Joystick {
+    pub kind: JoystickType,
+    /// Entradas pulsadas, con los bits del Kempston
+    state: u8,
+    /// Teclas del PC: arriba, abajo, izquierda, derecha y fuego
+    #[cfg(feature = "sdl-frontend")]
+    keys: [Keycode; 5],
+}
+
+impl Joystick {
+    pub fn new() -> Self {
+        Self {
+            kind: JoystickType::None,
+            state: 0,
+            #[cfg(feature = "sdl-frontend")]
+            keys: [Keycode::Up, Keycode::Down, Keycode::Left, Keycode::Right, Keycode::RCtrl],
+        }
+    }
+
+    /// Cambia de interfaz soltando todo lo pulsado
+    pub fn set_kind(&mut self, kind: JoystickType) {
+        self.kind = kind;
+        self.state = 0;
+    }
+
+    pub fn set(&mut self, input: JoyInput, pressed: bool) {
+        if pressed {
+            self.state |= 1 << input as u8;
+        } else {
+            self.state &= !(1 << input as u8);
+        }
+    }
+
+    pub fn release_all(&mut self) {
+        self.state = 0;
+    }
+
+    /// Lectura del puerto Kempston; None si no hay Kempston o el puerto no
+    /// es suyo
+    pub fn read_kempston(&self, port: u16) -> Option<u8> {
+        (self.kind == JoystickType::Kempston && port & 0x00E0 == 0).then_some(self.state)
+    }
+
+    /// Bits de teclado (0 = pulsada) que aporta el joystick a la lectura
+    /// de 0xFE con las filas de `high_byte`; se combina con AND
+    pub fn read_port_fe(&self, high_byte: u8) -> u8 {
+        let mut result = 0x1F;
+        if let Some(keys) = self.kind.keys() {
+            for (i, (row, bit)) in keys.into_iter().enumerate() {
+                if self.state & (1 << i) != 0 && high_byte & (1 << row) == 0 {
+                    result &= !(1 << bit);
+                }
+            }
+        }
+        result
+    }
+
+    /// Teclas del PC separadas por comas, con los nombres de SDL:
+    /// "Up,Down,Left,Right,Right Ctrl"
+    #[cfg(feature = "sdl-frontend")]
+    pub fn set_keys(&mut self, names: &str) -> Result<(), String> {
+        let keys = names
+            .split(',')
+            .map(|n| Keycode::from_name(n.trim()).ok_or(format!("Tecla desconocida: {}", n.trim())))
+            .collect::<Result<Vec<_>, _>>()?;
+        self.keys = keys
+            .try_into()
+            .map_err(|_| "Hacen falta 5 teclas: arriba, abajo, izquierda, derecha, fuego".to_string())?;
+        Ok(())
+    }
+
+    /// Tecla del PC; devuelve true si es del joystick (y no va al teclado)
+    #[cfg(feature = "sdl-frontend")]
+    pub fn key(&mut self, key: Keycode, pressed: bool) -> bool {
+        if self.kind == JoystickType::None {
+            return false;
+        }
+        let inputs = [JoyInput::Up, JoyInput::Down, JoyInput::Left, JoyInput::Right, JoyInput::Fire];
+        match self.keys.iter().position(|k| *k == key) {
+            Some(i) => {
+                self.set(inputs[i], pressed);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Botón de un mando: cruceta y A/B/X/Y como fuego
+    #[cfg(feature = "sdl-frontend")]
+    pub fn controller_button(&mut self, button: Button, pressed: bool) {
+        let input = match button {
+            Button::DPadUp => JoyInput::Up,
+            Button::DPadDown => JoyInput::Down,
+            Button::DPadLeft => JoyInput::Left,
+            Button::DPadRight => JoyInput::Right,
+            Button::A | Button::B | Button::X | Button::Y => JoyInput::Fire,
+            _ => return,
+        };
+        self.set(input, pressed);
+    }
+
+    /// Palanca izquierda de un mando, con zona muerta
+    #[cfg(feature = "sdl-frontend")]
+    pub fn controller_axis(&mut self, axis: Axis, value: i16) {
+        let (minus, plus) = match axis {
+            Axis::LeftX => (JoyInput::Left, JoyInput::Right),
+            Axis::LeftY => (JoyInput::Up, JoyInput::Down),
+            _ => return,
+        };
+        self.set(minus, value < -JOY_AXIS_DEADZONE);
+        self.set(plus, value > JOY_AXIS_DEADZONE);
+    }
+}
+
+impl Default for Joystick {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,49 @@
+//! Núcleo del emulador ZX Spectrum.
+//!
+//! La máquina (`ZxMachine`) no depende de SDL2: se puede construir con
+//! `ZxMachine::builder()` pasando los bytes de la ROM y ejecutarla sin
+//! ventana. El dibujo en pantalla vive detrás de la feature `sdl-frontend`.
+
+pub mod cpu_exec;
+pub mod disasm;
+pub mod asm;
+pub mod debugger;
+pub mod breakpoints;
+pub mod watchpoints;
+pub mod historial;
+pub mod traza;
+pub mod teclado;
+pub mod basic;
+pub mod joystick;
+pub mod botones;
+pub mod stack_tracker;
+pub mod video;
+pub mod interrupt;
+pub mod bus;
+pub mod beeper;
+pub mod ay;
+pub mod memoria;
+pub mod contencion;
+pub mod cinta;
+pub mod puertos;
+pub mod formatos;
+pub mod constantes;
+pub mod machine;
+pub mod cli;
+pub mod batch;
+
+#[cfg(feature = "sdl-frontend")]
+pub mod gui;
+#[cfg(feature = "sdl-frontend")]
+pub mod audio;
+
+#[derive(Copy, Clone, Debug)]
+pub enum LoadState {
+    None,
+    Rom,
+    Sna,
+    Z80,
+    Bin,
+    Tap,
+    Tzx,
+}
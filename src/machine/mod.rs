@@ -1 +1,2 @@
-pub mod zx_machine;
\ No newline at end of file
+pub mod model;
+pub mod zx_machine;
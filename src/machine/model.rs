@@ -0,0 +1,62 @@
+use crate::constantes::{
+    CPU_HZ, CPU_HZ_128, FIRST_SCREEN_LINE, FIRST_SCREEN_LINE_128, TSTATES_PER_FRAME,
+    TSTATES_PER_FRAME_128, TSTATES_PER_LINE, TSTATES_PER_LINE_128,
+};
+
+/// Modelo de Spectrum emulado
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum MachineType {
+    Spectrum48K,
+    /// 128K / +2: 8 bancos de RAM, 2 ROMs, paginación por 0x7FFD
+    Spectrum128K,
+}
+
+impl MachineType {
+    /// T-states entre dos INT
+    pub fn tstates_per_frame(self) -> u64 {
+        match self {
+            MachineType::Spectrum48K => TSTATES_PER_FRAME,
+            MachineType::Spectrum128K => TSTATES_PER_FRAME_128,
+        }
+    }
+
+    pub fn tstates_per_line(self) -> u64 {
+        match self {
+            MachineType::Spectrum48K => TSTATES_PER_LINE,
+            MachineType::Spectrum128K => TSTATES_PER_LINE_128,
+        }
+    }
+
+    /// Línea del primer píxel de pantalla (desde la INT)
+    pub fn first_screen_line(self) -> u64 {
+        match self {
+            MachineType::Spectrum48K => FIRST_SCREEN_LINE,
+            MachineType::Spectrum128K => FIRST_SCREEN_LINE_128,
+        }
+    }
+
+    /// Reloj de la CPU en Hz
+    pub fn cpu_hz(self) -> u64 {
+        match self {
+            MachineType::Spectrum48K => CPU_HZ,
+            MachineType::Spectrum128K => CPU_HZ_128,
+        }
+    }
+
+    /// "48", "48k", "128", "128k" (sin distinguir mayúsculas)
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "48" | "48k" => Some(MachineType::Spectrum48K),
+            "128" | "128k" => Some(MachineType::Spectrum128K),
+            _ => None,
+        }
+    }
+
+    /// Tamaño de la imagen de ROM (las dos ROMs del 128K van seguidas)
+    pub fn rom_size(self) -> usize {
+        match self {
+            MachineType::Spectrum48K => 16 * 1024,
+            MachineType::Spectrum128K => 32 * 1024,
+        }
+    }
+}
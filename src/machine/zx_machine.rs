@@ -1,23 +1,45 @@
 use std::collections::HashMap;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+#[cfg(feature = "sdl-frontend")]
 use sdl2::render::Canvas;
+#[cfg(feature = "sdl-frontend")]
 use sdl2::ttf::Font;
+#[cfg(feature = "sdl-frontend")]
 use sdl2::video::Window;
 use zilog_z80::cpu::CPU;
 
+use crate::ay::Ay;
 use crate::bus::ZxBus;
-use crate::constantes::TSTATES_PER_FRAME;
-use crate::cpu_exec::{step, CpuRunState, UnimplTracker};
+use crate::contencion::Contention;
+use crate::constantes::{
+    AUDIO_SAMPLE_RATE, FLAGS_NEW_KEY, HISTORY_SECONDS, HISTORY_STEPS, KEYFRAME_FRAMES, SYSVAR_FLAGS,
+};
+use crate::cpu_exec::{snapshot, step, CpuRunState, CpuSnapshot, UnimplTracker};
+use crate::historial::{CpuRegs, Delta, History, Keyframe, MachineRegs};
 use crate::interrupt::InterruptController;
+use crate::joystick::JoystickType;
+use crate::teclado::{unescape_text, KeyMapping};
 use crate::stack_tracker::StackTracker;
 use crate::video::Video;
-use crate::debugger::{Debugger, RunMode};
-use crate::formatos::load;
+use crate::breakpoints::{parse_address, Breakpoints};
+use crate::watchpoints::Watchpoints;
+use crate::debugger::{Debugger, RunMode, RunTarget};
+use crate::formatos::{load, save};
 use crate::formatos::load::LoadResult;
 use crate::LoadState;
+use crate::machine::model::MachineType;
+use crate::memoria::Memory128;
+
+/// Ruta de la ROM por defecto usada por `ZxMachine::new`
+pub const ROM_PATH_DEFAULT: &str = "ROMS/ZXSpectrum48.rom";
+/// ROMs del 128K (editor 128 + BASIC 48, 32 KB)
+pub const ROM128_PATH_DEFAULT: &str = "ROMS/ZXSpectrum128.rom";
 
 /// Estado completo de la máquina ZX Spectrum
 pub struct ZxMachine {
+    /// Modelo emulado (48K / 128K)
+    pub machine_type: MachineType,
+
     // CPU y BUS
     pub cpu: CPU,
     pub bus: ZxBus,
@@ -25,7 +47,10 @@ pub struct ZxMachine {
     // Estado de ejecución
     pub run_state: CpuRunState,
     pub interrupt_ctrl: InterruptController,
+    /// Línea INT de la ULA (activa durante el pulso del principio del frame)
     pub interrupt_pending: bool,
+    /// Frames completos desde que se creó la máquina
+    pub frames: u64,
 
     // Debug / tracking
     pub debugger: Debugger,
@@ -40,18 +65,45 @@ pub struct ZxMachine {
 
     pub debug_enabled: bool,
     pub load_state: LoadState,
+    /// Último programa cargado (sus breakpoints se guardan al lado)
+    pub program_path: Option<PathBuf>,
+
+    // Ejecución hacia atrás
+    pub history: History,
+    history_seconds: u32,
 }
 
 impl ZxMachine {
     /// Crea una máquina ZX exactamente igual a la inicialización actual del main
     pub fn new(video_scale: u32) -> Self {
+        let rom = std::fs::read(ROM_PATH_DEFAULT)
+            .expect("No se pudo cargar la ROM");
+
+        Self::builder()
+            .rom(rom)
+            .video_scale(video_scale)
+            .history(HISTORY_SECONDS)
+            .build()
+            .expect("No se pudo cargar la ROM")
+    }
+
+    /// Constructor configurable (no necesita ficheros ni SDL)
+    pub fn builder() -> ZxMachineBuilder {
+        ZxMachineBuilder::new()
+    }
+
+    /// Máquina sin ROM cargada (memoria a cero)
+    fn empty(video_scale: u32, machine_type: MachineType) -> Self {
         let mut m = Self {
+            machine_type,
+
             cpu: CPU::new(0xFFFF),
             bus: ZxBus::new(),
 
             run_state: CpuRunState::new(),
-            interrupt_ctrl: InterruptController::new(),
+            interrupt_ctrl: InterruptController::with_frame(machine_type.tstates_per_frame()),
             interrupt_pending: false,
+            frames: 0,
 
             debugger: Debugger::new(),
             executed_instrs: HashMap::new(),
@@ -63,16 +115,74 @@ impl ZxMachine {
 
             debug_enabled: false,
             load_state: LoadState::None,
+            program_path: None,
 
+            history: History::disabled(),
+            history_seconds: 0,
         };
 
-        // CARGA AUTOMÁTICA DE ROM
-        m.load_rom(Path::new("ROMS/ZXSpectrum48.rom"))
-            .expect("No se pudo cargar la ROM");
+        // Temporización y memoria propias del modelo
+        m.video.set_timing(machine_type.tstates_per_line(), machine_type.first_screen_line());
+        m.bus.beeper.set_cpu_hz(machine_type.cpu_hz());
+        m.bus.contention = Contention::new(machine_type);
+        if machine_type == MachineType::Spectrum128K {
+            let mut mem = Memory128::new(&[]);
+            mem.reset(&mut m.cpu.bus, true);
+            m.bus.mem128 = Some(mem);
+            m.enable_ay();
+        }
 
         m
     }
 
+    /// Conecta un AY (de serie en el 128K; interfaz opcional en el 48K)
+    pub fn enable_ay(&mut self) {
+        let mut ay = Ay::new(self.bus.beeper.sample_rate());
+        ay.set_cpu_hz(self.machine_type.cpu_hz());
+        ay.reset_clock(self.run_state.t_states);
+        self.bus.ay = Some(ay);
+    }
+
+    /// Cambia de modelo: carga su ROM por defecto y hace un POWER RESET
+    pub fn switch_machine(&mut self, machine_type: MachineType) -> Result<(), String> {
+        let path = match machine_type {
+            MachineType::Spectrum48K => ROM_PATH_DEFAULT,
+            MachineType::Spectrum128K => ROM128_PATH_DEFAULT,
+        };
+        let rom = std::fs::read(path).map_err(|e| format!("{}: {}", path, e))?;
+        self.switch_machine_with_rom(machine_type, rom)
+    }
+
+    /// Cambia de modelo con la ROM dada (POWER RESET)
+    pub fn switch_machine_with_rom(&mut self, machine_type: MachineType, rom: Vec<u8>) -> Result<(), String> {
+        let mut m = Self::builder()
+            .machine(machine_type)
+            .rom(rom)
+            .video_scale(self.video.scale)
+            .sample_rate(self.bus.beeper.sample_rate())
+            .history(self.history_seconds)
+            .build()?;
+
+        // Se conserva lo que no es estado de la máquina emulada
+        m.debug_enabled = self.debug_enabled;
+        m.debugger.breakpoints = std::mem::take(&mut self.debugger.breakpoints);
+        m.program_path = self.program_path.take();
+        m.bus.contention.enabled = self.bus.contention.enabled;
+        m.bus.tape = std::mem::take(&mut self.bus.tape);
+        m.bus.watch = std::mem::take(&mut self.bus.watch);
+        m.bus.trace = std::mem::take(&mut self.bus.trace);
+        m.bus.joystick = std::mem::take(&mut self.bus.joystick);
+        m.bus.joystick.release_all();
+        m.bus.keyboard.mode = self.bus.keyboard.mode;
+        if let (Some(ay), Some(old)) = (m.bus.ay.as_mut(), self.bus.ay.as_ref()) {
+            ay.muted = old.muted;
+        }
+        *self = m;
+
+        println!("ZxMachine: modelo {:?}", machine_type);
+        Ok(())
+    }
+
     /// Ejecuta CPU según el modo actual (Run / RunFast)
     pub fn run_frame(&mut self) {
         match self.debugger.mode {
@@ -80,83 +190,7 @@ impl ZxMachine {
              * RUN: 1 frame (50 Hz)
              * =========================== */
             RunMode::Run => {
-                let mut states: u64 = 0;
-
-                while states < TSTATES_PER_FRAME {
-                    if self.debugger.check_breakpoint(self.cpu.reg.pc) {
-                        self.debugger.pause();
-                        break;
-                    }
-                    let pc = self.cpu.reg.pc;
-                    let opcode = self.cpu.bus.read_byte(pc);
-
-                    // Intercepción universal de entrada de puertos ANTES de ejecutar
-                    // // El opcode 0xDB es "IN A, (n)"
-                    if opcode == 0xDB {
-                        let n = self.cpu.bus.read_byte(pc + 1);
-                        let port = ((self.cpu.reg.a as u16) << 8) | n as u16;
-
-                        if (port & 0x01) == 0 {
-                            let val = self.bus.in_port(port);
-                            self.cpu.reg.a = val;
-                            self.cpu.reg.pc = pc + 2;
-                            self.run_state.t_states += 11;
-                            return; // ⬅️ no llamar a step()
-                        }
-                    }
-                    // Intercepción universal para IN r, (C) -> Opcodes ED 40 a ED 78
-                    if opcode == 0xED {
-                        let op2 = self.cpu.bus.read_byte(pc + 1);
-                        if (op2 & 0xC7) == 0x40 {
-                            let port = self.cpu.reg.get_bc();
-                            if (port & 0x01) == 0 {
-                                let val = self.bus.in_port(port);
-
-                                match (op2 >> 3) & 7 {
-                                    0 => self.cpu.reg.b = val,
-                                    1 => self.cpu.reg.c = val,
-                                    2 => self.cpu.reg.d = val,
-                                    3 => self.cpu.reg.e = val,
-                                    4 => self.cpu.reg.h = val,
-                                    5 => self.cpu.reg.l = val,
-                                    7 => self.cpu.reg.a = val,
-                                    _ => {}
-                                }
-
-                                self.cpu.reg.pc = pc + 2;
-                                self.run_state.t_states += 12;
-                                return;
-                            }
-                        }
-                    }
-
-                    let snap = step(
-                        &mut self.cpu,
-                        &mut self.bus,
-                        &mut self.run_state,
-                        self.interrupt_pending,
-                        &mut self.executed_instrs,
-                        &mut self.unimpl_tracker,
-                        &mut self.stack_tracker,
-                        false,
-                    );
-
-                    if self.interrupt_ctrl.add_cycles(snap.instr_cycles) {
-                        self.interrupt_pending = true;
-                    }
-
-                    if self.interrupt_pending && self.cpu.reg.pc == 0x0038 {
-                        self.interrupt_pending = false;
-                    }
-
-                    states += snap.instr_cycles as u64;
-
-                    if self.debug_enabled {
-                        self.last_snapshot = Some(snap);
-                    }
-                }
-
-                self.video.on_vsync();
+                self.run_one_frame();
             }
 
             /* ===========================
@@ -164,41 +198,9 @@ impl ZxMachine {
              * =========================== */
             RunMode::RunFast => {
                 for _ in 0..10 {
-                    let mut states: u64 = 0;
-
-                    while states < TSTATES_PER_FRAME {
-                        if self.debugger.check_breakpoint(self.cpu.reg.pc) {
-                            self.debugger.pause();
-                            break;
-                        }
-
-                        let snap = step(
-                            &mut self.cpu,
-                            &mut self.bus,
-                            &mut self.run_state,
-                            self.interrupt_pending,
-                            &mut self.executed_instrs,
-                            &mut self.unimpl_tracker,
-                            &mut self.stack_tracker,
-                            false,
-                        );
-
-                        if self.interrupt_ctrl.add_cycles(snap.instr_cycles) {
-                            self.interrupt_pending = true;
-                        }
-
-                        if self.interrupt_pending && self.cpu.reg.pc == 0x0038 {
-                            self.interrupt_pending = false;
-                        }
-
-                        states += snap.instr_cycles as u64;
-
-                        if self.debug_enabled {
-                            self.last_snapshot = Some(snap);
-                        }
+                    if !self.run_one_frame() {
+                        break;
                     }
-
-                    self.video.on_vsync();
                 }
             }
 
@@ -213,6 +215,88 @@ impl ZxMachine {
         }
     }
 
+    /// Ejecuta instrucciones hasta la siguiente INT (fin de frame).
+    /// Devuelve false si se ha parado (breakpoint, watchpoint u objetivo).
+    fn run_one_frame(&mut self) -> bool {
+        loop {
+            // El core no da la vuelta al PC en 0xFFFF (desborda): se para antes
+            if self.cpu.reg.pc == 0xFFFF {
+                println!("PC en FFFF: ejecución detenida");
+                self.debugger.pause();
+                return false;
+            }
+
+            if self.debugger.check_breakpoint(&self.cpu) {
+                println!("Breakpoint en {:04X}", self.cpu.reg.pc);
+                return false;
+            }
+
+            let snap = self.step_recorded(false);
+
+            let frame_done = self.clock_tick(snap.instr_cycles);
+
+            if self.debug_enabled {
+                self.last_snapshot = Some(snap);
+            }
+
+            // Watchpoint: se para después de la instrucción que lo ha disparado
+            if self.check_watchpoint() {
+                self.debugger.pause();
+                return false;
+            }
+
+            // Step over / step out / run to
+            if self.debugger.check_target(&self.cpu) {
+                return false;
+            }
+
+            if frame_done {
+                return true;
+            }
+        }
+    }
+
+    /// Avanza el reloj de la máquina tras una instrucción de `cycles` T-states:
+    /// interrupciones y haz de vídeo. Devuelve true si se ha completado un frame.
+    fn clock_tick(&mut self, cycles: u32) -> bool {
+        let frame_done = self.interrupt_ctrl.add_cycles(cycles);
+
+        // La INT se activa al cerrar el frame y solo se puede aceptar
+        // mientras dura el pulso
+        self.interrupt_pending = frame_done || (self.interrupt_pending && self.interrupt_ctrl.int_active());
+
+        if frame_done {
+            // La INT marca el inicio del frame siguiente
+            let frame_end = self.run_state.t_states
+                .saturating_sub(self.interrupt_ctrl.tstates_accum);
+            self.bus.contention.frame_start = frame_end;
+            match &self.bus.mem128 {
+                Some(mem) => self.video.end_frame(&mem.screen(&self.cpu.bus), &self.bus.border_writes, frame_end),
+                None => self.video.end_frame(&self.cpu.bus, &self.bus.border_writes, frame_end),
+            }
+            self.bus.border_writes.retain(|&(t, _)| t >= frame_end);
+            self.video.on_vsync();
+            self.frames += 1;
+            let key_taken = self.cpu.bus.read_byte(SYSVAR_FLAGS) & FLAGS_NEW_KEY == 0;
+            self.bus.keyboard.on_frame(key_taken);
+            self.record_keyframe();
+            self.bus.trace.on_frame();
+            self.bus.beeper.end_frame(frame_end);
+            if let Some(ay) = self.bus.ay.as_mut() {
+                ay.end_frame(frame_end);
+            }
+        } else {
+            let t = self.run_state.t_states;
+            match &self.bus.mem128 {
+                Some(mem) => self.video.render_until(&mem.screen(&self.cpu.bus), &self.bus.border_writes, t),
+                None => self.video.render_until(&self.cpu.bus, &self.bus.border_writes, t),
+            }
+        }
+
+        frame_done
+    }
+
+    #[cfg(feature = "sdl-frontend")]
     pub fn draw_debug(
         &mut self,
         canvas: &mut Canvas<Window>,
@@ -228,15 +312,25 @@ impl ZxMachine {
             },
             &self.stack_tracker,
             self.load_state,
+            &self.bus.tape,
+            &self.debugger,
+            &self.bus.watch,
+            &self.history,
+            self.history_span(),
             self.debug_enabled,
         )
     }
 
-    /// Actualiza el framebuffer de vídeo a partir del bus
+    /// Redibuja el framebuffer de golpe a partir de la memoria (sin temporización).
+    /// En ejecución el framebuffer ya lo mantiene el renderer por scanlines.
     pub fn update_video_from_bus(&mut self) {
-        self.video.update_from_bus(&self.cpu.bus);
+        match &self.bus.mem128 {
+            Some(mem) => self.video.update_from_bus(&mem.screen(&self.cpu.bus), self.bus.border),
+            None => self.video.update_from_bus(&self.cpu.bus, self.bus.border),
+        }
     }
 
+    #[cfg(feature = "sdl-frontend")]
     pub fn draw_zx_screen(
         &mut self,
         canvas: &mut Canvas<Window>,
@@ -245,6 +339,31 @@ impl ZxMachine {
     }
 
     pub fn step_once(&mut self) {
+        // Como en run_one_frame: el core desborda al pasar de 0xFFFF
+        if self.cpu.reg.pc == 0xFFFF {
+            println!("PC en FFFF: ejecución detenida");
+            self.debugger.pause();
+            return;
+        }
+
+        let snap = self.step_recorded(true);
+
+        self.clock_tick(snap.instr_cycles);
+
+        if self.debug_enabled {
+            self.last_snapshot = Some(snap);
+        }
+
+        self.check_watchpoint();
+    }
+
+    /// Ejecuta una instrucción guardando en el historial cómo deshacerla
+    fn step_recorded(&mut self, from_step: bool) -> CpuSnapshot {
+        let before = self.history.enabled().then(|| self.machine_regs());
+        if before.is_some() {
+            self.bus.journal = Some(Vec::new());
+        }
+
         let snap = step(
             &mut self.cpu,
             &mut self.bus,
@@ -253,11 +372,251 @@ impl ZxMachine {
             &mut self.executed_instrs,
             &mut self.unimpl_tracker,
             &mut self.stack_tracker,
-            true,
+            from_step,
         );
 
+        if let Some(before) = before {
+            let writes = self.bus.journal.take().unwrap_or_default();
+            self.history.push(Delta { before, writes });
+        }
+
+        snap
+    }
+
+    /* ==================================================
+     * HISTORIAL: STEP BACK / RUN BACK / REBOBINADO
+     * ================================================== */
+
+    /// Segundos de keyframes (0 = sin historial)
+    pub fn set_history(&mut self, seconds: u32) {
+        self.history_seconds = seconds;
+        self.history = if seconds == 0 {
+            History::disabled()
+        } else {
+            History::new(
+                HISTORY_STEPS,
+                KEYFRAME_FRAMES * self.machine_type.tstates_per_frame(),
+                seconds as u64 * self.machine_type.cpu_hz(),
+            )
+        };
+    }
+
+    fn machine_regs(&self) -> MachineRegs {
+        MachineRegs {
+            cpu: CpuRegs::capture(&self.cpu),
+            run_state: self.run_state,
+            interrupt_pending: self.interrupt_pending,
+            frame_t: self.interrupt_ctrl.tstates_accum,
+            border: self.bus.border,
+            port_7ffd: self.bus.mem128.as_ref().map(|m| m.port_7ffd),
+        }
+    }
+
+    fn restore_machine_regs(&mut self, r: &MachineRegs) {
+        r.cpu.restore(&mut self.cpu);
+        self.run_state = r.run_state;
+        self.interrupt_pending = r.interrupt_pending;
+        self.interrupt_ctrl.tstates_accum = r.frame_t;
+        self.bus.border = r.border;
+    }
+
+    /// Deshace la última instrucción
+    pub fn step_back(&mut self) -> Result<(), String> {
+        let d = self.history.pop().ok_or("No hay historial")?;
+        self.undo(d);
+        self.after_rewind();
+        Ok(())
+    }
+
+    fn undo(&mut self, d: Delta) {
+        // Primero la paginación: las escrituras se hicieron con la de antes
+        if let (Some(mem), Some(port)) = (self.bus.mem128.as_mut(), d.before.port_7ffd)
+            && mem.port_7ffd != port
+        {
+            mem.page(&mut self.cpu.bus, port);
+        }
+        for &(addr, old) in d.writes.iter().rev() {
+            self.cpu.bus.write_byte(addr, old);
+        }
+        self.restore_machine_regs(&d.before);
+    }
+
+    /// Deshace instrucciones hasta llegar a un breakpoint (o al principio
+    /// del historial). Devuelve cuántas ha deshecho.
+    pub fn run_back(&mut self) -> usize {
+        let mut n = 0;
+        while let Some(d) = self.history.pop() {
+            self.undo(d);
+            n += 1;
+            if self.debugger.breakpoints.check(&self.cpu) {
+                break;
+            }
+        }
+        self.after_rewind();
+        n
+    }
+
+    /// Vuelve al keyframe más reciente de hace al menos `seconds` segundos
+    /// (o al más antiguo). Devuelve los segundos retrocedidos.
+    pub fn rewind_seconds(&mut self, seconds: f32) -> Result<f32, String> {
+        let now = self.run_state.t_states;
+        let back = (seconds.max(0.0) as f64 * self.machine_type.cpu_hz() as f64) as u64;
+        let target = now.saturating_sub(back);
+
+        let keyframes = self.history.keyframes();
+        if keyframes.is_empty() {
+            return Err("No hay historial".into());
+        }
+        let i = keyframes
+            .iter()
+            .rposition(|k| k.t_states() <= target)
+            .unwrap_or(0);
+
+        let k = self.history.rewind_to(i).ok_or("No hay historial")?;
+        let (regs, ram, banks) = (k.regs, k.ram.clone(), k.banks.clone());
+
+        match (self.bus.mem128.as_mut(), banks, regs.port_7ffd) {
+            (Some(mem), Some(banks), Some(port)) => {
+                mem.page(&mut self.cpu.bus, port);
+                for (n, bank) in banks.iter().enumerate() {
+                    mem.set_bank(&mut self.cpu.bus, n, bank);
+                }
+            }
+            _ => {
+                for (i, b) in ram.iter().enumerate() {
+                    self.cpu.bus.write_byte(0x4000 + i as u16, *b);
+                }
+            }
+        }
+        self.restore_machine_regs(&regs);
+        self.after_rewind();
+
+        Ok(now.saturating_sub(regs.run_state.t_states) as f32 / self.machine_type.cpu_hz() as f32)
+    }
+
+    /// Segundos de historial disponibles para el rebobinado
+    pub fn history_span(&self) -> f32 {
+        match self.history.keyframes().front() {
+            Some(k) => {
+                self.run_state.t_states.saturating_sub(k.t_states()) as f32
+                    / self.machine_type.cpu_hz() as f32
+            }
+            None => 0.0,
+        }
+    }
+
+    /// Keyframe al final de frame (si toca)
+    fn record_keyframe(&mut self) {
+        if !self.history.keyframe_due(self.run_state.t_states) {
+            return;
+        }
+
+        let banks = self
+            .bus
+            .mem128
+            .as_ref()
+            .map(|mem| (0..8).map(|n| mem.bank(&self.cpu.bus, n)).collect());
+        let ram = match banks {
+            Some(_) => Vec::new(),
+            None => self.cpu.bus.read_mem_slice(0x4000, 0xFFFF),
+        };
+
+        let k = Keyframe::new(self.machine_regs(), ram, banks);
+        self.history.push_keyframe(k);
+    }
+
+    /// Tras volver atrás: el tiempo ha retrocedido
+    fn after_rewind(&mut self) {
+        self.debugger.pause();
+
+        // Haz de vídeo, contención y sonido desde el principio del frame
+        let t = self.run_state.t_states;
+        let frame_start = t.saturating_sub(self.interrupt_ctrl.tstates_accum);
+        self.bus.border_writes.clear();
+        self.bus.border_writes.push((frame_start, self.bus.border));
+        self.video.start_frame(frame_start);
+        self.bus.contention.frame_start = frame_start;
+        self.bus.beeper.reset(t);
+        if let Some(ay) = self.bus.ay.as_mut() {
+            ay.reset_clock(t);
+        }
+        // El reloj de la cinta ya no es válido
+        self.bus.tape.stop();
+
+        self.update_video_from_bus();
         if self.debug_enabled {
-            self.last_snapshot = Some(snap);
+            let f = (self.cpu.reg.get_af() & 0xFF) as u8;
+            self.last_snapshot = Some(snapshot(&self.cpu, self.cpu.reg.pc, true, f, 0, 0));
+        }
+    }
+
+    /* ==================================================
+     * STEP OVER / STEP OUT / RUN TO
+     * ================================================== */
+
+    /// Ejecuta la instrucción en PC como un solo paso: CALL, RST, DJNZ
+    /// y los bloques que se repiten (LDIR, CPIR...) corren hasta la siguiente
+    pub fn step_over(&mut self) {
+        let pc = self.cpu.reg.pc;
+        let mut bytes = [0u8; 4];
+        for (i, b) in bytes.iter_mut().enumerate() {
+            *b = self.cpu.bus.read_byte(pc.wrapping_add(i as u16));
+        }
+
+        let steps_into = match bytes[0] {
+            // CALL nn / CALL cc,nn / RST p / DJNZ
+            0xCD | 0x10 => true,
+            op if op & 0xC7 == 0xC4 || op & 0xC7 == 0xC7 => true,
+            // LDIR, CPIR, INIR, OTIR y sus versiones hacia atrás
+            0xED => matches!(bytes[1], 0xB0..=0xB3 | 0xB8..=0xBB),
+            _ => false,
+        };
+
+        if !steps_into {
+            self.step_once();
+            return;
+        }
+
+        let (_, len) = crate::disasm::disassemble(&bytes, pc, pc);
+        self.debugger.run_until(RunTarget::Pc {
+            pc: pc.wrapping_add(len.max(1) as u16),
+            min_sp: Some(self.cpu.reg.sp),
+        });
+    }
+
+    /// Corre hasta que vuelve la llamada (o interrupción) en curso
+    pub fn step_out(&mut self) -> Result<(), String> {
+        let slot = self
+            .stack_tracker
+            .return_slot(self.cpu.reg.sp)
+            .ok_or("No hay ninguna llamada en la pila")?;
+
+        self.debugger.run_until(RunTarget::Return { slot });
+        Ok(())
+    }
+
+    /// Corre hasta llegar a `addr`
+    pub fn run_to(&mut self, addr: u16) {
+        self.debugger.run_until(RunTarget::Pc { pc: addr, min_sp: None });
+    }
+
+    /// Corre hasta la dirección marcada en la ventana de instrucciones
+    pub fn run_to_cursor(&mut self) -> Result<(), String> {
+        let addr = self.debugger.cursor.ok_or("No hay ninguna dirección marcada")?;
+        self.run_to(addr);
+        Ok(())
+    }
+
+    /// Informa del watchpoint disparado por la última instrucción (si lo hay)
+    fn check_watchpoint(&mut self) -> bool {
+        match self.bus.watch.take_hit() {
+            Some(hit) => {
+                let text = hit.describe();
+                println!("{}", text);
+                self.debugger.console.output = text;
+                true
+            }
+            None => false,
         }
     }
 
@@ -279,29 +638,234 @@ impl ZxMachine {
     //     println!("ZxMachine: carga completada -> {:?}", self.load_state);
     // }
 
+    #[cfg(feature = "sdl-frontend")]
     pub fn load_from_dialog(&mut self) -> Result<(), String> {
-        let kind = load::load_file_dialog(&mut self.cpu, &mut self.run_state)?;
+        let path = load::pick_load_file()?;
+        self.load_file(&path)
+    }
+
+    /// Activa / desactiva la contención de la ULA
+    pub fn set_contention(&mut self, enabled: bool) {
+        self.bus.contention.enabled = enabled;
+        println!("Contención {}", if enabled { "ON" } else { "OFF" });
+    }
+
+    /// Conecta otra interfaz de joystick
+    pub fn set_joystick(&mut self, kind: JoystickType) {
+        self.bus.joystick.set_kind(kind);
+        println!("Joystick: {}", kind.name());
+    }
+
+    /// Cambia la traducción de las teclas del PC
+    pub fn set_key_mapping(&mut self, mode: KeyMapping) {
+        self.bus.keyboard.mode = mode;
+        println!("Teclado: {}", mode.name());
+    }
+
+    /// Teclea `text` (pegar, --type). Las palabras clave se teclean según el
+    /// modo del cursor si está el editor del 48K: en el 48K o en el 128K
+    /// con la ROM 1 (modo 48 BASIC)
+    pub fn autotype(&mut self, text: &str) {
+        let keywords = self.bus.mem128.as_ref().is_none_or(|m| m.rom_selected() == 1);
+        self.bus.keyboard.autotype(text, keywords);
+        println!("Tecleando {} caracteres", text.chars().count());
+    }
+
+    /// Silencia / activa un canal del AY (0 = A, 1 = B, 2 = C)
+    pub fn toggle_ay_channel(&mut self, channel: usize) {
+        if let Some(ay) = self.bus.ay.as_mut() {
+            ay.toggle_mute(channel);
+            println!(
+                "AY canal {}: {}",
+                (b'A' + channel as u8) as char,
+                if ay.muted[channel] { "MUTE" } else { "ON" }
+            );
+        }
+    }
+
+    /* ==================================================
+     * CONTROLES DE LA CINTA
+     * ================================================== */
+
+    pub fn tape_play(&mut self) {
+        self.bus.tape.play(self.run_state.t_states);
+    }
+
+    pub fn tape_stop(&mut self) {
+        self.bus.tape.stop();
+    }
+
+    pub fn tape_rewind(&mut self) {
+        self.bus.tape.rewind();
+    }
+
+    /// Carga un fichero (rom/sna/z80/bin/tap/tzx) sin pasar por el diálogo
+    pub fn load_file(&mut self, path: &Path) -> Result<(), String> {
+        let kind = load::load_file(&mut self.cpu, &mut self.bus, &mut self.run_state, path)?;
 
         self.on_file_loaded(kind);
+        self.load_breakpoints(path);
 
         Ok(())
     }
 
-    fn on_file_loaded(&mut self, kind: LoadResult) {
-        // Estado común tras cualquier carga
-        self.interrupt_pending = false;
-        self.interrupt_ctrl = InterruptController::new();
-        self.last_snapshot = None;
-        self.run_state.halted = false;
+    /* ==================================================
+     * BREAKPOINTS Y WATCHPOINTS (guardados por programa en <fichero>.bp)
+     * ================================================== */
+
+    /// Fichero de breakpoints de un programa
+    pub fn breakpoints_path(program: &Path) -> PathBuf {
+        program.with_extension("bp")
+    }
+
+    fn load_breakpoints(&mut self, program: &Path) {
+        self.debugger.breakpoints = Breakpoints::new();
+        self.bus.watch = Watchpoints::new();
+        self.program_path = Some(program.to_path_buf());
+
+        // Sin fichero: listas vacías
+        let Ok(text) = std::fs::read_to_string(Self::breakpoints_path(program)) else {
+            return;
+        };
+
+        for line in text.lines().map(str::trim).filter(|l| !l.is_empty()) {
+            let result = if line.trim_start_matches('#').starts_with('w') {
+                self.bus.watch.load_line(line)
+            } else {
+                self.debugger.breakpoints.load_line(line)
+            };
+            if let Err(e) = result {
+                eprintln!("Breakpoints: {}", e);
+            }
+        }
+    }
+
+    fn save_breakpoints(&self) -> Result<(), String> {
+        let Some(program) = &self.program_path else {
+            return Ok(());
+        };
+        let path = Self::breakpoints_path(program);
+        let text = self.debugger.breakpoints.to_text() + &self.bus.watch.to_text();
+
+        // Sin nada que guardar se borra el fichero
+        if text.is_empty() {
+            if path.exists() {
+                std::fs::remove_file(&path).map_err(|e| e.to_string())?;
+            }
+            return Ok(());
+        }
+        std::fs::write(&path, text).map_err(|e| e.to_string())
+    }
+
+    /// Ejecuta un comando de la consola del debugger:
+    /// breakpoints (bp, tbp, bd, be, bc, bl), watchpoints (wp, wio, wd, we, wc, wl)
+    /// y "go <dir>" (correr hasta la dirección)
+    pub fn debug_command(&mut self, line: &str) -> Result<String, String> {
+        let (cmd, arg) = line.trim().split_once(' ').unwrap_or((line.trim(), ""));
+        if cmd.eq_ignore_ascii_case("go") {
+            let addr = parse_address(arg).ok_or(format!("Dirección no válida: {}", arg))?;
+            self.run_to(addr);
+            return Ok(format!("Corriendo hasta {:04X}", addr));
+        }
+        if cmd.eq_ignore_ascii_case("trace") {
+            return self.bus.trace.command(arg);
+        }
+        if cmd.eq_ignore_ascii_case("joy") {
+            if !arg.is_empty() {
+                let kind = JoystickType::parse(arg)
+                    .ok_or(format!("Joystick no válido: {} (none, kempston, sinclair1, sinclair2, cursor)", arg))?;
+                self.set_joystick(kind);
+            }
+            return Ok(format!("Joystick: {}", self.bus.joystick.kind.name()));
+        }
+        if cmd.eq_ignore_ascii_case("keys") {
+            if !arg.is_empty() {
+                let mode = KeyMapping::parse(arg)
+                    .ok_or(format!("Modo de teclado no válido: {} (positional, symbolic)", arg))?;
+                self.set_key_mapping(mode);
+            }
+            return Ok(format!("Teclado: {}", self.bus.keyboard.mode.name()));
+        }
+        if cmd.eq_ignore_ascii_case("type") {
+            let text = unescape_text(line.trim_start()[cmd.len()..].strip_prefix(' ').unwrap_or(""));
+            self.autotype(&text);
+            return Ok(format!("Tecleando: {}", text.escape_debug()));
+        }
+        if cmd.eq_ignore_ascii_case("nmi") {
+            self.nmi();
+            return Ok("NMI pedida".to_string());
+        }
+
+        let out = if line.trim_start().starts_with(['w', 'W']) {
+            self.bus.watch.command(line)?
+        } else {
+            self.debugger.breakpoints.command(line)?
+        };
+        self.save_breakpoints()?;
+        Ok(out)
+    }
 
+    /// Activa la línea NMI: se atiende antes de la siguiente instrucción
+    /// (aunque la máquina esté en pausa, al dar STEP)
+    pub fn nmi(&mut self) {
+        self.run_state.nmi_pending = true;
+        println!("ZxMachine: NMI");
+    }
+
+    /// Pone o quita un breakpoint en el PC actual
+    pub fn toggle_breakpoint_at_pc(&mut self) -> Result<(), String> {
+        self.debugger.breakpoints.toggle_at(self.cpu.reg.pc);
+        self.save_breakpoints()
+    }
+
+    #[cfg(feature = "sdl-frontend")]
+    pub fn save_from_dialog(&self) -> Result<(), String> {
+        save::save_file_dialog(&self.cpu, &self.bus, &self.run_state)
+    }
+
+    /// Carga código sin cabecera en `org` (como un .bin, pero en cualquier dirección)
+    pub fn load_bytes(&mut self, data: &[u8], org: u16) {
+        crate::formatos::bin::load_bin_at(&mut self.cpu, &mut self.run_state, data, org);
+        self.on_file_loaded(LoadResult::Bin);
+    }
+
+    /// Guarda la imagen actual (pantalla y borde) en PNG
+    pub fn save_screenshot(&self, path: &Path) -> Result<(), String> {
+        crate::formatos::png::save_screenshot(&self.video.framebuffer, path)
+    }
+
+    /// Guarda un snapshot (sna/z80) con el estado actual
+    pub fn save_file(&self, path: &Path) -> Result<(), String> {
+        save::save_file(&self.cpu, &self.bus, &self.run_state, path)?;
+        println!("ZxMachine: guardado {}", path.display());
+        Ok(())
+    }
+
+    fn on_file_loaded(&mut self, kind: LoadResult) {
         // Estado visual / lógico
         self.load_state = match kind {
             LoadResult::Rom => LoadState::Rom,
             LoadResult::Sna => LoadState::Sna,
             LoadResult::Z80 => LoadState::Z80,
             LoadResult::Bin => LoadState::Bin,
+            LoadResult::Tap => LoadState::Tap,
+            LoadResult::Tzx => LoadState::Tzx,
         };
 
+        // Una cinta solo se inserta: la máquina sigue corriendo
+        if let LoadResult::Tap | LoadResult::Tzx = kind {
+            println!("ZxMachine: cinta insertada");
+            return;
+        }
+
+        // Estado común tras cualquier carga
+        self.history.clear();
+        self.interrupt_pending = false;
+        self.interrupt_ctrl = InterruptController::with_frame(self.machine_type.tstates_per_frame());
+        self.last_snapshot = None;
+        self.run_state.halted = false;
+        self.restart_frame_clocks();
+
         println!("ZxMachine: cargado {:?}", self.load_state);
     }
 
@@ -319,17 +883,29 @@ impl ZxMachine {
         self.run_state.halted = false;
         self.run_state.allow_interrupts = true;
 
+        // ======================
+        // Paginación 128K (ROM 0, banco 0)
+        // ======================
+        if let Some(mem) = self.bus.mem128.as_mut() {
+            mem.reset(&mut self.cpu.bus, false);
+        }
+        if let Some(ay) = self.bus.ay.as_mut() {
+            ay.reset();
+        }
+
         // ======================
         // Interrupciones
         // ======================
         self.interrupt_pending = false;
-        self.interrupt_ctrl = InterruptController::new();
+        self.interrupt_ctrl = InterruptController::with_frame(self.machine_type.tstates_per_frame());
+        self.restart_frame_clocks();
 
         // ======================
         // Debug / tracking
         // ======================
         self.last_snapshot = None;
         self.executed_instrs.clear();
+        self.history.clear();
         //self.unimpl_tracker.clear();
         //self.stack_tracker.clear();
 
@@ -345,6 +921,21 @@ impl ZxMachine {
 
         println!("ZxMachine: reset completo");
     }
+    /// Alinea el haz de vídeo y el beeper con el reloj tras reiniciar los contadores
+    fn restart_frame_clocks(&mut self) {
+        self.bus.border_writes.clear();
+        // El borde actual (p.ej. el de un snapshot) vale desde el principio
+        self.bus.border_writes.push((self.run_state.t_states, self.bus.border));
+        self.video.start_frame(self.run_state.t_states);
+        self.bus.contention.frame_start = self.run_state.t_states;
+        self.bus.beeper.reset(self.run_state.t_states);
+        if let Some(ay) = self.bus.ay.as_mut() {
+            ay.reset_clock(self.run_state.t_states);
+        }
+        // El reloj de la cinta ya no es válido
+        self.bus.tape.stop();
+    }
+
     pub fn power_reset_machine(&mut self) {
         // RESET normal
         self.reset_machine();
@@ -354,6 +945,9 @@ impl ZxMachine {
         // ======================
         //self.bus.clear_ram_48k();
         self.cpu.bus.clear_mem_slice(0x4000, 0xFFFF);
+        if let Some(mem) = self.bus.mem128.as_mut() {
+            mem.reset(&mut self.cpu.bus, true);
+        }
         // ======================
         // Trackers
         // ======================
@@ -374,9 +968,27 @@ impl ZxMachine {
         let data = std::fs::read(path)
             .map_err(|e| format!("ROM: {}", e))?;
 
-        if data.len() != 16 * 1024 {
-            return Err("La ROM debe ser de 16 KB".into());
+        self.load_rom_bytes(&data)
+    }
+
+    // Carga la ROM desde memoria (16 KB; 32 KB en el 128K)
+    pub fn load_rom_bytes(&mut self, data: &[u8]) -> Result<(), String> {
+        if data.len() != self.machine_type.rom_size() {
+            return Err(format!(
+                "La ROM debe ser de {} KB",
+                self.machine_type.rom_size() / 1024
+            ));
+        }
+
+        // 128K: las dos ROMs van a Memory128, que pagina la ROM 0
+        if let Some(mem) = self.bus.mem128.as_mut() {
+            *mem = Memory128::new(data);
+            mem.reset(&mut self.cpu.bus, false);
+            self.cpu.reg.pc = 0x0000;
+            self.cpu.reg.sp = 0xFFFF;
+            return Ok(());
         }
+
         // ⬇️ CARGAR EN EL BUS DE LA CPU
         for (i, b) in data.iter().enumerate() {
             self.cpu.bus.write_byte(i as u16, *b);
@@ -395,4 +1007,99 @@ impl ZxMachine {
     }
 }
 
+/// Constructor de `ZxMachine`.
+///
+/// ```ignore
+/// let machine = ZxMachine::builder().rom(rom_bytes).build()?;
+/// ```
+pub struct ZxMachineBuilder {
+    machine_type: MachineType,
+    rom: Option<Vec<u8>>,
+    video_scale: u32,
+    sample_rate: u32,
+    ay: bool,
+    contention: bool,
+    history_seconds: u32,
+}
+
+impl ZxMachineBuilder {
+    pub fn new() -> Self {
+        Self {
+            machine_type: MachineType::Spectrum48K,
+            rom: None,
+            video_scale: 1,
+            sample_rate: AUDIO_SAMPLE_RATE,
+            ay: false,
+            contention: true,
+            history_seconds: 0,
+        }
+    }
+
+    /// Modelo emulado (48K por defecto)
+    pub fn machine(mut self, machine_type: MachineType) -> Self {
+        self.machine_type = machine_type;
+        self
+    }
+
+    /// Bytes de la ROM (16 KB; 32 KB en el 128K). Sin ROM la memoria queda a cero.
+    pub fn rom(mut self, data: Vec<u8>) -> Self {
+        self.rom = Some(data);
+        self
+    }
+
+    /// Escala de la pantalla ZX (solo la usa el frontend)
+    pub fn video_scale(mut self, scale: u32) -> Self {
+        self.video_scale = scale;
+        self
+    }
+
+    /// Frecuencia de muestreo del beeper (44100 / 48000)
+    pub fn sample_rate(mut self, rate: u32) -> Self {
+        self.sample_rate = rate;
+        self
+    }
+
+    /// Interfaz AY en el 48K (el 128K siempre lo lleva)
+    pub fn ay(mut self, enabled: bool) -> Self {
+        self.ay = enabled;
+        self
+    }
+
+    /// Contención de la ULA (activada por defecto)
+    pub fn contention(mut self, enabled: bool) -> Self {
+        self.contention = enabled;
+        self
+    }
+
+    /// Historial para ejecutar hacia atrás: segundos de rebobinado
+    /// (0 por defecto: sin historial)
+    pub fn history(mut self, seconds: u32) -> Self {
+        self.history_seconds = seconds;
+        self
+    }
+
+    pub fn build(self) -> Result<ZxMachine, String> {
+        let mut m = ZxMachine::empty(self.video_scale, self.machine_type);
+        m.bus.beeper.set_sample_rate(self.sample_rate);
+        m.bus.contention.enabled = self.contention;
+        m.set_history(self.history_seconds);
+        if self.ay && m.bus.ay.is_none() {
+            m.enable_ay();
+        }
+        if let Some(ay) = m.bus.ay.as_mut() {
+            ay.set_sample_rate(self.sample_rate);
+        }
+
+        if let Some(rom) = self.rom {
+            m.load_rom_bytes(&rom)?;
+        }
+
+        Ok(m)
+    }
+}
 
+impl Default for ZxMachineBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
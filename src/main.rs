@@ -1,62 +1,106 @@
-mod cpu_exec;
-mod gui;
-mod disasm;
-mod debugger;
-mod teclado;
-mod botones;
-mod stack_tracker;
-mod video;
-mod interrupt;
-mod bus;
-mod formatos;
-mod constantes;
-mod machine;
-
+use sdl2::clipboard::ClipboardUtil;
 use sdl2::event::Event;
-use sdl2::keyboard::Keycode;
+use sdl2::keyboard::{Keycode, Mod, TextInputUtil};
 
 //use std::collections::HashMap;
 use std::time::{Duration, Instant};
 
-use botones::ButtonAction;
+use zx::audio::AudioOut;
+use zx::botones;
+use zx::botones::ButtonAction;
+use zx::debugger::RunMode;
+
+use zx::cli::{CliOptions, USAGE};
+use zx::constantes::{
+    ALTO_VENTANA, ANCHO_VENTANA, AUDIO_LATENCY_MS, ESCALA_PANTALLA_ZX, MARGEN_NEGRO,
+    ZX_FRAME_H, ZX_FRAME_W,
+};
+use zx::machine::model::MachineType;
+use zx::machine::zx_machine::ZxMachine;
+use zx::teclado::KeyMapping;
 
-use crate::constantes::{ALTO_VENTANA, ANCHO_VENTANA, ESCALA_PANTALLA_ZX, ESCALA_VENTANA_ZX};
-use crate::machine::zx_machine::ZxMachine;
+// Teclea en el Spectrum el texto del portapapeles
+fn paste_clipboard(machine: &mut ZxMachine, clipboard: &ClipboardUtil) {
+    match clipboard.clipboard_text() {
+        Ok(text) if !text.is_empty() => machine.autotype(&text),
+        Ok(_) => println!("Portapapeles vacío"),
+        Err(e) => println!("Portapapeles: {}", e),
+    }
+}
 
-#[derive(Copy, Clone, Debug)]
-pub enum LoadState {
-    None,
-    Rom,
-    Sna,
-    Z80,
-    Bin,
+// El texto se recoge con la consola del debugger abierta o, para el
+// Spectrum, con el teclado en modo simbólico
+fn sync_text_input(machine: &ZxMachine, text_input: &TextInputUtil) {
+    if machine.debugger.console.active || machine.bus.keyboard.mode == KeyMapping::Symbolic {
+        text_input.start();
+    } else {
+        text_input.stop();
+    }
 }
 
 fn main() -> Result<(), String> {
-    let mut machine = ZxMachine::new(ESCALA_VENTANA_ZX);
+    let opts = match CliOptions::parse(std::env::args().skip(1)) {
+        Ok(o) => o,
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, USAGE);
+            std::process::exit(2);
+        }
+    };
+    if opts.help {
+        println!("{}", USAGE);
+        return Ok(());
+    }
+
+    let mut machine = opts.build_machine()?;
+    if let Some(keys) = &opts.joystick_keys
+        && let Err(e) = machine.bus.joystick.set_keys(keys)
+    {
+        eprintln!("{}\n\n{}", e, USAGE);
+        std::process::exit(2);
+    }
 
     // SDL
     let sdl = sdl2::init()?;
     let video_sub = sdl.video()?;
     let ttf = sdl2::ttf::init().map_err(|e| e.to_string())?;
+    let controller_sub = sdl.game_controller()?;
+    // Los mandos se abren al conectarse (también los que ya lo están al
+    // arrancar) y hay que guardarlos para que sigan mandando eventos
+    let mut controllers = Vec::new();
 
-    let debug_window = video_sub
-        .window("ZX Debugger", ANCHO_VENTANA, ALTO_VENTANA)
-        .position_centered()
-        .build()
-        .map_err(|e| e.to_string())?;
+    let mut debug_canvas = if opts.debug_window {
+        let debug_window = video_sub
+            .window("ZX Debugger", ANCHO_VENTANA, ALTO_VENTANA)
+            .position_centered()
+            .build()
+            .map_err(|e| e.to_string())?;
 
-    let mut debug_canvas = debug_window
-        .into_canvas()
-        .accelerated()
-        .present_vsync()
-        .build()
-        .map_err(|e| e.to_string())?;
+        let canvas = debug_window
+            .into_canvas()
+            .accelerated()
+            .present_vsync()
+            .build()
+            .map_err(|e| e.to_string())?;
+        Some(canvas)
+    } else {
+        None
+    };
 
     let font = ttf.load_font("FONTS/DejaVuSansMono.ttf", 16)?;
 
+    // Con --scale la ventana se ajusta a la imagen
+    let (zx_w, zx_h) = match opts.scale {
+        Some(s) => (
+            ZX_FRAME_W as u32 * s + 2 * MARGEN_NEGRO as u32,
+            ZX_FRAME_H as u32 * s + 2 * MARGEN_NEGRO as u32,
+        ),
+        None => (
+            ZX_FRAME_W as u32 * ESCALA_PANTALLA_ZX,
+            ZX_FRAME_H as u32 * ESCALA_PANTALLA_ZX,
+        ),
+    };
     let zx_window = video_sub
-        .window("ZX Spectrum", 256 * ESCALA_PANTALLA_ZX, 192 * ESCALA_PANTALLA_ZX)
+        .window("ZX Spectrum", zx_w, zx_h)
         .position_centered()
         .build()
         .map_err(|e| e.to_string())?;
@@ -68,6 +112,25 @@ fn main() -> Result<(), String> {
         .build()
         .map_err(|e| e.to_string())?;
 
+    // Audio: si no hay dispositivo se sigue sin sonido y con temporizador
+    let audio = match AudioOut::new(&sdl) {
+        Ok(a) => {
+            machine.bus.beeper.set_sample_rate(a.freq());
+            if let Some(ay) = machine.bus.ay.as_mut() {
+                ay.set_sample_rate(a.freq());
+            }
+            Some(a)
+        }
+        Err(e) => {
+            println!("Audio no disponible: {}", e);
+            None
+        }
+    };
+
+    let text_input = video_sub.text_input();
+    let clipboard = video_sub.clipboard();
+    sync_text_input(&machine, &text_input);
+
     let mut event_pump = sdl.event_pump()?;
     let frame_duration = Duration::from_micros(20000);
 
@@ -77,36 +140,214 @@ fn main() -> Result<(), String> {
         // ===================== EVENTOS =====================
         for event in event_pump.poll_iter() {
             match event {
-                Event::Quit { .. }
-                | Event::KeyDown { keycode: Some(Keycode::Escape), .. } => break 'running,
+                Event::Quit { .. } => break 'running,
+
+                // ---------- Consola del debugger ----------
+                Event::TextInput { text, .. } if machine.debugger.console.active => {
+                    machine.debugger.console.input.push_str(&text);
+                }
+                Event::KeyDown { keycode: Some(k), .. } if machine.debugger.console.active => {
+                    match k {
+                        Keycode::Return | Keycode::KpEnter => {
+                            let line = std::mem::take(&mut machine.debugger.console.input);
+                            machine.debugger.console.output = match machine.debug_command(&line) {
+                                Ok(out) => out,
+                                Err(e) => format!("Error: {}", e),
+                            };
+                        }
+                        Keycode::Backspace => {
+                            machine.debugger.console.input.pop();
+                        }
+                        Keycode::Escape => {
+                            machine.debugger.console.active = false;
+                            sync_text_input(&machine, &text_input);
+                        }
+                        _ => {}
+                    }
+                }
+
+                // Teclado simbólico: el carácter escrito
+                Event::TextInput { text, .. } => machine.bus.keyboard.type_text(&text),
+
+                Event::KeyDown { keycode: Some(Keycode::Escape), .. } => break 'running,
+
+                // F7: step / F8: step over / Shift+F8: step out / F4: run to cursor
+                Event::KeyDown { keycode: Some(Keycode::F7), repeat: false, .. } => {
+                    machine.step_once();
+                }
+                Event::KeyDown { keycode: Some(Keycode::F8), keymod, repeat: false, .. } => {
+                    if keymod.intersects(Mod::LSHIFTMOD | Mod::RSHIFTMOD) {
+                        if let Err(e) = machine.step_out() {
+                            println!("Step out: {}", e);
+                        }
+                    } else {
+                        machine.step_over();
+                    }
+                }
+                Event::KeyDown { keycode: Some(Keycode::F4), repeat: false, .. } => {
+                    if let Err(e) = machine.run_to_cursor() {
+                        println!("Run to: {}", e);
+                    }
+                }
+
+                // F6: step back / Shift+F6: run back
+                Event::KeyDown { keycode: Some(Keycode::F6), keymod, repeat: false, .. } => {
+                    if keymod.intersects(Mod::LSHIFTMOD | Mod::RSHIFTMOD) {
+                        machine.run_back();
+                    } else if let Err(e) = machine.step_back() {
+                        println!("Step back: {}", e);
+                    }
+                }
+
+                // F10: pegar
+                Event::KeyDown { keycode: Some(Keycode::F10), repeat: false, .. } => {
+                    paste_clipboard(&mut machine, &clipboard);
+                }
+
+                // F9: breakpoint en el PC actual
+                Event::KeyDown { keycode: Some(Keycode::F9), repeat: false, .. } => {
+                    if let Err(e) = machine.toggle_breakpoint_at_pc() {
+                        println!("Breakpoints: {}", e);
+                    }
+                }
 
                 Event::KeyDown { keycode: Some(k), repeat: false, .. } => {
-                    machine.bus.keyboard.key_down(k);
+                    machine.bus.key_event(k, true);
                 }
                 Event::KeyUp { keycode: Some(k), .. } => {
-                    machine.bus.keyboard.key_up(k);
+                    machine.bus.key_event(k, false);
+                }
+
+                // ---------- Mandos ----------
+                Event::ControllerDeviceAdded { which, .. } => match controller_sub.open(which) {
+                    Ok(c) => {
+                        println!("Mando conectado: {}", c.name());
+                        controllers.push(c);
+                    }
+                    Err(e) => println!("Mando no disponible: {}", e),
+                },
+                Event::ControllerDeviceRemoved { which, .. } => {
+                    controllers.retain(|c| c.instance_id() != which);
+                    machine.bus.joystick.release_all();
+                }
+                Event::ControllerButtonDown { button, .. } => {
+                    machine.bus.joystick.controller_button(button, true);
+                }
+                Event::ControllerButtonUp { button, .. } => {
+                    machine.bus.joystick.controller_button(button, false);
                 }
+                Event::ControllerAxisMotion { axis, value, .. } => {
+                    machine.bus.joystick.controller_axis(axis, value);
+                }
+
+                // Los botones y paneles están en la ventana del debugger
+                Event::MouseButtonDown { x, y, window_id, .. }
+                    if debug_canvas.as_ref().is_some_and(|c| c.window().id() == window_id) =>
+                {
+                    // Clic en la ventana de instrucciones: marca el cursor (run to)
+                    if let Some(addr) = machine
+                        .last_snapshot
+                        .as_ref()
+                        .and_then(|s| zx::gui::instruction_at(s, x, y))
+                    {
+                        machine.debugger.cursor = Some(addr);
+                    }
+
+                    // Clic en la barra de rebobinado
+                    if let Some(secs) = zx::gui::rewind_at(machine.history_span(), x, y)
+                        && let Err(e) = machine.rewind_seconds(secs)
+                    {
+                        println!("Rebobinar: {}", e);
+                    }
 
-                Event::MouseButtonDown { x, y, .. } => {
                     for b in botones::default_buttons() {
                         if b.contains(x, y) {
                             match b.action {
                                 ButtonAction::Step => {
                                     machine.step_once();
                                 }
+                                ButtonAction::StepOver => machine.step_over(),
+                                ButtonAction::StepOut => {
+                                    if let Err(e) = machine.step_out() {
+                                        println!("Step out: {}", e);
+                                    }
+                                }
+                                ButtonAction::RunToCursor => {
+                                    if let Err(e) = machine.run_to_cursor() {
+                                        println!("Run to: {}", e);
+                                    }
+                                }
+
+                                ButtonAction::StepBack => {
+                                    if let Err(e) = machine.step_back() {
+                                        println!("Step back: {}", e);
+                                    }
+                                }
+                                ButtonAction::RunBack => {
+                                    machine.run_back();
+                                }
 
                                 ButtonAction::Run => machine.debugger.run(),
                                 ButtonAction::RunFast => machine.debugger.run_fast(),
                                 ButtonAction::Pause => machine.debugger.pause(),
 
                                 ButtonAction::Reset => machine.reset_machine(),
+                                ButtonAction::Nmi => machine.nmi(),
+                                ButtonAction::JoystickToggle => {
+                                    let next = machine.bus.joystick.kind.next();
+                                    machine.set_joystick(next);
+                                }
+                                ButtonAction::Paste => paste_clipboard(&mut machine, &clipboard),
+                                ButtonAction::KeyMappingToggle => {
+                                    machine.bus.keyboard.release_all();
+                                    let next = machine.bus.keyboard.mode.next();
+                                    machine.set_key_mapping(next);
+                                    sync_text_input(&machine, &text_input);
+                                }
                                 ButtonAction::HwReset => machine.power_reset_machine(),
 
+                                ButtonAction::TapePlay => machine.tape_play(),
+                                ButtonAction::TapeStop => machine.tape_stop(),
+                                ButtonAction::TapeRewind => machine.tape_rewind(),
+
+                                ButtonAction::MuteA => machine.toggle_ay_channel(0),
+                                ButtonAction::MuteB => machine.toggle_ay_channel(1),
+                                ButtonAction::MuteC => machine.toggle_ay_channel(2),
+                                ButtonAction::Console => {
+                                    let console = &mut machine.debugger.console;
+                                    console.active = !console.active;
+                                    if console.active {
+                                        // Que el Spectrum no se quede con teclas pulsadas
+                                        machine.bus.keyboard.release_all();
+                                        machine.bus.joystick.release_all();
+                                    }
+                                    sync_text_input(&machine, &text_input);
+                                }
+                                ButtonAction::ContentionToggle => {
+                                    let enabled = !machine.bus.contention.enabled;
+                                    machine.set_contention(enabled);
+                                }
+
+                                ButtonAction::MachineToggle => {
+                                    let next = match machine.machine_type {
+                                        MachineType::Spectrum48K => MachineType::Spectrum128K,
+                                        MachineType::Spectrum128K => MachineType::Spectrum48K,
+                                    };
+                                    if let Err(e) = machine.switch_machine(next) {
+                                        println!("Cambio de modelo fallido: {}", e);
+                                    }
+                                }
+
                                 ButtonAction::Load => {
                                     if let Err(e) = machine.load_from_dialog() {
                                         println!("Carga cancelada o error: {}", e);
                                     }
                                 }
+                                ButtonAction::Save => {
+                                    if let Err(e) = machine.save_from_dialog() {
+                                        println!("Guardado cancelado o error: {}", e);
+                                    }
+                                }
                                 ButtonAction::DebugToggle => {
                                     machine.debug_enabled = !machine.debug_enabled;
 
@@ -132,18 +373,48 @@ fn main() -> Result<(), String> {
 
         machine.run_frame();
 
+        // --frames: fin de la ejecución por script
+        if opts.frames.is_some_and(|n| machine.frames >= n) {
+            break 'running;
+        }
+
         // ===================== RENDER =====================
-        machine.update_video_from_bus();
+        // En ejecución el framebuffer lo rellena el renderer por scanlines;
+        // en pausa se redibuja desde la memoria para ver los cambios del STEP
+        if machine.debugger.mode == RunMode::Paused {
+            machine.update_video_from_bus();
+        }
 
-        machine.draw_debug(&mut debug_canvas, &font)?;
-        debug_canvas.present();
+        if let Some(canvas) = debug_canvas.as_mut() {
+            machine.draw_debug(canvas, &font)?;
+            canvas.present();
+        }
 
         machine.draw_zx_screen(&mut zx_canvas)?;
         zx_canvas.present();
 
-        let elapsed = frame_start.elapsed();
-        if elapsed < frame_duration {
-            std::thread::sleep(frame_duration - elapsed);
+        // ===================== AUDIO / SINCRONIZACIÓN =====================
+        let samples = machine.bus.take_audio();
+
+        match &audio {
+            // En RUN el reloj es el audio: esperar a que la cola baje
+            Some(a) if machine.debugger.mode == RunMode::Run => {
+                a.push(&samples)?;
+                while a.queued_ms() > AUDIO_LATENCY_MS {
+                    std::thread::sleep(Duration::from_millis(1));
+                }
+            }
+            // En FAST o PAUSA no se reproduce: temporizador de 50 Hz
+            _ => {
+                if let Some(a) = &audio {
+                    a.clear();
+                }
+
+                let elapsed = frame_start.elapsed();
+                if elapsed < frame_duration {
+                    std::thread::sleep(frame_duration - elapsed);
+                }
+            }
         }
         // Experimento
         //let kk = Duration::from_micros(200000);
@@ -151,6 +422,14 @@ fn main() -> Result<(), String> {
         // Fin Experimento
     }
 
+    if let Some(path) = &opts.screenshot {
+        if machine.debugger.mode == RunMode::Paused {
+            machine.update_video_from_bus();
+        }
+        machine.save_screenshot(path)?;
+        println!("Captura guardada en {}", path.display());
+    }
+
     Ok(())
 }
 
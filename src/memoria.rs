@@ -0,0 +1,232 @@
+use zilog_z80::bus::Bus;
+use crate::constantes::BANK_SIZE;
+use crate::video::ScreenMemory;
+
+/* ==================================================
+ * MEMORIA PAGINADA DEL 128K
+ * ==================================================
+ * El core zilog_z80 solo ve un bus plano de 64K, así que la
+ * paginación se hace copiando bancos dentro y fuera del bus:
+ *
+ *   0x0000-0x3FFF  ROM 0 (editor 128) o ROM 1 (BASIC 48)
+ *   0x4000-0x7FFF  banco 5 (siempre)
+ *   0x8000-0xBFFF  banco 2 (siempre)
+ *   0xC000-0xFFFF  banco seleccionado en 0x7FFD (bits 0-2)
+ *
+ * Mientras un banco está mapeado su contenido "vivo" es el del
+ * bus; la copia de `banks` solo vale para los no mapeados.
+ *
+ * Con el banco 5 o 2 mapeado también en 0xC000 el mismo banco
+ * aparece dos veces en el bus: cada escritura en una ventana se
+ * copia a la otra (`alias`), así al despaginar las dos coinciden.
+ *
+ * Puerto 0x7FFD (escritura):
+ *   bits 0-2  banco en 0xC000
+ *   bit 3     pantalla sombra (banco 7)
+ *   bit 4     ROM (0 = 128, 1 = 48)
+ *   bit 5     bloqueo de la paginación hasta el reset
+ */
+
+pub struct Memory128 {
+    /// 8 bancos de RAM de 16K
+    banks: Vec<Vec<u8>>,
+    /// ROM 0 (editor 128) y ROM 1 (BASIC 48)
+    roms: [Vec<u8>; 2],
+    /// Último valor escrito en 0x7FFD
+    pub port_7ffd: u8,
+    /// Escritura en 0x7FFD pendiente de aplicar al bus
+    pending: Option<u8>,
+}
+
+impl Memory128 {
+    /// `rom`: las dos ROMs seguidas (32K). Si falta alguna queda a cero.
+    pub fn new(rom: &[u8]) -> Self {
+        let mut roms = [vec![0; BANK_SIZE], vec![0; BANK_SIZE]];
+        for (i, chunk) in rom.chunks(BANK_SIZE).take(2).enumerate() {
+            roms[i][..chunk.len()].copy_from_slice(chunk);
+        }
+
+        Self {
+            banks: vec![vec![0; BANK_SIZE]; 8],
+            roms,
+            port_7ffd: 0,
+            pending: None,
+        }
+    }
+
+    pub fn paged_bank(&self) -> usize {
+        (self.port_7ffd & 0x07) as usize
+    }
+
+    pub fn rom_selected(&self) -> usize {
+        ((self.port_7ffd >> 4) & 0x01) as usize
+    }
+
+    pub fn shadow_screen(&self) -> bool {
+        self.port_7ffd & 0x08 != 0
+    }
+
+    pub fn locked(&self) -> bool {
+        self.port_7ffd & 0x20 != 0
+    }
+
+    /// Escritura en 0x7FFD desde un OUT (se aplica con `apply_pending`,
+    /// que es quien tiene acceso al bus de la CPU)
+    pub fn write_port(&mut self, value: u8) {
+        if !self.locked() {
+            self.pending = Some(value);
+        }
+    }
+
+    /// Aplica la paginación pendiente, si la hay
+    pub fn apply_pending(&mut self, bus: &mut Bus) {
+        if let Some(value) = self.pending.take() {
+            self.page(bus, value);
+        }
+    }
+
+    /// Reset: ROM 0, banco 0, sin bloqueo. Con `power` se borra la RAM.
+    pub fn reset(&mut self, bus: &mut Bus, power: bool) {
+        self.pending = None;
+
+        if power {
+            self.banks.iter_mut().for_each(|b| b.fill(0));
+            bus.clear_mem_slice(0x4000, 0xFFFF);
+        } else {
+            let out = bus.read_mem_slice(0xC000, 0xFFFF);
+            self.store_bank(bus, self.paged_bank(), out);
+        }
+
+        self.port_7ffd = 0;
+        write_slice(bus, 0xC000, &self.banks[0]);
+        write_rom(bus, &self.roms[0]);
+    }
+
+    /// Cambia la configuración de 0x7FFD copiando bancos y ROM en el bus
+    pub fn page(&mut self, bus: &mut Bus, value: u8) {
+        let old_bank = self.paged_bank();
+        let old_rom = self.rom_selected();
+        self.port_7ffd = value;
+
+        let new_bank = self.paged_bank();
+        if new_bank != old_bank {
+            // Guardar el banco saliente y traer el nuevo
+            let out = bus.read_mem_slice(0xC000, 0xFFFF);
+            self.store_bank(bus, old_bank, out);
+
+            let data = match new_bank {
+                5 => bus.read_mem_slice(0x4000, 0x7FFF),
+                2 => bus.read_mem_slice(0x8000, 0xBFFF),
+                n => self.banks[n].clone(),
+            };
+            write_slice(bus, 0xC000, &data);
+        }
+
+        if self.rom_selected() != old_rom {
+            write_rom(bus, &self.roms[self.rom_selected()]);
+        }
+    }
+
+    /// Otra dirección del bus con el mismo byte que `addr`: solo existe
+    /// con el banco 5 o 2 mapeado también en 0xC000
+    pub fn alias(&self, addr: u16) -> Option<u16> {
+        match (self.paged_bank(), addr) {
+            (5, 0x4000..=0x7FFF) => Some(addr + 0x8000),
+            (5, 0xC000..=0xFFFF) => Some(addr - 0x8000),
+            (2, 0x8000..=0xBFFF) => Some(addr + 0x4000),
+            (2, 0xC000..=0xFFFF) => Some(addr - 0x4000),
+            _ => None,
+        }
+    }
+
+    /// Si el banco de 0xC000 es el 5 o el 2 (dos ventanas en el bus)
+    pub fn aliased(&self) -> bool {
+        matches!(self.paged_bank(), 5 | 2)
+    }
+
+    /// Contenido actual de un banco (del bus si está mapeado)
+    pub fn bank(&self, bus: &Bus, n: usize) -> Vec<u8> {
+        match n {
+            5 => bus.read_mem_slice(0x4000, 0x7FFF),
+            2 => bus.read_mem_slice(0x8000, 0xBFFF),
+            _ if n == self.paged_bank() => bus.read_mem_slice(0xC000, 0xFFFF),
+            _ => self.banks[n].clone(),
+        }
+    }
+
+    /// Escribe un banco completo (carga de snapshots)
+    pub fn set_bank(&mut self, bus: &mut Bus, n: usize, data: &[u8]) {
+        let len = data.len().min(BANK_SIZE);
+        self.banks[n][..len].copy_from_slice(&data[..len]);
+
+        let bank = &self.banks[n];
+        match n {
+            5 => write_slice(bus, 0x4000, bank),
+            2 => write_slice(bus, 0x8000, bank),
+            _ => {}
+        }
+        if n == self.paged_bank() {
+            write_slice(bus, 0xC000, bank);
+        }
+    }
+
+    /// Guarda el banco que sale de 0xC000. El 5 y el 2 tienen además
+    /// su ventana fija (ya igual a 0xC000 gracias a `alias`).
+    fn store_bank(&mut self, bus: &mut Bus, n: usize, data: Vec<u8>) {
+        match n {
+            5 => write_slice(bus, 0x4000, &data),
+            2 => write_slice(bus, 0x8000, &data),
+            _ => {}
+        }
+        self.banks[n] = data;
+    }
+
+    /// Vista de la pantalla que está mostrando la ULA
+    pub fn screen<'a>(&'a self, bus: &'a Bus) -> Screen128<'a> {
+        let source = if !self.shadow_screen() {
+            ScreenSource::Bus(0x4000)
+        } else if self.paged_bank() == 7 {
+            ScreenSource::Bus(0xC000)
+        } else {
+            ScreenSource::Bank(&self.banks[7])
+        };
+
+        Screen128 { bus, source }
+    }
+}
+
+fn write_slice(bus: &mut Bus, start: u16, data: &[u8]) {
+    for (i, b) in data.iter().enumerate() {
+        bus.write_byte(start.wrapping_add(i as u16), *b);
+    }
+}
+
+/// Copia una ROM en 0x0000-0x3FFF saltándose la protección.
+/// (`set_romspace(1, 0)` deja un rango vacío: nada protegido)
+fn write_rom(bus: &mut Bus, rom: &[u8]) {
+    bus.set_romspace(1, 0);
+    write_slice(bus, 0x0000, rom);
+    bus.set_romspace(0x0000, 0x3FFF);
+}
+
+enum ScreenSource<'a> {
+    /// Pantalla en el bus a partir de esta dirección
+    Bus(u16),
+    /// Banco 7 no mapeado
+    Bank(&'a [u8]),
+}
+
+/// Pantalla normal (banco 5) o sombra (banco 7)
+pub struct Screen128<'a> {
+    bus: &'a Bus,
+    source: ScreenSource<'a>,
+}
+
+impl ScreenMemory for Screen128<'_> {
+    fn screen_byte(&self, offset: u16) -> u8 {
+        match self.source {
+            ScreenSource::Bus(base) => self.bus.read_byte(base + offset),
+            ScreenSource::Bank(bank) => bank[offset as usize],
+        }
+    }
+}
@@ -0,0 +1,225 @@
+use zilog_z80::cpu::CPU;
+use crate::bus::ZxBus;
+
+/* ==================================================
+ * E/S DE PUERTOS
+ * ==================================================
+ * El core zilog_z80 no tiene callbacks de E/S: sus IN/OUT
+ * solo avanzan el PC. Todas las instrucciones de puertos se
+ * ejecutan aquí, ANTES de llamar a cpu.execute(), y se
+ * despachan a ZxBus::in_port / ZxBus::out_port.
+ *
+ * Las de bloque de memoria (LDIR, CPIR...) también van aquí: el core
+ * las ejecuta enteras en una sola llamada (y con los T-states de una
+ * pasada). Aquí, como INIR/OTIR, hacen una pasada por step, así que
+ * historial, watchpoints, contención e interrupciones ven cada byte.
+ */
+
+/// Ejecuta la instrucción de E/S o de bloque en `bytes` (si lo es).
+///
+/// `t` es el T-state absoluto al empezar la instrucción; las escrituras
+/// se sellan con el T-state de su ciclo de E/S (para el borde).
+///
+/// Devuelve los T-states consumidos, o `None` si la instrucción
+/// no es de E/S y debe ejecutarla el core.
+pub fn exec_io(cpu: &mut CPU, zx_bus: &mut ZxBus, bytes: &[u8; 4], t: u64) -> Option<u32> {
+    let pc = cpu.reg.pc;
+
+    match bytes[0] {
+        // IN A,(n): puerto = A*256 + n. No afecta a los flags.
+        // Ciclos 4,3,4: E/S en el último
+        0xDB => {
+            let port = ((cpu.reg.a as u16) << 8) | bytes[1] as u16;
+            cpu.reg.a = zx_bus.in_port(port, t + 8, &cpu.bus);
+            cpu.reg.pc = pc.wrapping_add(2);
+            Some(11)
+        }
+
+        // OUT (n),A: puerto = A*256 + n. Ciclos 4,3,4: E/S en el último
+        0xD3 => {
+            let port = ((cpu.reg.a as u16) << 8) | bytes[1] as u16;
+            zx_bus.out_port(port, cpu.reg.a, t + 8);
+            cpu.reg.pc = pc.wrapping_add(2);
+            Some(11)
+        }
+
+        0xED => exec_io_ed(cpu, zx_bus, bytes[1], t),
+
+        _ => None,
+    }
+}
+
+fn exec_io_ed(cpu: &mut CPU, zx_bus: &mut ZxBus, op: u8, t: u64) -> Option<u32> {
+    let pc = cpu.reg.pc;
+
+    // IN r,(C) -> ED 40, 48, 50, 58, 60, 68, 70, 78
+    if op & 0xC7 == 0x40 {
+        // Ciclos 4,4,4: E/S en el último
+        let val = zx_bus.in_port(cpu.reg.get_bc(), t + 8, &cpu.bus);
+
+        match (op >> 3) & 0x07 {
+            0 => cpu.reg.b = val,
+            1 => cpu.reg.c = val,
+            2 => cpu.reg.d = val,
+            3 => cpu.reg.e = val,
+            4 => cpu.reg.h = val,
+            5 => cpu.reg.l = val,
+            7 => cpu.reg.a = val,
+            _ => {} // Caso 6 es IN (C) que solo afecta a flags
+        }
+
+        // S, Z, P según el valor; H y N a 0; C se conserva
+        let f = &mut cpu.reg.flags;
+        f.s = val & 0x80 != 0;
+        f.z = val == 0;
+        f.b5 = val & 0x20 != 0;
+        f.h = false;
+        f.b3 = val & 0x08 != 0;
+        f.p = val.count_ones().is_multiple_of(2);
+        f.n = false;
+
+        cpu.reg.pc = pc.wrapping_add(2);
+        return Some(12);
+    }
+
+    // OUT (C),r -> ED 41, 49, 51, 59, 61, 69, 71, 79
+    if op & 0xC7 == 0x41 {
+        let val = match (op >> 3) & 0x07 {
+            0 => cpu.reg.b,
+            1 => cpu.reg.c,
+            2 => cpu.reg.d,
+            3 => cpu.reg.e,
+            4 => cpu.reg.h,
+            5 => cpu.reg.l,
+            7 => cpu.reg.a,
+            _ => 0, // OUT (C),0 (no documentada, NMOS)
+        };
+
+        // Ciclos 4,4,4: E/S en el último
+        zx_bus.out_port(cpu.reg.get_bc(), val, t + 8);
+        cpu.reg.pc = pc.wrapping_add(2);
+        return Some(12);
+    }
+
+    match op {
+        // LDI / LDD / LDIR / LDDR
+        0xA0 | 0xA8 | 0xB0 | 0xB8 => {
+            let inc = op & 0x08 == 0;
+            let repeat = op & 0x10 != 0;
+
+            let (hl, de) = (cpu.reg.get_hl(), cpu.reg.get_de());
+            let val = cpu.bus.read_byte(hl);
+            cpu.bus.write_byte(de, val);
+            cpu.reg.set_hl(if inc { hl.wrapping_add(1) } else { hl.wrapping_sub(1) });
+            cpu.reg.set_de(if inc { de.wrapping_add(1) } else { de.wrapping_sub(1) });
+            let bc = cpu.reg.get_bc().wrapping_sub(1);
+            cpu.reg.set_bc(bc);
+
+            // S, Z y C se conservan; 5 y 3 salen de dato + A
+            let n = val.wrapping_add(cpu.reg.a);
+            let f = &mut cpu.reg.flags;
+            f.b5 = n & 0x02 != 0;
+            f.h = false;
+            f.b3 = n & 0x08 != 0;
+            f.p = bc != 0;
+            f.n = false;
+
+            Some(block_finish(cpu, repeat && bc != 0))
+        }
+
+        // CPI / CPD / CPIR / CPDR
+        0xA1 | 0xA9 | 0xB1 | 0xB9 => {
+            let inc = op & 0x08 == 0;
+            let repeat = op & 0x10 != 0;
+
+            let hl = cpu.reg.get_hl();
+            let val = cpu.bus.read_byte(hl);
+            cpu.reg.set_hl(if inc { hl.wrapping_add(1) } else { hl.wrapping_sub(1) });
+            let bc = cpu.reg.get_bc().wrapping_sub(1);
+            cpu.reg.set_bc(bc);
+
+            // C se conserva; 5 y 3 salen de resultado - H
+            let a = cpu.reg.a;
+            let r = a.wrapping_sub(val);
+            let half = a & 0x0F < val & 0x0F;
+            let n = r.wrapping_sub(half as u8);
+            let f = &mut cpu.reg.flags;
+            f.s = r & 0x80 != 0;
+            f.z = r == 0;
+            f.b5 = n & 0x02 != 0;
+            f.h = half;
+            f.b3 = n & 0x08 != 0;
+            f.p = bc != 0;
+            f.n = true;
+
+            Some(block_finish(cpu, repeat && bc != 0 && r != 0))
+        }
+
+        // INI / IND / INIR / INDR
+        0xA2 | 0xAA | 0xB2 | 0xBA => {
+            let inc = op & 0x08 == 0;
+            let repeat = op & 0x10 != 0;
+
+            // El puerto usa B ANTES de decrementar. Ciclos 4,5,4,3(,5)
+            let val = zx_bus.in_port(cpu.reg.get_bc(), t + 9, &cpu.bus);
+            let hl = cpu.reg.get_hl();
+            cpu.bus.write_byte(hl, val);
+            cpu.reg.set_hl(if inc { hl.wrapping_add(1) } else { hl.wrapping_sub(1) });
+            cpu.reg.b = cpu.reg.b.wrapping_sub(1);
+
+            let c = if inc { cpu.reg.c.wrapping_add(1) } else { cpu.reg.c.wrapping_sub(1) };
+            set_block_io_flags(cpu, val, val as u16 + c as u16);
+
+            Some(block_finish(cpu, repeat && cpu.reg.b != 0))
+        }
+
+        // OUTI / OUTD / OTIR / OTDR
+        0xA3 | 0xAB | 0xB3 | 0xBB => {
+            let inc = op & 0x08 == 0;
+            let repeat = op & 0x10 != 0;
+
+            // El puerto usa B DESPUÉS de decrementar. Ciclos 4,5,3,4(,5)
+            let hl = cpu.reg.get_hl();
+            let val = cpu.bus.read_byte(hl);
+            cpu.reg.b = cpu.reg.b.wrapping_sub(1);
+            zx_bus.out_port(cpu.reg.get_bc(), val, t + 12);
+            cpu.reg.set_hl(if inc { hl.wrapping_add(1) } else { hl.wrapping_sub(1) });
+
+            set_block_io_flags(cpu, val, val as u16 + cpu.reg.l as u16);
+
+            Some(block_finish(cpu, repeat && cpu.reg.b != 0))
+        }
+
+        _ => None,
+    }
+}
+
+/// Flags de INI/OUTI y familia (comportamiento real del Z80, incluidos
+/// los no documentados): S, Z, 5, 3 salen de B; N es el bit 7 del dato;
+/// H y C indican acarreo de `k`; P es la paridad de (k & 7) ^ B.
+fn set_block_io_flags(cpu: &mut CPU, val: u8, k: u16) {
+    let b = cpu.reg.b;
+    let f = &mut cpu.reg.flags;
+
+    f.s = b & 0x80 != 0;
+    f.z = b == 0;
+    f.b5 = b & 0x20 != 0;
+    f.b3 = b & 0x08 != 0;
+    f.n = val & 0x80 != 0;
+    f.h = k > 0xFF;
+    f.c = k > 0xFF;
+    f.p = (((k as u8) & 0x07) ^ b).count_ones().is_multiple_of(2);
+}
+
+/// Avanza el PC (o lo deja en la instrucción si hay que repetir)
+/// y devuelve los T-states: 21 si repite, 16 si termina.
+fn block_finish(cpu: &mut CPU, repeat: bool) -> u32 {
+    if repeat {
+        // El PC se queda apuntando a la instrucción: se repite en el
+        // siguiente step (así las interrupciones pueden entrar entre medias)
+        21
+    } else {
+        cpu.reg.pc = cpu.reg.pc.wrapping_add(2);
+        16
+    }
+}
@@ -46,6 +46,19 @@ impl StackTracker {
             .find(|w| w.addr == addr)
             .map(|w| w.kind)
     }
+
+    /// Dirección de la pila (a partir de `sp`) donde está la vuelta de la
+    /// llamada en curso: la más baja escrita por un CALL/RST o una INT
+    pub fn return_slot(&self, sp: u16) -> Option<u16> {
+        (0..self.max_events as u16)
+            .map(|i| sp.wrapping_add(i))
+            .find(|&addr| {
+                matches!(
+                    self.last_write_to(addr),
+                    Some(StackWriteKind::Call | StackWriteKind::Interrupt)
+                )
+            })
+    }
 }
 
 
@@ -1,42 +1,316 @@
+use std::collections::VecDeque;
+
+#[cfg(feature = "sdl-frontend")]
 use sdl2::keyboard::Keycode;
 
+use crate::basic::keyword_chords;
+use crate::constantes::{AUTOTYPE_WAIT_FRAMES, KEY_FORGET_FRAMES, KEY_HOLD_FRAMES};
+
+/* ==================================================
+ * TECLADO
+ * ==================================================
+ * Las teclas del PC se traducen a combinaciones de teclas de la matriz
+ * (Retroceso = CAPS + 0, cursores = CAPS + 5..8, '"' = SYMBOL + P...).
+ * Todas las teclas de una combinación se pulsan y se sueltan a la vez,
+ * entre dos frames, y cada tecla de la matriz lleva la cuenta de quién
+ * la tiene pulsada: soltar un cursor no suelta el CAPS de otro.
+ *
+ * Dos modos:
+ *   posicional: cada tecla del PC es la tecla del Spectrum en su sitio
+ *               (Mayús = CAPS SHIFT, Ctrl = SYMBOL SHIFT)
+ *   simbólico:  se escribe el carácter del PC (eventos de texto) y se
+ *               pulsa la combinación que lo da en el Spectrum
+ *
+ * El texto escrito va a una cola: cada combinación se mantiene
+ * KEY_HOLD_FRAMES frames y una tecla que se repite espera a que la ROM
+ * la haya olvidado (KEY_FORGET_FRAMES).
+ *
+ * Autotecleo (pegar, --type): el texto entra por la misma cola, con las
+ * palabras clave del 48K tecleadas según el modo K/L/E (basic.rs), y
+ * cada combinación espera a que la ROM haya recogido la anterior (bit 5
+ * de FLAGS a 0), como mucho AUTOTYPE_WAIT_FRAMES.
+ */
+
+/// Tecla de la matriz: (fila, bit)
+pub type MatrixKey = (usize, u8);
+
+pub const CAPS_SHIFT: MatrixKey = (0, 0);
+pub const SYMBOL_SHIFT: MatrixKey = (7, 1);
+
+/// Teclas de cada fila, del bit 0 al 4 (\0 = CAPS SHIFT, \x01 = SYMBOL SHIFT)
+const MATRIX: [&[u8; 5]; 8] = [
+    b"\0ZXCV", b"ASDFG", b"QWERT", b"12345", b"09876", b"POIUY", b"\nLKJH", b" \x01MNB",
+];
+
+/// Caracteres con SYMBOL SHIFT y su tecla
+const SYMBOL_KEYS: [(char, u8); 26] = [
+    ('!', b'1'), ('@', b'2'), ('#', b'3'), ('$', b'4'), ('%', b'5'), ('&', b'6'), ('\'', b'7'),
+    ('(', b'8'), (')', b'9'), ('_', b'0'), ('<', b'R'), ('>', b'T'), (';', b'O'), ('"', b'P'),
+    ('^', b'H'), ('-', b'J'), ('+', b'K'), ('=', b'L'), (':', b'Z'), ('£', b'X'), ('?', b'C'),
+    ('/', b'V'), ('*', b'B'), (',', b'N'), ('.', b'M'), ('↑', b'H'),
+];
+
+/// Caracteres del modo extendido: CAPS + SYMBOL y después SYMBOL + tecla
+const EXTENDED_KEYS: [(char, u8); 8] = [
+    ('[', b'Y'), (']', b'U'), ('{', b'F'), ('}', b'G'), ('\\', b'D'), ('|', b'S'), ('~', b'A'),
+    ('©', b'P'),
+];
+
+/// Tecla de la matriz con esa letra (mayúscula), número, espacio o '\n'
+pub fn matrix_key(c: u8) -> Option<MatrixKey> {
+    MATRIX
+        .iter()
+        .enumerate()
+        .find_map(|(row, keys)| keys.iter().position(|&k| k == c).map(|bit| (row, bit as u8)))
+}
+
+/// Combinaciones que hay que pulsar, una tras otra, para escribir `c`
+pub fn char_chords(c: char) -> Option<Vec<Vec<MatrixKey>>> {
+    let key = |k: u8| matrix_key(k).unwrap();
+    let chords = match c {
+        'a'..='z' | '0'..='9' | ' ' => vec![vec![key(c.to_ascii_uppercase() as u8)]],
+        'A'..='Z' => vec![vec![CAPS_SHIFT, key(c as u8)]],
+        '\n' | '\r' => vec![vec![key(b'\n')]],
+        _ => {
+            if let Some(&(_, k)) = SYMBOL_KEYS.iter().find(|(s, _)| *s == c) {
+                vec![vec![SYMBOL_SHIFT, key(k)]]
+            } else {
+                let &(_, k) = EXTENDED_KEYS.iter().find(|(s, _)| *s == c)?;
+                vec![vec![CAPS_SHIFT, SYMBOL_SHIFT], vec![SYMBOL_SHIFT, key(k)]]
+            }
+        }
+    };
+    Some(chords)
+}
+
+/// Texto de la línea de comandos o de la consola: `\n` es un ENTER y
+/// `\\` una barra
+pub fn unescape_text(s: &str) -> String {
+    let mut out = String::new();
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        match (c, chars.clone().next()) {
+            ('\\', Some('n')) => {
+                out.push('\n');
+                chars.next();
+            }
+            ('\\', Some('\\')) => {
+                out.push('\\');
+                chars.next();
+            }
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// Cómo se traducen las teclas del PC
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum KeyMapping {
+    Positional,
+    Symbolic,
+}
+
+impl KeyMapping {
+    /// "positional" / "symbolic" (o en castellano)
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "positional" | "posicional" => Some(KeyMapping::Positional),
+            "symbolic" | "simbolico" | "simbólico" => Some(KeyMapping::Symbolic),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            KeyMapping::Positional => "posicional",
+            KeyMapping::Symbolic => "simbólico",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            KeyMapping::Positional => KeyMapping::Symbolic,
+            KeyMapping::Symbolic => KeyMapping::Positional,
+        }
+    }
+}
+
 pub struct Keyboard {
     // 8 filas (una por cada bit del byte alto de la dirección del puerto)
     // Cada fila tiene 5 bits (bits 0-4). Un bit a 0 significa tecla PULSADA.
     pub rows: [u8; 8],
-
+    pub mode: KeyMapping,
+    /// Cuántas combinaciones tienen pulsada cada tecla de la matriz
+    held: [[u8; 5]; 8],
+    /// Frame en el que se soltó cada tecla de la matriz
+    released_at: [[Option<u64>; 5]; 8],
+    /// Frames desde el arranque (los cuenta on_frame)
+    frame: u64,
+    /// Combinaciones del texto escrito pendientes de pulsar; true si
+    /// esperan a que la ROM recoja la tecla anterior
+    typed: VecDeque<(Vec<MatrixKey>, bool)>,
+    /// Frames que lleva esperando a la ROM la siguiente combinación
+    waited: u64,
+    /// Frame desde el que se puede seguir escribiendo
+    resume_at: u64,
+    /// Combinación del texto pulsada ahora y frames que le quedan
+    typing: Option<(Vec<MatrixKey>, u32)>,
+    /// Teclas del PC pulsadas y la combinación que pulsó cada una
+    #[cfg(feature = "sdl-frontend")]
+    pc_keys: Vec<(Keycode, Vec<MatrixKey>)>,
 }
 impl Keyboard {
     pub fn new() -> Self {
         Self {
             // Inicializamos con 0x1F (00011111), que significa "ninguna tecla pulsada"
             rows: [0x1F; 8],
+            mode: KeyMapping::Positional,
+            held: [[0; 5]; 8],
+            released_at: [[None; 5]; 8],
+            frame: 0,
+            typed: VecDeque::new(),
+            waited: 0,
+            resume_at: 0,
+            typing: None,
+            #[cfg(feature = "sdl-frontend")]
+            pc_keys: Vec::new(),
+        }
+    }
 
+    /// Pulsa o suelta directamente una tecla de la matriz (fila 0-7, bit 0-4).
+    /// Permite alimentar el teclado sin depender de SDL (tests, herramientas).
+    pub fn set_key(&mut self, row: usize, bit: u8, pressed: bool) {
+        if pressed {
+            self.rows[row] &= !(1 << bit); // Ponemos el bit a 0 (Pulsada)
+        } else {
+            self.rows[row] |= 1 << bit;    // Ponemos el bit a 1 (Soltada)
         }
     }
 
-    /// Llamar cuando se pulse una tecla del PC
-    pub fn key_down(&mut self, key: Keycode) {
-        self.update_key_state(key, true);
+    /// Pulsa una combinación (se suma a lo que ya esté pulsado)
+    pub fn press(&mut self, keys: &[MatrixKey]) {
+        for &(row, bit) in keys {
+            self.held[row][bit as usize] += 1;
+            self.set_key(row, bit, true);
+        }
     }
 
-    /// Llamar cuando se suelte una tecla del PC
-    pub fn key_up(&mut self, key: Keycode) {
-        self.update_key_state(key, false);
+    /// Suelta una combinación; cada tecla sigue pulsada si otra la usa
+    pub fn release(&mut self, keys: &[MatrixKey]) {
+        for &(row, bit) in keys {
+            let n = &mut self.held[row][bit as usize];
+            *n = n.saturating_sub(1);
+            if *n == 0 {
+                self.set_key(row, bit, false);
+                self.released_at[row][bit as usize] = Some(self.frame);
+            }
+        }
     }
 
-    /// Actualiza el bit correspondiente en la matriz del Spectrum
-    fn update_key_state(&mut self, key: Keycode, pressed: bool) {
-        // Obtenemos fila y bit. Si la tecla no está mapeada, no hacemos nada.
-        if let Some((row, bit)) = self.get_matrix_coords(key) {
-            if pressed {
-                self.rows[row] &= !(1 << bit); // Ponemos el bit a 0 (Pulsada)
-            } else {
-                self.rows[row] |= 1 << bit;    // Ponemos el bit a 1 (Soltada)
+    /// Suelta todo y olvida el texto pendiente
+    pub fn release_all(&mut self) {
+        self.rows = [0x1F; 8];
+        self.held = [[0; 5]; 8];
+        self.typed.clear();
+        self.waited = 0;
+        self.typing = None;
+        #[cfg(feature = "sdl-frontend")]
+        self.pc_keys.clear();
+    }
+
+    /// Añade texto a la cola; los caracteres sin tecla se ignoran
+    pub fn type_text(&mut self, text: &str) {
+        for c in text.chars() {
+            if let Some(chords) = char_chords(c) {
+                self.typed.extend(chords.into_iter().map(|c| (c, false)));
             }
         }
     }
 
+    /// Autotecleo: con `keywords` el texto es un listado para el editor
+    /// del 48K; sin él se escribe carácter a carácter (editor del 128)
+    pub fn autotype(&mut self, text: &str, keywords: bool) {
+        let chords = if keywords {
+            keyword_chords(text)
+        } else {
+            text.chars().filter_map(char_chords).flatten().collect()
+        };
+        self.typed.extend(chords.into_iter().map(|c| (c, true)));
+    }
+
+    /// No escribe nada en los próximos `frames` (p.ej. mientras arranca la ROM)
+    pub fn pause_typing(&mut self, frames: u64) {
+        self.resume_at = self.frame + frames;
+    }
+
+    /// Queda texto por escribir
+    pub fn typing(&self) -> bool {
+        self.typing.is_some() || !self.typed.is_empty()
+    }
+
+    /// Llamar en cada frame: suelta o pulsa la siguiente combinación del
+    /// texto. `key_taken`: la ROM ha recogido la última tecla
+    pub fn on_frame(&mut self, key_taken: bool) {
+        self.frame += 1;
+        match self.typing.take() {
+            Some((chord, left)) if left > 1 => self.typing = Some((chord, left - 1)),
+            // Al soltar se deja un frame sin nada pulsado
+            Some((chord, _)) => self.release(&chord),
+            None => {
+                let Some((chord, wait_rom)) = self.typed.front() else {
+                    return;
+                };
+                if self.frame < self.resume_at || !self.can_press(chord) {
+                    return;
+                }
+                if *wait_rom && !key_taken && self.waited < AUTOTYPE_WAIT_FRAMES {
+                    self.waited += 1;
+                    return;
+                }
+                let (chord, _) = self.typed.pop_front().unwrap();
+                self.waited = 0;
+                self.press(&chord);
+                self.typing = Some((chord, KEY_HOLD_FRAMES));
+            }
+        }
+    }
+
+    /// Las teclas de la combinación (salvo los SHIFT) están sueltas y la ROM
+    /// ya las ha olvidado
+    fn can_press(&self, chord: &[MatrixKey]) -> bool {
+        chord
+            .iter()
+            .filter(|k| **k != CAPS_SHIFT && **k != SYMBOL_SHIFT)
+            .all(|&(row, bit)| {
+                self.held[row][bit as usize] == 0
+                    && self.released_at[row][bit as usize]
+                        .is_none_or(|f| self.frame >= f + KEY_FORGET_FRAMES)
+            })
+    }
+
+    /// Llamar cuando se pulse una tecla del PC
+    #[cfg(feature = "sdl-frontend")]
+    pub fn key_down(&mut self, key: Keycode) {
+        if self.pc_keys.iter().any(|(k, _)| *k == key) {
+            return;
+        }
+        if let Some(chord) = self.get_matrix_coords(key) {
+            self.press(&chord);
+            self.pc_keys.push((key, chord));
+        }
+    }
+
+    /// Llamar cuando se suelte una tecla del PC: suelta lo que pulsó, aunque
+    /// entre medias haya cambiado el modo
+    #[cfg(feature = "sdl-frontend")]
+    pub fn key_up(&mut self, key: Keycode) {
+        if let Some(i) = self.pc_keys.iter().position(|(k, _)| *k == key) {
+            let (_, chord) = self.pc_keys.swap_remove(i);
+            self.release(&chord);
+        }
+    }
+
     /// Devuelve el estado del puerto 0xFE basado en las filas seleccionadas (bits a 0 en high_byte)
     pub fn read_port_fe(&self, high_byte: u8) -> u8 {
         let mut result = 0x1F; // 5 bits bajos en 1
@@ -54,67 +328,54 @@ impl Keyboard {
         result
     }
 
-    /// Mapeo de teclas PC -> Matriz ZX Spectrum (Fila, Bit)
-    fn get_matrix_coords(&self, key: Keycode) -> Option<(usize, u8)> {
-        match key {
-            // Fila 0: (FEFE) -> Caps, Z, X, C, V
-            Keycode::LShift => Some((0, 0)),
-            Keycode::Z => Some((0, 1)),
-            Keycode::X => Some((0, 2)),
-            Keycode::C => Some((0, 3)),
-            Keycode::V => Some((0, 4)),
-
-            // Fila 1: (FDFE) -> A, S, D, F, G
-            Keycode::A => Some((1, 0)),
-            Keycode::S => Some((1, 1)),
-            Keycode::D => Some((1, 2)),
-            Keycode::F => Some((1, 3)),
-            Keycode::G => Some((1, 4)),
-
-            // Fila 2: (FBFE) -> Q, W, E, R, T
-            Keycode::Q => Some((2, 0)),
-            Keycode::W => Some((2, 1)),
-            Keycode::E => Some((2, 2)),
-            Keycode::R => Some((2, 3)),
-            Keycode::T => Some((2, 4)),
-
-            // Fila 3: (F7FE) -> 1, 2, 3, 4, 5
-            Keycode::Num1 => Some((3, 0)),
-            Keycode::Num2 => Some((3, 1)),
-            Keycode::Num3 => Some((3, 2)),
-            Keycode::Num4 => Some((3, 3)),
-            Keycode::Num5 => Some((3, 4)),
-
-            // Fila 4: (EFFE) -> 0, 9, 8, 7, 6
-            Keycode::Num0 => Some((4, 0)),
-            Keycode::Num9 => Some((4, 1)),
-            Keycode::Num8 => Some((4, 2)),
-            Keycode::Num7 => Some((4, 3)),
-            Keycode::Num6 => Some((4, 4)),
-
-            // Fila 5: (DFFE) -> P, O, I, U, Y
-            Keycode::P => Some((5, 0)),
-            Keycode::O => Some((5, 1)),
-            Keycode::I => Some((5, 2)),
-            Keycode::U => Some((5, 3)),
-            Keycode::Y => Some((5, 4)),
-
-            // Fila 6: (BFFE) -> Enter, L, K, J, H
-            Keycode::Return => Some((6, 0)),
-            Keycode::L => Some((6, 1)),
-            Keycode::K => Some((6, 2)),
-            Keycode::J => Some((6, 3)),
-            Keycode::H => Some((6, 4)),
-
-            // Fila 7: (7FFE) -> Space, Sym, M, N, B
-            Keycode::Space => Some((7, 0)),
-            Keycode::LCtrl => Some((7, 1)), // Symbol Shift
-            Keycode::M => Some((7, 2)),
-            Keycode::N => Some((7, 3)),
-            Keycode::B => Some((7, 4)),
+    /// Mapeo de teclas PC -> combinación de teclas de la matriz. En el modo
+    /// simbólico solo las teclas que no escriben texto (el resto llega
+    /// con type_text)
+    #[cfg(feature = "sdl-frontend")]
+    fn get_matrix_coords(&self, key: Keycode) -> Option<Vec<MatrixKey>> {
+        let key_at = |c: u8| matrix_key(c).unwrap();
+        let chord = match key {
+            Keycode::Return | Keycode::KpEnter => vec![key_at(b'\n')],
+            // Borrar (DELETE)
+            Keycode::Backspace | Keycode::Delete => vec![CAPS_SHIFT, key_at(b'0')],
+            // Cursores
+            Keycode::Left => vec![CAPS_SHIFT, key_at(b'5')],
+            Keycode::Down => vec![CAPS_SHIFT, key_at(b'6')],
+            Keycode::Up => vec![CAPS_SHIFT, key_at(b'7')],
+            Keycode::Right => vec![CAPS_SHIFT, key_at(b'8')],
+            // CAPS LOCK, EDIT, BREAK y modo extendido
+            Keycode::CapsLock => vec![CAPS_SHIFT, key_at(b'2')],
+            Keycode::Home => vec![CAPS_SHIFT, key_at(b'1')],
+            Keycode::End => vec![CAPS_SHIFT, key_at(b' ')],
+            Keycode::Tab => vec![CAPS_SHIFT, SYMBOL_SHIFT],
 
-            _ => None,
-        }
+            _ if self.mode == KeyMapping::Symbolic => return None,
+
+            Keycode::LShift | Keycode::RShift => vec![CAPS_SHIFT],
+            Keycode::LCtrl => vec![SYMBOL_SHIFT],
+            Keycode::Space => vec![key_at(b' ')],
+
+            // Letras y números en su tecla; la puntuación sin Mayús con el
+            // SYMBOL SHIFT que la da (',' '.' '-' '=' ';' '\'' '/')
+            _ => {
+                let name = key.name();
+                let mut chars = name.chars();
+                let (Some(c), None) = (chars.next(), chars.next()) else {
+                    return None;
+                };
+                match matrix_key(c.to_ascii_uppercase() as u8) {
+                    Some(k) if c.is_ascii_alphanumeric() => vec![k],
+                    _ => char_chords(c).filter(|c| c.len() == 1)?.remove(0),
+                }
+            }
+        };
+        Some(chord)
+    }
+}
+
+impl Default for Keyboard {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
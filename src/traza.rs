@@ -0,0 +1,418 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use zilog_z80::cpu::CPU;
+use crate::breakpoints::parse_address;
+
+/* ==================================================
+ * TRAZA DE EJECUCIÓN
+ * ==================================================
+ * Escribe una línea por instrucción ejecutada (registros de ANTES
+ * de ejecutarla) en un fichero de texto. El formato es una plantilla
+ * con campos entre llaves y anchura opcional:
+ *
+ *   {T} {PC} {BYTES:11} {INSTR:20} AF={AF} ... {FLAGS}
+ *
+ * Se puede arrancar/parar al llegar a una dirección o a un número de
+ * frame (contados desde "trace on") y filtrar rangos de direcciones.
+ *
+ * Comandos de consola:
+ *   trace on <fichero> / trace off
+ *   trace start <dir|frame:N|->  /  trace stop <dir|frame:N|->
+ *   trace only <ini>-<fin>  /  trace skip <ini>-<fin>  /  trace all
+ *   trace format <default|csv|plantilla>
+ */
+
+pub const TRACE_FORMAT_DEFAULT: &str = "{T:10} {PC} {BYTES:11} {INSTR:20} AF={AF} BC={BC} DE={DE} HL={HL} AF'={AF'} BC'={BC'} DE'={DE'} HL'={HL'} IX={IX} IY={IY} SP={SP} I={I} R={R} {FLAGS}";
+pub const TRACE_FORMAT_CSV: &str = "{T};{PC};{BYTES};{INSTR};{AF};{BC};{DE};{HL};{AF'};{BC'};{DE'};{HL'};{IX};{IY};{SP};{I};{R};{FLAGS}";
+
+/// Momento de empezar o terminar
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Trigger {
+    /// Al ir a ejecutar la instrucción de esa dirección
+    Addr(u16),
+    /// Al acabar ese frame (contados desde que se abre la traza)
+    Frame(u64),
+}
+
+impl Trigger {
+    /// "8000", "$8000", "FADE", "frame:50" (un prefijo 'f' sería
+    /// ambiguo con las direcciones F000-FFFF)
+    pub fn parse(s: &str) -> Option<Self> {
+        let frame = s.get(..6).filter(|p| p.eq_ignore_ascii_case("frame:")).map(|_| &s[6..]);
+        match frame {
+            Some(n) => n.parse().ok().map(Trigger::Frame),
+            None => parse_address(s).map(Trigger::Addr),
+        }
+    }
+
+    fn describe(&self) -> String {
+        match self {
+            Trigger::Addr(a) => format!("{:04X}", a),
+            Trigger::Frame(n) => format!("frame {}", n),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Field {
+    T, Pc, Bytes, Instr, A, F,
+    Af, Bc, De, Hl, Af_, Bc_, De_, Hl_,
+    Ix, Iy, Sp, I, R, Flags,
+}
+
+impl Field {
+    fn parse(name: &str) -> Option<Self> {
+        Some(match name.to_ascii_uppercase().as_str() {
+            "T" => Field::T,
+            "PC" => Field::Pc,
+            "BYTES" => Field::Bytes,
+            "INSTR" => Field::Instr,
+            "A" => Field::A,
+            "F" => Field::F,
+            "AF" => Field::Af,
+            "BC" => Field::Bc,
+            "DE" => Field::De,
+            "HL" => Field::Hl,
+            "AF'" => Field::Af_,
+            "BC'" => Field::Bc_,
+            "DE'" => Field::De_,
+            "HL'" => Field::Hl_,
+            "IX" => Field::Ix,
+            "IY" => Field::Iy,
+            "SP" => Field::Sp,
+            "I" => Field::I,
+            "R" => Field::R,
+            "FLAGS" => Field::Flags,
+            _ => return None,
+        })
+    }
+}
+
+enum Piece {
+    Text(String),
+    Field(Field, usize),
+}
+
+/// Plantilla de línea ya troceada
+pub struct TraceFormat {
+    pieces: Vec<Piece>,
+}
+
+impl TraceFormat {
+    pub fn parse(src: &str) -> Result<Self, String> {
+        let mut pieces = Vec::new();
+        let mut rest = src;
+
+        while let Some(open) = rest.find('{') {
+            if open > 0 {
+                pieces.push(Piece::Text(rest[..open].to_string()));
+            }
+            let close = rest[open..]
+                .find('}')
+                .ok_or(format!("Falta '}}' en el formato: {}", src))?
+                + open;
+            let inner = &rest[open + 1..close];
+            let (name, width) = match inner.split_once(':') {
+                Some((n, w)) => (n, w.parse().map_err(|_| format!("Anchura no válida: {}", inner))?),
+                None => (inner, 0),
+            };
+            let field = Field::parse(name).ok_or(format!("Campo desconocido: {{{}}}", name))?;
+            pieces.push(Piece::Field(field, width));
+            rest = &rest[close + 1..];
+        }
+        if !rest.is_empty() {
+            pieces.push(Piece::Text(rest.to_string()));
+        }
+
+        Ok(Self { pieces })
+    }
+
+    /// Línea de una instrucción (sin el salto de línea)
+    pub fn line(&self, cpu: &CPU, t_states: u64, pc: u16, bytes: &[u8], mnemonic: &str) -> String {
+        let mut s = String::with_capacity(160);
+
+        for p in &self.pieces {
+            let (field, width) = match p {
+                Piece::Text(t) => {
+                    s.push_str(t);
+                    continue;
+                }
+                Piece::Field(f, w) => (*f, *w),
+            };
+            let value = match field {
+                Field::T => t_states.to_string(),
+                Field::Pc => format!("{:04X}", pc),
+                Field::Bytes => bytes.iter().map(|b| format!("{:02X}", b)).collect::<Vec<_>>().join(" "),
+                Field::Instr => mnemonic.to_string(),
+                Field::A => format!("{:02X}", cpu.reg.a),
+                Field::F => format!("{:02X}", cpu.reg.get_af() as u8),
+                Field::Af => format!("{:04X}", cpu.reg.get_af()),
+                Field::Bc => format!("{:04X}", cpu.reg.get_bc()),
+                Field::De => format!("{:04X}", cpu.reg.get_de()),
+                Field::Hl => format!("{:04X}", cpu.reg.get_hl()),
+                Field::Af_ => format!("{:04X}", cpu.alt.get_af()),
+                Field::Bc_ => format!("{:04X}", cpu.alt.get_bc()),
+                Field::De_ => format!("{:04X}", cpu.alt.get_de()),
+                Field::Hl_ => format!("{:04X}", cpu.alt.get_hl()),
+                Field::Ix => format!("{:04X}", cpu.reg.get_ix()),
+                Field::Iy => format!("{:04X}", cpu.reg.get_iy()),
+                Field::Sp => format!("{:04X}", cpu.reg.sp),
+                Field::I => format!("{:02X}", cpu.reg.i),
+                Field::R => format!("{:02X}", cpu.reg.r),
+                Field::Flags => flags(cpu.reg.get_af() as u8),
+            };
+            s.push_str(&format!("{:<width$}", value, width = width));
+        }
+
+        s
+    }
+}
+
+/// "SZ5H3PNC" con '-' en los que están a cero
+pub fn flags(f: u8) -> String {
+    "SZ5H3PNC"
+        .chars()
+        .enumerate()
+        .map(|(i, c)| if f & (0x80 >> i) != 0 { c } else { '-' })
+        .collect()
+}
+
+pub struct Trace {
+    out: Option<BufWriter<File>>,
+    path: Option<PathBuf>,
+    format: TraceFormat,
+    pub start: Option<Trigger>,
+    pub stop: Option<Trigger>,
+    /// Solo estos rangos (vacío: todos)
+    pub only: Vec<(u16, u16)>,
+    /// Rangos que no se escriben (p. ej. la ROM)
+    pub skip: Vec<(u16, u16)>,
+    recording: bool,
+    frame: u64,
+    lines: u64,
+}
+
+impl Trace {
+    pub fn new() -> Self {
+        Self {
+            out: None,
+            path: None,
+            format: TraceFormat::parse(TRACE_FORMAT_DEFAULT).unwrap(),
+            start: None,
+            stop: None,
+            only: Vec::new(),
+            skip: Vec::new(),
+            recording: false,
+            frame: 0,
+            lines: 0,
+        }
+    }
+
+    /// Abre el fichero. Sin disparador de inicio se graba desde ya.
+    pub fn open(&mut self, path: &Path) -> Result<(), String> {
+        self.close();
+        let file = File::create(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        self.out = Some(BufWriter::new(file));
+        self.path = Some(path.to_path_buf());
+        self.recording = self.start.is_none();
+        self.frame = 0;
+        self.lines = 0;
+        Ok(())
+    }
+
+    pub fn close(&mut self) {
+        if let Some(mut out) = self.out.take()
+            && let Err(e) = out.flush()
+        {
+            println!("Traza: {}", e);
+        }
+        self.recording = false;
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.out.is_some()
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.out.is_some() && self.recording
+    }
+
+    /// Líneas escritas desde que se abrió
+    pub fn lines(&self) -> u64 {
+        self.lines
+    }
+
+    pub fn set_format(&mut self, src: &str) -> Result<(), String> {
+        self.format = TraceFormat::parse(match src {
+            "default" => TRACE_FORMAT_DEFAULT,
+            "csv" => TRACE_FORMAT_CSV,
+            _ => src,
+        })?;
+        Ok(())
+    }
+
+    /// Instrucción a punto de ejecutarse (desde cpu_exec::step)
+    pub fn record(&mut self, cpu: &CPU, t_states: u64, pc: u16, bytes: &[u8], mnemonic: &str) {
+        if self.out.is_none() {
+            return;
+        }
+
+        if !self.recording && self.start == Some(Trigger::Addr(pc)) {
+            self.recording = true;
+        }
+        if self.recording && self.stop == Some(Trigger::Addr(pc)) {
+            self.close();
+            return;
+        }
+        if !self.recording || !self.wanted(pc) {
+            return;
+        }
+
+        let line = self.format.line(cpu, t_states, pc, bytes, mnemonic);
+        if let Some(out) = self.out.as_mut() {
+            match writeln!(out, "{}", line) {
+                Ok(()) => self.lines += 1,
+                Err(e) => {
+                    println!("Traza: {}", e);
+                    self.close();
+                }
+            }
+        }
+    }
+
+    /// Fin de frame (para los disparadores por frame)
+    pub fn on_frame(&mut self) {
+        if self.out.is_none() {
+            return;
+        }
+        self.frame += 1;
+
+        if !self.recording
+            && let Some(Trigger::Frame(n)) = self.start
+            && self.frame >= n
+        {
+            self.recording = true;
+        }
+        if let Some(Trigger::Frame(n)) = self.stop
+            && self.frame >= n
+        {
+            self.close();
+        }
+    }
+
+    fn wanted(&self, pc: u16) -> bool {
+        let inside = |&(a, b): &(u16, u16)| (a..=b).contains(&pc);
+        (self.only.is_empty() || self.only.iter().any(inside)) && !self.skip.iter().any(inside)
+    }
+
+    /* ==================================================
+     * COMANDOS DE CONSOLA
+     * ================================================== */
+
+    /// `line` sin el "trace" del principio
+    pub fn command(&mut self, line: &str) -> Result<String, String> {
+        let line = line.trim();
+        let (cmd, arg) = line.split_once(' ').unwrap_or((line, ""));
+        let arg = arg.trim();
+
+        match cmd.to_ascii_lowercase().as_str() {
+            "" => Ok(self.describe()),
+            "on" => {
+                if arg.is_empty() {
+                    return Err("Falta el fichero".into());
+                }
+                self.open(Path::new(arg))?;
+                Ok(self.describe())
+            }
+            "off" => {
+                self.close();
+                Ok(format!("Traza cerrada: {} líneas", self.lines))
+            }
+            "start" | "stop" => {
+                let trigger = match arg {
+                    "-" => None,
+                    _ => Some(Trigger::parse(arg).ok_or(format!("Disparador no válido: {}", arg))?),
+                };
+                if cmd.eq_ignore_ascii_case("start") {
+                    self.start = trigger;
+                    // Con el fichero ya abierto y nada escrito todavía, el
+                    // nuevo disparador decide si se graba desde ya
+                    if self.is_open() && self.lines == 0 {
+                        self.recording = self.start.is_none();
+                    }
+                } else {
+                    self.stop = trigger;
+                }
+                Ok(self.describe())
+            }
+            "only" | "skip" => {
+                let range = parse_range(arg)?;
+                if cmd.eq_ignore_ascii_case("only") {
+                    self.only.push(range);
+                } else {
+                    self.skip.push(range);
+                }
+                Ok(self.describe())
+            }
+            "all" => {
+                self.only.clear();
+                self.skip.clear();
+                Ok(self.describe())
+            }
+            "format" => {
+                self.set_format(arg)?;
+                Ok("Formato cambiado".into())
+            }
+            _ => Err(format!("Comando de traza desconocido: {}", cmd)),
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        let state = match (&self.path, self.is_open(), self.recording) {
+            (Some(p), true, true) => format!("Grabando en {} ({} líneas)", p.display(), self.lines),
+            (Some(p), true, false) => format!("Esperando en {}", p.display()),
+            _ => "Traza cerrada".to_string(),
+        };
+
+        let mut s = state;
+        if let Some(t) = self.start {
+            s += &format!(", empieza en {}", t.describe());
+        }
+        if let Some(t) = self.stop {
+            s += &format!(", para en {}", t.describe());
+        }
+        for (a, b) in &self.only {
+            s += &format!(", solo {:04X}-{:04X}", a, b);
+        }
+        for (a, b) in &self.skip {
+            s += &format!(", sin {:04X}-{:04X}", a, b);
+        }
+        s
+    }
+}
+
+impl Default for Trace {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// "0000-3FFF" o una sola dirección
+fn parse_range(s: &str) -> Result<(u16, u16), String> {
+    let bad = || format!("Rango no válido: {}", s);
+    match s.split_once('-') {
+        Some((a, b)) => {
+            let (a, b) = (parse_address(a).ok_or_else(bad)?, parse_address(b).ok_or_else(bad)?);
+            if a > b {
+                return Err(bad());
+            }
+            Ok((a, b))
+        }
+        None => {
+            let a = parse_address(s).ok_or_else(bad)?;
+            Ok((a, a))
+        }
+    }
+}
+
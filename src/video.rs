@@ -1,7 +1,57 @@
 use zilog_z80::bus::Bus;
+use crate::constantes::{
+    FIRST_SCREEN_LINE, TSTATES_PER_LINE, ZX_BORDER, ZX_FRAME_H, ZX_FRAME_W,
+};
+
+/// Ancho y alto del framebuffer (pantalla + borde)
+pub const FB_W: usize = ZX_FRAME_W as usize;
+pub const FB_H: usize = ZX_FRAME_H as usize;
+
+// Esquina superior izquierda del área de papel dentro del framebuffer
+const PAPER_X: usize = ZX_BORDER as usize;
+const PAPER_Y: usize = ZX_BORDER as usize;
+
+/* ==================================================
+ * TEMPORIZACIÓN DEL HAZ (BEAM)
+ * ==================================================
+ * La ULA dibuja 2 píxeles por T-state. Cada "posición del haz" es
+ * una pareja de píxeles del framebuffer, recorridas en orden raster.
+ * La primera línea visible es la 16 del frame (64 - 48 de borde
+ * superior) y el borde izquierdo se dibuja en los últimos 24 T-states
+ * de la línea anterior.
+ */
+const BEAM_W: usize = FB_W / 2;
+const BEAM_TOTAL: usize = BEAM_W * FB_H;
+const LEFT_BORDER_T: u64 = (ZX_BORDER / 2) as u64;
+
+/// Memoria de pantalla que lee la ULA (6912 bytes: píxeles y atributos).
+///
+/// En el 48K es siempre 0x4000 del bus; en el 128K puede ser el
+/// banco 5 o el 7 (pantalla sombra).
+pub trait ScreenMemory {
+    /// Byte `offset` (0..6912) de la pantalla
+    fn screen_byte(&self, offset: u16) -> u8;
+}
+
+impl ScreenMemory for Bus {
+    fn screen_byte(&self, offset: u16) -> u8 {
+        self.read_byte(0x4000 + offset)
+    }
+}
+
+/// Color RGB real del índice 0-15 del framebuffer (8-15 con BRIGHT)
+pub fn rgb(index: u8) -> (u8, u8, u8) {
+    let level = if index >= 8 { 255 } else { 192 };
+    let code = index & 0x07;
+
+    // Bits del código: G R B
+    let on = |bit: u8| if code & bit != 0 { level } else { 0 };
+    (on(2), on(4), on(1))
+}
 
 pub struct Video {
-    /// Buffer final de imagen (256x192). Almacenamos el color real (0-15) de cada píxel.
+    /// Buffer final de imagen (352x296, pantalla 256x192 + borde).
+    /// Almacenamos el color real (0-15) de cada píxel.
     /// Esto facilita mucho el dibujo en SDL después.
     pub framebuffer: Vec<u8>,
     pub scale: u32,
@@ -9,15 +59,40 @@ pub struct Video {
     // FLASH (color que hace flash cada ~0,32 s)
     pub flash_counter: u32,
     pub flash_phase: bool,
+
+    // Temporización del modelo: T-states por línea y primera línea visible
+    line_t: u64,
+    first_visible_line: u64,
+
+    // Renderer por scanlines: frame en curso y posición del haz
+    back: Vec<u8>,
+    beam: usize,
+    frame_start: u64,
+    border: u8,
+    border_idx: usize,
+    // Byte de píxeles y atributo que la ULA tiene "cargados" para la celda actual
+    latch_pixels: u8,
+    latch_attr: u8,
 }
 
 impl Video {
     pub fn new(scale: u32) -> Self {
         Self {
-            framebuffer: vec![0; 256 * 192],
+            framebuffer: vec![0; FB_W * FB_H],
             scale,
             flash_counter: 0,
             flash_phase: false,
+
+            line_t: TSTATES_PER_LINE,
+            first_visible_line: FIRST_SCREEN_LINE - ZX_BORDER as u64,
+
+            back: vec![0; FB_W * FB_H],
+            beam: 0,
+            frame_start: 0,
+            border: 0,
+            border_idx: 0,
+            latch_pixels: 0,
+            latch_attr: 0,
         }
     }
 
@@ -26,46 +101,131 @@ impl Video {
         self.flash_counter = 0;
         self.flash_phase = false;
     }
-    
-    /// Actualiza el framebuffer combinando píxeles y atributos
-    pub fn update_from_bus(&mut self, bus: &Bus) {
+
+    /// Temporización del haz: T-states por línea y línea (desde la INT)
+    /// del primer píxel de pantalla. Por defecto la del 48K.
+    pub fn set_timing(&mut self, tstates_per_line: u64, first_screen_line: u64) {
+        self.line_t = tstates_per_line;
+        self.first_visible_line = first_screen_line - ZX_BORDER as u64;
+    }
+
+    /// T-state (desde el inicio del frame) en el que se dibuja la posición `beam`
+    fn beam_tstate(&self, beam: usize) -> u64 {
+        let row = (beam / BEAM_W) as u64;
+        let col = (beam % BEAM_W) as u64;
+        (row + self.first_visible_line) * self.line_t + col - LEFT_BORDER_T
+    }
+
+    /// Empieza un frame nuevo en el T-state absoluto `t` (momento de la INT)
+    pub fn start_frame(&mut self, t: u64) {
+        self.frame_start = t;
+        self.beam = 0;
+        self.border_idx = 0;
+    }
+
+    /// Dibuja en el frame en curso todo lo que la ULA habría emitido
+    /// hasta el T-state absoluto `t`.
+    ///
+    /// `border_writes` son los cambios de borde del frame `(t, color)`,
+    /// en orden, tal como los registra `ZxBus::out_port`.
+    pub fn render_until<M: ScreenMemory + ?Sized>(&mut self, mem: &M, border_writes: &[(u64, u8)], t: u64) {
+        let t_frame = t.saturating_sub(self.frame_start);
+
+        while self.beam < BEAM_TOTAL {
+            let beam_t = self.beam_tstate(self.beam);
+            if beam_t >= t_frame {
+                break;
+            }
+
+            // Cambios de borde ocurridos antes de este punto del haz
+            while let Some(&(wt, color)) = border_writes.get(self.border_idx) {
+                if wt.saturating_sub(self.frame_start) > beam_t {
+                    break;
+                }
+                self.border = color;
+                self.border_idx += 1;
+            }
+
+            self.draw_beam(mem);
+            self.beam += 1;
+        }
+    }
+
+    /// Termina el frame en curso (INT en el T-state absoluto `t`):
+    /// completa lo que falte, lo publica en `framebuffer` y empieza otro.
+    pub fn end_frame<M: ScreenMemory + ?Sized>(&mut self, mem: &M, border_writes: &[(u64, u8)], t: u64) {
+        self.render_until(mem, border_writes, t);
+
+        // Si el frame se cortó antes de tiempo (carga, reset...) el resto
+        // se completa con el borde actual
+        while self.beam < BEAM_TOTAL {
+            self.draw_beam(mem);
+            self.beam += 1;
+        }
+
+        std::mem::swap(&mut self.framebuffer, &mut self.back);
+        self.start_frame(t);
+    }
+
+    /// Dibuja la pareja de píxeles de la posición actual del haz
+    fn draw_beam<M: ScreenMemory + ?Sized>(&mut self, mem: &M) {
+        let y = self.beam / BEAM_W;
+        let x = (self.beam % BEAM_W) * 2;
+        let idx = y * FB_W + x;
+
+        let in_paper = (PAPER_Y..PAPER_Y + 192).contains(&y)
+            && (PAPER_X..PAPER_X + 256).contains(&x);
+
+        if !in_paper {
+            // El borde nunca tiene BRIGHT
+            self.back[idx] = self.border;
+            self.back[idx + 1] = self.border;
+            return;
+        }
+
+        let sy = y - PAPER_Y;
+        let sx = x - PAPER_X;
+        let x_byte = sx / 8;
+
+        // La ULA lee píxeles y atributo al principio de cada celda de 8 píxeles
+        if sx.is_multiple_of(8) {
+            self.latch_pixels = mem.screen_byte(zx_screen_addr(x_byte, sy));
+            self.latch_attr = mem.screen_byte(zx_attr_addr(x_byte, sy));
+        }
+
+        let (ink, paper) = attr_colors(self.latch_attr, self.flash_phase);
+        for i in 0..2 {
+            let bit = (sx % 8) + i;
+            let pixel_on = (self.latch_pixels & (0x80 >> bit)) != 0;
+            self.back[idx + i] = if pixel_on { ink } else { paper };
+        }
+    }
+
+    /// Actualiza el framebuffer de golpe: borde con el color `border` (0-7)
+    /// y pantalla combinando píxeles y atributos (sin temporización)
+    pub fn update_from_bus<M: ScreenMemory + ?Sized>(&mut self, mem: &M, border: u8) {
+        // 0. Borde (el borde nunca tiene BRIGHT)
+        self.framebuffer.fill(border & 0x07);
+
         for y in 0..192 {
             for x_byte in 0..32 {
                 // 1. Leer el byte de píxeles (8 píxeles horizontales)
                 let pixel_addr = zx_screen_addr(x_byte, y);
-                let pixel_byte = bus.read_byte(pixel_addr);
+                let pixel_byte = mem.screen_byte(pixel_addr);
 
                 // 2. Leer el byte de atributo correspondiente a esta celda de 8x8
-                // La dirección de atributo es 0x5800 + (y/8 * 32) + x_byte
-                let attr_addr = 0x5800 + ((y / 8) * 32 + x_byte) as u16;
-                let attr = bus.read_byte(attr_addr);
-
-                // 3. Extraer componentes del atributo
-                let ink = attr & 0x07;            // Bits 0-2
-                let paper = (attr >> 3) & 0x07;   // Bits 3-5
-                let bright = (attr & 0x40) != 0; // Bit 6
-                let flash = (attr & 0x80) != 0;  // Bit 7
-
-                // 4. Aplicar brillo (colores 8-15)
-                let mut ink_color = ink;
-                let mut paper_color = paper;
-                if bright {
-                    ink_color += 8;
-                    paper_color += 8;
-                }
+                let attr = mem.screen_byte(zx_attr_addr(x_byte, y));
 
-                // 5. Lógica de FLASH (invertir si toca)
-                if flash && self.flash_phase {
-                    std::mem::swap(&mut ink_color, &mut paper_color);
-                }
+                // 3. Colores de tinta y papel (BRIGHT y FLASH incluidos)
+                let (ink_color, paper_color) = attr_colors(attr, self.flash_phase);
 
-                // 6. Dibujar los 8 píxeles en el framebuffer
+                // 4. Dibujar los 8 píxeles en el framebuffer
                 for bit in 0..8 {
                     let pixel_on = (pixel_byte & (0x80 >> bit)) != 0;
                     let final_color = if pixel_on { ink_color } else { paper_color };
 
-                    let pixel_x = x_byte * 8 + bit;
-                    self.framebuffer[y * 256 + pixel_x] = final_color;
+                    let pixel_x = PAPER_X + x_byte * 8 + bit;
+                    self.framebuffer[(PAPER_Y + y) * FB_W + pixel_x] = final_color;
                 }
             }
         }
@@ -81,12 +241,43 @@ impl Video {
     }
 }
 
+/// Colores (tinta, papel) de un atributo, con BRIGHT (8-15) y FLASH aplicados
+fn attr_colors(attr: u8, flash_phase: bool) -> (u8, u8) {
+    // Extraer componentes del atributo
+    let ink = attr & 0x07;            // Bits 0-2
+    let paper = (attr >> 3) & 0x07;   // Bits 3-5
+    let bright = (attr & 0x40) != 0; // Bit 6
+    let flash = (attr & 0x80) != 0;  // Bit 7
+
+    // Aplicar brillo (colores 8-15)
+    let mut ink_color = ink;
+    let mut paper_color = paper;
+    if bright {
+        ink_color += 8;
+        paper_color += 8;
+    }
+
+    // Lógica de FLASH (invertir si toca)
+    if flash && flash_phase {
+        std::mem::swap(&mut ink_color, &mut paper_color);
+    }
+
+    (ink_color, paper_color)
+}
+
+// Las direcciones son relativas al inicio de la pantalla (0x4000 en el 48K)
+
+/// Dirección de atributo: 0x1800 + (y/8 * 32) + x_byte
+pub fn zx_attr_addr(x_byte: usize, y: usize) -> u16 {
+    0x1800 + ((y / 8) * 32 + x_byte) as u16
+}
+
 /// Direccionamiento entrelazado del Spectrum
-fn zx_screen_addr(x_byte: usize, y: usize) -> u16 {
+pub fn zx_screen_addr(x_byte: usize, y: usize) -> u16 {
     let y = y as u16;
     let x = x_byte as u16;
 
-    // Formato dirección: 010 (banda) (row) (block) (x)
+    // Formato dirección: (banda) (row) (block) (x)
     // banda: bits 7,6 de y
     // row: bits 2,1,0 de y
     // block: bits 5,4,3 de y
@@ -94,5 +285,5 @@ fn zx_screen_addr(x_byte: usize, y: usize) -> u16 {
     let row = (y & 0b0000_0111) << 8;
     let block = (y & 0b0011_1000) << 2;
 
-    0x4000 | band | row | block | x
+    band | row | block | x
 }
@@ -0,0 +1,490 @@
+use zilog_z80::cpu::CPU;
+use crate::contencion::PreRegs;
+
+/* ==================================================
+ * WATCHPOINTS
+ * ==================================================
+ * Paran la ejecución cuando una instrucción lee o escribe en
+ * un rango de memoria o de puertos de E/S.
+ *
+ * El core zilog_z80 accede a su memoria sin pasar por nosotros,
+ * así que las lecturas y escrituras de datos se deducen de la
+ * instrucción decodificada con los registros de ANTES de
+ * ejecutarla (como la contención). Los valores anteriores se
+ * guardan antes de ejecutar y los nuevos se leen después.
+ * Los puertos sí pasan por ZxBus::in_port / ZxBus::out_port.
+ *
+ * Las lecturas de opcodes y operandos no cuentan: solo datos.
+ */
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum MemAccess {
+    Read(u16),
+    Write(u16),
+}
+
+pub struct Watchpoint {
+    /// Rango (incluido) tras aplicar la máscara
+    pub start: u16,
+    pub end: u16,
+    /// Máscara de la dirección (puertos con decodificación parcial)
+    pub mask: u16,
+    /// Puertos de E/S en lugar de memoria
+    pub io: bool,
+    pub read: bool,
+    pub write: bool,
+    pub enabled: bool,
+    pub hits: u32,
+    /// Último valor visto en el puerto (E/S)
+    last: Option<u8>,
+}
+
+impl Watchpoint {
+    fn matches(&self, addr: u16, io: bool, write: bool) -> bool {
+        let a = addr & self.mask;
+        self.enabled
+            && self.io == io
+            && (if write { self.write } else { self.read })
+            && (self.start..=self.end).contains(&a)
+    }
+
+    /// Rango, máscara y tipo tal como se escriben en el comando
+    fn spec(&self) -> String {
+        let mut s = format!("{:04X}", self.start);
+        if self.end != self.start {
+            s.push_str(&format!("-{:04X}", self.end));
+        }
+        if self.mask != 0xFFFF {
+            s.push_str(&format!("/{:04X}", self.mask));
+        }
+        let kind = match (self.read, self.write) {
+            (true, true) => "rw",
+            (true, false) => "r",
+            _ => "w",
+        };
+        format!("{} {}", s, kind)
+    }
+
+    /// Línea de la lista: "1 * MEM 4000-57FF w (3)"
+    pub fn describe(&self, n: usize) -> String {
+        format!(
+            "{} {} {} {} ({})",
+            n,
+            if self.enabled { '*' } else { ' ' },
+            if self.io { "IO" } else { "MEM" },
+            self.spec(),
+            self.hits
+        )
+    }
+}
+
+/// Acceso que ha disparado un watchpoint
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WatchHit {
+    /// Número del watchpoint (desde 1)
+    pub n: usize,
+    /// Dirección de la instrucción
+    pub pc: u16,
+    pub addr: u16,
+    pub io: bool,
+    pub write: bool,
+    pub old: u8,
+    pub new: u8,
+}
+
+impl WatchHit {
+    pub fn describe(&self) -> String {
+        let op = match (self.io, self.write) {
+            (true, true) => "OUT",
+            (true, false) => "IN",
+            (false, true) => "escribe",
+            (false, false) => "lee",
+        };
+        format!(
+            "Watchpoint {}: PC={:04X} {} ({:04X}) {:02X} -> {:02X}",
+            self.n, self.pc, op, self.addr, self.old, self.new
+        )
+    }
+}
+
+#[derive(Default)]
+pub struct Watchpoints {
+    list: Vec<Watchpoint>,
+    /// Instrucción en curso (para los accesos a puertos)
+    pc: u16,
+    hit: Option<WatchHit>,
+}
+
+impl Watchpoints {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn list(&self) -> &[Watchpoint] {
+        &self.list
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.list.is_empty()
+    }
+
+    fn has_mem(&self) -> bool {
+        self.list.iter().any(|w| w.enabled && !w.io)
+    }
+
+    /// Añade un watchpoint; devuelve su número (desde 1)
+    pub fn add(&mut self, start: u16, end: u16, mask: u16, io: bool, read: bool, write: bool) -> usize {
+        self.list.push(Watchpoint {
+            start: start.min(end),
+            end: start.max(end),
+            mask,
+            io,
+            read,
+            write,
+            enabled: true,
+            hits: 0,
+            last: None,
+        });
+        self.list.len()
+    }
+
+    /// El último acceso que ha disparado (se consume)
+    pub fn take_hit(&mut self) -> Option<WatchHit> {
+        self.hit.take()
+    }
+
+    /// Registra un acceso; el primero que dispara en la instrucción se queda
+    fn access(&mut self, addr: u16, io: bool, write: bool, old: u8, new: u8) {
+        let pc = self.pc;
+
+        if let Some((i, w)) = self
+            .list
+            .iter_mut()
+            .enumerate()
+            .find(|(_, w)| w.matches(addr, io, write))
+        {
+            w.hits += 1;
+            let old = if io { w.last.unwrap_or(new) } else { old };
+            w.last = Some(new);
+
+            if self.hit.is_none() {
+                self.hit = Some(WatchHit { n: i + 1, pc, addr, io, write, old, new });
+            }
+        }
+    }
+
+    /* ==================================================
+     * OBSERVACIÓN (llamado desde cpu_exec::step y ZxBus)
+     * ================================================== */
+
+    /// Antes de ejecutar: apunta el PC y guarda los valores que la
+    /// instrucción puede sobrescribir (solo si hay watchpoints de memoria)
+    pub fn before(&mut self, cpu: &CPU, r: &PreRegs, bytes: &[u8; 4]) -> Vec<(u16, u8)> {
+        self.pc = r.pc;
+
+        if !self.has_mem() {
+            return Vec::new();
+        }
+
+        data_accesses(r, bytes, None)
+            .into_iter()
+            .filter_map(|a| match a {
+                MemAccess::Write(addr) => Some((addr, cpu.bus.read_byte(addr))),
+                MemAccess::Read(_) => None,
+            })
+            .collect()
+    }
+
+    /// Después de ejecutar (`cycles` del core, sin contención)
+    pub fn after(&mut self, cpu: &CPU, r: &PreRegs, bytes: &[u8; 4], cycles: u32, old: &[(u16, u8)]) {
+        if !self.has_mem() {
+            return;
+        }
+
+        for a in data_accesses(r, bytes, Some(cycles)) {
+            match a {
+                MemAccess::Read(addr) => {
+                    let v = cpu.bus.read_byte(addr);
+                    self.access(addr, false, false, v, v);
+                }
+                MemAccess::Write(addr) => {
+                    let new = cpu.bus.read_byte(addr);
+                    let old = old.iter().find(|(a, _)| *a == addr).map_or(new, |&(_, v)| v);
+                    self.access(addr, false, true, old, new);
+                }
+            }
+        }
+    }
+
+    /// Lectura (IN) o escritura (OUT) de un puerto
+    pub fn on_io(&mut self, port: u16, value: u8, write: bool) {
+        if !self.list.is_empty() {
+            self.access(port, true, write, value, value);
+        }
+    }
+
+    /* ==================================================
+     * COMANDOS
+     * ==================================================
+     *   wp <ini>[-<fin>][/<máscara>] [r|w|rw]   memoria (por defecto w)
+     *   wio <ini>[-<fin>][/<máscara>] [r|w|rw]  puertos (por defecto rw)
+     *   wd <n> / we <n>                         desactivar / activar
+     *   wc <n> | wc *                           borrar uno / todos
+     *   wl                                      listar
+     * Ejemplo: "wio FE/FF w" para las escrituras a la ULA.
+     */
+
+    /// Ejecuta un comando; devuelve el texto a mostrar
+    pub fn command(&mut self, line: &str) -> Result<String, String> {
+        let line = line.trim();
+        let (cmd, rest) = line.split_once(' ').unwrap_or((line, ""));
+        let mut args = rest.split_whitespace();
+
+        match cmd.to_ascii_lowercase().as_str() {
+            "wp" | "wio" => {
+                let io = cmd.eq_ignore_ascii_case("wio");
+                let range = args.next().ok_or("Falta la dirección")?;
+                let (start, end, mask) = parse_range(range)?;
+
+                let (read, write) = match args.next().map(str::to_ascii_lowercase).as_deref() {
+                    None if io => (true, true),
+                    None | Some("w") => (false, true),
+                    Some("r") => (true, false),
+                    Some("rw") => (true, true),
+                    Some(k) => return Err(format!("Tipo no válido: {} (r, w o rw)", k)),
+                };
+
+                let n = self.add(start, end, mask, io, read, write);
+                Ok(self.list[n - 1].describe(n))
+            }
+            "wd" | "we" => {
+                let n = self.index(rest.trim())?;
+                self.list[n].enabled = cmd.eq_ignore_ascii_case("we");
+                Ok(self.list[n].describe(n + 1))
+            }
+            "wc" if rest.trim() == "*" => {
+                self.list.clear();
+                Ok("Watchpoints borrados".into())
+            }
+            "wc" => {
+                let n = self.index(rest.trim())?;
+                self.list.remove(n);
+                Ok("Watchpoint borrado".into())
+            }
+            "wl" if self.list.is_empty() => Ok("Sin watchpoints".into()),
+            "wl" => Ok(self
+                .list
+                .iter()
+                .enumerate()
+                .map(|(i, w)| w.describe(i + 1))
+                .collect::<Vec<_>>()
+                .join("\n")),
+            _ => Err(format!("Comando desconocido: {}", cmd)),
+        }
+    }
+
+    fn index(&self, s: &str) -> Result<usize, String> {
+        match s.parse::<usize>() {
+            Ok(n) if n >= 1 && n <= self.list.len() => Ok(n - 1),
+            _ => Err(format!("No existe el watchpoint {}", s)),
+        }
+    }
+
+    /// Una línea por watchpoint con el comando que lo crea
+    /// ("#" delante si está desactivado)
+    pub fn to_text(&self) -> String {
+        self.list
+            .iter()
+            .map(|w| {
+                format!(
+                    "{}{} {}\n",
+                    if w.enabled { "" } else { "#" },
+                    if w.io { "wio" } else { "wp" },
+                    w.spec()
+                )
+            })
+            .collect()
+    }
+
+    /// Carga una línea de `to_text`
+    pub fn load_line(&mut self, line: &str) -> Result<(), String> {
+        let (enabled, cmd) = match line.strip_prefix('#') {
+            Some(cmd) => (false, cmd),
+            None => (true, line),
+        };
+        self.command(cmd)?;
+        if let Some(w) = self.list.last_mut() {
+            w.enabled = enabled;
+        }
+        Ok(())
+    }
+}
+
+/// "4000", "4000-57FF", "FE/FF" (direcciones en hex)
+fn parse_range(s: &str) -> Result<(u16, u16, u16), String> {
+    let bad = || format!("Rango no válido: {}", s);
+    let hex = crate::breakpoints::parse_address;
+
+    let (range, mask) = match s.split_once('/') {
+        Some((r, m)) => (r, hex(m).ok_or_else(bad)?),
+        None => (s, 0xFFFF),
+    };
+    let (start, end) = match range.split_once('-') {
+        Some((a, b)) => (hex(a).ok_or_else(bad)?, hex(b).ok_or_else(bad)?),
+        None => {
+            let a = hex(range).ok_or_else(bad)?;
+            (a, a)
+        }
+    };
+
+    Ok((start & mask, end & mask, mask))
+}
+
+/* ==================================================
+ * ACCESOS A DATOS POR INSTRUCCIÓN
+ * ==================================================
+ * `cycles` son los T-states del core: distinguen RET cc y
+ * CALL cc tomados. Con `None` se suponen tomados (para
+ * guardar los valores de antes de ejecutar).
+ */
+
+pub fn data_accesses(r: &PreRegs, bytes: &[u8; 4], cycles: Option<u32>) -> Vec<MemAccess> {
+    let mut v = Vec::with_capacity(4);
+
+    match bytes[0] {
+        0xCB => cb_data(r.hl, bytes[1], &mut v),
+        0xED => ed_data(r, bytes, &mut v),
+        0xDD => indexed_data(r, r.ix, bytes, cycles, &mut v),
+        0xFD => indexed_data(r, r.iy, bytes, cycles, &mut v),
+        op => main_data(r, r.hl, op, [bytes[1], bytes[2]], cycles, &mut v),
+    }
+
+    v
+}
+
+/// Instrucciones sin prefijo; `hl` es HL (o IX/IY con prefijo)
+fn main_data(r: &PreRegs, hl: u16, op: u8, n: [u8; 2], cycles: Option<u32>, v: &mut Vec<MemAccess>) {
+    use MemAccess::{Read, Write};
+
+    let nn = u16::from_le_bytes(n);
+    let sp = r.sp;
+    let taken = |t: u32| cycles.is_none_or(|c| c == t);
+    let pop = [Read(sp), Read(sp.wrapping_add(1))];
+    let push = [Write(sp.wrapping_sub(1)), Write(sp.wrapping_sub(2))];
+    let (x, y, z) = (op >> 6, (op >> 3) & 7, op & 7);
+
+    match (x, z) {
+        (1, _) if op == 0x76 => {}
+        // LD (HL),r
+        (1, _) if y == 6 => v.push(Write(hl)),
+        // LD r,(HL)
+        (1, 6) => v.push(Read(hl)),
+        // ALU A,(HL)
+        (2, 6) => v.push(Read(hl)),
+
+        (0, 2) => match y {
+            0 => v.push(Write(r.bc)),
+            1 => v.push(Read(r.bc)),
+            2 => v.push(Write(r.de)),
+            3 => v.push(Read(r.de)),
+            4 => v.extend([Write(nn), Write(nn.wrapping_add(1))]),
+            5 => v.extend([Read(nn), Read(nn.wrapping_add(1))]),
+            6 => v.push(Write(nn)),
+            _ => v.push(Read(nn)),
+        },
+        // INC (HL) / DEC (HL)
+        (0, 4) | (0, 5) if y == 6 => v.extend([Read(hl), Write(hl)]),
+        // LD (HL),n
+        (0, 6) if y == 6 => v.push(Write(hl)),
+
+        // RET cc
+        (3, 0) if taken(11) => v.extend(pop),
+        // POP rr / RET
+        (3, 1) if matches!(y, 0 | 1 | 2 | 4 | 6) => v.extend(pop),
+        // EX (SP),HL
+        (3, 3) if y == 4 => v.extend([
+            Read(sp),
+            Read(sp.wrapping_add(1)),
+            Write(sp.wrapping_add(1)),
+            Write(sp),
+        ]),
+        // CALL cc,nn
+        (3, 4) if taken(17) => v.extend(push),
+        // PUSH rr / CALL nn
+        (3, 5) if y & 1 == 0 || y == 1 => v.extend(push),
+        // RST p
+        (3, 7) => v.extend(push),
+
+        _ => {}
+    }
+}
+
+/// CB xx sobre (HL): BIT solo lee
+fn cb_data(addr: u16, op: u8, v: &mut Vec<MemAccess>) {
+    if op & 0x07 == 6 {
+        v.push(MemAccess::Read(addr));
+        if op >> 6 != 1 {
+            v.push(MemAccess::Write(addr));
+        }
+    }
+}
+
+fn ed_data(r: &PreRegs, b: &[u8; 4], v: &mut Vec<MemAccess>) {
+    use MemAccess::{Read, Write};
+
+    let op = b[1];
+    let hl = r.hl;
+
+    match op {
+        // LD (nn),rr / LD rr,(nn)
+        _ if op & 0xC7 == 0x43 => {
+            let nn = u16::from_le_bytes([b[2], b[3]]);
+            if op & 0x08 == 0 {
+                v.extend([Write(nn), Write(nn.wrapping_add(1))]);
+            } else {
+                v.extend([Read(nn), Read(nn.wrapping_add(1))]);
+            }
+        }
+        // RETN / RETI
+        _ if op & 0xC7 == 0x45 => v.extend([Read(r.sp), Read(r.sp.wrapping_add(1))]),
+        // RRD / RLD
+        0x67 | 0x6F => v.extend([Read(hl), Write(hl)]),
+        // LDI / LDD / LDIR / LDDR (una pasada por step: puertos::exec_io)
+        0xA0 | 0xA8 | 0xB0 | 0xB8 => v.extend([Read(hl), Write(r.de)]),
+        // CPI / CPD / CPIR / CPDR
+        0xA1 | 0xA9 | 0xB1 | 0xB9 => v.push(Read(hl)),
+        // INI / IND / INIR / INDR
+        0xA2 | 0xAA | 0xB2 | 0xBA => v.push(Write(hl)),
+        // OUTI / OUTD / OTIR / OTDR
+        0xA3 | 0xAB | 0xB3 | 0xBB => v.push(Read(hl)),
+        _ => {}
+    }
+}
+
+/// DD/FD xx: `ii` es IX o IY
+fn indexed_data(r: &PreRegs, ii: u16, b: &[u8; 4], cycles: Option<u32>, v: &mut Vec<MemAccess>) {
+    use MemAccess::{Read, Write};
+
+    let op = b[1];
+    let addr = ii.wrapping_add(b[2] as i8 as u16);
+    let (x, y, z) = (op >> 6, (op >> 3) & 7, op & 7);
+
+    match op {
+        // DD CB d op
+        0xCB => cb_data(addr, b[3], v),
+        // LD (IX+d),n
+        0x36 => v.push(Write(addr)),
+        // INC (IX+d) / DEC (IX+d)
+        0x34 | 0x35 => v.extend([Read(addr), Write(addr)]),
+        0x76 => {}
+        // LD (IX+d),r
+        _ if x == 1 && y == 6 => v.push(Write(addr)),
+        // LD r,(IX+d) / ALU A,(IX+d)
+        _ if (x == 1 || x == 2) && z == 6 => v.push(Read(addr)),
+        // Resto: instrucción normal con IX/IY en lugar de HL
+        _ => {
+            let cycles = cycles.map(|c| c.saturating_sub(4));
+            main_data(r, ii, op, [b[2], b[3]], cycles, v);
+        }
+    }
+}
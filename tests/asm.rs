@@ -0,0 +1,148 @@
+mod common;
+
+use common::temporal;
+use std::path::Path;
+use zx::asm::{self, SaveBin};
+use zx::disasm::disassemble;
+
+// Ensambla una línea en 0x8000
+fn codigo(linea: &str) -> Vec<u8> {
+    let a = asm::assemble(&format!("  ORG $8000\n  {}\n", linea)).unwrap();
+    a.binary().1
+}
+
+// Codificación
+//
+// Prefijos CB/ED/DD/FD, índices con desplazamiento, saltos relativos
+// hacia delante y hacia atrás, directivas y expresiones.
+#[test]
+fn test_codificacion() {
+    assert_eq!(codigo("ld a,(ix-2)"), [0xDD, 0x7E, 0xFE]);
+    assert_eq!(codigo("LD (IY+5),$12"), [0xFD, 0x36, 0x05, 0x12]);
+    assert_eq!(codigo("set 5,(hl)"), [0xCB, 0xEE]);
+    assert_eq!(codigo("RES 1,(IX+3)"), [0xDD, 0xCB, 0x03, 0x8E]);
+    assert_eq!(codigo("LD ($5C3C),DE"), [0xED, 0x53, 0x3C, 0x5C]);
+    assert_eq!(codigo("ld hl,(0x5C3C)"), [0x2A, 0x3C, 0x5C]);
+    assert_eq!(codigo("ex af,af'"), [0x08]);
+    assert_eq!(codigo("OUT ($FE),A"), [0xD3, 0xFE]);
+    assert_eq!(codigo("in e,(c)"), [0xED, 0x58]);
+    assert_eq!(codigo("IM 2"), [0xED, 0x5E]);
+    assert_eq!(codigo("ld ixh,b"), [0xDD, 0x60]);
+    assert_eq!(codigo("rst 38h"), [0xFF]);
+    assert_eq!(codigo("cp 'K'"), [0xFE, b'K']);
+
+    let a = asm::assemble(
+        "\
+PANTALLA EQU $4000
+LARGO = 6912 / 4 * 4
+        ORG $8000
+inicio: ld hl,PANTALLA
+        ld bc,LARGO - 1
+.bucle  djnz .bucle
+        jr nz,fin
+        jp inicio
+tabla:  db \"ok\", 13, %1010, -1
+        dw inicio, $
+        ds 3, $AA
+fin:    ret
+        end inicio",
+    )
+    .unwrap();
+
+    assert_eq!(a.symbols["PANTALLA"], 0x4000);
+    assert_eq!(a.symbols["inicio.bucle"], 0x8006);
+    assert_eq!(a.symbols["fin"], 0x8019);
+    assert_eq!(a.entry, Some(0x8000));
+    assert_eq!(
+        a.binary(),
+        (
+            0x8000,
+            vec![
+                0x21, 0x00, 0x40, 0x01, 0xFF, 0x1A, 0x10, 0xFE, 0x20, 0x0F, 0xC3, 0x00, 0x80,
+                b'o', b'k', 13, 0x0A, 0xFF, 0x00, 0x80, 0x12, 0x80, 0xAA, 0xAA, 0xAA, 0xC9,
+            ]
+        )
+    );
+    assert!(a.saves.is_empty());
+}
+
+// Errores
+//
+// Con el número de línea; los símbolos solo fallan si no aparecen en
+// ninguna de las dos pasadas.
+#[test]
+fn test_errores() {
+    let error = |src: &str| asm::assemble(src).err().unwrap();
+
+    assert_eq!(error(" nop\n lx a,1"), "Línea 2: Instrucción desconocida: LX");
+    assert_eq!(error(" jp nada"), "Línea 1: Símbolo no definido: nada");
+    assert_eq!(error(" ld a,256"), "Línea 1: No cabe en un byte: 256");
+    assert_eq!(error(" ld ixh,h\n"), "Línea 1: Operandos no válidos: LD ixh,h");
+    assert_eq!(error(" ld (ix+200),a"), "Línea 1: Desplazamiento fuera de rango: 200");
+    assert_eq!(error("a: nop\na: nop"), "Línea 2: Etiqueta repetida: a");
+    assert_eq!(error(" jr lejos\n ds 200\nlejos: nop"), "Línea 1: Salto relativo fuera de rango: lejos");
+    assert_eq!(error(" im 3"), "Línea 1: Modo de interrupción no válido: 3");
+    assert_eq!(error(" jr pe,0"), "Línea 1: Condición no válida: PE");
+}
+
+// Ida y vuelta con el desensamblador
+//
+// Cada instrucción desensamblada vuelve a ensamblarse a algo que se
+// desensambla igual (los códigos repetidos del juego ED dan la misma
+// instrucción con otros bytes).
+#[test]
+fn test_ida_y_vuelta() {
+    let mut casos = Vec::new();
+    for op in 0..=255u8 {
+        casos.push([op, 0x05, 0x34, 0x12]);
+        casos.push([0xCB, op, 0x00, 0x00]);
+        casos.push([0xED, op, 0x34, 0x12]);
+        casos.push([0xDD, op, 0x05, 0x12]);
+        casos.push([0xFD, 0xCB, 0xFB, op]);
+    }
+
+    for bytes in casos {
+        let (texto, len) = disassemble(&bytes, 0x8000, 0x8000);
+        // Formas que solo existen en el desensamblador
+        if texto.contains("(HL),(C)") || texto.contains("(C),(HL)") || texto.contains("),(HL)") {
+            continue;
+        }
+
+        let a = asm::assemble(&format!(" ORG $8000\n {}", texto))
+            .unwrap_or_else(|e| panic!("{:02X?} {}: {}", &bytes[..len as usize], texto, e));
+        let (otra, _) = disassemble(a.bytes(0x8000, 4), 0x8000, 0x8000);
+        assert_eq!(otra, texto, "{:02X?}", &bytes[..len as usize]);
+    }
+}
+
+// Programas de tests/z80
+//
+// Todos se ensamblan; start.asm con el SAVEBIN del fuente y los
+// ficheros que escribe.
+#[test]
+fn test_programas() {
+    for entrada in std::fs::read_dir("tests/z80").unwrap() {
+        let path = entrada.unwrap().path();
+        if path.extension().is_some_and(|e| e == "asm") {
+            asm::assemble_file(&path).unwrap();
+        }
+    }
+
+    let a = asm::assemble_file(Path::new("tests/z80/start.asm")).unwrap();
+    assert_eq!(a.saves, [SaveBin { name: "start.bin".into(), start: 0, len: 16 }]);
+    assert_eq!(
+        a.binary().1,
+        [0x3E, 0x01, 0x06, 0x02, 0x80, 0xA7, 0xA8, 0x21, 0x00, 0x10, 0x77, 0x23, 0x2B, 0x18, 0xF1, 0x76]
+    );
+
+    let dir = temporal("asm");
+    std::fs::create_dir_all(&dir).unwrap();
+    a.write_files(&dir).unwrap();
+    assert_eq!(std::fs::read(dir.join("start.bin")).unwrap(), a.binary().1);
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    // DISP: se escribe en 0 pero las etiquetas van a $8000
+    let tiny = asm::assemble_file(Path::new("tests/z80/cursor_tiny.asm")).unwrap();
+    assert_eq!(tiny.binary().0, 0);
+    assert_eq!(tiny.entry, Some(0x8000));
+}
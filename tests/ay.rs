@@ -0,0 +1,118 @@
+mod common;
+
+use common::cargar_programa;
+use zx::ay::Ay;
+use zx::machine::model::MachineType;
+use zx::machine::zx_machine::ZxMachine;
+
+// AY con el canal A a `periodo` y volumen máximo, solo tono
+fn ay_tono_a(periodo: u16) -> Ay {
+    let mut ay = Ay::new(44100);
+    for (reg, valor) in [(0, periodo as u8), (1, (periodo >> 8) as u8), (7, 0x3E), (8, 0x0F)] {
+        ay.select(reg);
+        ay.write(0, valor);
+    }
+    ay
+}
+
+fn cruces_por_cero(samples: &[i16]) -> usize {
+    samples
+        .windows(2)
+        .filter(|w| (w[0] < 0) != (w[1] < 0))
+        .count()
+}
+
+// Puertos 0xFFFD / 0xBFFD
+//
+// Selección, escritura y lectura del registro, con los bits sobrantes a 0.
+#[test]
+fn test_puertos_ay() {
+    let mut m = ZxMachine::builder()
+        .machine(MachineType::Spectrum128K)
+        .build()
+        .unwrap();
+    // LD BC,0xFFFD / LD A,1 / OUT (C),A / LD B,0xBF / LD A,0xFF / OUT (C),A
+    // LD B,0xFF / IN E,(C)
+    cargar_programa(&mut m, &[
+        0x01, 0xFD, 0xFF, 0x3E, 0x01, 0xED, 0x79, 0x06, 0xBF, 0x3E, 0xFF, 0xED, 0x79,
+        0x06, 0xFF, 0xED, 0x58,
+    ]);
+
+    for _ in 0..8 {
+        m.step_once();
+    }
+
+    let ay = m.bus.ay.as_ref().unwrap();
+    assert_eq!(ay.selected_register(), 1);
+    assert_eq!(ay.register(1), 0x0F); // tono grueso: 4 bits
+    assert_eq!(m.cpu.reg.e, 0x0F);
+}
+
+// 48K sin interfaz AY
+//
+// 0xFFFD no responde (bus a 0xFF); con `.ay(true)` sí.
+#[test]
+fn test_48k_sin_ay() {
+    let programa = [0x01, 0xFD, 0xFF, 0xED, 0x58]; // LD BC,0xFFFD / IN E,(C)
+
+    let mut m = ZxMachine::builder().build().unwrap();
+    assert!(m.bus.ay.is_none());
+    cargar_programa(&mut m, &programa);
+    m.step_once();
+    m.step_once();
+    assert_eq!(m.cpu.reg.e, 0xFF);
+
+    let mut m = ZxMachine::builder().ay(true).build().unwrap();
+    cargar_programa(&mut m, &programa);
+    m.step_once();
+    m.step_once();
+    assert_eq!(m.cpu.reg.e, 0x00);
+}
+
+// Tono de ~1 kHz
+//
+// 1,75 MHz / 16 / 109 = 1003 Hz: ~1000 cruces por cero en medio segundo.
+#[test]
+fn test_tono_1khz() {
+    let mut ay = ay_tono_a(109);
+    for f in 1..=25 {
+        ay.end_frame(f * 69888);
+    }
+
+    let cruces = cruces_por_cero(&ay.samples);
+    assert!((980..=1020).contains(&cruces), "cruces = {}", cruces);
+    assert!(ay.samples.iter().any(|&s| s > 1000));
+}
+
+// Canal silenciado
+//
+// Con el canal A muteado la salida es cero.
+#[test]
+fn test_canal_silenciado() {
+    let mut ay = ay_tono_a(109);
+    ay.toggle_mute(0);
+    ay.end_frame(69888);
+
+    assert!(!ay.samples.is_empty());
+    assert!(ay.samples.iter().all(|&s| s == 0));
+}
+
+// Mezcla con el beeper
+//
+// En un frame del 128K salen las mismas muestras que solo con el beeper.
+#[test]
+fn test_mezcla_con_beeper() {
+    let mut m = ZxMachine::builder()
+        .machine(MachineType::Spectrum128K)
+        .build()
+        .unwrap();
+    // JR $ (bucle infinito)
+    cargar_programa(&mut m, &[0x18, 0xFE]);
+    m.debugger.run();
+    m.run_frame();
+
+    let audio = m.bus.take_audio();
+    // 70908 T a 3,5469 MHz y 44,1 kHz: ~881,6 muestras
+    assert!((880..=883).contains(&audio.len()), "muestras = {}", audio.len());
+    assert!(m.bus.ay.as_ref().unwrap().samples.len() <= 1);
+}
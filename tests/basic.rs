@@ -0,0 +1,126 @@
+use zx::basic::keyword_chords;
+use zx::cli::CliOptions;
+use zx::constantes::{AUTOTYPE_WAIT_FRAMES, SYSVAR_FLAGS};
+use zx::machine::model::MachineType;
+use zx::machine::zx_machine::ZxMachine;
+
+// Nombre de cada tecla de la matriz (fila, bit)
+const TECLAS: [[&str; 5]; 8] = [
+    ["CS", "Z", "X", "C", "V"],
+    ["A", "S", "D", "F", "G"],
+    ["Q", "W", "E", "R", "T"],
+    ["1", "2", "3", "4", "5"],
+    ["0", "9", "8", "7", "6"],
+    ["P", "O", "I", "U", "Y"],
+    ["EN", "L", "K", "J", "H"],
+    ["SP", "SS", "M", "N", "B"],
+];
+
+// Teclas del listado, cada combinación como "SS+P"
+fn teclas(texto: &str) -> Vec<String> {
+    keyword_chords(texto)
+        .iter()
+        .map(|c| c.iter().map(|&(fila, bit)| TECLAS[fila][bit as usize]).collect::<Vec<_>>().join("+"))
+        .collect()
+}
+
+// Máquina sin ROM en un JR $
+fn maquina(modelo: MachineType) -> ZxMachine {
+    let mut m = ZxMachine::builder().machine(modelo).build().unwrap();
+    m.cpu.bus.write_byte(0x8000, 0x18);
+    m.cpu.bus.write_byte(0x8001, 0xFE);
+    m.cpu.reg.pc = 0x8000;
+    m
+}
+
+fn frame(m: &mut ZxMachine) {
+    let n = m.frames;
+    while m.frames == n {
+        m.step_once();
+    }
+}
+
+// Modo K
+//
+// Número de línea, orden con su letra, THEN y ':' vuelven al modo K; las
+// cadenas se escriben tal cual y los espacios junto a palabras clave sobran.
+#[test]
+fn test_modo_k() {
+    assert_eq!(teclas("10 PRINT \"Hola\"\n"), ["1", "0", "P", "SS+P", "CS+H", "O", "L", "A", "SS+P", "EN"]);
+    assert_eq!(
+        teclas("20 IF a<>1 THEN GO TO 10: BEEP 1,2"),
+        ["2", "0", "U", "A", "SS+W", "1", "SS+G", "G", "1", "0", "SS+Z", "CS+SS", "SS+Z", "1", "SS+N", "2"]
+    );
+    assert_eq!(teclas("50 GOTO 10"), ["5", "0", "G", "1", "0"]);
+    assert_eq!(teclas("run\r\n"), ["R", "EN"]);
+}
+
+// Modo L y E
+//
+// Funciones en modo E, operadores con SYMBOL y palabras que solo lo son
+// sueltas ("total" no lleva un TO dentro).
+#[test]
+fn test_modo_l_e() {
+    assert_eq!(
+        teclas("30 LET total=INT (RND*10) AND a$"),
+        [
+            "3", "0", "L", "T", "O", "T", "A", "L", "SS+L", "CS+SS", "R", "SS+8", "CS+SS", "T", "SS+B",
+            "1", "0", "SS+9", "SS+Y", "A", "SS+4",
+        ]
+    );
+    assert_eq!(
+        teclas("60 OPEN #4: CLOSE#4"),
+        ["6", "0", "CS+SS", "SS+4", "4", "SS+Z", "CS+SS", "SS+5", "4"]
+    );
+    assert_eq!(teclas("70 PRINT \"TO\";x"), ["7", "0", "P", "SS+P", "CS+T", "CS+O", "SS+P", "SS+O", "X"]);
+}
+
+// REM
+//
+// El resto de la línea es texto, con ':' y palabras clave incluidos.
+#[test]
+fn test_rem() {
+    assert_eq!(
+        teclas("40 REM print: ok\n41"),
+        ["4", "0", "E", "P", "R", "I", "N", "T", "SS+Z", "SP", "O", "K", "EN", "4", "1"]
+    );
+}
+
+// Autotecleo en la máquina
+//
+// Cada tecla espera a que la ROM recoja la anterior (FLAGS, bit 5) o a que
+// pase AUTOTYPE_WAIT_FRAMES; en el editor del 128 el texto va letra a letra.
+#[test]
+fn test_autotecleo() {
+    let mut m = maquina(MachineType::Spectrum48K);
+    m.cpu.bus.write_byte(SYSVAR_FLAGS, 0x20);
+    m.debug_command("type PRINT\\n").unwrap();
+
+    for _ in 0..10 {
+        frame(&mut m);
+        assert_eq!(m.bus.keyboard.rows[5], 0x1F);
+    }
+    // La ROM recoge la tecla: P (PRINT en modo K)
+    m.cpu.bus.write_byte(SYSVAR_FLAGS, 0x00);
+    frame(&mut m);
+    assert_eq!(m.bus.keyboard.rows[5], 0x1E);
+    assert_eq!(m.bus.keyboard.rows[0], 0x1F);
+
+    // Sin nadie que la recoja, ENTER sale a los AUTOTYPE_WAIT_FRAMES
+    m.cpu.bus.write_byte(SYSVAR_FLAGS, 0x20);
+    let mut n = 0;
+    while m.bus.keyboard.rows[6] == 0x1F {
+        frame(&mut m);
+        n += 1;
+    }
+    assert_eq!(n, 2 + AUTOTYPE_WAIT_FRAMES + 1);
+
+    // 128K con la ROM 0: "PRINT" son cinco letras
+    let mut m = maquina(MachineType::Spectrum128K);
+    m.autotype("PRINT");
+    frame(&mut m);
+    assert_eq!((m.bus.keyboard.rows[0], m.bus.keyboard.rows[5]), (0x1E, 0x1E));
+
+    let o = CliOptions::parse(["--type", "LOAD \"\"\\n"]).unwrap();
+    assert_eq!(o.type_text.as_deref(), Some("LOAD \"\"\n"));
+}
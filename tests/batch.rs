@@ -0,0 +1,271 @@
+mod common;
+
+use common::temporal;
+use std::path::Path;
+use std::process::Command;
+use zx::asm;
+use zx::batch::{self, BatchOptions, Expect, Stop, Until};
+use zx::machine::zx_machine::ZxMachine;
+
+// Programa de tests/z80 ensamblado: dirección y bytes
+fn programa(nombre: &str) -> (u16, Vec<u8>) {
+    asm::assemble_file(Path::new("tests/z80").join(nombre).as_path()).unwrap().binary()
+}
+
+// Máquina sin ROM con el programa cargado
+fn maquina(nombre: &str) -> ZxMachine {
+    let (org, bytes) = programa(nombre);
+    let mut m = ZxMachine::builder().build().unwrap();
+    m.load_bytes(&bytes, org);
+    m
+}
+
+// start.asm
+//
+// Hasta el JR del final: A, HL y el byte escrito en 0x1000.
+#[test]
+fn test_start() {
+    let mut m = maquina("start.asm");
+
+    let expects = [
+        Expect::condition("A==1 && B==2 && HL==0x1000").unwrap(),
+        Expect::memory("1000=01").unwrap(),
+    ];
+    let r = batch::run_and_check(&mut m, 1, Some(Until::Pc(0x000D)), &expects);
+
+    assert_eq!(r.stop, Stop::Pc(0x000D));
+    assert!(r.passed(), "{:?}", r.failures);
+}
+
+// video_test.asm
+//
+// Rellena los píxeles de la pantalla: memoria y hash de la pantalla.
+#[test]
+fn test_video() {
+    let mut m = maquina("video_test.asm");
+
+    let expects = [
+        Expect::memory("4000=FF,FF").unwrap(),
+        Expect::memory("57FF=FF,00").unwrap(),
+        Expect::screen("B3F10C3197779725").unwrap(),
+    ];
+    let r = batch::run_and_check(&mut m, 20, Some(Until::Pc(0x000F)), &expects);
+
+    assert!(r.passed(), "{:?}", r.failures);
+    assert_eq!(batch::screen_hash(&m), 0xB3F1_0C31_9777_9725);
+}
+
+// Programas de tests/z80 (los demás)
+//
+// Cada caso son las opciones de zx-batch para un programa y, si hace
+// falta, el PC inicial. Sin ROM: los que llaman a la ROM se comprueban
+// hasta la primera llamada, y al cargar un binario no hay interrupciones.
+#[rustfmt::skip]
+const CASOS: &[(&str, Option<u16>, &[&str])] = &[
+    ("alu.asm", None, &[
+        "--until-halt", "--frames", "1",
+        "--expect", "A==0x7E && F==0x02 && BC==0x0102 && DE==0x0304 && HL==0x0506",
+    ]),
+    // RST 00h (en 0x0038) vuelve al principio
+    ("jumps.asm", None, &[
+        "--until-pc", "0038", "--frames", "1",
+        "--expect", "A==0x0B && B==0 && SP==0x5C00",
+    ]),
+    // El ORG $0008 pisa LD DE,$ABCD y el ORG $0010 el LD BC,$0000; el
+    // SAVEBIN acaba en $0011
+    ("stack.asm", None, &[
+        "--until-pc", "0012", "--frames", "1",
+        "--expect", "SP==0x5BFA && BC==0x00C9 && DE==0xC9CD && HL==0x0F0F",
+        "--expect-mem", "5BFA=0F,0F,CD,C9,34,12",
+    ]),
+    // No hay rutina en 0x0008: RST $08 cae en medio del LD DE
+    ("stack_colors.asm", None, &[
+        "--until-pc", "0008", "--frames", "1",
+        "--expect", "SP==0x7FF8 && HL==0x3333",
+        "--expect-mem", "7FF8=15,00,33,33,22,22,11,11",
+    ]),
+    ("screen_corners.asm", None, &[
+        "--until-halt", "--frames", "1",
+        "--expect-mem", "4000=80", "--expect-mem", "401F=01",
+        "--expect-mem", "57E0=80", "--expect-mem", "57FF=01",
+        "--expect-mem", "5800=42", "--expect-mem", "581F=42",
+        "--expect-mem", "5AE0=42", "--expect-mem", "5AFF=42",
+        "--expect-screen", "E652D34EC6FE1575",
+    ]),
+    // Atributo: la fila (24..1) con FLASH desde la 12
+    ("all_colors_flash.asm", None, &[
+        "--until-halt", "--frames", "10",
+        "--expect", "HL==0x5B00",
+        "--expect-mem", "5800=98,98", "--expect-mem", "5980=8C",
+        "--expect-mem", "59A0=0B", "--expect-mem", "5AFF=01",
+        "--expect-screen", "F1A84767767544A5",
+    ]),
+    // El bucle de atributos deja en A el B|C del contador: solo el
+    // primero es $CE
+    ("flash_test.asm", None, &[
+        "--until-halt", "--frames", "10",
+        "--expect", "HL==0x5B00 && BC==0",
+        "--expect-mem", "4000=FF,FF", "--expect-mem", "57FF=FF",
+        "--expect-mem", "5800=CE,FF,FE,FF", "--expect-mem", "5AFE=02,01",
+        "--expect-screen", "3DCB301229A229EB",
+    ]),
+    ("video_attr_test.asm", None, &[
+        "--until-halt", "--frames", "10",
+        "--expect", "HL==0x5800",
+        "--expect-mem", "4000=AA,AA", "--expect-mem", "57FF=AA",
+        "--expect-mem", "5800=4E,4E", "--expect-mem", "5AFF=4E",
+        "--expect-screen", "6FA5CF37656DEF25",
+    ]),
+    // Sin interrupciones el contador (0x0043) no avanza
+    ("pba00.asm", None, &[
+        "--until-halt", "--frames", "1",
+        "--expect", "PC==0x000E && SP==0xFFFE",
+        "--expect-mem", "0043=00",
+    ]),
+    // SAVEBIN desde 0: 32K de NOPs antes del código
+    ("pba01.asm", None, &[
+        "--until-halt", "--frames", "3",
+        "--expect", "PC==0x8003",
+    ]),
+    // Con la rutina de la INT en 0x0038
+    ("cursor_blink.asm", Some(0x8000), &[
+        "--until-pc", "0DAF", "--frames", "1",
+        "--expect", "SP==0xFFFD && I==0x3F",
+        "--expect-mem", "FFFD=0E,80", "--expect-mem", "0038=F3",
+    ]),
+    ("cursor_blink_peque.asm", Some(0x8000), &[
+        "--until-pc", "0DAF", "--frames", "1",
+        "--expect", "SP==0xFFFD",
+        "--expect-mem", "FFFD=0A,80",
+    ]),
+    ("cursor_mini.asm", None, &[
+        "--until-pc", "0DAF", "--frames", "1",
+        "--expect", "SP==0xFEFE && I==0x3F",
+        "--expect-mem", "FEFE=0E,80",
+    ]),
+    ("cursor_print_at.asm", Some(0xC000), &[
+        "--until-pc", "0DAF", "--frames", "1",
+        "--expect", "SP==0xFEFE && I==0x3F",
+        "--expect-mem", "FEFE=0E,C0",
+    ]),
+    ("cursor_system.asm", Some(0xC000), &[
+        "--until-pc", "0DAF", "--frames", "1",
+        "--expect", "SP==0xFEFE && I==0x3F",
+        "--expect-mem", "FEFE=0E,C0",
+    ]),
+    // DISP: el código queda en 0x0000 con las etiquetas en 0x8000
+    ("cursor_tiny.asm", None, &[
+        "--until-pc", "0DAF", "--frames", "1",
+        "--expect", "SP==0xFEFE && I==0x3F",
+        "--expect-mem", "FEFE=0E,00",
+    ]),
+];
+
+#[test]
+fn test_programas() {
+    for (nombre, pc, args) in CASOS {
+        let o = BatchOptions::parse(std::iter::once(*nombre).chain(args.iter().copied())).unwrap();
+        let mut m = maquina(nombre);
+        if let Some(pc) = pc {
+            m.cpu.reg.pc = *pc;
+        }
+
+        let r = batch::run_and_check(&mut m, o.frames, o.until, &o.expects);
+        assert!(r.passed(), "{}: {:?}", nombre, r.failures);
+    }
+
+    // Todos los programas tienen su caso (o un test propio)
+    let propios = ["start.asm", "video_test.asm", "stack_call_test.asm"];
+    for entrada in std::fs::read_dir("tests/z80").unwrap() {
+        let nombre = entrada.unwrap().file_name().into_string().unwrap();
+        assert!(propios.contains(&nombre.as_str()) || CASOS.iter().any(|c| c.0 == nombre), "{} sin caso", nombre);
+    }
+}
+
+// Fallos
+//
+// Un PC al que no se llega y comprobaciones que no se cumplen.
+#[test]
+fn test_fallos() {
+    let mut m = maquina("start.asm");
+
+    let expects = [
+        Expect::condition("A==2").unwrap(),
+        Expect::memory("1000=01,02").unwrap(),
+    ];
+    let r = batch::run_and_check(&mut m, 2, Some(Until::Halt), &expects);
+
+    assert_eq!(r.stop, Stop::Frames(2));
+    assert_eq!(
+        r.failures,
+        [
+            "No se llegó a HALT en 2 frames",
+            "No se cumple: A==2",
+            "Memoria en 1000: 01,00 (se esperaba 01,02)",
+        ]
+    );
+
+    assert!(Expect::memory("1000=100").is_err());
+    assert!(Expect::screen("xyz").is_err());
+    assert!(BatchOptions::parse(["--frames", "10"]).is_err());
+    assert!(BatchOptions::parse(["a.bin", "--expect", "A=="]).is_err());
+}
+
+// PC en 0xFFFF
+//
+// Un programa que se sale de la memoria para con un fallo en vez de
+// desbordar el core; step_once tampoco avanza.
+#[test]
+fn test_pc_ffff() {
+    let nops = temporal("nops.bin");
+    std::fs::write(&nops, [0u8; 16]).unwrap();
+    let salida = Command::new(env!("CARGO_BIN_EXE_zx-batch"))
+        .args(["--org", "FFF0", "--frames", "1"])
+        .arg(&nops)
+        .output()
+        .unwrap();
+    std::fs::remove_file(&nops).unwrap();
+
+    assert_eq!(salida.status.code(), Some(1));
+    let texto = String::from_utf8_lossy(&salida.stdout);
+    assert!(texto.contains("PcOverflow") && texto.contains("FALLO: PC en FFFF"), "{}", texto);
+
+    let mut m = ZxMachine::builder().build().unwrap();
+    m.cpu.reg.pc = 0xFFFF;
+    m.step_once();
+    assert_eq!(m.cpu.reg.pc, 0xFFFF);
+    assert_eq!(batch::run(&mut m, 1, None), Stop::PcOverflow);
+}
+
+// Binario zx-batch
+//
+// stack_call_test.asm: la pila con las direcciones de retorno; sale
+// con 0 si se cumple todo y con 1 si algo falla.
+#[test]
+fn test_binario() {
+    let (org, bytes) = programa("stack_call_test.asm");
+    assert_eq!(org, 0x8000);
+    let programa = temporal("stack_call_test.bin");
+    std::fs::write(&programa, bytes).unwrap();
+
+    let zx_batch = |extra: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_zx-batch"))
+            .args(["--org", "8000", "--until-pc", "800A", "--expect", "SP==0x9000"])
+            .args(extra)
+            .arg(&programa)
+            .output()
+            .unwrap()
+    };
+
+    let ok = zx_batch(&["--expect-mem", "8FFA=13,80,18,80,0A,80"]);
+    assert!(ok.status.success(), "{}", String::from_utf8_lossy(&ok.stdout));
+
+    let fallo = zx_batch(&["--expect", "PC==0"]);
+    assert_eq!(fallo.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&fallo.stdout).contains("FALLO: No se cumple: PC==0"));
+
+    let error = zx_batch(&["--frames", "x"]);
+    assert_eq!(error.status.code(), Some(2));
+
+    std::fs::remove_file(&programa).unwrap();
+}
@@ -0,0 +1,76 @@
+use zx::beeper::Beeper;
+use zx::machine::zx_machine::ZxMachine;
+
+// Muestras por frame
+//
+// 69888 T-states a 3,5 MHz y 44,1 kHz son ~880,6 muestras.
+#[test]
+fn test_muestras_por_frame() {
+    let mut b = Beeper::new(44100);
+
+    let mut total = 0;
+    for f in 1..=50 {
+        b.end_frame(f * 69888);
+        total += b.take_samples().len();
+    }
+
+    // 50 frames = 3494400 T = 0,9984 s
+    assert!((44025..=44035).contains(&total), "total = {}", total);
+}
+
+// Silencio
+//
+// Sin cambios de nivel la salida es cero.
+#[test]
+fn test_silencio() {
+    let mut b = Beeper::new(48000);
+    b.end_frame(69888);
+
+    assert!(b.samples.iter().all(|&s| s == 0));
+}
+
+// Onda cuadrada de 1 kHz
+//
+// Un flanco cada 1750 T-states: ~1000 cruces por cero (2 por ciclo) en 0,5 s.
+#[test]
+fn test_onda_cuadrada_1khz() {
+    let mut b = Beeper::new(44100);
+    let mut level = false;
+    let mut t = 0u64;
+
+    for f in 1..=25u64 {
+        let frame_end = f * 69888;
+        while t < frame_end {
+            level = !level;
+            b.set_level(t, level);
+            t += 1750;
+        }
+        b.end_frame(frame_end);
+    }
+
+    let s = b.take_samples();
+    let crossings = s.windows(2).filter(|w| (w[0] < 0) != (w[1] < 0)).count();
+
+    assert!((990..=1010).contains(&crossings), "cruces = {}", crossings);
+    assert!(s.iter().any(|&v| v.abs() > 4000));
+}
+
+// Beeper desde un programa
+//
+// OUT (0xFE) con el bit 4 alternando produce sonido.
+#[test]
+fn test_beeper_desde_programa() {
+    let mut m = ZxMachine::builder().sample_rate(48000).build().unwrap();
+    // XOR 0x10 / OUT (0xFE),A / JR -6
+    for (i, b) in [0xEE, 0x10, 0xD3, 0xFE, 0x18, 0xFA].iter().enumerate() {
+        m.cpu.bus.write_byte(i as u16, *b);
+    }
+    m.run_state.iff1 = false;
+
+    m.debugger.run();
+    m.run_frame();
+
+    let s = m.bus.beeper.take_samples();
+    assert!(!s.is_empty());
+    assert!(s.iter().any(|&v| v != 0));
+}
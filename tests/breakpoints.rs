@@ -0,0 +1,129 @@
+mod common;
+
+use common::{maquina_con_programa, temporal};
+use zx::breakpoints::{Breakpoints, Condition};
+use zx::debugger::RunMode;
+use zx::machine::zx_machine::ZxMachine;
+
+// Condiciones sobre registros y memoria
+//
+// Comparaciones, lógicos, (HL) como lectura de memoria y paréntesis normales.
+#[test]
+fn test_condiciones() {
+    let mut m = ZxMachine::builder().build().unwrap();
+    m.cpu.reg.a = 0x20;
+    m.cpu.reg.set_hl(0x9000);
+    m.cpu.reg.set_ix(0x9000);
+    m.cpu.bus.write_byte(0x9000, 0x07);
+    m.cpu.bus.write_byte(0x9005, 0x33);
+
+    let cierto = |src: &str| Condition::parse(src).unwrap().eval(&m.cpu);
+
+    assert!(cierto("A==0x20 && (HL)!=0"));
+    assert!(cierto("(HL)==7 || A==0"));
+    assert!(!cierto("A==$21"));
+    assert!(cierto("(IX+5)==33h"));
+    assert!(cierto("HL>=0x8000 && !(A<32)"));
+    assert!(cierto("(A & 0x0F)==0"));
+    assert!(cierto("(0x9000)==7"));
+
+    assert!(Condition::parse("A==").is_err());
+    assert!(Condition::parse("Q==1").is_err());
+    assert!(Condition::parse("(HL==1").is_err());
+}
+
+// Breakpoint condicional al correr
+//
+// DJNZ cuenta B de 5 a 0: para en la vuelta en que B==2 y cuenta el acierto.
+#[test]
+fn test_breakpoint_condicional() {
+    // LD B,5 / bucle: DJNZ bucle / JR $
+    let mut m = maquina_con_programa(&[0x06, 0x05, 0x10, 0xFE, 0x18, 0xFE]);
+    m.debug_command("bp 8002 if B==2").unwrap();
+
+    m.debugger.run();
+    m.run_frame();
+
+    assert_eq!(m.debugger.mode, RunMode::Paused);
+    assert_eq!(m.cpu.reg.pc, 0x8002);
+    assert_eq!(m.cpu.reg.b, 2);
+    assert_eq!(m.debugger.breakpoints.list()[0].hits, 1);
+
+    // Al reanudar no se vuelve a parar en el mismo sitio
+    m.debugger.run();
+    m.run_frame();
+    assert_eq!(m.debugger.mode, RunMode::Run);
+    assert_eq!(m.cpu.reg.pc, 0x8004);
+}
+
+// Temporales y desactivados
+//
+// El temporal se borra al saltar; uno desactivado no para.
+#[test]
+fn test_temporal_y_desactivado() {
+    // bucle: JR bucle
+    let mut m = maquina_con_programa(&[0x18, 0xFE]);
+    m.debug_command("tbp 0x8000").unwrap();
+
+    m.debugger.run();
+    m.run_frame();
+    assert_eq!(m.debugger.mode, RunMode::Paused);
+    assert!(m.debugger.breakpoints.is_empty());
+
+    m.debug_command("bp 8000").unwrap();
+    m.debug_command("bd 1").unwrap();
+    m.debugger.run();
+    m.run_frame();
+    assert_eq!(m.debugger.mode, RunMode::Run);
+    assert_eq!(m.debugger.breakpoints.list()[0].hits, 0);
+}
+
+// Comandos
+//
+// Listado, borrado y errores de la consola.
+#[test]
+fn test_comandos() {
+    let mut bps = Breakpoints::new();
+
+    assert_eq!(bps.command("bl").unwrap(), "Sin breakpoints");
+    bps.command("bp 8000").unwrap();
+    bps.command("tbp $9000 if A==1").unwrap();
+    assert_eq!(bps.command("bl").unwrap(), "1 * 8000 (0)\n2 * 9000 (0) T if A==1");
+
+    bps.command("bc 1").unwrap();
+    assert_eq!(bps.list()[0].addr, 0x9000);
+    bps.command("bc *").unwrap();
+    assert!(bps.is_empty());
+
+    assert!(bps.command("bd 3").is_err());
+    assert!(bps.command("bp zz").is_err());
+    assert!(bps.command("bp 8000 if A=").is_err());
+    assert!(bps.command("xyz").is_err());
+}
+
+// Persistencia por programa
+//
+// Los breakpoints se guardan en <programa>.bp y vuelven al cargarlo otra vez.
+#[test]
+fn test_persistencia() {
+    let dir = temporal("bp");
+    std::fs::create_dir_all(&dir).unwrap();
+    let programa = dir.join("juego.bin");
+    std::fs::write(&programa, [0x18, 0xFE, 0x00, 0x00]).unwrap();
+
+    let mut m = ZxMachine::builder().build().unwrap();
+    m.load_file(&programa).unwrap();
+    m.debug_command("bp 8000 if (HL)!=0").unwrap();
+    m.debug_command("tbp 8002").unwrap();
+    m.debug_command("bd 2").unwrap();
+
+    let mut otra = ZxMachine::builder().build().unwrap();
+    otra.load_file(&programa).unwrap();
+    assert_eq!(otra.debugger.breakpoints.to_text(), "bp 8000 if (HL)!=0\n#tbp 8002\n");
+
+    // Sin breakpoints el fichero desaparece
+    otra.debug_command("bc *").unwrap();
+    assert!(!ZxMachine::breakpoints_path(&programa).exists());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,116 @@
+mod common;
+
+use common::temporal;
+use std::path::PathBuf;
+use zx::cli::CliOptions;
+use zx::debugger::RunMode;
+use zx::formatos::png;
+use zx::machine::model::MachineType;
+
+// Todas las opciones
+//
+// Con valor separado o con '=', el fichero en cualquier posición y
+// breakpoints repetidos.
+#[test]
+fn test_opciones() {
+    let o = CliOptions::parse([
+        "--machine", "128K", "juego.tap", "--scale=3", "--no-debug-window",
+        "--breakpoint", "8000", "--breakpoint=9000 if A==1", "--run",
+        "--frames", "50", "--screenshot", "out.png", "--rom", "mi.rom",
+    ])
+    .unwrap();
+
+    assert_eq!(o.file, Some(PathBuf::from("juego.tap")));
+    assert_eq!(o.rom, Some(PathBuf::from("mi.rom")));
+    assert_eq!(o.machine, MachineType::Spectrum128K);
+    assert_eq!(o.scale, Some(3));
+    assert!(!o.debug_window);
+    assert_eq!(o.breakpoints, ["8000", "9000 if A==1"]);
+    assert!(o.run);
+    assert_eq!(o.frames, Some(50));
+    assert_eq!(o.screenshot, Some(PathBuf::from("out.png")));
+
+    // Sin argumentos: valores por defecto
+    assert_eq!(CliOptions::parse(Vec::<String>::new()).unwrap(), CliOptions::default());
+    assert!(CliOptions::parse(["-h"]).unwrap().help);
+}
+
+// Errores
+//
+// Opciones desconocidas, valores que faltan o no son válidos.
+#[test]
+fn test_errores() {
+    assert!(CliOptions::parse(["--xyz"]).is_err());
+    assert!(CliOptions::parse(["--rom"]).is_err());
+    assert!(CliOptions::parse(["--machine", "16k"]).is_err());
+    assert!(CliOptions::parse(["--scale", "0"]).is_err());
+    assert!(CliOptions::parse(["--frames", "-1"]).is_err());
+    assert!(CliOptions::parse(["--run=1"]).is_err());
+    assert!(CliOptions::parse(["a.sna", "b.sna"]).is_err());
+}
+
+// Máquina desde la línea de comandos
+//
+// ROM propia, programa cargado, breakpoint de la sesión y --frames corriendo.
+#[test]
+fn test_maquina() {
+    let rom = temporal("rom");
+    let programa = temporal("prog.bin");
+    std::fs::write(&rom, vec![0u8; 16 * 1024]).unwrap();
+    // bucle: JR bucle
+    std::fs::write(&programa, [0x18, 0xFE, 0x00, 0x00]).unwrap();
+
+    let o = CliOptions::parse([
+        "--rom", rom.to_str().unwrap(),
+        "--breakpoint", "8000",
+        "--frames", "3",
+        programa.to_str().unwrap(),
+    ])
+    .unwrap();
+    let mut m = o.build_machine().unwrap();
+
+    assert_eq!(m.cpu.reg.pc, 0x8000);
+    assert_eq!(m.debugger.mode, RunMode::Run);
+    assert_eq!(m.debugger.breakpoints.list()[0].addr, 0x8000);
+    // El breakpoint no se guarda junto al programa
+    assert!(!zx::machine::zx_machine::ZxMachine::breakpoints_path(&programa).exists());
+
+    let sin_rom = CliOptions::parse(["--rom", "/no/existe.rom"]).unwrap();
+    assert!(sin_rom.build_machine().is_err());
+
+    m.debugger.breakpoints.command("bc *").unwrap();
+    while m.frames < 3 {
+        m.run_frame();
+    }
+    assert_eq!(m.frames, 3);
+
+    std::fs::remove_file(&rom).unwrap();
+    std::fs::remove_file(&programa).unwrap();
+}
+
+// Captura en PNG
+//
+// Cabecera, tamaño del framebuffer y CRC de los chunks.
+#[test]
+fn test_captura_png() {
+    let pixels: Vec<u8> = (0..16u8).cycle().take(352 * 296).collect();
+    let data = png::encode(&pixels, 352, 296).unwrap();
+
+    assert_eq!(&data[..8], b"\x89PNG\r\n\x1a\n");
+    assert_eq!(&data[12..16], b"IHDR");
+    assert_eq!(u32::from_be_bytes(data[16..20].try_into().unwrap()), 352);
+    assert_eq!(u32::from_be_bytes(data[20..24].try_into().unwrap()), 296);
+    assert_eq!(&data[data.len() - 8..data.len() - 4], b"IEND");
+    // CRC de IEND (sin datos): siempre AE 42 60 82
+    assert_eq!(&data[data.len() - 4..], [0xAE, 0x42, 0x60, 0x82]);
+    // Filas RGB sin comprimir más la cabecera y los bloques de zlib
+    assert!(data.len() > 296 * (1 + 352 * 3));
+
+    assert!(png::encode(&pixels, 10, 10).is_err());
+
+    let path = temporal("captura.png");
+    let m = zx::machine::zx_machine::ZxMachine::builder().build().unwrap();
+    m.save_screenshot(&path).unwrap();
+    assert!(std::fs::read(&path).unwrap().starts_with(b"\x89PNG"));
+    std::fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,26 @@
+// Ayudas compartidas por los tests (cada fichero usa solo algunas)
+#![allow(dead_code)]
+
+use std::path::PathBuf;
+use zx::machine::zx_machine::ZxMachine;
+
+// Fichero o directorio temporal único por test
+pub fn temporal(nombre: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("zx_test_{}_{}", std::process::id(), nombre))
+}
+
+// Copia el programa en 0x8000 y deja ahí el PC, con la pila en 0xBFF0
+pub fn cargar_programa(m: &mut ZxMachine, programa: &[u8]) {
+    for (i, b) in programa.iter().enumerate() {
+        m.cpu.bus.write_byte(0x8000 + i as u16, *b);
+    }
+    m.cpu.reg.pc = 0x8000;
+    m.cpu.reg.sp = 0xBFF0;
+}
+
+// Máquina sin ROM con el programa cargado en 0x8000
+pub fn maquina_con_programa(programa: &[u8]) -> ZxMachine {
+    let mut m = ZxMachine::builder().build().unwrap();
+    cargar_programa(&mut m, programa);
+    m
+}
@@ -0,0 +1,142 @@
+use zx::contencion::Contention;
+use zx::machine::model::MachineType;
+use zx::machine::zx_machine::ZxMachine;
+
+// Primer T-state contendido del 48K
+const T0: u64 = 14335;
+
+// 48K sin ROM con el programa en `org`, a punto de ejecutar en el T-state `t`
+fn maquina(org: u16, programa: &[u8], t: u64) -> ZxMachine {
+    let mut m = ZxMachine::builder().build().unwrap();
+    for (i, b) in programa.iter().enumerate() {
+        m.cpu.bus.write_byte(org + i as u16, *b);
+    }
+    m.cpu.reg.pc = org;
+    m.cpu.reg.sp = 0xFF00;
+    m.run_state.t_states = t;
+    m
+}
+
+// T-states que cuesta la siguiente instrucción
+fn ciclos(m: &mut ZxMachine) -> u64 {
+    let t = m.run_state.t_states;
+    m.step_once();
+    m.run_state.t_states - t
+}
+
+// Patrón 6,5,4,3,2,1,0,0
+//
+// Solo en los 128 T-states de papel de las 192 líneas de pantalla.
+#[test]
+fn test_patron_de_retrasos() {
+    let c = Contention::new(MachineType::Spectrum48K);
+
+    let linea: Vec<u32> = (0..10).map(|i| c.delay(T0 + i)).collect();
+    assert_eq!(linea, [6, 5, 4, 3, 2, 1, 0, 0, 6, 5]);
+
+    assert_eq!(c.delay(T0 - 1), 0);
+    assert_eq!(c.delay(T0 + 128), 0); // borde derecho
+    assert_eq!(c.delay(T0 + 224), 6); // línea siguiente
+    assert_eq!(c.delay(T0 + 192 * 224), 0); // borde inferior
+
+    // El 128K empieza más tarde y sus líneas son de 228
+    let c = Contention::new(MachineType::Spectrum128K);
+    assert_eq!(c.delay(14361), 6);
+    assert_eq!(c.delay(14361 + 228), 6);
+}
+
+// Fetch contendido
+//
+// LD A,B en 0x4000: 4 T-states + 6 de espera.
+#[test]
+fn test_fetch_contendido() {
+    let mut m = maquina(0x4000, &[0x78], T0);
+    assert_eq!(ciclos(&mut m), 10);
+
+    // Fuera de la pantalla no hay espera
+    let mut m = maquina(0x4000, &[0x78], 1000);
+    assert_eq!(ciclos(&mut m), 4);
+
+    // En 0x8000 nunca
+    let mut m = maquina(0x8000, &[0x78], T0);
+    assert_eq!(ciclos(&mut m), 4);
+}
+
+// Lectura de datos contendida
+//
+// LD A,(HL) desde 0x8000 con HL en la pantalla: pc:4, hl:3 (+6).
+#[test]
+fn test_lectura_contendida() {
+    let mut m = maquina(0x8000, &[0x7E], T0 - 4);
+    m.cpu.reg.set_hl(0x4000);
+    assert_eq!(ciclos(&mut m), 7 + 6);
+}
+
+// LDIR a la pantalla
+//
+// Cada pasada es un step con su patrón: pc:4, pc+1:4, hl:3, de:3, de:1 x2
+// y, si repite, de:1 x5. Fuera de la pantalla, 21 + 21 + 16.
+#[test]
+fn test_ldir_contendido() {
+    // LD HL,0x9000 / LD DE,... / LD BC,3 / LDIR
+    let ldir = |de: u16| {
+        let [lo, hi] = de.to_le_bytes();
+        maquina(0x8000, &[0x21, 0x00, 0x90, 0x11, lo, hi, 0x01, 0x03, 0x00, 0xED, 0xB0], 0)
+    };
+
+    let mut m = ldir(0x9100);
+    for _ in 0..3 {
+        m.step_once();
+    }
+    let pasadas: Vec<u64> = (0..3).map(|_| ciclos(&mut m)).collect();
+    assert_eq!(pasadas, [21, 21, 16]);
+
+    // de en T0+11 (+3), T0+17 (+5) y T0+24, T0+32, T0+40 (+6)
+    let mut m = ldir(0x4000);
+    for _ in 0..3 {
+        m.step_once();
+    }
+    m.run_state.t_states = T0;
+    assert_eq!(ciclos(&mut m), 21 + 3 + 5 + 6 + 6 + 6);
+    assert_eq!(m.cpu.reg.pc, 0x8009);
+}
+
+// Puertos
+//
+// OUT (0xFE),A con A = 0: N:1, C:3. OUT (0xFF),A con A = 0x40: C:1 x4.
+#[test]
+fn test_puertos_contendidos() {
+    // pc:4, pc+1:3, E/S: la parte contendida cae en T0
+    let mut m = maquina(0x8000, &[0x3E, 0x00, 0xD3, 0xFE], 0);
+    m.step_once();
+    m.run_state.t_states = T0 - 8;
+    assert_eq!(ciclos(&mut m), 11 + 6);
+
+    // Byte alto contendido y puerto impar: 6+1, 0+1, 6+1, 0+1 -> +12
+    let mut m = maquina(0x8000, &[0x3E, 0x40, 0xD3, 0xFF], 0);
+    m.step_once();
+    m.run_state.t_states = T0 - 7;
+    assert_eq!(ciclos(&mut m), 11 + 12);
+
+    // Ni ULA ni byte alto contendido: sin espera
+    let mut m = maquina(0x8000, &[0x3E, 0x80, 0xD3, 0xFF], 0);
+    m.step_once();
+    m.run_state.t_states = T0 - 7;
+    assert_eq!(ciclos(&mut m), 11);
+}
+
+// Interruptor
+//
+// Con la contención desactivada cuentan solo los ciclos del core.
+#[test]
+fn test_contencion_desactivada() {
+    let mut m = ZxMachine::builder().contention(false).build().unwrap();
+    m.cpu.bus.write_byte(0x4000, 0x78);
+    m.cpu.reg.pc = 0x4000;
+    m.run_state.t_states = T0;
+    assert_eq!(ciclos(&mut m), 4);
+
+    let mut m = maquina(0x4000, &[0x78], T0);
+    m.set_contention(false);
+    assert_eq!(ciclos(&mut m), 4);
+}
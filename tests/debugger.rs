@@ -0,0 +1,103 @@
+mod common;
+
+use common::maquina_con_programa;
+use zx::debugger::RunMode;
+use zx::machine::zx_machine::ZxMachine;
+
+// Máquina sin ROM con el programa cargado en 0x8000 y una subrutina en 0x8010:
+//   8000 CALL 8010 / 8003 LD A,7 / 8005 JR $
+//   8010 LD B,50 / 8012 DJNZ $ / 8014 RET
+fn maquina_con_subrutina() -> ZxMachine {
+    let mut m = maquina_con_programa(&[0xCD, 0x10, 0x80, 0x3E, 0x07, 0x18, 0xFE]);
+    let subrutina = [0x06, 0x32, 0x10, 0xFE, 0xC9];
+    for (i, b) in subrutina.iter().enumerate() {
+        m.cpu.bus.write_byte(0x8010 + i as u16, *b);
+    }
+    m
+}
+
+// Step over de un CALL
+//
+// La subrutina entera es un solo paso: para en la instrucción siguiente.
+#[test]
+fn test_step_over_call() {
+    let mut m = maquina_con_subrutina();
+
+    m.step_over();
+    assert_eq!(m.debugger.mode, RunMode::Run);
+    m.run_frame();
+
+    assert_eq!(m.debugger.mode, RunMode::Paused);
+    assert_eq!(m.cpu.reg.pc, 0x8003);
+    assert_eq!(m.cpu.reg.b, 0);
+    assert_eq!(m.cpu.reg.sp, 0xBFF0);
+    assert!(m.debugger.target.is_none());
+
+    // Una instrucción normal es un STEP sin más
+    m.step_over();
+    assert_eq!(m.debugger.mode, RunMode::Paused);
+    assert_eq!(m.cpu.reg.pc, 0x8005);
+    assert_eq!(m.cpu.reg.a, 7);
+}
+
+// Step over de un DJNZ
+//
+// El bucle corre hasta que B llega a 0.
+#[test]
+fn test_step_over_djnz() {
+    let mut m = maquina_con_subrutina();
+    m.cpu.reg.pc = 0x8010;
+    m.step_once();
+    assert_eq!(m.cpu.reg.pc, 0x8012);
+
+    m.step_over();
+    m.run_frame();
+
+    assert_eq!(m.debugger.mode, RunMode::Paused);
+    assert_eq!(m.cpu.reg.pc, 0x8014);
+    assert_eq!(m.cpu.reg.b, 0);
+}
+
+// Step out
+//
+// Dentro de la subrutina corre hasta volver al llamador (según el StackTracker).
+#[test]
+fn test_step_out() {
+    let mut m = maquina_con_subrutina();
+
+    // Sin llamadas en la pila no hay a dónde volver
+    assert!(m.step_out().is_err());
+
+    m.step_once(); // CALL
+    m.step_once(); // LD B,50
+    assert_eq!(m.cpu.reg.pc, 0x8012);
+
+    m.step_out().unwrap();
+    m.run_frame();
+
+    assert_eq!(m.debugger.mode, RunMode::Paused);
+    assert_eq!(m.cpu.reg.pc, 0x8003);
+    assert_eq!(m.cpu.reg.sp, 0xBFF0);
+}
+
+// Run to
+//
+// Con "go" desde la consola y con el cursor de la ventana de instrucciones.
+#[test]
+fn test_run_to() {
+    let mut m = maquina_con_subrutina();
+
+    assert!(m.run_to_cursor().is_err());
+
+    m.debug_command("go 8012").unwrap();
+    m.run_frame();
+    assert_eq!(m.debugger.mode, RunMode::Paused);
+    assert_eq!(m.cpu.reg.pc, 0x8012);
+
+    m.debugger.cursor = Some(0x8005);
+    m.run_to_cursor().unwrap();
+    m.run_frame();
+    assert_eq!(m.debugger.mode, RunMode::Paused);
+    assert_eq!(m.cpu.reg.pc, 0x8005);
+    assert_eq!(m.cpu.reg.a, 7);
+}
@@ -0,0 +1,191 @@
+mod common;
+
+use common::cargar_programa;
+use zx::debugger::RunMode;
+use zx::machine::model::MachineType;
+use zx::machine::zx_machine::ZxMachine;
+
+// Máquina sin ROM, con historial, y el programa cargado en 0x8000
+fn maquina_con_historial(machine: MachineType, programa: &[u8]) -> ZxMachine {
+    let mut m = ZxMachine::builder()
+        .machine(machine)
+        .history(2)
+        .build()
+        .unwrap();
+    cargar_programa(&mut m, programa);
+    m
+}
+
+// Step back
+//
+// Deshace registros, memoria y pila (CALL) instrucción a instrucción.
+#[test]
+fn test_step_back() {
+    // LD HL,0x9000 / LD (HL),0x42 / CALL 0x8010 / ... 8010: JR $
+    let mut m = maquina_con_historial(
+        MachineType::Spectrum48K,
+        &[0x21, 0x00, 0x90, 0x36, 0x42, 0xCD, 0x10, 0x80],
+    );
+    m.cpu.bus.write_byte(0x8010, 0x18);
+    m.cpu.bus.write_byte(0x8011, 0xFE);
+    m.cpu.bus.write_byte(0x9000, 0x11);
+    let t0 = m.run_state.t_states;
+
+    for _ in 0..3 {
+        m.step_once();
+    }
+    assert_eq!(m.cpu.reg.pc, 0x8010);
+    assert_eq!(m.cpu.reg.sp, 0xBFEE);
+    assert_eq!(m.history.len(), 3);
+
+    // CALL: la dirección de retorno desaparece de la pila
+    m.step_back().unwrap();
+    assert_eq!(m.cpu.reg.pc, 0x8005);
+    assert_eq!(m.cpu.reg.sp, 0xBFF0);
+    assert_eq!(m.cpu.bus.read_byte(0xBFEE), 0);
+    assert_eq!(m.cpu.bus.read_byte(0xBFEF), 0);
+
+    m.step_back().unwrap();
+    assert_eq!(m.cpu.bus.read_byte(0x9000), 0x11);
+    m.step_back().unwrap();
+    assert_eq!(m.cpu.reg.pc, 0x8000);
+    assert_eq!(m.cpu.reg.get_hl(), 0);
+    assert_eq!(m.run_state.t_states, t0);
+
+    assert!(m.step_back().is_err());
+    assert_eq!(m.debugger.mode, RunMode::Paused);
+}
+
+// Step back en el 128K
+//
+// Deshacer una escritura en 0xC000 va al banco que estaba paginado,
+// y deshacer el OUT a 0x7FFD vuelve a paginar el anterior.
+#[test]
+fn test_step_back_paginacion() {
+    // LD A,1 / LD BC,0x7FFD / OUT (C),A / LD A,0x55 / LD (0xC000),A
+    let mut m = maquina_con_historial(
+        MachineType::Spectrum128K,
+        &[0x3E, 0x01, 0x01, 0xFD, 0x7F, 0xED, 0x79, 0x3E, 0x55, 0x32, 0x00, 0xC0],
+    );
+    m.cpu.bus.write_byte(0xC000, 0xAA);
+
+    for _ in 0..5 {
+        m.step_once();
+    }
+    assert_eq!(m.bus.mem128.as_ref().unwrap().paged_bank(), 1);
+    assert_eq!(m.cpu.bus.read_byte(0xC000), 0x55);
+
+    m.step_back().unwrap();
+    assert_eq!(m.cpu.bus.read_byte(0xC000), 0x00);
+    m.step_back().unwrap();
+    m.step_back().unwrap();
+    assert_eq!(m.bus.mem128.as_ref().unwrap().paged_bank(), 0);
+    assert_eq!(m.cpu.bus.read_byte(0xC000), 0xAA);
+}
+
+// Step back de un LDIR
+//
+// Cada pasada del LDIR es un step: deshacerlas una a una devuelve los
+// bytes copiados, no solo el primero.
+#[test]
+fn test_step_back_ldir() {
+    // LD HL,0x9000 / LD DE,0xA000 / LD BC,4 / LDIR / JR $
+    let mut m = maquina_con_historial(
+        MachineType::Spectrum48K,
+        &[0x21, 0x00, 0x90, 0x11, 0x00, 0xA0, 0x01, 0x04, 0x00, 0xED, 0xB0, 0x18, 0xFE],
+    );
+    for (i, b) in [0x11, 0x22, 0x33, 0x44].into_iter().enumerate() {
+        m.cpu.bus.write_byte(0x9000 + i as u16, b);
+    }
+
+    while m.cpu.reg.pc != 0x800B {
+        m.step_once();
+    }
+    assert_eq!(m.history.len(), 7);
+    assert_eq!(m.cpu.bus.read_byte(0xA003), 0x44);
+
+    m.step_back().unwrap();
+    assert_eq!(m.cpu.reg.pc, 0x8009);
+    assert_eq!(m.cpu.reg.get_bc(), 1);
+    assert_eq!(m.cpu.bus.read_byte(0xA003), 0x00);
+    assert_eq!(m.cpu.bus.read_byte(0xA002), 0x33);
+    for _ in 0..3 {
+        m.step_back().unwrap();
+    }
+    for i in 0..4 {
+        assert_eq!(m.cpu.bus.read_byte(0xA000 + i), 0x00);
+    }
+    assert_eq!(m.cpu.reg.get_bc(), 4);
+    assert_eq!(m.cpu.reg.get_de(), 0xA000);
+}
+
+// Run back
+//
+// Vuelve hacia atrás hasta el breakpoint (DJNZ con B==3).
+#[test]
+fn test_run_back() {
+    // LD B,10 / bucle: DJNZ bucle / JR $
+    let mut m = maquina_con_historial(MachineType::Spectrum48K, &[0x06, 0x0A, 0x10, 0xFE, 0x18, 0xFE]);
+
+    m.debugger.run();
+    m.run_frame();
+    assert_eq!(m.cpu.reg.pc, 0x8004);
+
+    m.debug_command("bp 8002 if B==3").unwrap();
+    assert!(m.run_back() > 0);
+    assert_eq!(m.debugger.mode, RunMode::Paused);
+    assert_eq!(m.cpu.reg.pc, 0x8002);
+    assert_eq!(m.cpu.reg.b, 3);
+
+    // Sin breakpoints, hasta el principio del historial
+    m.debug_command("bc *").unwrap();
+    m.run_back();
+    assert_eq!(m.cpu.reg.pc, 0x8000);
+    assert!(m.history.is_empty());
+}
+
+// Rebobinado a un keyframe
+//
+// Volver unos frames atrás y repetirlos deja la máquina igual que estaba.
+#[test]
+fn test_rebobinado() {
+    // DI / LD HL,0x9000 / bucle: INC (HL) / INC DE / JR bucle
+    let mut m = maquina_con_historial(
+        MachineType::Spectrum48K,
+        &[0xF3, 0x21, 0x00, 0x90, 0x34, 0x13, 0x18, 0xFC],
+    );
+
+    m.debugger.run();
+    for _ in 0..20 {
+        m.run_frame();
+    }
+    let (t, de, contador) = (m.run_state.t_states, m.cpu.reg.get_de(), m.cpu.bus.read_byte(0x9000));
+    assert!(m.history.keyframes().len() >= 3);
+
+    let atras = m.rewind_seconds(0.15).unwrap();
+    assert!(atras >= 0.15);
+    assert_eq!(m.debugger.mode, RunMode::Paused);
+    assert!(m.cpu.reg.get_de() < de);
+
+    m.debugger.run();
+    while m.run_state.t_states < t {
+        m.step_once();
+    }
+    assert_eq!(m.run_state.t_states, t);
+    assert_eq!(m.cpu.reg.get_de(), de);
+    assert_eq!(m.cpu.bus.read_byte(0x9000), contador);
+}
+
+// Sin historial por defecto
+//
+// El builder no graba nada salvo que se pida con .history().
+#[test]
+fn test_sin_historial() {
+    let mut m = ZxMachine::builder().build().unwrap();
+    m.cpu.reg.pc = 0x8000;
+    m.step_once();
+
+    assert!(!m.history.enabled());
+    assert!(m.step_back().is_err());
+    assert!(m.rewind_seconds(1.0).is_err());
+}
@@ -0,0 +1,236 @@
+use zx::asm;
+use zx::machine::zx_machine::ZxMachine;
+
+// Máquina sin ROM con el fuente ensamblado; empieza en el primer ORG
+fn maquina(fuente: &str) -> ZxMachine {
+    let a = asm::assemble(fuente).unwrap();
+    let (org, bytes) = a.binary();
+    let mut m = ZxMachine::builder().build().unwrap();
+    for (i, b) in bytes.iter().enumerate() {
+        m.cpu.bus.write_byte(org.wrapping_add(i as u16), *b);
+    }
+    m.cpu.reg.pc = a.entry.unwrap_or(org);
+    m
+}
+
+// Ejecuta hasta que el PC llega a `pc`; devuelve los T-states del último paso
+fn hasta_pc(m: &mut ZxMachine, pc: u16) -> u64 {
+    for _ in 0..100_000 {
+        let t = m.run_state.t_states;
+        m.step_once();
+        if m.cpu.reg.pc == pc {
+            return m.run_state.t_states - t;
+        }
+    }
+    panic!("No se llegó a {:04X}", pc);
+}
+
+const IM1_HALT: &str = "
+        org $8000
+inicio: di
+        ld sp,$9000
+        im 1
+        ei
+        halt
+vuelta: jr vuelta
+        org $38
+        inc c
+        ret
+        end inicio";
+
+// IM 0 / IM 1 con HALT
+//
+// La INT despierta al HALT, apila la dirección siguiente y salta a 0x38
+// en 13 T-states; en IM 0 el bus flotante (0xFF) es un RST 38.
+#[test]
+fn test_im1_halt() {
+    for modo in ["im 1", "im 0"] {
+        let mut m = maquina(&IM1_HALT.replace("im 1", modo));
+
+        let t = hasta_pc(&mut m, 0x0038);
+        assert_eq!(t, 13, "{}", modo);
+        assert_eq!(m.run_state.im, if modo == "im 0" { 0 } else { 1 });
+        assert_eq!(m.frames, 1);
+        assert!(!m.run_state.halted);
+        assert!(!m.run_state.iff1 && !m.run_state.iff2);
+        assert_eq!(m.cpu.reg.sp, 0x8FFE);
+        // Dirección de vuelta: la instrucción después del HALT
+        assert_eq!(m.cpu.bus.read_byte(0x8FFE), 0x08);
+        assert_eq!(m.cpu.bus.read_byte(0x8FFF), 0x80);
+
+        hasta_pc(&mut m, 0x8008);
+        assert_eq!(m.cpu.reg.c, 1);
+    }
+}
+
+// IM 2
+//
+// El vector se lee de I*256 + 0xFF y la aceptación cuesta 19 T-states
+// (más los 12 del JR en el que llega).
+#[test]
+fn test_im2() {
+    let mut m = maquina(
+        "
+        org $8000
+        di
+        ld sp,$A000
+        ld a,$90
+        ld i,a
+        im 2
+        ei
+bucle:  jr bucle
+        org $90FF
+        dw rutina
+rutina: jr rutina",
+    );
+
+    let t = hasta_pc(&mut m, 0x9101);
+    assert_eq!(t, 12 + 19);
+    assert_eq!(m.run_state.im, 2);
+    assert_eq!(m.cpu.reg.sp, 0x9FFE);
+    assert_eq!(m.cpu.bus.read_byte(0x9FFE), 0x0B);
+    assert_eq!(m.cpu.bus.read_byte(0x9FFF), 0x80);
+}
+
+// Pulso de INT de 32 T-states
+//
+// Con las interrupciones desactivadas durante el pulso la INT se pierde;
+// activadas dentro del pulso, se acepta.
+#[test]
+fn test_pulso_int() {
+    let fuente = "
+        org $8000
+        di
+        ld sp,$9000
+        im 1
+bucle:  jr bucle";
+
+    // EI después del pulso: no hay INT en este frame
+    let mut m = maquina(fuente);
+    while m.frames == 0 {
+        m.step_once();
+    }
+    assert!(m.interrupt_pending);
+    while m.interrupt_pending {
+        m.step_once();
+    }
+    assert!(m.interrupt_ctrl.tstates_accum >= 32);
+    m.run_state.iff1 = true;
+    for _ in 0..1000 {
+        m.step_once();
+        assert_ne!(m.cpu.reg.pc, 0x0038);
+    }
+
+    // Dentro del pulso: la siguiente instrucción ya va a 0x38
+    let mut m = maquina(fuente);
+    while m.frames == 0 {
+        m.step_once();
+    }
+    m.run_state.iff1 = true;
+    m.step_once();
+    assert_eq!(m.cpu.reg.pc, 0x0038);
+}
+
+// IFF2, LD A,I y RETN
+//
+// LD A,I copia IFF2 en P/V; RETN devuelve IFF2 a IFF1 en 14 T-states.
+#[test]
+fn test_iff2_retn() {
+    let mut m = maquina(
+        "
+        org $8000
+        ld sp,$9000
+        di
+        ld a,i
+        push af
+        ei
+        nop
+        ld a,i
+        push af
+        ld hl,vuelta
+        push hl
+        di
+        retn
+vuelta: jr vuelta",
+    );
+
+    // Hasta el RETN
+    hasta_pc(&mut m, 0x8011);
+    assert_eq!(m.cpu.bus.read_byte(0x8FFE) & 0x04, 0);
+    assert_eq!(m.cpu.bus.read_byte(0x8FFC) & 0x04, 0x04);
+    assert!(!m.run_state.iff1);
+
+    // Como si una NMI hubiera guardado IFF1 en IFF2
+    m.run_state.iff2 = true;
+    let t = m.run_state.t_states;
+    m.step_once();
+    assert_eq!(m.run_state.t_states - t, 14);
+    assert_eq!(m.cpu.reg.pc, 0x8013);
+    assert!(m.run_state.iff1);
+}
+
+// RETI
+//
+// Vuelve a la dirección de la pila en 14 T-states, como RETN.
+#[test]
+fn test_reti() {
+    let mut m = maquina(
+        "
+        org $8000
+        ld sp,$9000
+        ld hl,vuelta
+        push hl
+        reti
+vuelta: jr vuelta",
+    );
+
+    hasta_pc(&mut m, 0x8007);
+    let t = hasta_pc(&mut m, 0x8009);
+    assert_eq!(t, 14);
+    assert_eq!(m.cpu.reg.sp, 0x9000);
+}
+
+// NMI
+//
+// Saca del HALT aunque no haya INT, salta a 0x66 en 11 T-states guardando
+// IFF1 en IFF2 y RETN (14 T-states) lo devuelve. También desde la consola.
+#[test]
+fn test_nmi() {
+    let mut m = maquina(
+        "
+        org $8000
+inicio: ld sp,$9000
+        ei
+        nop
+        halt
+vuelta: jr vuelta
+        org $66
+        retn
+        end inicio",
+    );
+    while !m.run_state.halted {
+        m.step_once();
+    }
+
+    m.nmi();
+    let t = hasta_pc(&mut m, 0x0066);
+    assert_eq!(t, 11);
+    assert!(!m.run_state.halted);
+    assert!(!m.run_state.iff1 && m.run_state.iff2);
+    assert_eq!(m.cpu.reg.sp, 0x8FFE);
+    assert_eq!(m.cpu.bus.read_byte(0x8FFE), 0x06);
+    assert_eq!(m.cpu.bus.read_byte(0x8FFF), 0x80);
+
+    let t = hasta_pc(&mut m, 0x8006);
+    assert_eq!(t, 14);
+    assert!(m.run_state.iff1);
+
+    // Con las interrupciones desactivadas IFF2 queda a 0
+    m.run_state.iff1 = false;
+    m.debug_command("nmi").unwrap();
+    m.step_once();
+    assert_eq!(m.cpu.reg.pc, 0x0066);
+    assert!(!m.run_state.iff2);
+    m.step_once();
+    assert!(!m.run_state.iff1);
+}
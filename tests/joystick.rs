@@ -0,0 +1,104 @@
+use zilog_z80::bus::Bus;
+use zx::bus::ZxBus;
+use zx::cli::CliOptions;
+use zx::joystick::{JoyInput, JoystickType};
+use zx::machine::model::MachineType;
+use zx::machine::zx_machine::ZxMachine;
+
+// Bus con un joystick y las entradas pulsadas
+fn bus_con(kind: JoystickType, entradas: &[JoyInput]) -> ZxBus {
+    let mut bus = ZxBus::new();
+    bus.joystick.set_kind(kind);
+    for e in entradas {
+        bus.joystick.set(*e, true);
+    }
+    bus
+}
+
+// Kempston
+//
+// Puerto 0x1F con los bits a 1 para lo pulsado; sin Kempston el puerto es
+// del bus flotante (0xFF en el borde).
+#[test]
+fn test_kempston() {
+    let mem = Bus::new(0xFFFF);
+    let mut bus = bus_con(JoystickType::Kempston, &[JoyInput::Up, JoyInput::Fire]);
+
+    assert_eq!(bus.in_port(0x001F, 0, &mem), 0x18);
+    assert_eq!(bus.in_port(0xFF1F, 0, &mem), 0x18);
+    bus.joystick.set(JoyInput::Up, false);
+    bus.joystick.set(JoyInput::Right, true);
+    assert_eq!(bus.in_port(0x001F, 0, &mem), 0x11);
+    // Las teclas no se enteran
+    assert_eq!(bus.in_port(0x00FE, 0, &mem) & 0x1F, 0x1F);
+
+    bus.joystick.set_kind(JoystickType::None);
+    assert_eq!(bus.in_port(0x001F, 0, &mem), 0xFF);
+}
+
+// Sinclair y Cursor
+//
+// Cada dirección pulsa su tecla en la fila 1-5 o 0-6 y se suma a lo que
+// se pulse en el teclado.
+#[test]
+fn test_sobre_teclas() {
+    let mem = Bus::new(0xFFFF);
+    // (interfaz, entrada, puerto de la fila, bit)
+    let casos = [
+        (JoystickType::Sinclair1, JoyInput::Left, 0xEFFE, 4),
+        (JoystickType::Sinclair1, JoyInput::Up, 0xEFFE, 1),
+        (JoystickType::Sinclair1, JoyInput::Fire, 0xEFFE, 0),
+        (JoystickType::Sinclair2, JoyInput::Right, 0xF7FE, 1),
+        (JoystickType::Sinclair2, JoyInput::Fire, 0xF7FE, 4),
+        (JoystickType::Cursor, JoyInput::Left, 0xF7FE, 4),
+        (JoystickType::Cursor, JoyInput::Down, 0xEFFE, 4),
+        (JoystickType::Cursor, JoyInput::Right, 0xEFFE, 2),
+    ];
+    for (kind, entrada, puerto, bit) in casos {
+        let mut bus = bus_con(kind, &[entrada]);
+        assert_eq!(bus.in_port(puerto, 0, &mem) & 0x1F, 0x1F & !(1 << bit), "{:?} {:?}", kind, entrada);
+        // Otras filas no cambian
+        assert_eq!(bus.in_port(0xFEFE, 0, &mem) & 0x1F, 0x1F);
+    }
+
+    // Con el teclado: Q (fila FBFE) y fuego del Sinclair 1 leyendo las dos filas
+    let mut bus = bus_con(JoystickType::Sinclair1, &[JoyInput::Fire]);
+    bus.keyboard.set_key(2, 0, true);
+    assert_eq!(bus.in_port(0xEBFE, 0, &mem) & 0x1F, 0x1E);
+    assert_eq!(bus.in_port(0x7FFE, 0, &mem) & 0x1F, 0x1F);
+}
+
+// Cambio de interfaz
+//
+// Desde la consola y la línea de comandos; al cambiar se suelta todo. Se
+// mantiene al cambiar de modelo.
+#[test]
+fn test_cambio() {
+    let mut m = ZxMachine::builder().build().unwrap();
+    assert_eq!(m.bus.joystick.kind, JoystickType::None);
+    assert_eq!(m.debug_command("joy").unwrap(), "Joystick: ninguno");
+    assert_eq!(m.debug_command("joy kempston").unwrap(), "Joystick: Kempston");
+    assert!(m.debug_command("joy atari").is_err());
+
+    m.bus.joystick.set(JoyInput::Fire, true);
+    m.debug_command("joy sinclair2").unwrap();
+    assert_eq!(m.bus.joystick.kind, JoystickType::Sinclair2);
+    assert_eq!(m.bus.joystick.read_port_fe(0x00), 0x1F);
+
+    // Cambiar de modelo conserva la interfaz
+    let rom = vec![0; MachineType::Spectrum128K.rom_size()];
+    m.switch_machine_with_rom(MachineType::Spectrum128K, rom).unwrap();
+    assert_eq!(m.bus.joystick.kind, JoystickType::Sinclair2);
+
+    // El botón JOY recorre todas y vuelve a empezar
+    let mut kind = JoystickType::None;
+    for _ in 0..5 {
+        kind = kind.next();
+    }
+    assert_eq!(kind, JoystickType::None);
+
+    let o = CliOptions::parse(["--joystick", "Cursor", "--joystick-keys=W,S,A,D,Space"]).unwrap();
+    assert_eq!(o.joystick, JoystickType::Cursor);
+    assert_eq!(o.joystick_keys.as_deref(), Some("W,S,A,D,Space"));
+    assert!(CliOptions::parse(["--joystick", "atari"]).is_err());
+}
@@ -0,0 +1,188 @@
+mod common;
+
+use common::{cargar_programa, temporal};
+use zx::machine::model::MachineType;
+use zx::machine::zx_machine::ZxMachine;
+use zx::video::FB_W;
+
+// 128K sin ROM con el programa en 0x8000 (banco 2, siempre mapeado)
+fn maquina_128_con_programa(programa: &[u8]) -> ZxMachine {
+    let mut m = ZxMachine::builder()
+        .machine(MachineType::Spectrum128K)
+        .build()
+        .unwrap();
+    cargar_programa(&mut m, programa);
+    m
+}
+
+// LD A,valor / LD BC,0x7FFD / OUT (C),A
+fn out_7ffd(valor: u8) -> Vec<u8> {
+    vec![0x3E, valor, 0x01, 0xFD, 0x7F, 0xED, 0x79]
+}
+
+// Paginación por 0x7FFD
+//
+// Un OUT cambia el banco de 0xC000; el contenido del saliente se conserva.
+#[test]
+fn test_paginacion_banco_c000() {
+    let mut prog = out_7ffd(0x01);
+    prog.extend(out_7ffd(0x00));
+    let mut m = maquina_128_con_programa(&prog);
+    m.cpu.bus.write_byte(0xC000, 0xAA);
+
+    for _ in 0..3 {
+        m.step_once();
+    }
+    assert_eq!(m.bus.mem128.as_ref().unwrap().paged_bank(), 1);
+    assert_eq!(m.cpu.bus.read_byte(0xC000), 0x00);
+    m.cpu.bus.write_byte(0xC000, 0x55);
+
+    for _ in 0..3 {
+        m.step_once();
+    }
+    assert_eq!(m.cpu.bus.read_byte(0xC000), 0xAA);
+
+    let mem = m.bus.mem128.as_ref().unwrap();
+    assert_eq!(mem.bank(&m.cpu.bus, 1)[0], 0x55);
+}
+
+// Banco 5 mapeado dos veces
+//
+// Con el banco 5 también en 0xC000, lo escrito por cualquiera de las dos
+// ventanas se ve en la otra y sobrevive al despaginarlo.
+#[test]
+fn test_banco_5_en_c000() {
+    let mut prog = out_7ffd(0x05);
+    prog.extend([0x3E, 0x55, 0x32, 0x00, 0x40]); // LD A,55 / LD (4000),A
+    prog.extend([0x3E, 0x66, 0x32, 0x01, 0xC0]); // LD A,66 / LD (C001),A
+    prog.extend(out_7ffd(0x00));
+    let mut m = maquina_128_con_programa(&prog);
+
+    for _ in 0..7 {
+        m.step_once();
+    }
+    assert_eq!(m.cpu.bus.read_byte(0xC000), 0x55);
+    assert_eq!(m.cpu.bus.read_byte(0x4001), 0x66);
+
+    for _ in 0..3 {
+        m.step_once();
+    }
+    assert_eq!(m.bus.mem128.as_ref().unwrap().paged_bank(), 0);
+    assert_eq!(m.cpu.bus.read_byte(0x4000), 0x55);
+    assert_eq!(m.cpu.bus.read_byte(0x4001), 0x66);
+}
+
+// Bloqueo (bit 5)
+//
+// Tras bloquear, los OUT a 0x7FFD se ignoran hasta el reset.
+#[test]
+fn test_paginacion_bloqueada() {
+    let mut prog = out_7ffd(0x23); // banco 3 + bloqueo
+    prog.extend(out_7ffd(0x04));
+    let mut m = maquina_128_con_programa(&prog);
+
+    for _ in 0..6 {
+        m.step_once();
+    }
+    let mem = m.bus.mem128.as_ref().unwrap();
+    assert!(mem.locked());
+    assert_eq!(mem.paged_bank(), 3);
+
+    m.reset_machine();
+    let mem = m.bus.mem128.as_ref().unwrap();
+    assert!(!mem.locked());
+    assert_eq!(mem.paged_bank(), 0);
+}
+
+// Cambio de ROM (bit 4)
+//
+// 0x0000-0x3FFF pasa de la ROM 0 a la ROM 1 y sigue protegida.
+#[test]
+fn test_cambio_de_rom() {
+    let mut rom = vec![0x11; 16 * 1024];
+    rom.extend(vec![0x22; 16 * 1024]);
+    let mut m = ZxMachine::builder()
+        .machine(MachineType::Spectrum128K)
+        .rom(rom)
+        .build()
+        .unwrap();
+    assert_eq!(m.cpu.bus.read_byte(0x1234), 0x11);
+
+    let mem = m.bus.mem128.as_mut().unwrap();
+    mem.page(&mut m.cpu.bus, 0x10);
+    assert_eq!(m.cpu.bus.read_byte(0x1234), 0x22);
+
+    m.cpu.bus.write_byte(0x1234, 0x99);
+    assert_eq!(m.cpu.bus.read_byte(0x1234), 0x22);
+
+    // Una ROM de 16K no vale para el 128K
+    assert!(ZxMachine::builder()
+        .machine(MachineType::Spectrum128K)
+        .rom(vec![0; 16 * 1024])
+        .build()
+        .is_err());
+}
+
+// Pantalla sombra (bit 3)
+//
+// Con el bit 3 la ULA muestra el banco 7, esté o no paginado.
+#[test]
+fn test_pantalla_sombra_banco_7() {
+    let mut m = maquina_128_con_programa(&[]);
+    let mem = m.bus.mem128.as_mut().unwrap();
+
+    // Banco 7 con el primer byte de pantalla a 0xFF y tinta blanca
+    let mut bank7 = vec![0u8; 16 * 1024];
+    bank7[0] = 0xFF;
+    bank7[0x1800] = 0x07;
+    mem.set_bank(&mut m.cpu.bus, 7, &bank7);
+
+    let primer_pixel = 48 * FB_W + 48;
+
+    m.update_video_from_bus();
+    assert_eq!(m.video.framebuffer[primer_pixel], 0); // banco 5 vacío
+
+    let mem = m.bus.mem128.as_mut().unwrap();
+    mem.page(&mut m.cpu.bus, 0x08);
+    m.update_video_from_bus();
+    assert_eq!(m.video.framebuffer[primer_pixel], 7);
+
+    // Paginado en 0xC000 se ve lo escrito por la CPU
+    let mem = m.bus.mem128.as_mut().unwrap();
+    mem.page(&mut m.cpu.bus, 0x0F);
+    m.cpu.bus.write_byte(0xC000, 0x00);
+    m.update_video_from_bus();
+    assert_eq!(m.video.framebuffer[primer_pixel], 0);
+}
+
+// Snapshot .z80 de 128K
+//
+// Los 8 bancos y 0x7FFD vuelven iguales; en un 48K la carga falla.
+#[test]
+fn test_z80_128k_ida_y_vuelta() {
+    let mut m = maquina_128_con_programa(&[]);
+    let mem = m.bus.mem128.as_mut().unwrap();
+    for n in 0..8 {
+        mem.set_bank(&mut m.cpu.bus, n, &vec![0x10 + n as u8; 16 * 1024]);
+    }
+    mem.page(&mut m.cpu.bus, 0x1E); // banco 6, ROM 1, sombra
+    m.cpu.reg.pc = 0x8123;
+
+    let path = temporal("banco128.z80");
+    m.save_file(&path).unwrap();
+    assert!(m.save_file(&temporal("banco128.sna")).is_err());
+
+    let mut m2 = maquina_128_con_programa(&[]);
+    m2.load_file(&path).unwrap();
+    let mut m48 = ZxMachine::builder().build().unwrap();
+    assert!(m48.load_file(&path).is_err());
+    std::fs::remove_file(&path).ok();
+
+    let mem2 = m2.bus.mem128.as_ref().unwrap();
+    assert_eq!(mem2.port_7ffd, 0x1E);
+    assert_eq!(m2.cpu.reg.pc, 0x8123);
+    for n in 0..8 {
+        assert!(mem2.bank(&m2.cpu.bus, n).iter().all(|&b| b == 0x10 + n as u8), "banco {}", n);
+    }
+    assert_eq!(m2.cpu.bus.read_byte(0xC000), 0x16);
+}
@@ -0,0 +1,181 @@
+use zilog_z80::bus::Bus;
+use zx::bus::ZxBus;
+use zx::contencion::Contention;
+use zx::machine::model::MachineType;
+use zx::machine::zx_machine::ZxMachine;
+
+// Máquina sin ROM con el programa cargado en 0x0000
+fn maquina_en_cero(programa: &[u8]) -> ZxMachine {
+    let mut m = ZxMachine::builder().build().unwrap();
+    for (i, b) in programa.iter().enumerate() {
+        m.cpu.bus.write_byte(i as u16, *b);
+    }
+    m.cpu.reg.sp = 0xFFF0;
+    m
+}
+
+// OUT (n),A
+//
+// Cambia el color del borde y cuesta 11 T-states.
+#[test]
+fn test_out_n_a_borde() {
+    // LD A,0x02 / OUT (0xFE),A
+    let mut m = maquina_en_cero(&[0x3E, 0x02, 0xD3, 0xFE]);
+
+    m.step_once();
+    let t0 = m.run_state.t_states;
+    m.step_once();
+
+    assert_eq!(m.bus.border, 2);
+    assert_eq!(m.run_state.t_states - t0, 11);
+    assert_eq!(m.cpu.reg.pc, 0x0004);
+}
+
+// OUT (C),r
+//
+// Bit 4 = altavoz, bit 3 = MIC.
+#[test]
+fn test_out_c_r_altavoz_mic() {
+    // LD BC,0x00FE / LD E,0x1D / OUT (C),E
+    let mut m = maquina_en_cero(&[0x01, 0xFE, 0x00, 0x1E, 0x1D, 0xED, 0x59]);
+
+    m.step_once();
+    m.step_once();
+    let t0 = m.run_state.t_states;
+    m.step_once();
+
+    assert_eq!(m.bus.border, 5);
+    assert!(m.bus.speaker);
+    assert!(m.bus.mic);
+    assert_eq!(m.run_state.t_states - t0, 12);
+}
+
+// OUT a puerto impar
+//
+// No llega a la ULA.
+#[test]
+fn test_out_puerto_impar() {
+    // LD A,0x07 / OUT (0xFF),A
+    let mut m = maquina_en_cero(&[0x3E, 0x07, 0xD3, 0xFF]);
+
+    m.step_once();
+    m.step_once();
+
+    assert_eq!(m.bus.border, 0);
+}
+
+// OTIR
+//
+// Cada iteración es un step: 21 T-states mientras B != 0, 16 al final.
+#[test]
+fn test_otir() {
+    // LD HL,0x0100 / LD BC,0x02FE / OTIR
+    let mut m = maquina_en_cero(&[0x21, 0x00, 0x01, 0x01, 0xFE, 0x02, 0xED, 0xB3]);
+    m.cpu.bus.write_byte(0x0100, 0x03);
+    m.cpu.bus.write_byte(0x0101, 0x06);
+
+    m.step_once();
+    m.step_once();
+
+    let t0 = m.run_state.t_states;
+    m.step_once();
+    assert_eq!(m.bus.border, 3);
+    assert_eq!(m.cpu.reg.pc, 0x0006);
+    assert_eq!(m.run_state.t_states - t0, 21);
+
+    let t1 = m.run_state.t_states;
+    m.step_once();
+    assert_eq!(m.bus.border, 6);
+    assert_eq!(m.cpu.reg.pc, 0x0008);
+    assert_eq!(m.run_state.t_states - t1, 16);
+
+    assert_eq!(m.cpu.reg.b, 0);
+    assert_eq!(m.cpu.reg.get_hl(), 0x0102);
+    let f = (m.cpu.reg.get_af() & 0x00FF) as u8;
+    assert_eq!(f & 0x40, 0x40); // Z = 1
+}
+
+// IN r,(C)
+//
+// Lee el teclado, fija S/Z/P y conserva el carry.
+#[test]
+fn test_in_r_c_flags() {
+    // SCF / LD BC,0xFEFE / IN D,(C)
+    let mut m = maquina_en_cero(&[0x37, 0x01, 0xFE, 0xFE, 0xED, 0x50]);
+    m.bus.keyboard.set_key(0, 1, true); // Z pulsada
+
+    m.step_once();
+    m.step_once();
+    m.step_once();
+
+    assert_eq!(m.cpu.reg.d, 0xE0 | 0x1D);
+    let f = (m.cpu.reg.get_af() & 0x00FF) as u8;
+    assert_eq!(f & 0x01, 0x01); // C conservado
+    assert_eq!(f & 0x80, 0x80); // S = 1
+    assert_eq!(f & 0x40, 0x00); // Z = 0
+}
+
+// INI
+//
+// Guarda en (HL), incrementa HL y decrementa B.
+#[test]
+fn test_ini() {
+    // LD HL,0x0200 / LD BC,0x01FF / INI
+    let mut m = maquina_en_cero(&[0x21, 0x00, 0x02, 0x01, 0xFF, 0x01, 0xED, 0xA2]);
+
+    m.step_once();
+    m.step_once();
+    let t0 = m.run_state.t_states;
+    m.step_once();
+
+    assert_eq!(m.cpu.bus.read_byte(0x0200), 0xFF);
+    assert_eq!(m.cpu.reg.get_hl(), 0x0201);
+    assert_eq!(m.cpu.reg.b, 0);
+    assert_eq!(m.run_state.t_states - t0, 16);
+}
+
+// IN A,(n) a puerto impar
+//
+// Antes no se interceptaba y A no cambiaba.
+#[test]
+fn test_in_a_n_puerto_impar() {
+    // LD A,0x00 / IN A,(0xFF)
+    let mut m = maquina_en_cero(&[0x3E, 0x00, 0xDB, 0xFF]);
+
+    m.step_once();
+    m.step_once();
+
+    assert_eq!(m.cpu.reg.a, 0xFF);
+}
+
+// Bus flotante
+//
+// Un puerto sin periférico devuelve el byte que lee la ULA: píxeles y
+// atributo de dos columnas por cada 8 T-states del papel, 0xFF en el
+// borde y en los huecos.
+#[test]
+fn test_bus_flotante() {
+    let mut mem = Bus::new(0xFFFF);
+    for (addr, b) in [(0x4000, 0x12), (0x5800, 0x34), (0x4001, 0x56), (0x5801, 0x78), (0x4100, 0x9A)] {
+        mem.write_byte(addr, b);
+    }
+
+    for (modelo, primero) in [(MachineType::Spectrum48K, 14338), (MachineType::Spectrum128K, 14364)] {
+        let mut bus = ZxBus::new();
+        bus.contention = Contention::new(modelo);
+        let linea = modelo.tstates_per_line();
+
+        let leidos: Vec<u8> = (primero - 1..primero + 6).map(|t| bus.in_port(0x40FF, t, &mem)).collect();
+        assert_eq!(leidos, [0xFF, 0x12, 0x34, 0x56, 0x78, 0xFF, 0xFF], "{:?}", modelo);
+
+        // Línea 1 de la pantalla; borde superior, derecho e inferior
+        assert_eq!(bus.in_port(0x40FF, primero + linea, &mem), 0x9A);
+        assert_eq!(bus.in_port(0x40FF, 100, &mem), 0xFF);
+        assert_eq!(bus.in_port(0x40FF, primero + 128, &mem), 0xFF);
+        assert_eq!(bus.in_port(0x40FF, primero + 192 * linea, &mem), 0xFF);
+
+        // Relativo al inicio del frame
+        bus.contention.frame_start = 1_000_000;
+        assert_eq!(bus.in_port(0x40FF, 1_000_000 + primero, &mem), 0x12);
+    }
+}
@@ -0,0 +1,160 @@
+mod common;
+
+use common::temporal;
+use zx::machine::zx_machine::ZxMachine;
+
+// Máquina sin ROM con registros, RAM y borde "raros"
+fn maquina_de_prueba() -> ZxMachine {
+    let mut m = ZxMachine::builder().build().unwrap();
+
+    m.cpu.reg.set_af(0x12D7);
+    m.cpu.reg.set_bc(0x3456);
+    m.cpu.reg.set_de(0x789A);
+    m.cpu.reg.set_hl(0xBCDE);
+    m.cpu.alt.set_af(0xF00F);
+    m.cpu.alt.set_bc(0x1111);
+    m.cpu.alt.set_de(0x2222);
+    m.cpu.alt.set_hl(0x3333);
+    m.cpu.reg.set_ix(0x4444);
+    m.cpu.reg.set_iy(0x5C3A);
+    m.cpu.reg.sp = 0xFF00;
+    m.cpu.reg.pc = 0x8123;
+    m.cpu.reg.i = 0x3F;
+    m.cpu.reg.r = 0x85;
+
+    m.run_state.iff1 = true;
+    m.run_state.iff2 = true;
+    m.run_state.im = 2;
+    m.bus.border = 5;
+
+    // Secuencias que ejercitan el RLE (incluidos ED sueltos y dobles)
+    for (i, b) in [0xED, 0x00, 0x00, 0x00, 0x00, 0x00, 0xED, 0xED, 0x42, 0xED, 0x07]
+        .iter()
+        .enumerate()
+    {
+        m.cpu.bus.write_byte(0x9000 + i as u16, *b);
+    }
+    for a in 0xC000..0xC100u16 {
+        m.cpu.bus.write_byte(a, 0xAA);
+    }
+    for a in 0x4000..0x4100u16 {
+        m.cpu.bus.write_byte(a, a as u8);
+    }
+
+    m
+}
+
+fn comprobar_iguales(a: &ZxMachine, b: &ZxMachine) {
+    assert_eq!(a.cpu.reg.get_af(), b.cpu.reg.get_af());
+    assert_eq!(a.cpu.reg.get_bc(), b.cpu.reg.get_bc());
+    assert_eq!(a.cpu.reg.get_de(), b.cpu.reg.get_de());
+    assert_eq!(a.cpu.reg.get_hl(), b.cpu.reg.get_hl());
+    assert_eq!(a.cpu.alt.get_af(), b.cpu.alt.get_af());
+    assert_eq!(a.cpu.alt.get_bc(), b.cpu.alt.get_bc());
+    assert_eq!(a.cpu.alt.get_de(), b.cpu.alt.get_de());
+    assert_eq!(a.cpu.alt.get_hl(), b.cpu.alt.get_hl());
+    assert_eq!(a.cpu.reg.get_ix(), b.cpu.reg.get_ix());
+    assert_eq!(a.cpu.reg.get_iy(), b.cpu.reg.get_iy());
+    assert_eq!(a.cpu.reg.sp, b.cpu.reg.sp);
+    assert_eq!(a.cpu.reg.pc, b.cpu.reg.pc);
+    assert_eq!(a.cpu.reg.i, b.cpu.reg.i);
+    assert_eq!(a.cpu.reg.r, b.cpu.reg.r);
+    assert_eq!(a.run_state.iff1, b.run_state.iff1);
+    assert_eq!(a.run_state.iff2, b.run_state.iff2);
+    assert_eq!(a.run_state.im, b.run_state.im);
+    assert_eq!(a.bus.border, b.bus.border);
+}
+
+// Guardar y cargar .z80 (v3)
+//
+// El estado vuelve idéntico, RAM incluida.
+#[test]
+fn test_z80_ida_y_vuelta() {
+    let m = maquina_de_prueba();
+    let path = temporal("ida_vuelta.z80");
+    m.save_file(&path).unwrap();
+
+    let mut m2 = ZxMachine::builder().build().unwrap();
+    m2.load_file(&path).unwrap();
+    std::fs::remove_file(&path).ok();
+
+    comprobar_iguales(&m, &m2);
+    for a in 0x4000..=0xFFFFu16 {
+        assert_eq!(m.cpu.bus.read_byte(a), m2.cpu.bus.read_byte(a), "RAM {:04X}", a);
+    }
+}
+
+// Guardar y cargar .sna
+//
+// El PC viaja en la pila; el resto de la RAM no cambia.
+#[test]
+fn test_sna_ida_y_vuelta() {
+    let m = maquina_de_prueba();
+    let path = temporal("ida_vuelta.sna");
+    m.save_file(&path).unwrap();
+
+    assert_eq!(std::fs::metadata(&path).unwrap().len(), 49179);
+
+    let mut m2 = ZxMachine::builder().build().unwrap();
+    m2.load_file(&path).unwrap();
+    std::fs::remove_file(&path).ok();
+
+    comprobar_iguales(&m, &m2);
+    for a in (0x4000..0xFEFEu16).chain(0xFF00..=0xFFFF) {
+        assert_eq!(m.cpu.bus.read_byte(a), m2.cpu.bus.read_byte(a), "RAM {:04X}", a);
+    }
+}
+
+// Snapshot en HALT
+//
+// Se guarda el PC del HALT: al cargar, la CPU vuelve a pararse en él en
+// vez de seguir con la instrucción siguiente.
+#[test]
+fn test_halt_ida_y_vuelta() {
+    for ext in ["z80", "sna"] {
+        let mut m = maquina_de_prueba();
+        m.run_state.iff1 = false;
+        m.cpu.bus.write_byte(0x8123, 0x76); // HALT
+        m.step_once();
+        assert!(m.run_state.halted);
+        assert_eq!(m.cpu.reg.pc, 0x8124);
+
+        let path = temporal(&format!("halt.{}", ext));
+        m.save_file(&path).unwrap();
+        let mut m2 = ZxMachine::builder().build().unwrap();
+        m2.load_file(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+
+        assert_eq!(m2.cpu.reg.pc, 0x8123, "{}", ext);
+        m2.step_once();
+        assert!(m2.run_state.halted, "{}", ext);
+        assert_eq!(m2.cpu.reg.pc, 0x8124);
+    }
+}
+
+// Cabecera .z80
+//
+// PC = 0 (v2/v3), bit 7 de R y borde en el byte 12, cabecera extendida de 54.
+#[test]
+fn test_z80_cabecera_v3() {
+    let m = maquina_de_prueba();
+    let path = temporal("cabecera.z80");
+    m.save_file(&path).unwrap();
+    let data = std::fs::read(&path).unwrap();
+    std::fs::remove_file(&path).ok();
+
+    assert_eq!(&data[0..2], &[0x12, 0xD7]); // A, F
+    assert_eq!(&data[6..8], &[0, 0]);
+    assert_eq!(data[11], 0x05);
+    assert_eq!(data[12], 0x01 | (5 << 1));
+    assert_eq!(&data[30..32], &[54, 0]);
+    assert_eq!(&data[32..34], &[0x23, 0x81]);
+    assert!(data.len() < 49152); // RAM comprimida
+}
+
+// Extensión desconocida
+#[test]
+fn test_guardar_formato_no_soportado() {
+    let m = ZxMachine::builder().build().unwrap();
+    assert!(m.save_file(&temporal("x.tap")).is_err());
+}
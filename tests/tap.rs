@@ -0,0 +1,170 @@
+use zx::formatos::tap::{TapFile, LD_BYTES};
+use zx::machine::model::MachineType;
+use zx::machine::zx_machine::ZxMachine;
+
+// Bloque .tap: flag + datos + checksum (XOR de todo)
+fn bloque(flag: u8, datos: &[u8]) -> Vec<u8> {
+    let mut b = vec![flag];
+    b.extend_from_slice(datos);
+    b.push(b.iter().fold(0, |acc, x| acc ^ x));
+    b
+}
+
+// Imagen .tap con los bloques dados
+fn imagen(bloques: &[Vec<u8>]) -> Vec<u8> {
+    let mut data = Vec::new();
+    for b in bloques {
+        data.extend_from_slice(&(b.len() as u16).to_le_bytes());
+        data.extend_from_slice(b);
+    }
+    data
+}
+
+// Máquina sin ROM parada en LD-BYTES, llamada desde 0x1234
+fn maquina_en_ld_bytes(tape: TapFile, flag: u8, ix: u16, de: u16) -> ZxMachine {
+    let mut m = ZxMachine::builder().build().unwrap();
+    m.cpu.reg.sp = 0xFFF0;
+    m.cpu.bus.write_byte(0xFFF0, 0x34);
+    m.cpu.bus.write_byte(0xFFF1, 0x12);
+    m.cpu.reg.pc = LD_BYTES;
+    m.cpu.reg.a = flag;
+    m.cpu.reg.flags.c = true; // LOAD
+    m.cpu.reg.set_ix(ix);
+    m.cpu.reg.set_de(de);
+    m.bus.tape.insert(tape.into_blocks());
+    m
+}
+
+// Lectura de bloques
+//
+// Cada bloque lleva delante su longitud en little endian.
+#[test]
+fn test_tap_bloques() {
+    let data = imagen(&[bloque(0x00, &[1; 17]), bloque(0xFF, &[9, 8])]);
+    let tape = TapFile::from_bytes(&data).unwrap();
+
+    assert_eq!(tape.blocks.len(), 2);
+    assert_eq!(tape.blocks[0].len(), 19);
+    assert_eq!(tape.blocks[0][0], 0x00);
+    assert_eq!(tape.blocks[1], &[0xFF, 9, 8, 0xFF ^ 9 ^ 8]);
+
+    // Bloque truncado
+    assert!(TapFile::from_bytes(&[0x05, 0x00, 0xFF]).is_err());
+}
+
+// Trap de LD-BYTES
+//
+// Copia el bloque en IX, deja carry a 1 y vuelve al llamador.
+#[test]
+fn test_trap_carga() {
+    let tape = TapFile::from_bytes(&imagen(&[bloque(0xFF, &[1, 2, 3])])).unwrap();
+    let mut m = maquina_en_ld_bytes(tape, 0xFF, 0x8000, 3);
+
+    m.step_once();
+
+    assert_eq!(m.cpu.bus.read_byte(0x8000), 1);
+    assert_eq!(m.cpu.bus.read_byte(0x8001), 2);
+    assert_eq!(m.cpu.bus.read_byte(0x8002), 3);
+    assert_eq!(m.cpu.reg.pc, 0x1234);
+    assert_eq!(m.cpu.reg.sp, 0xFFF2);
+    assert_eq!(m.cpu.reg.get_ix(), 0x8003);
+    assert_eq!(m.cpu.reg.get_de(), 0);
+    assert!(m.cpu.reg.flags.c);
+    assert!(m.run_state.iff1);
+    assert!(m.bus.tape.at_end());
+}
+
+// Retorno con SP=0xFFFF
+//
+// La dirección de vuelta está partida entre 0xFFFF y 0x0000: no debe
+// desbordar.
+#[test]
+fn test_trap_sp_ffff() {
+    let tape = TapFile::from_bytes(&imagen(&[bloque(0xFF, &[1, 2, 3])])).unwrap();
+    let mut m = maquina_en_ld_bytes(tape, 0xFF, 0x8000, 3);
+    m.cpu.reg.sp = 0xFFFF;
+    m.cpu.bus.write_byte(0xFFFF, 0x34);
+    m.cpu.bus.write_byte(0x0000, 0x12);
+
+    m.step_once();
+
+    assert_eq!(m.cpu.reg.pc, 0x1234);
+    assert_eq!(m.cpu.reg.sp, 0x0001);
+}
+
+// Flag distinto
+//
+// La ROM descarta el bloque (p.ej. cabecera cuando espera datos): carry a 0.
+#[test]
+fn test_trap_flag_distinto() {
+    let tape = TapFile::from_bytes(&imagen(&[bloque(0x00, &[1, 2, 3])])).unwrap();
+    let mut m = maquina_en_ld_bytes(tape, 0xFF, 0x8000, 3);
+
+    m.step_once();
+
+    assert_eq!(m.cpu.bus.read_byte(0x8000), 0);
+    assert_eq!(m.cpu.reg.pc, 0x1234);
+    assert!(!m.cpu.reg.flags.c);
+}
+
+// Checksum erróneo
+//
+// Los datos se copian pero el carry queda a 0 ("Tape loading error").
+#[test]
+fn test_trap_checksum() {
+    let mut b = bloque(0xFF, &[1, 2, 3]);
+    *b.last_mut().unwrap() ^= 0x55;
+    let tape = TapFile::from_bytes(&imagen(&[b])).unwrap();
+    let mut m = maquina_en_ld_bytes(tape, 0xFF, 0x8000, 3);
+
+    m.step_once();
+
+    assert_eq!(m.cpu.bus.read_byte(0x8002), 3);
+    assert_eq!(m.cpu.reg.pc, 0x1234);
+    assert!(!m.cpu.reg.flags.c);
+}
+
+// Sin cinta
+//
+// La ROM sigue su curso normal.
+#[test]
+fn test_sin_cinta() {
+    let mut m = ZxMachine::builder().build().unwrap();
+    m.cpu.reg.pc = LD_BYTES;
+
+    m.step_once();
+
+    assert_eq!(m.cpu.reg.pc, LD_BYTES + 1); // NOP de la memoria vacía
+}
+
+// 128K
+//
+// Con la ROM 0 (editor 128) en 0x0556 hay otro código: ni trap ni cinta en
+// marcha. Con la ROM 1 (BASIC 48) sí carga.
+#[test]
+fn test_trap_128k() {
+    let tape = TapFile::from_bytes(&imagen(&[bloque(0xFF, &[1, 2, 3])])).unwrap();
+    let mut m = ZxMachine::builder().machine(MachineType::Spectrum128K).build().unwrap();
+    m.cpu.reg.sp = 0xBFF0;
+    m.cpu.bus.write_byte(0xBFF0, 0x34);
+    m.cpu.bus.write_byte(0xBFF1, 0x12);
+    m.cpu.reg.a = 0xFF;
+    m.cpu.reg.flags.c = true;
+    m.cpu.reg.set_ix(0x8000);
+    m.cpu.reg.set_de(3);
+    m.bus.tape.insert(tape.into_blocks());
+
+    m.cpu.reg.pc = LD_BYTES;
+    m.step_once();
+    assert_eq!(m.cpu.reg.pc, LD_BYTES + 1);
+    assert!(!m.bus.tape.is_playing());
+    assert_eq!(m.cpu.bus.read_byte(0x8000), 0);
+
+    let mem = m.bus.mem128.as_mut().unwrap();
+    mem.page(&mut m.cpu.bus, 0x10);
+    m.cpu.reg.pc = LD_BYTES;
+    m.step_once();
+    assert_eq!(m.cpu.reg.pc, 0x1234);
+    assert_eq!(m.cpu.bus.read_byte(0x8002), 3);
+    assert!(m.cpu.reg.flags.c);
+}
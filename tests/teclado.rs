@@ -0,0 +1,116 @@
+use zilog_z80::bus::Bus;
+use zx::bus::ZxBus;
+use zx::cli::CliOptions;
+use zx::constantes::KEY_FORGET_FRAMES;
+use zx::machine::model::MachineType;
+use zx::machine::zx_machine::ZxMachine;
+use zx::teclado::{char_chords, matrix_key, KeyMapping, Keyboard, CAPS_SHIFT, SYMBOL_SHIFT};
+
+// Frames hasta que la cola deja de escribir, con lo pulsado en cada uno
+fn escribir(k: &mut Keyboard, texto: &str) -> Vec<[u8; 8]> {
+    k.type_text(texto);
+    let mut frames = Vec::new();
+    while k.typing() {
+        k.on_frame(true);
+        frames.push(k.rows);
+    }
+    frames
+}
+
+// Caracteres
+//
+// Letras, mayúsculas con CAPS, puntuación con SYMBOL y los del modo
+// extendido en dos combinaciones.
+#[test]
+fn test_caracteres() {
+    let a = matrix_key(b'A').unwrap();
+    let p = matrix_key(b'P').unwrap();
+    assert_eq!(a, (1, 0));
+    assert_eq!(char_chords('a'), Some(vec![vec![a]]));
+    assert_eq!(char_chords('A'), Some(vec![vec![CAPS_SHIFT, a]]));
+    assert_eq!(char_chords('"'), Some(vec![vec![SYMBOL_SHIFT, p]]));
+    assert_eq!(char_chords(';'), Some(vec![vec![SYMBOL_SHIFT, (5, 1)]]));
+    assert_eq!(char_chords('\n'), Some(vec![vec![(6, 0)]]));
+    assert_eq!(char_chords('7'), Some(vec![vec![(4, 3)]]));
+    assert_eq!(char_chords('['), Some(vec![vec![CAPS_SHIFT, SYMBOL_SHIFT], vec![SYMBOL_SHIFT, (5, 4)]]));
+    assert_eq!(char_chords('ñ'), None);
+}
+
+// Combinaciones que comparten tecla
+//
+// Soltar un cursor (CAPS + 5) con el borrar (CAPS + 0) pulsado deja CAPS
+// pulsado.
+#[test]
+fn test_combinaciones() {
+    let mem = Bus::new(0xFFFF);
+    let mut bus = ZxBus::new();
+    let izquierda = [CAPS_SHIFT, (3, 4)];
+    let borrar = [CAPS_SHIFT, (4, 0)];
+
+    bus.keyboard.press(&izquierda);
+    bus.keyboard.press(&borrar);
+    bus.keyboard.release(&izquierda);
+    assert_eq!(bus.in_port(0xFEFE, 0, &mem) & 0x1F, 0x1E);
+    assert_eq!(bus.in_port(0xF7FE, 0, &mem) & 0x1F, 0x1F);
+    assert_eq!(bus.in_port(0xEFFE, 0, &mem) & 0x1F, 0x1E);
+
+    bus.keyboard.release(&borrar);
+    assert_eq!(bus.in_port(0x00FE, 0, &mem) & 0x1F, 0x1F);
+
+    bus.keyboard.press(&borrar);
+    bus.keyboard.release_all();
+    assert_eq!(bus.keyboard.rows, [0x1F; 8]);
+}
+
+// Texto escrito
+//
+// Cada combinación se mantiene dos frames y se suelta en el siguiente;
+// una tecla repetida espera a que la ROM la olvide.
+#[test]
+fn test_texto() {
+    let mut k = Keyboard::new();
+    let frames = escribir(&mut k, "aS");
+    assert_eq!(frames.len(), 6);
+    assert_eq!(frames[0][1], 0x1E);
+    assert_eq!(frames[1][1], 0x1E);
+    assert_eq!(frames[2], [0x1F; 8]);
+    // 'S' mayúscula: CAPS y S a la vez
+    assert_eq!((frames[3][0], frames[3][1]), (0x1E, 0x1D));
+    assert_eq!(frames[5], [0x1F; 8]);
+
+    // "aa": la segunda 'a' espera KEY_FORGET_FRAMES desde que se soltó
+    let mut k = Keyboard::new();
+    let frames = escribir(&mut k, "aa");
+    let pulsada: Vec<usize> = (0..frames.len()).filter(|&i| frames[i][1] == 0x1E).collect();
+    assert_eq!(pulsada, [0, 1, 2 + KEY_FORGET_FRAMES as usize, 3 + KEY_FORGET_FRAMES as usize]);
+}
+
+// Modo y máquina
+//
+// El modo se elige en la consola y en la línea de comandos y se mantiene
+// al cambiar de modelo; la cola avanza con los frames de la máquina.
+#[test]
+fn test_modo() {
+    let mut m = ZxMachine::builder().build().unwrap();
+    assert_eq!(m.bus.keyboard.mode, KeyMapping::Positional);
+    assert_eq!(m.debug_command("keys symbolic").unwrap(), "Teclado: simbólico");
+    assert_eq!(m.bus.keyboard.mode, KeyMapping::Symbolic);
+    assert!(m.debug_command("keys azerty").is_err());
+    let rom = vec![0; MachineType::Spectrum48K.rom_size()];
+    m.switch_machine_with_rom(MachineType::Spectrum48K, rom).unwrap();
+    assert_eq!(m.bus.keyboard.mode, KeyMapping::Symbolic);
+
+    // JR $ en 0x8000
+    m.cpu.bus.write_byte(0x8000, 0x18);
+    m.cpu.bus.write_byte(0x8001, 0xFE);
+    m.cpu.reg.pc = 0x8000;
+    m.bus.keyboard.type_text("\"");
+    while m.frames == 0 {
+        m.step_once();
+    }
+    assert_eq!(m.bus.keyboard.rows[7], 0x1D);
+    assert_eq!(m.bus.keyboard.rows[5], 0x1E);
+
+    assert_eq!(CliOptions::parse(["--keys", "symbolic"]).unwrap().keys, KeyMapping::Symbolic);
+    assert!(CliOptions::parse(["--keys", "x"]).is_err());
+}
@@ -0,0 +1,142 @@
+mod common;
+
+use common::{maquina_con_programa, temporal};
+use std::path::PathBuf;
+use zx::traza::{flags, Trace, Trigger};
+
+fn leer(path: &PathBuf) -> Vec<String> {
+    let s = std::fs::read_to_string(path).unwrap();
+    std::fs::remove_file(path).unwrap();
+    s.lines().map(str::to_string).collect()
+}
+
+// Formato de las líneas
+//
+// Registros de antes de ejecutar la instrucción, bytes y desensamblado.
+#[test]
+fn test_formato() {
+    let path = temporal("formato");
+    // LD A,0x42 / LD HL,0x1234 / JR $
+    let mut m = maquina_con_programa(&[0x3E, 0x42, 0x21, 0x34, 0x12, 0x18, 0xFE]);
+    m.debug_command(&format!("trace on {}", path.display())).unwrap();
+
+    m.step_once();
+    m.step_once();
+    m.debug_command("trace off").unwrap();
+
+    let lineas = leer(&path);
+    assert_eq!(lineas.len(), 2);
+    assert!(lineas[0].starts_with("0          8000 3E 42       LD A,0x42"));
+    assert!(lineas[1].contains("8002 21 34 12    LD HL,0x1234"));
+    assert!(lineas[1].contains(" AF=42"));
+    assert!(lineas[1].contains(" HL=0000 "));
+
+    assert_eq!(flags(0xC1), "SZ-----C");
+}
+
+// Disparadores por dirección y filtros
+//
+// Empieza al llegar a la subrutina, no escribe el bucle excluido y
+// se cierra al volver.
+#[test]
+fn test_disparadores_y_filtros() {
+    let path = temporal("filtros");
+    // CALL 0x8010 / JR $ ... 8010: LD B,3 / 8012: DJNZ $ / 8014: RET
+    let mut m = maquina_con_programa(&[0xCD, 0x10, 0x80, 0x18, 0xFE]);
+    for (i, b) in [0x06, 0x03, 0x10, 0xFE, 0xC9].iter().enumerate() {
+        m.cpu.bus.write_byte(0x8010 + i as u16, *b);
+    }
+    m.debug_command("trace start 8010").unwrap();
+    m.debug_command("trace stop 8003").unwrap();
+    m.debug_command("trace skip 8012").unwrap();
+    assert!(m.debug_command("trace only 9000-8000").is_err());
+    m.debug_command(&format!("trace on {}", path.display())).unwrap();
+    assert!(!m.bus.trace.is_recording());
+
+    m.debugger.run();
+    m.run_frame();
+    assert!(!m.bus.trace.is_open());
+    assert_eq!(m.bus.trace.lines(), 2);
+
+    let lineas = leer(&path);
+    assert!(lineas[0].contains("8010 06 03"));
+    assert!(lineas[1].contains("8014 C9"));
+}
+
+// Disparador puesto con la traza ya abierta
+//
+// "trace on" y después "trace start": no se graba hasta llegar al disparador.
+#[test]
+fn test_disparador_despues_de_abrir() {
+    let path = temporal("despues");
+    // CALL 0x8010 / JR $ ... 8010: RET
+    let mut m = maquina_con_programa(&[0xCD, 0x10, 0x80, 0x18, 0xFE]);
+    m.cpu.bus.write_byte(0x8010, 0xC9);
+    m.debug_command(&format!("trace on {}", path.display())).unwrap();
+    assert!(m.bus.trace.is_recording());
+    m.debug_command("trace start 8010").unwrap();
+    assert!(!m.bus.trace.is_recording());
+
+    m.step_once();
+    m.step_once();
+    m.debug_command("trace off").unwrap();
+
+    let lineas = leer(&path);
+    assert_eq!(lineas.len(), 1);
+    assert!(lineas[0].contains("8010 C9"));
+}
+
+// Disparadores por frame
+//
+// Con "frame:2" la traza empieza al acabar el segundo frame y termina en el
+// cuarto. Las direcciones F000-FFFF siguen siendo direcciones.
+#[test]
+fn test_disparadores_por_frame() {
+    let path = temporal("frames");
+    // DI / bucle: INC A / JR bucle
+    let mut m = maquina_con_programa(&[0xF3, 0x3C, 0x18, 0xFD]);
+    assert_eq!(Trigger::parse("FADE"), Some(Trigger::Addr(0xFADE)));
+    assert_eq!(Trigger::parse("f000"), Some(Trigger::Addr(0xF000)));
+    assert_eq!(Trigger::parse("frame:x"), None);
+    m.bus.trace.start = Trigger::parse("frame:2");
+    m.bus.trace.stop = Trigger::parse("FRAME:4");
+    m.bus.trace.open(&path).unwrap();
+
+    m.debugger.run();
+    m.run_frame();
+    m.run_frame();
+    assert!(m.bus.trace.is_recording());
+    assert_eq!(m.bus.trace.lines(), 0);
+    m.run_frame();
+    m.run_frame();
+    assert!(!m.bus.trace.is_open());
+
+    // Unos 2 frames de INC A (4 T) / JR (12 T): 2 líneas cada 16 T
+    let n = leer(&path).len();
+    assert!((2 * 69888 / 8 - 10..=2 * 69888 / 8 + 10).contains(&n));
+}
+
+// Plantillas
+//
+// CSV, plantilla propia con anchura y errores.
+#[test]
+fn test_plantillas() {
+    let mut t = Trace::new();
+    assert!(t.set_format("csv").is_ok());
+    assert!(t.set_format("{PC} {XX}").is_err());
+    assert!(t.set_format("{PC").is_err());
+    assert!(t.set_format("{INSTR:x}").is_err());
+    assert!(t.command("format {PC}:{A:4}|").is_ok());
+    assert!(t.command("on").is_err());
+    assert!(t.command("xyz").is_err());
+
+    let path = temporal("plantilla");
+    let mut m = maquina_con_programa(&[0x3E, 0x07, 0x00]);
+    m.bus.trace = t;
+    m.bus.trace.open(&path).unwrap();
+    m.step_once();
+    m.step_once();
+    m.bus.trace.close();
+
+    assert_eq!(leer(&path), ["8000:00  |", "8002:07  |"]);
+}
@@ -0,0 +1,194 @@
+use zilog_z80::bus::Bus;
+use zx::bus::ZxBus;
+use zx::cinta::TapeDeck;
+use zx::formatos::tzx::{TzxBlock, TzxFile};
+
+// Imagen .tzx v1.20 con los bloques (ya codificados) dados
+fn imagen(bloques: &[&[u8]]) -> Vec<u8> {
+    let mut data = b"ZXTape!\x1A\x01\x14".to_vec();
+    for b in bloques {
+        data.extend_from_slice(b);
+    }
+    data
+}
+
+// Cabecera y bloques
+//
+// Bloques de señal, de control e informativos.
+#[test]
+fn test_tzx_bloques() {
+    let data = imagen(&[
+        &[0x30, 3, b'A', b'B', b'C'],
+        &[0x10, 0xE8, 0x03, 0x02, 0x00, 0xFF, 0xAA],
+        &[0x12, 0x78, 0x08, 0x10, 0x00],
+        &[0x13, 0x02, 0x9B, 0x02, 0xDF, 0x02],
+        &[0x24, 0x03, 0x00],
+        &[0x25],
+        &[0x20, 0x00, 0x00],
+        &[0x21, 0x01, b'G'],
+        &[0x22],
+    ]);
+    let tzx = TzxFile::from_bytes(&data).unwrap();
+
+    assert_eq!(tzx.version, (1, 20));
+    assert_eq!(tzx.blocks.len(), 9);
+    assert_eq!(tzx.blocks[0], TzxBlock::Text("ABC".into()));
+    assert_eq!(tzx.blocks[1], TzxBlock::Standard { pause_ms: 1000, data: vec![0xFF, 0xAA] });
+    assert_eq!(tzx.blocks[2], TzxBlock::PureTone { pulse: 2168, count: 16 });
+    assert_eq!(tzx.blocks[3], TzxBlock::PulseSeq(vec![667, 735]));
+    assert_eq!(tzx.blocks[4], TzxBlock::LoopStart(3));
+    assert_eq!(tzx.blocks[6], TzxBlock::Pause(0));
+    assert_eq!(tzx.blocks[7], TzxBlock::GroupStart("G".into()));
+
+    // Bloques obsoletos con longitud propia: 0x34 (8 bytes) y 0x40
+    // (tipo y longitud de 3 bytes)
+    let data = imagen(&[
+        &[0x34, 1, 2, 3, 4, 5, 6, 7, 8],
+        &[0x40, 0x00, 0x02, 0x00, 0x00, 0xAA, 0xBB],
+        &[0x12, 0x78, 0x08, 0x10, 0x00],
+    ]);
+    let tzx = TzxFile::from_bytes(&data).unwrap();
+    assert_eq!(tzx.blocks[0], TzxBlock::Other(0x34));
+    assert_eq!(tzx.blocks[1], TzxBlock::Other(0x40));
+    assert_eq!(tzx.blocks[2], TzxBlock::PureTone { pulse: 2168, count: 16 });
+
+    // Firma incorrecta o bloque truncado
+    assert!(TzxFile::from_bytes(b"ZXTapX!\x1A\x01\x14").is_err());
+    assert!(TzxFile::from_bytes(&imagen(&[&[0x10, 0x00, 0x00, 0x05, 0x00]])).is_err());
+}
+
+// Datos generalizados (0x19)
+//
+// Tono guía por PRLE y datos de 1 bit por símbolo.
+#[test]
+fn test_tzx_generalizado() {
+    #[rustfmt::skip]
+    let cuerpo: &[u8] = &[
+        0x00, 0x00,             // pausa
+        0x01, 0x00, 0x00, 0x00, // TOTP
+        0x01,                   // NPP
+        0x01,                   // ASP
+        0x04, 0x00, 0x00, 0x00, // TOTD
+        0x02,                   // NPD
+        0x02,                   // ASD
+        0x00, 0x10, 0x00,       // símbolo guía: 16 T
+        0x00, 0x05, 0x00,       // PRLE: símbolo 0 x5
+        0x00, 0x01, 0x00, 0x02, 0x00, // "0": 1 T + 2 T
+        0x00, 0x03, 0x00, 0x00, 0x00, // "1": 3 T (pulso 0 = fin)
+        0b1010_0000,            // datos: 1 0 1 0
+    ];
+    let mut bloque = vec![0x19];
+    bloque.extend_from_slice(&(cuerpo.len() as u32).to_le_bytes());
+    bloque.extend_from_slice(cuerpo);
+
+    let tzx = TzxFile::from_bytes(&imagen(&[&bloque])).unwrap();
+    let TzxBlock::Generalized { defs, symbols, .. } = &tzx.blocks[0] else {
+        panic!("no es un bloque generalizado");
+    };
+
+    assert_eq!(defs.len(), 3);
+    assert_eq!(defs[2], (0, vec![3]));
+    assert_eq!(symbols, &[0, 0, 0, 0, 0, 2, 1, 2, 1]);
+
+    // ASD = 3: 2 bits por símbolo y el índice 3 no existe
+    #[rustfmt::skip]
+    let cuerpo: &[u8] = &[
+        0x00, 0x00,             // pausa
+        0x00, 0x00, 0x00, 0x00, // TOTP
+        0x00,                   // NPP
+        0x00,                   // ASP
+        0x02, 0x00, 0x00, 0x00, // TOTD
+        0x01,                   // NPD
+        0x03,                   // ASD
+        0x00, 0x01, 0x00,       // "0"
+        0x00, 0x02, 0x00,       // "1"
+        0x00, 0x03, 0x00,       // "2"
+        0b1011_0000,            // datos: 2, 3
+    ];
+    let mut bloque = vec![0x19];
+    bloque.extend_from_slice(&(cuerpo.len() as u32).to_le_bytes());
+    bloque.extend_from_slice(cuerpo);
+    assert!(TzxFile::from_bytes(&imagen(&[&bloque])).is_err());
+}
+
+// Señal EAR de un tono puro
+//
+// Cada pulso invierte el nivel; al acabar la cinta se para sola.
+#[test]
+fn test_deck_tono() {
+    let mut deck = TapeDeck::new();
+    deck.insert(vec![TzxBlock::PureTone { pulse: 100, count: 3 }]);
+
+    assert!(deck.ear(0)); // parada: 1
+    deck.play(1000);
+
+    assert!(deck.ear(1000));
+    assert!(deck.ear(1099));
+    assert!(!deck.ear(1100));
+    assert!(deck.ear(1250));
+    assert!(deck.is_playing());
+
+    deck.ear(1300);
+    assert!(!deck.is_playing());
+    assert!(deck.at_end());
+}
+
+// Bucles y parada
+//
+// El bucle repite el pulso 3 veces; la pausa 0 para la cinta.
+#[test]
+fn test_deck_bucle_y_parada() {
+    let mut deck = TapeDeck::new();
+    deck.insert(vec![
+        TzxBlock::LoopStart(3),
+        TzxBlock::PulseSeq(vec![10]),
+        TzxBlock::LoopEnd,
+        TzxBlock::Pause(0),
+        TzxBlock::PulseSeq(vec![10]),
+    ]);
+
+    deck.play(0);
+    assert!(deck.ear(0));
+    assert!(!deck.ear(10));
+    assert!(deck.ear(20));
+    assert!(deck.is_playing());
+
+    deck.ear(30);
+    assert!(!deck.is_playing());
+    assert_eq!(deck.position(), (4, 5));
+
+    // PLAY de nuevo sigue tras la pausa
+    deck.play(100);
+    assert!(deck.is_playing());
+    assert_eq!(deck.position(), (5, 5));
+}
+
+// EAR en el puerto 0xFE
+//
+// El bit 6 sigue la señal; los bits 5 y 7 siempre a 1.
+#[test]
+fn test_ear_en_puerto() {
+    let mut bus = ZxBus::new();
+    let mem = Bus::new(0xFFFF);
+    bus.tape.insert(vec![TzxBlock::PulseSeq(vec![500, 500])]);
+
+    assert_eq!(bus.in_port(0xFEFE, 0, &mem) & 0xE0, 0xE0);
+
+    bus.tape.play(0);
+    assert_eq!(bus.in_port(0xFEFE, 10, &mem) & 0xE0, 0xE0);
+    assert_eq!(bus.in_port(0xFEFE, 510, &mem) & 0xE0, 0xA0);
+}
+
+// Trap con un bloque turbo
+//
+// No se puede cargar de golpe: la cinta se pone en marcha.
+#[test]
+fn test_trap_turbo_pone_en_marcha() {
+    let mut m = zx::machine::zx_machine::ZxMachine::builder().build().unwrap();
+    m.bus.tape.insert(vec![TzxBlock::PureTone { pulse: 2168, count: 100 }]);
+    m.cpu.reg.pc = zx::formatos::tap::LD_BYTES;
+
+    m.step_once();
+
+    assert!(m.bus.tape.is_playing());
+}
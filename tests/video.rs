@@ -0,0 +1,114 @@
+use zx::constantes::{ZX_BORDER, ZX_FRAME_H, ZX_FRAME_W};
+use zx::machine::zx_machine::ZxMachine;
+use zx::video::{FB_H, FB_W};
+
+// Tamaño del framebuffer
+//
+// 352x296: pantalla 256x192 más el borde del 48K.
+#[test]
+fn test_framebuffer_completo() {
+    let m = ZxMachine::builder().build().unwrap();
+
+    assert_eq!(FB_W, 352);
+    assert_eq!(FB_H, 296);
+    assert_eq!(ZX_FRAME_W as usize, FB_W);
+    assert_eq!(ZX_FRAME_H as usize, FB_H);
+    assert_eq!(m.video.framebuffer.len(), FB_W * FB_H);
+}
+
+// Color del borde
+//
+// Todo lo que queda fuera del área de papel toma el color de ZxBus::border.
+#[test]
+fn test_borde_color() {
+    let mut m = ZxMachine::builder().build().unwrap();
+    m.bus.border = 2;
+    m.update_video_from_bus();
+
+    let fb = &m.video.framebuffer;
+    assert_eq!(fb[0], 2); // esquina superior izquierda
+    assert_eq!(fb[FB_W * FB_H - 1], 2); // esquina inferior derecha
+    assert_eq!(fb[200 * FB_W + 10], 2); // borde izquierdo
+    assert_eq!(fb[200 * FB_W + FB_W - 10], 2); // borde derecho
+}
+
+// Posición del área de papel
+//
+// El primer píxel de la pantalla (0x4000, bit 7) cae en (48, 48).
+#[test]
+fn test_papel_desplazado() {
+    let mut m = ZxMachine::builder().build().unwrap();
+    m.cpu.bus.write_byte(0x4000, 0x80);
+    m.cpu.bus.write_byte(0x5800, 0x0F); // papel azul, tinta blanca
+    m.bus.border = 4;
+    m.update_video_from_bus();
+
+    let b = ZX_BORDER as usize;
+    let fb = &m.video.framebuffer;
+    assert_eq!(fb[b * FB_W + b], 7); // tinta
+    assert_eq!(fb[b * FB_W + b + 1], 1); // papel
+    assert_eq!(fb[b * FB_W + b - 1], 4); // borde justo a la izquierda
+    assert_eq!(fb[(b - 1) * FB_W + b], 4); // borde justo encima
+}
+
+// Cambio de borde a mitad de frame
+//
+// El haz cambia de color exactamente en el T-state de la escritura:
+// línea 100 del frame (fila 84 del framebuffer), columna de papel 0.
+#[test]
+fn test_borde_mitad_de_frame() {
+    let bus = zilog_z80::bus::Bus::new(0xFFFF);
+    let mut video = zx::video::Video::new(1);
+    let writes = [(0, 1), (100 * 224, 2)];
+
+    video.start_frame(0);
+    video.end_frame(&bus, &writes, 69888);
+
+    let fb = &video.framebuffer;
+    assert_eq!(fb[83 * FB_W + 340], 1); // final de la línea anterior
+    assert_eq!(fb[84 * FB_W + 46], 1); // borde izquierdo: antes del cambio
+    assert_eq!(fb[84 * FB_W + 320], 2); // borde derecho: después del cambio
+    assert_eq!(fb[200 * FB_W], 2);
+}
+
+// Cambio de atributo a mitad de celda ("rainbow")
+//
+// Las 4 primeras líneas de la celda usan el atributo antiguo.
+#[test]
+fn test_atributo_mitad_de_celda() {
+    let mut bus = zilog_z80::bus::Bus::new(0xFFFF);
+    let mut video = zx::video::Video::new(1);
+    let b = ZX_BORDER as usize;
+
+    bus.write_byte(0x5800, 0x08); // papel azul
+    video.start_frame(0);
+    video.render_until(&bus, &[], 68 * 224);
+
+    bus.write_byte(0x5800, 0x10); // papel rojo
+    video.end_frame(&bus, &[], 69888);
+
+    let fb = &video.framebuffer;
+    assert_eq!(fb[b * FB_W + b], 1);
+    assert_eq!(fb[(b + 3) * FB_W + b], 1);
+    assert_eq!(fb[(b + 4) * FB_W + b], 2);
+    assert_eq!(fb[(b + 7) * FB_W + b], 2);
+}
+
+// Borde desde un programa
+//
+// OUT (0xFE),A llega al renderer con su marca de tiempo.
+#[test]
+fn test_borde_desde_programa() {
+    let mut m = ZxMachine::builder().build().unwrap();
+    // LD A,0x03 / OUT (0xFE),A / JR -2
+    for (i, b) in [0x3E, 0x03, 0xD3, 0xFE, 0x18, 0xFE].iter().enumerate() {
+        m.cpu.bus.write_byte(i as u16, *b);
+    }
+
+    m.debugger.run();
+    m.run_frame();
+    m.run_frame();
+
+    assert_eq!(m.video.framebuffer[0], 3);
+    assert_eq!(m.video.framebuffer[FB_W * FB_H - 1], 3);
+}
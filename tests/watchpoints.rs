@@ -0,0 +1,175 @@
+mod common;
+
+use common::{maquina_con_programa, temporal};
+use zx::contencion::PreRegs;
+use zx::debugger::RunMode;
+use zx::machine::zx_machine::ZxMachine;
+use zx::watchpoints::{data_accesses, MemAccess};
+
+fn regs() -> PreRegs {
+    PreRegs {
+        pc: 0x8000,
+        a: 0,
+        bc: 0x0010,
+        de: 0x9000,
+        hl: 0x6000,
+        sp: 0xBFF0,
+        ix: 0x7000,
+        iy: 0x5C3A,
+        ir: 0,
+    }
+}
+
+// Escritura en la pantalla
+//
+// Para después de la instrucción, con su dirección y los valores de antes y después.
+#[test]
+fn test_watchpoint_escritura() {
+    // LD HL,0x4000 / LD (HL),0xFF / JR $
+    let mut m = maquina_con_programa(&[0x21, 0x00, 0x40, 0x36, 0xFF, 0x18, 0xFE]);
+    m.debug_command("wp 4000-57FF").unwrap();
+
+    m.debugger.run();
+    m.run_frame();
+
+    assert_eq!(m.debugger.mode, RunMode::Paused);
+    assert_eq!(m.cpu.reg.pc, 0x8005);
+    assert_eq!(m.debugger.console.output, "Watchpoint 1: PC=8003 escribe (4000) 00 -> FF");
+    assert_eq!(m.bus.watch.list()[0].hits, 1);
+}
+
+// Lecturas de datos, no de código
+//
+// Un watchpoint de lectura sobre el propio programa no salta al ejecutarlo;
+// LD A,(nn) sí.
+#[test]
+fn test_watchpoint_lectura() {
+    // LD A,(0x8010) / JR $
+    let mut m = maquina_con_programa(&[0x3A, 0x10, 0x80, 0x18, 0xFE]);
+    m.cpu.bus.write_byte(0x8010, 0x42);
+    m.debug_command("wp 8000-8004 r").unwrap();
+    m.debug_command("wp 8010 r").unwrap();
+
+    m.debugger.run();
+    m.run_frame();
+
+    assert_eq!(m.debugger.mode, RunMode::Paused);
+    assert_eq!(m.debugger.console.output, "Watchpoint 2: PC=8000 lee (8010) 42 -> 42");
+    assert_eq!(m.bus.watch.list()[0].hits, 0);
+
+    // El bucle JR $ ya no lee datos: sigue corriendo
+    m.debugger.run();
+    m.run_frame();
+    assert_eq!(m.debugger.mode, RunMode::Run);
+}
+
+// Instrucciones de bloque
+//
+// Cada pasada de LDIR y CPIR es un step: salta el watchpoint de un byte
+// que no es el primero.
+#[test]
+fn test_watchpoint_bloques() {
+    // LD HL,0x9000 / LD DE,0xA000 / LD BC,4 / LDIR / LD HL,0x9000 /
+    // LD BC,4 / LD A,0x77 / CPIR / JR $
+    let mut m = maquina_con_programa(&[
+        0x21, 0x00, 0x90, 0x11, 0x00, 0xA0, 0x01, 0x04, 0x00, 0xED, 0xB0,
+        0x21, 0x00, 0x90, 0x01, 0x04, 0x00, 0x3E, 0x77, 0xED, 0xB1, 0x18, 0xFE,
+    ]);
+    for (i, b) in [0x11, 0x22, 0x33, 0x44].into_iter().enumerate() {
+        m.cpu.bus.write_byte(0x9000 + i as u16, b);
+    }
+    m.debug_command("wp A002 w").unwrap();
+    m.debug_command("wp 9003 r").unwrap();
+
+    m.debugger.run();
+    m.run_frame();
+    assert_eq!(m.debugger.mode, RunMode::Paused);
+    assert_eq!(m.debugger.console.output, "Watchpoint 1: PC=8009 escribe (A002) 00 -> 33");
+
+    // La lectura de 9003 en el LDIR y luego en el CPIR
+    m.debugger.run();
+    m.run_frame();
+    assert_eq!(m.debugger.console.output, "Watchpoint 2: PC=8009 lee (9003) 44 -> 44");
+    m.debugger.run();
+    m.run_frame();
+    assert_eq!(m.debugger.console.output, "Watchpoint 2: PC=8013 lee (9003) 44 -> 44");
+    assert_eq!(m.bus.watch.list()[0].hits, 1);
+    assert_eq!(m.bus.watch.list()[1].hits, 2);
+}
+
+// Puertos de E/S
+//
+// "wio FE/FF w" salta con OUT (0xFE) pero no con IN de 0xFE;
+// el valor anterior es el último que pasó por el puerto.
+#[test]
+fn test_watchpoint_puertos() {
+    // LD A,2 / OUT (0xFE),A / IN A,(0xFE) / LD A,5 / OUT (0xFE),A / JR $
+    let mut m = maquina_con_programa(&[
+        0x3E, 0x02, 0xD3, 0xFE, 0xDB, 0xFE, 0x3E, 0x05, 0xD3, 0xFE, 0x18, 0xFE,
+    ]);
+    m.debug_command("wio FE/FF w").unwrap();
+
+    m.debugger.run();
+    m.run_frame();
+    assert_eq!(m.debugger.console.output, "Watchpoint 1: PC=8002 OUT (02FE) 02 -> 02");
+
+    m.debugger.run();
+    m.run_frame();
+    assert_eq!(m.cpu.reg.pc, 0x800A);
+    assert_eq!(m.debugger.console.output, "Watchpoint 1: PC=8008 OUT (05FE) 02 -> 05");
+}
+
+// Accesos deducidos por instrucción
+//
+// Saltos condicionales, bloques, indexados y BIT.
+#[test]
+fn test_accesos_por_instruccion() {
+    use MemAccess::{Read, Write};
+    let r = regs();
+
+    // RET NZ no tomado (5 T) / tomado (11 T)
+    assert!(data_accesses(&r, &[0xC0, 0, 0, 0], Some(5)).is_empty());
+    assert_eq!(data_accesses(&r, &[0xC0, 0, 0, 0], Some(11)), [Read(0xBFF0), Read(0xBFF1)]);
+    // PUSH BC
+    assert_eq!(data_accesses(&r, &[0xC5, 0, 0, 0], Some(11)), [Write(0xBFEF), Write(0xBFEE)]);
+    // LDIR
+    assert_eq!(data_accesses(&r, &[0xED, 0xB0, 0, 0], Some(21)), [Read(0x6000), Write(0x9000)]);
+    // LD (IX-1),A / BIT 0,(IX+2) / SET 0,(HL)
+    assert_eq!(data_accesses(&r, &[0xDD, 0x77, 0xFF, 0], Some(19)), [Write(0x6FFF)]);
+    assert_eq!(data_accesses(&r, &[0xDD, 0xCB, 0x02, 0x46], Some(20)), [Read(0x7002)]);
+    assert_eq!(data_accesses(&r, &[0xCB, 0xC6, 0, 0], Some(15)), [Read(0x6000), Write(0x6000)]);
+    // PUSH IY / LD (nn),IY
+    assert_eq!(data_accesses(&r, &[0xFD, 0xE5, 0, 0], Some(15)), [Write(0xBFEF), Write(0xBFEE)]);
+    assert_eq!(data_accesses(&r, &[0xFD, 0x22, 0x00, 0x90], Some(20)), [Write(0x9000), Write(0x9001)]);
+}
+
+// Comandos y persistencia
+//
+// Los watchpoints se guardan en el .bp junto con los breakpoints.
+#[test]
+fn test_comandos_y_persistencia() {
+    let dir = temporal("wp");
+    std::fs::create_dir_all(&dir).unwrap();
+    let programa = dir.join("juego.bin");
+    std::fs::write(&programa, [0x18, 0xFE, 0x00, 0x00]).unwrap();
+
+    let mut m = ZxMachine::builder().build().unwrap();
+    m.load_file(&programa).unwrap();
+    m.debug_command("bp 8000").unwrap();
+    m.debug_command("wp 5800-5AFF rw").unwrap();
+    m.debug_command("wio 7FFD").unwrap();
+    m.debug_command("wd 2").unwrap();
+    assert!(m.debug_command("wp 4000 x").is_err());
+    assert!(m.debug_command("wd 3").is_err());
+    assert_eq!(
+        m.debug_command("wl").unwrap(),
+        "1 * MEM 5800-5AFF rw (0)\n2   IO 7FFD rw (0)"
+    );
+
+    let mut otra = ZxMachine::builder().build().unwrap();
+    otra.load_file(&programa).unwrap();
+    assert_eq!(otra.bus.watch.to_text(), "wp 5800-5AFF rw\n#wio 7FFD rw\n");
+    assert_eq!(otra.debugger.breakpoints.to_text(), "bp 8000\n");
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -112,12 +112,14 @@ fn test_jr_forward() {
 
     // JR +2
     // NOP        (saltado)
+    // NOP        (saltado)
     // LD A,0x42
     cpu.bus.write_byte(0x0000, 0x18); // JR
     cpu.bus.write_byte(0x0001, 0x02);
     cpu.bus.write_byte(0x0002, 0x00); // NOP
-    cpu.bus.write_byte(0x0003, 0x3E); // LD A,0x42
-    cpu.bus.write_byte(0x0004, 0x42);
+    cpu.bus.write_byte(0x0003, 0x00); // NOP
+    cpu.bus.write_byte(0x0004, 0x3E); // LD A,0x42
+    cpu.bus.write_byte(0x0005, 0x42);
 
     cpu.reg.pc = 0x0000;
 
@@ -450,7 +452,6 @@ fn test_call_ret() {
 // 
 // RST es como un CALL corto a una dirección fija.
 #[test]
-#[test]
 fn test_rst_38() {
     let mut cpu = CPU::new(0xFFFF);
 
@@ -0,0 +1,57 @@
+use zx::machine::zx_machine::ZxMachine;
+
+// ROM de prueba: 16 KB con un programa mínimo en 0x0000
+fn rom_con_programa(programa: &[u8]) -> Vec<u8> {
+    let mut rom = vec![0u8; 16 * 1024];
+    rom[..programa.len()].copy_from_slice(programa);
+    rom
+}
+
+// Builder sin ROM
+//
+// La máquina se construye sin SDL ni ficheros.
+#[test]
+fn test_builder_sin_rom() {
+    let m = ZxMachine::builder().build().unwrap();
+
+    assert_eq!(m.cpu.reg.pc, 0x0000);
+    assert_eq!(m.cpu.bus.read_byte(0x0000), 0x00);
+}
+
+// Builder con ROM en memoria
+//
+// La ROM queda en 0x0000 y protegida contra escritura.
+#[test]
+fn test_builder_con_rom() {
+    let rom = rom_con_programa(&[0x3E, 0x42]); // LD A,0x42
+    let mut m = ZxMachine::builder().rom(rom).build().unwrap();
+
+    assert_eq!(m.cpu.bus.read_byte(0x0000), 0x3E);
+    assert_eq!(m.cpu.bus.read_byte(0x0001), 0x42);
+
+    m.cpu.bus.write_byte(0x0000, 0xFF);
+    assert_eq!(m.cpu.bus.read_byte(0x0000), 0x3E);
+}
+
+// ROM de tamaño incorrecto
+#[test]
+fn test_builder_rom_invalida() {
+    let r = ZxMachine::builder().rom(vec![0u8; 100]).build();
+    assert!(r.is_err());
+}
+
+// Ejecución sin ventana
+//
+// LD A,0x42 / JR -2 : un frame entero sin SDL.
+#[test]
+fn test_run_frame_headless() {
+    let rom = rom_con_programa(&[0x3E, 0x42, 0x18, 0xFC]);
+    let mut m = ZxMachine::builder().rom(rom).build().unwrap();
+
+    m.debugger.run();
+    m.run_frame();
+    m.update_video_from_bus();
+
+    assert_eq!(m.cpu.reg.a, 0x42);
+    assert!(m.run_state.t_states > 0);
+}